@@ -239,12 +239,15 @@ func renderTapEvent(event *common.TapEvent) string {
 		util.AddressToString(event.GetSource()),
 		dst,
 	)
+	if tlsIdentity := event.GetSourceTlsIdentity(); tlsIdentity != "" {
+		flow = fmt.Sprintf("%s tls=%s", flow, tlsIdentity)
+	}
 
 	http := event.GetHttp()
 	httpEvent := http.Event
 	switch ev := httpEvent.(type) {
 	case *common.TapEvent_Http_RequestInit_:
-		return fmt.Sprintf("req id=%d:%d %s :method=%s :authority=%s :path=%s",
+		req := fmt.Sprintf("req id=%d:%d %s :method=%s :authority=%s :path=%s",
 			ev.RequestInit.Id.Base,
 			ev.RequestInit.Id.Stream,
 			flow,
@@ -252,6 +255,10 @@ func renderTapEvent(event *common.TapEvent) string {
 			ev.RequestInit.Authority,
 			ev.RequestInit.Path,
 		)
+		if fault := ev.RequestInit.GetFault(); fault != "" {
+			req = fmt.Sprintf("%s fault=%s", req, fault)
+		}
+		return req
 	case *common.TapEvent_Http_ResponseInit_:
 		return fmt.Sprintf("rsp id=%d:%d %s :status=%d latency=%dµs",
 			ev.ResponseInit.Id.Base,
@@ -217,6 +217,52 @@ func TestEventToString(t *testing.T) {
 		}
 	})
 
+	t.Run("Includes the source's TLS identity", func(t *testing.T) {
+		event := toTapEvent(&common.TapEvent_Http{
+			Event: &common.TapEvent_Http_RequestInit_{
+				RequestInit: &common.TapEvent_Http_RequestInit{
+					Method: &common.HttpMethod{
+						Type: &common.HttpMethod_Registered_{
+							Registered: common.HttpMethod_GET,
+						},
+					},
+					Authority: "hello.default:7777",
+					Path:      "/",
+				},
+			},
+		})
+		event.SourceTlsIdentity = "web.emojivoto.conduit-managed.conduit.svc.cluster.local"
+
+		expectedOutput := "req id=7:8 src=1.2.3.4:5555 dst=2.3.4.5:6666 tls=web.emojivoto.conduit-managed.conduit.svc.cluster.local :method=GET :authority=hello.default:7777 :path=/"
+		output := renderTapEvent(event)
+		if output != expectedOutput {
+			t.Fatalf("Expecting command output to be [%s], got [%s]", expectedOutput, output)
+		}
+	})
+
+	t.Run("Includes the fault injected into the request", func(t *testing.T) {
+		event := toTapEvent(&common.TapEvent_Http{
+			Event: &common.TapEvent_Http_RequestInit_{
+				RequestInit: &common.TapEvent_Http_RequestInit{
+					Method: &common.HttpMethod{
+						Type: &common.HttpMethod_Registered_{
+							Registered: common.HttpMethod_GET,
+						},
+					},
+					Authority: "hello.default:7777",
+					Path:      "/",
+					Fault:     "abort",
+				},
+			},
+		})
+
+		expectedOutput := "req id=7:8 src=1.2.3.4:5555 dst=2.3.4.5:6666 :method=GET :authority=hello.default:7777 :path=/ fault=abort"
+		output := renderTapEvent(event)
+		if output != expectedOutput {
+			t.Fatalf("Expecting command output to be [%s], got [%s]", expectedOutput, output)
+		}
+	})
+
 	t.Run("Converts HTTP response init event to string", func(t *testing.T) {
 		event := toTapEvent(&common.TapEvent_Http{
 			Event: &common.TapEvent_Http_ResponseInit_{
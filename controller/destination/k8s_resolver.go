@@ -3,6 +3,7 @@ package destination
 import (
 	"errors"
 	"fmt"
+	"net"
 	"reflect"
 	"regexp"
 	"strings"
@@ -30,6 +31,12 @@ func (s *serviceId) String() string {
 }
 
 func (k *k8sResolver) canResolve(host string, port int) (bool, error) {
+	// Any IP address can be resolved, though only a service's cluster IP
+	// resolves to any endpoints.
+	if net.ParseIP(host) != nil {
+		return true, nil
+	}
+
 	id, err := k.localKubernetesServiceIdFromDNSName(host)
 	if err != nil {
 		return false, err
@@ -39,6 +46,10 @@ func (k *k8sResolver) canResolve(host string, port int) (bool, error) {
 }
 
 func (k *k8sResolver) streamResolution(host string, port int, listener updateListener) error {
+	if net.ParseIP(host) != nil {
+		return k.streamClusterIPResolution(host, port, listener)
+	}
+
 	id, err := k.localKubernetesServiceIdFromDNSName(host)
 	if err != nil {
 		log.Error(err)
@@ -56,6 +67,32 @@ func (k *k8sResolver) streamResolution(host string, port int, listener updateLis
 	return k.resolveKubernetesService(id.String(), port, listener)
 }
 
+// streamClusterIPResolution resolves the service that has been allocated the
+// cluster IP `ip`. Proxies look up the original destinations of opaque TCP
+// connections this way. An address that isn't a cluster IP is reported as not
+// existing, so that the proxy connects to it directly.
+func (k *k8sResolver) streamClusterIPResolution(ip string, port int, listener updateListener) error {
+	svc, exists, err := k.endpointsWatcher.GetServiceByClusterIP(ip)
+	if err != nil {
+		log.Error(err)
+		return err
+	}
+
+	if !exists {
+		listener.NoEndpoints(false)
+		<-listener.Done()
+		return nil
+	}
+
+	id := &serviceId{
+		namespace: svc.Namespace,
+		name:      svc.Name,
+	}
+	listener.SetServiceId(id)
+
+	return k.resolveKubernetesService(id.String(), port, listener)
+}
+
 func (k *k8sResolver) resolveKubernetesService(id string, port int, listener updateListener) error {
 	k.endpointsWatcher.Subscribe(id, uint32(port), listener)
 
@@ -7,6 +7,7 @@ import (
 	"testing"
 
 	"k8s.io/api/core/v1"
+	metav1 "k8s.io/apimachinery/pkg/apis/meta/v1"
 
 	"github.com/runconduit/conduit/controller/k8s"
 )
@@ -24,6 +25,7 @@ func TestK8sResolver(t *testing.T) {
 			"name2.ns.svc.some.namespace.",
 			"name3.ns.svc.cluster.local",
 			"name4.ns.svc.cluster.local."}
+		resolvableServiceNames = append(resolvableServiceNames, "10.1.2.3", "fd00::1")
 		unresolvableServiceNames := []string{"name", "name.ns.svc.other.local"}
 
 		resolver := k8sResolver{k8sDNSZoneLabels: someKubernetesDNSZone}
@@ -119,6 +121,70 @@ func TestK8sResolver(t *testing.T) {
 			t.Fatalf("Expected listener [%v] to have been subscribed then unsubscribed to endpoint watcher, got: %+v", listener, mockEndpointsWatcher)
 		}
 	})
+
+	t.Run("subscribes the listener to resolve services by cluster IP", func(t *testing.T) {
+		mockEndpointsWatcher := &k8s.MockEndpointsWatcher{
+			ExistsToReturn: true,
+			ServiceToReturn: &v1.Service{
+				ObjectMeta: metav1.ObjectMeta{Name: "name1", Namespace: "ns"},
+				Spec:       v1.ServiceSpec{Type: v1.ServiceTypeClusterIP, ClusterIP: "10.1.2.3"},
+			},
+		}
+
+		resolver := k8sResolver{
+			k8sDNSZoneLabels: someKubernetesDNSZone,
+			endpointsWatcher: mockEndpointsWatcher,
+		}
+
+		listener, cancelFn := newCollectUpdateListener()
+
+		done := make(chan bool, 1)
+		go func() {
+			err := resolver.streamResolution("10.1.2.3", 3306, listener)
+			if err != nil {
+				t.Fatalf("Unexpected error: %v", err)
+			}
+			done <- true
+		}()
+		cancelFn()
+		<-done
+
+		if mockEndpointsWatcher.HostReceived != "10.1.2.3" {
+			t.Fatalf("Expected service to be looked up by cluster IP [10.1.2.3], got [%s]", mockEndpointsWatcher.HostReceived)
+		}
+		if mockEndpointsWatcher.ListenerSubscribed != listener || mockEndpointsWatcher.ListenerUnsubscribed != listener {
+			t.Fatalf("Expected listener [%v] to have been subscribed then unsubscribed to endpoint watcher, got: %+v", listener, mockEndpointsWatcher)
+		}
+	})
+
+	t.Run("reports addresses that aren't cluster IPs as not existing", func(t *testing.T) {
+		mockEndpointsWatcher := &k8s.MockEndpointsWatcher{ExistsToReturn: false}
+
+		resolver := k8sResolver{
+			k8sDNSZoneLabels: someKubernetesDNSZone,
+			endpointsWatcher: mockEndpointsWatcher,
+		}
+
+		listener, cancelFn := newCollectUpdateListener()
+
+		done := make(chan bool, 1)
+		go func() {
+			err := resolver.streamResolution("10.9.9.9", 3306, listener)
+			if err != nil {
+				t.Fatalf("Unexpected error: %v", err)
+			}
+			done <- true
+		}()
+		cancelFn()
+		<-done
+
+		if mockEndpointsWatcher.ListenerSubscribed != nil {
+			t.Fatalf("Expected listener not to be subscribed, got: %+v", mockEndpointsWatcher)
+		}
+		if !reflect.DeepEqual(listener.noEndpoints, []bool{false}) {
+			t.Fatalf("Expected the address to be reported as not existing, got: %v", listener.noEndpoints)
+		}
+	})
 }
 
 func TestLocalKubernetesServiceIdFromDNSName(t *testing.T) {
@@ -8,9 +8,10 @@ import (
 
 // implements the updateListener interface
 type collectUpdateListener struct {
-	added   []common.TcpAddress
-	removed []common.TcpAddress
-	context context.Context
+	added       []common.TcpAddress
+	removed     []common.TcpAddress
+	noEndpoints []bool
+	context     context.Context
 }
 
 func (c *collectUpdateListener) Update(add []common.TcpAddress, remove []common.TcpAddress) {
@@ -22,7 +23,9 @@ func (c *collectUpdateListener) Done() <-chan struct{} {
 	return c.context.Done()
 }
 
-func (c *collectUpdateListener) NoEndpoints(exists bool) {}
+func (c *collectUpdateListener) NoEndpoints(exists bool) {
+	c.noEndpoints = append(c.noEndpoints, exists)
+}
 
 func (c *collectUpdateListener) SetServiceId(id *serviceId) {}
 
@@ -638,6 +638,8 @@ type TapEvent struct {
 	Source          *TcpAddress            `protobuf:"bytes,1,opt,name=source" json:"source,omitempty"`
 	Destination     *TcpAddress            `protobuf:"bytes,2,opt,name=destination" json:"destination,omitempty"`
 	DestinationMeta *TapEvent_EndpointMeta `protobuf:"bytes,4,opt,name=destination_meta,json=destinationMeta" json:"destination_meta,omitempty"`
+	// The identity the source authenticated with, if it connected over TLS.
+	SourceTlsIdentity string `protobuf:"bytes,5,opt,name=source_tls_identity,json=sourceTlsIdentity" json:"source_tls_identity,omitempty"`
 	// Types that are valid to be assigned to Event:
 	//	*TapEvent_Http_
 	Event isTapEvent_Event `protobuf_oneof:"event"`
@@ -684,6 +686,13 @@ func (m *TapEvent) GetDestinationMeta() *TapEvent_EndpointMeta {
 	return nil
 }
 
+func (m *TapEvent) GetSourceTlsIdentity() string {
+	if m != nil {
+		return m.SourceTlsIdentity
+	}
+	return ""
+}
+
 func (m *TapEvent) GetHttp() *TapEvent_Http {
 	if x, ok := m.GetEvent().(*TapEvent_Http_); ok {
 		return x.Http
@@ -944,6 +953,9 @@ type TapEvent_Http_RequestInit struct {
 	Scheme    *Scheme                 `protobuf:"bytes,3,opt,name=scheme" json:"scheme,omitempty"`
 	Authority string                  `protobuf:"bytes,4,opt,name=authority" json:"authority,omitempty"`
 	Path      string                  `protobuf:"bytes,5,opt,name=path" json:"path,omitempty"`
+	// The kind of fault that the proxy injected into the request, if any:
+	// `delay`, `abort`, or `reset`.
+	Fault string `protobuf:"bytes,6,opt,name=fault" json:"fault,omitempty"`
 }
 
 func (m *TapEvent_Http_RequestInit) Reset()                    { *m = TapEvent_Http_RequestInit{} }
@@ -986,6 +998,13 @@ func (m *TapEvent_Http_RequestInit) GetPath() string {
 	return ""
 }
 
+func (m *TapEvent_Http_RequestInit) GetFault() string {
+	if m != nil {
+		return m.Fault
+	}
+	return ""
+}
+
 type TapEvent_Http_ResponseInit struct {
 	Id               *TapEvent_Http_StreamId   `protobuf:"bytes,1,opt,name=id" json:"id,omitempty"`
 	SinceRequestInit *google_protobuf.Duration `protobuf:"bytes,2,opt,name=since_request_init,json=sinceRequestInit" json:"since_request_init,omitempty"`
@@ -1091,66 +1110,67 @@ func init() {
 func init() { proto.RegisterFile("common/common.proto", fileDescriptor0) }
 
 var fileDescriptor0 = []byte{
-	// 962 bytes of a gzipped FileDescriptorProto
-	0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0xff, 0xc4, 0x55, 0xcf, 0x6e, 0xe3, 0xb6,
-	0x13, 0xb6, 0xfe, 0x58, 0x8e, 0x47, 0x4e, 0x7e, 0xfa, 0x31, 0xc1, 0x22, 0x35, 0xba, 0x6d, 0xd6,
-	0x68, 0x8a, 0x24, 0x07, 0xa5, 0xf5, 0x16, 0x46, 0xb7, 0xe8, 0x25, 0x76, 0x84, 0xd8, 0xe8, 0x6e,
-	0xa2, 0xd2, 0xea, 0xd9, 0x90, 0x2d, 0x6e, 0x2c, 0xd4, 0x16, 0x55, 0x92, 0x0e, 0xe0, 0xf7, 0xe8,
-	0xad, 0x40, 0xaf, 0xbd, 0xf6, 0x7d, 0xfa, 0x18, 0x45, 0xef, 0x05, 0x29, 0x5a, 0x56, 0xb2, 0xbb,
-	0xd9, 0x45, 0x7b, 0xe8, 0x49, 0x9c, 0xe1, 0x37, 0x9f, 0xbe, 0xe1, 0x70, 0x86, 0xb0, 0x3f, 0xa3,
-	0xcb, 0x25, 0xcd, 0xce, 0x8b, 0x8f, 0x9f, 0x33, 0x2a, 0x28, 0xda, 0x9b, 0xd1, 0x2c, 0x59, 0xa5,
-	0xc2, 0x2f, 0xbc, 0xed, 0x4f, 0x6e, 0x29, 0xbd, 0x5d, 0x90, 0x73, 0xb5, 0x3b, 0x5d, 0xbd, 0x3e,
-	0x4f, 0x56, 0x2c, 0x16, 0xe9, 0x06, 0xdf, 0xf9, 0xd3, 0x00, 0x18, 0x0a, 0x91, 0xbf, 0x22, 0x62,
-	0x4e, 0x13, 0x74, 0x05, 0xc0, 0xc8, 0x6d, 0xca, 0x05, 0x61, 0x24, 0x39, 0x34, 0x8e, 0x8c, 0x93,
-	0xbd, 0xee, 0xb1, 0x7f, 0x9f, 0xd3, 0xdf, 0xe2, 0x7d, 0x5c, 0x82, 0x87, 0x35, 0x5c, 0x09, 0x45,
-	0x9f, 0x41, 0x6b, 0x95, 0x55, 0xa8, 0xcc, 0x23, 0xe3, 0xa4, 0x39, 0xac, 0xe1, 0x7b, 0xde, 0x4e,
-	0x06, 0xb0, 0x65, 0x40, 0x0d, 0xb0, 0xae, 0x82, 0xc8, 0xab, 0xa1, 0x1d, 0xb0, 0xc3, 0x9b, 0x71,
-	0xe4, 0x19, 0xd2, 0x15, 0xfe, 0x10, 0x79, 0x26, 0x02, 0x70, 0x2e, 0x83, 0x97, 0x41, 0x14, 0x78,
-	0x16, 0x6a, 0x42, 0x3d, 0xbc, 0x88, 0x06, 0x43, 0xcf, 0x46, 0x2e, 0x34, 0x6e, 0xc2, 0x68, 0x74,
-	0x73, 0x3d, 0xf6, 0xea, 0xd2, 0x18, 0xdc, 0x5c, 0x5f, 0x07, 0x83, 0xc8, 0x73, 0x24, 0xc7, 0x30,
-	0xb8, 0xb8, 0xf4, 0x1a, 0x12, 0x1e, 0xe1, 0x8b, 0x41, 0xe0, 0xed, 0xf4, 0x1d, 0xb0, 0xc5, 0x3a,
-	0x27, 0x9d, 0x5f, 0x0d, 0x70, 0xc6, 0xb3, 0x39, 0x59, 0x12, 0x34, 0x78, 0x4b, 0xc6, 0xcf, 0x1e,
-	0x66, 0x5c, 0x60, 0xff, 0x6d, 0xb6, 0xcf, 0xee, 0x65, 0x2b, 0x05, 0x46, 0x51, 0xe8, 0xd5, 0xa4,
-	0x40, 0xb9, 0x1a, 0x7b, 0x46, 0x29, 0x70, 0x0c, 0xcd, 0x51, 0x78, 0x91, 0x24, 0x8c, 0x70, 0x8e,
-	0x0e, 0xc0, 0x4e, 0xf3, 0xbb, 0xaf, 0x94, 0xb8, 0xc6, 0xb0, 0x86, 0x95, 0x85, 0xce, 0x94, 0xb7,
-	0xa7, 0xfe, 0xe5, 0x76, 0x0f, 0x1e, 0x4a, 0x1e, 0x85, 0x77, 0x3d, 0x8d, 0xed, 0xf5, 0x6d, 0x30,
-	0xd3, 0xbc, 0xf3, 0x05, 0xd8, 0xd2, 0x8b, 0x0e, 0xa0, 0xfe, 0x3a, 0x65, 0x5c, 0x28, 0x42, 0x07,
-	0x17, 0x06, 0x42, 0x60, 0x2f, 0x62, 0x2e, 0x14, 0x9f, 0x83, 0xd5, 0xba, 0xf3, 0x1d, 0x40, 0x34,
-	0xcb, 0x37, 0x3a, 0x4e, 0x25, 0x8b, 0x0a, 0x72, 0xbb, 0x1f, 0xbd, 0xf9, 0x3f, 0x0d, 0xc3, 0x66,
-	0x9a, 0x4b, 0xb2, 0x9c, 0xb2, 0x82, 0x6c, 0x17, 0xab, 0x75, 0xe7, 0x05, 0xb8, 0x97, 0x84, 0x8b,
-	0x34, 0x53, 0xf7, 0x0f, 0x3d, 0x01, 0x87, 0xab, 0x63, 0x55, 0x8c, 0x4d, 0xac, 0x2d, 0x15, 0x1a,
-	0x8b, 0x79, 0x71, 0x86, 0x58, 0xad, 0x3b, 0x09, 0x58, 0x01, 0xe5, 0xe8, 0x0c, 0xbc, 0x5b, 0x96,
-	0xcf, 0x26, 0x5c, 0xc4, 0x62, 0xc5, 0x27, 0x33, 0x9a, 0x14, 0xc1, 0xbb, 0xc3, 0x1a, 0xde, 0x93,
-	0x3b, 0x63, 0xb5, 0x31, 0xa0, 0x09, 0x91, 0x58, 0x46, 0x38, 0x11, 0x13, 0xc2, 0x18, 0x65, 0x05,
-	0xd6, 0xdc, 0x60, 0xd5, 0x4e, 0x20, 0x37, 0x24, 0xb6, 0x5f, 0x07, 0x8b, 0x64, 0x49, 0xe7, 0x17,
-	0x17, 0x76, 0xa2, 0x38, 0x0f, 0xee, 0x48, 0x26, 0x50, 0x17, 0x1c, 0x4e, 0x57, 0x6c, 0x46, 0x74,
-	0xc2, 0xed, 0x87, 0x09, 0x6f, 0x0f, 0x06, 0x6b, 0x24, 0xfa, 0x16, 0xdc, 0x64, 0x9b, 0xa1, 0xae,
-	0xcc, 0x63, 0x81, 0x55, 0x38, 0x0a, 0xc1, 0xab, 0x98, 0x93, 0x25, 0x11, 0xf1, 0xa1, 0xad, 0x28,
-	0xde, 0xe8, 0xc0, 0x8d, 0x4a, 0x3f, 0xc8, 0x92, 0x9c, 0xa6, 0x99, 0x78, 0x45, 0x44, 0x8c, 0xff,
-	0x57, 0x09, 0x97, 0x0e, 0xf4, 0x1c, 0xec, 0xb9, 0x10, 0xf9, 0xa1, 0xa5, 0x58, 0x9e, 0xbe, 0x93,
-	0x45, 0x36, 0xb4, 0xbc, 0x2b, 0x12, 0xdc, 0xfe, 0xd9, 0x80, 0x56, 0x95, 0x16, 0x8d, 0xc0, 0x59,
-	0xc4, 0x53, 0xb2, 0xe0, 0x87, 0xc6, 0x91, 0x75, 0xe2, 0x76, 0xbf, 0xfc, 0x20, 0x35, 0xfe, 0x4b,
-	0x15, 0x13, 0x64, 0x82, 0xad, 0xb1, 0x26, 0x68, 0xbf, 0x00, 0xb7, 0xe2, 0x46, 0x1e, 0x58, 0x3f,
-	0x92, 0xb5, 0xae, 0xbf, 0x5c, 0xca, 0xab, 0x79, 0x17, 0x2f, 0x56, 0x44, 0x57, 0xbf, 0x30, 0xbe,
-	0x31, 0xbf, 0x36, 0xda, 0x7f, 0x35, 0xc0, 0x96, 0x3a, 0xd1, 0x35, 0xb4, 0x18, 0xf9, 0x69, 0x45,
-	0xb8, 0x98, 0xa4, 0x59, 0x2a, 0x74, 0x79, 0x4e, 0x1f, 0x4d, 0xce, 0xc7, 0x45, 0xc4, 0x28, 0x4b,
-	0xc5, 0xb0, 0x86, 0x5d, 0xb6, 0x35, 0xd1, 0xf7, 0xb0, 0xcb, 0x08, 0xcf, 0x69, 0xc6, 0x49, 0x41,
-	0x58, 0x94, 0xed, 0xec, 0x7d, 0x84, 0x45, 0x88, 0x66, 0x6c, 0xb1, 0x8a, 0x5d, 0x48, 0xd4, 0x94,
-	0x24, 0x4b, 0xf4, 0xf9, 0x9f, 0x7e, 0x18, 0x63, 0x90, 0x25, 0x85, 0xc4, 0xd2, 0x6c, 0xf7, 0x60,
-	0x67, 0x2c, 0x18, 0x89, 0x97, 0xa3, 0x44, 0xb6, 0xc7, 0x34, 0xe6, 0xfa, 0xde, 0x63, 0xb5, 0x56,
-	0xad, 0xa4, 0xf6, 0x95, 0x76, 0x1b, 0x6b, 0xab, 0xfd, 0x87, 0x01, 0x6e, 0x25, 0x73, 0xd4, 0x03,
-	0x33, 0x4d, 0xf4, 0x81, 0x7d, 0xfe, 0xb8, 0x9a, 0xcd, 0xff, 0xb0, 0x99, 0x26, 0xb2, 0x17, 0x96,
-	0x6a, 0xde, 0xbf, 0xeb, 0x4a, 0x6f, 0x5f, 0x04, 0xac, 0x91, 0xc8, 0x2f, 0xdb, 0xbb, 0xc8, 0xfe,
-	0xc9, 0xdb, 0x67, 0x6a, 0xd9, 0xf6, 0x1f, 0x43, 0x33, 0x5e, 0x89, 0x39, 0x65, 0xa9, 0x58, 0xab,
-	0x6b, 0xdf, 0xc4, 0x5b, 0x47, 0x39, 0x14, 0xea, 0xdb, 0xa1, 0xd0, 0xfe, 0xdd, 0x80, 0x56, 0xb5,
-	0x0c, 0xff, 0x38, 0xbd, 0x2b, 0x40, 0x3c, 0xcd, 0x66, 0x64, 0x72, 0xef, 0x5e, 0x99, 0x7a, 0xce,
-	0x15, 0x0f, 0xa8, 0xbf, 0x79, 0x40, 0xfd, 0x4b, 0xfd, 0x80, 0x62, 0x4f, 0x05, 0x55, 0xcf, 0xf7,
-	0x53, 0x70, 0x65, 0x0b, 0xe9, 0xf9, 0xa4, 0x12, 0xdf, 0xc5, 0x20, 0x5d, 0xc5, 0x60, 0x6a, 0xff,
-	0x66, 0xca, 0x82, 0x94, 0x85, 0xfd, 0xef, 0x15, 0x8f, 0x60, 0x7f, 0x43, 0x54, 0x6d, 0x01, 0xeb,
-	0x7d, 0x4c, 0xff, 0xd7, 0x4c, 0x95, 0xd3, 0x3f, 0x86, 0xbd, 0x92, 0x64, 0xba, 0x16, 0x84, 0xab,
-	0x2a, 0xda, 0xb8, 0xec, 0xae, 0xbe, 0x74, 0xa2, 0x63, 0xb0, 0x08, 0xe5, 0xaa, 0x90, 0x6e, 0x77,
-	0xff, 0x61, 0xce, 0x01, 0xe5, 0x58, 0xee, 0xf7, 0x1b, 0x50, 0x27, 0x32, 0xf9, 0x72, 0x71, 0xf6,
-	0x14, 0x76, 0x42, 0xa9, 0x63, 0x46, 0x17, 0x95, 0xb7, 0xb3, 0x01, 0x56, 0x34, 0x08, 0x3d, 0x63,
-	0xea, 0x28, 0x91, 0xcf, 0xff, 0x0e, 0x00, 0x00, 0xff, 0xff, 0xa8, 0x49, 0x72, 0x40, 0x16, 0x09,
-	0x00, 0x00,
+	// 989 bytes of a gzipped FileDescriptorProto
+	0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0xff, 0xc5, 0x56, 0xcd, 0x6e, 0xdb, 0x46,
+	0x10, 0x0e, 0x25, 0x8a, 0x92, 0x86, 0xb6, 0xcb, 0xac, 0x83, 0x40, 0x25, 0x9a, 0xb6, 0x11, 0x9a,
+	0xa2, 0xf6, 0x81, 0x6e, 0x95, 0x42, 0x48, 0x8b, 0x5e, 0x2c, 0x99, 0xb0, 0x85, 0xa6, 0x36, 0x4b,
+	0xb1, 0x67, 0x81, 0x12, 0x37, 0x36, 0x51, 0x89, 0x64, 0xc9, 0xa5, 0x01, 0x3d, 0x43, 0xaf, 0x39,
+	0xe7, 0x9a, 0x6b, 0x5f, 0xa9, 0x8f, 0x50, 0xf4, 0x05, 0x3a, 0xfb, 0x23, 0x8a, 0x76, 0x12, 0x27,
+	0x68, 0x0f, 0x3d, 0x69, 0x67, 0x76, 0xe6, 0xe3, 0x37, 0xbf, 0x2b, 0xd8, 0x5f, 0xa4, 0xab, 0x55,
+	0x9a, 0x1c, 0xc9, 0x1f, 0x27, 0xcb, 0x53, 0x96, 0x92, 0xbd, 0x45, 0x9a, 0x44, 0x65, 0xcc, 0x1c,
+	0xa9, 0xb5, 0x3f, 0xbd, 0x4c, 0xd3, 0xcb, 0x25, 0x3d, 0x12, 0xb7, 0xf3, 0xf2, 0xc5, 0x51, 0x54,
+	0xe6, 0x21, 0x8b, 0x37, 0xf6, 0xfd, 0xbf, 0x35, 0x80, 0x33, 0xc6, 0xb2, 0x9f, 0x28, 0xbb, 0x4a,
+	0x23, 0x72, 0x0a, 0x90, 0xd3, 0xcb, 0xb8, 0x60, 0x34, 0xa7, 0x51, 0x4f, 0xfb, 0x5c, 0xfb, 0x6a,
+	0x6f, 0xf0, 0xc4, 0xb9, 0x89, 0xe9, 0x6c, 0xed, 0x1d, 0xbf, 0x32, 0x3e, 0xbb, 0xe7, 0xd7, 0x5c,
+	0xc9, 0x17, 0xb0, 0x53, 0x26, 0x35, 0xa8, 0x06, 0x42, 0x75, 0xd1, 0xe6, 0x86, 0xb6, 0x9f, 0x00,
+	0x6c, 0x11, 0x48, 0x1b, 0x9a, 0xa7, 0x6e, 0x60, 0xdd, 0x23, 0x1d, 0xd0, 0xbd, 0x8b, 0x69, 0x60,
+	0x69, 0x5c, 0xe5, 0xfd, 0x12, 0x58, 0x0d, 0x02, 0x60, 0x9c, 0xb8, 0xcf, 0xdd, 0xc0, 0xb5, 0x9a,
+	0xa4, 0x0b, 0x2d, 0xef, 0x38, 0x18, 0x9f, 0x59, 0x3a, 0x31, 0xa1, 0x7d, 0xe1, 0x05, 0x93, 0x8b,
+	0xf3, 0xa9, 0xd5, 0xe2, 0xc2, 0xf8, 0xe2, 0xfc, 0xdc, 0x1d, 0x07, 0x96, 0xc1, 0x31, 0xce, 0xdc,
+	0xe3, 0x13, 0xab, 0xcd, 0xcd, 0x03, 0xff, 0x78, 0xec, 0x5a, 0x9d, 0x91, 0x01, 0x3a, 0x5b, 0x67,
+	0xb4, 0xff, 0x4a, 0x03, 0x63, 0xba, 0xb8, 0xa2, 0x2b, 0x4a, 0xc6, 0x6f, 0x89, 0xf8, 0xf1, 0xed,
+	0x88, 0xa5, 0xed, 0x7f, 0x8d, 0xf6, 0xf1, 0x8d, 0x68, 0x39, 0xc1, 0x20, 0xf0, 0x30, 0x5c, 0x24,
+	0xc8, 0x4f, 0x53, 0x4b, 0xab, 0x08, 0x4e, 0xa1, 0x3b, 0xf1, 0x8e, 0xa3, 0x28, 0xa7, 0x45, 0x41,
+	0x1e, 0x80, 0x1e, 0x67, 0xd7, 0xdf, 0x0a, 0x72, 0x6d, 0x44, 0x15, 0x12, 0x39, 0x14, 0xda, 0xa1,
+	0xf8, 0x96, 0x39, 0x78, 0x70, 0x9b, 0xf2, 0xc4, 0xbb, 0x1e, 0x2a, 0xdb, 0xe1, 0x48, 0x87, 0x46,
+	0x9c, 0xf5, 0xbf, 0x06, 0x9d, 0x6b, 0x11, 0xaf, 0xf5, 0x22, 0xce, 0x0b, 0x26, 0x00, 0x0d, 0x5f,
+	0x0a, 0x84, 0x80, 0xbe, 0x0c, 0x51, 0xd9, 0x10, 0x4a, 0x71, 0xee, 0xff, 0x08, 0x10, 0x2c, 0xb2,
+	0x0d, 0x8f, 0x03, 0x8e, 0x22, 0x9c, 0xcc, 0xc1, 0xc7, 0x6f, 0x7e, 0x4f, 0x99, 0xf9, 0x68, 0xc4,
+	0xc1, 0xb2, 0x34, 0x97, 0x60, 0xbb, 0xbe, 0x38, 0xf7, 0xbf, 0x03, 0xf3, 0x84, 0x16, 0x2c, 0x4e,
+	0x44, 0xff, 0x91, 0x87, 0x60, 0x14, 0x22, 0xad, 0x02, 0xb1, 0xeb, 0x2b, 0x49, 0xb8, 0x86, 0xec,
+	0x4a, 0xe6, 0xd0, 0x17, 0xe7, 0x7e, 0x04, 0x4d, 0x37, 0x2d, 0x30, 0x64, 0xeb, 0x32, 0xcf, 0x16,
+	0xb3, 0x82, 0x85, 0xac, 0x2c, 0x66, 0x8b, 0x34, 0x92, 0xce, 0xbb, 0x18, 0xe8, 0x1e, 0xbf, 0x99,
+	0x8a, 0x8b, 0x31, 0xea, 0xb9, 0x2d, 0xb2, 0xa1, 0x6c, 0x46, 0xf3, 0x3c, 0xcd, 0xa5, 0x6d, 0x63,
+	0x63, 0x2b, 0x6e, 0x5c, 0x7e, 0xc1, 0x6d, 0x47, 0x2d, 0x68, 0xd2, 0x24, 0xea, 0xff, 0x69, 0x42,
+	0x27, 0x08, 0x33, 0xf7, 0x9a, 0x26, 0x8c, 0x0c, 0x90, 0x5e, 0x5a, 0xe6, 0x0b, 0xaa, 0x02, 0xb6,
+	0x6f, 0x07, 0xbc, 0x4d, 0x8c, 0xaf, 0x2c, 0xc9, 0x0f, 0x60, 0x46, 0xdb, 0x08, 0x55, 0x65, 0xee,
+	0x72, 0xac, 0x9b, 0x13, 0x0f, 0xac, 0x9a, 0x38, 0x5b, 0x51, 0x16, 0xf6, 0x74, 0x01, 0xf1, 0xc6,
+	0x04, 0x6e, 0x58, 0x3a, 0x6e, 0x12, 0x65, 0x69, 0x9c, 0x30, 0x1c, 0xc7, 0xd0, 0xff, 0xa8, 0xe6,
+	0xce, 0x15, 0xc4, 0x81, 0x7d, 0xc9, 0x6c, 0xc6, 0x96, 0xc5, 0x2c, 0x8e, 0xd0, 0x23, 0x66, 0xeb,
+	0x5e, 0x4b, 0x64, 0xf6, 0xbe, 0xbc, 0x0a, 0x96, 0xc5, 0x44, 0x5d, 0x90, 0xa7, 0xa0, 0x5f, 0xe1,
+	0x6c, 0xf7, 0x9a, 0xe2, 0xab, 0x8f, 0xde, 0xf9, 0x55, 0xbe, 0x00, 0x78, 0x6f, 0x71, 0x63, 0xfb,
+	0xa5, 0x06, 0x3b, 0x75, 0x1a, 0x64, 0x02, 0xc6, 0x32, 0x9c, 0xd3, 0x65, 0x81, 0x99, 0x6b, 0x22,
+	0xce, 0x37, 0x1f, 0xc4, 0xde, 0x79, 0x2e, 0x7c, 0xdc, 0x84, 0xe5, 0x6b, 0x5f, 0x01, 0xd8, 0xd8,
+	0x32, 0x35, 0x35, 0xb1, 0xa0, 0xf9, 0x2b, 0x5d, 0xab, 0x7e, 0xe1, 0x47, 0xde, 0xca, 0xd7, 0xe1,
+	0xb2, 0xa4, 0xaa, 0x5b, 0xa4, 0xf0, 0x7d, 0xe3, 0x99, 0x66, 0xff, 0xce, 0xe7, 0x0b, 0xf9, 0x91,
+	0x73, 0xd8, 0xc9, 0xe9, 0x6f, 0x25, 0xa6, 0x66, 0x16, 0x27, 0x31, 0x53, 0xe5, 0x3c, 0xb8, 0x33,
+	0x38, 0x9c, 0x74, 0xe1, 0x31, 0x41, 0x07, 0x0c, 0xd4, 0xcc, 0xb7, 0x22, 0xf9, 0x19, 0x76, 0xb1,
+	0x76, 0x59, 0x9a, 0x14, 0x54, 0x02, 0xca, 0x32, 0x1f, 0xbe, 0x0f, 0x50, 0xba, 0x28, 0xc4, 0x9d,
+	0xbc, 0x26, 0x4b, 0x8a, 0x0a, 0x12, 0x1b, 0x51, 0xe5, 0xff, 0xe0, 0xc3, 0x10, 0x31, 0x89, 0x92,
+	0x62, 0x25, 0xda, 0x43, 0xe8, 0x4c, 0x59, 0x4e, 0xc3, 0xd5, 0x24, 0xe2, 0xe3, 0x34, 0x0f, 0x0b,
+	0x35, 0x27, 0xbe, 0x38, 0x8b, 0xd1, 0x13, 0xf7, 0x82, 0xbb, 0xee, 0x2b, 0xc9, 0xfe, 0x4b, 0x03,
+	0xb3, 0x16, 0x39, 0x19, 0xe2, 0xc0, 0x47, 0x2a, 0x61, 0x5f, 0xde, 0xcd, 0x66, 0xf3, 0x3d, 0x9c,
+	0xfe, 0x88, 0xcf, 0xce, 0x4a, 0xbc, 0x0f, 0xef, 0x1a, 0x81, 0xed, 0x0b, 0xe2, 0x2b, 0x4b, 0xec,
+	0xd5, 0xcd, 0x3a, 0x90, 0xd1, 0x3f, 0x7c, 0xfb, 0x0e, 0xae, 0xd6, 0xc4, 0x27, 0xd0, 0x0d, 0x4b,
+	0xf4, 0xcc, 0x79, 0x47, 0xeb, 0xa2, 0xfa, 0x5b, 0x45, 0xb5, 0x44, 0x5a, 0xdb, 0x25, 0x22, 0xd6,
+	0x5e, 0x58, 0x2e, 0x59, 0xcf, 0x90, 0xbd, 0x22, 0x04, 0xfb, 0x0f, 0x6c, 0xdf, 0x7a, 0x71, 0xfe,
+	0x75, 0xd0, 0xa7, 0x40, 0x8a, 0x38, 0xc1, 0x59, 0xbb, 0xd1, 0x6d, 0x0d, 0xb5, 0x2d, 0xe5, 0x33,
+	0xec, 0x6c, 0x9e, 0x61, 0xe7, 0x44, 0x3d, 0xc3, 0xbe, 0x25, 0x9c, 0xea, 0x59, 0xff, 0x0c, 0x4c,
+	0x3e, 0x58, 0x6a, 0xcb, 0x89, 0x74, 0xec, 0xfa, 0xc0, 0x55, 0x72, 0xbd, 0xd9, 0xaf, 0x1b, 0xbc,
+	0x4c, 0x55, 0xb9, 0xff, 0x7f, 0xc6, 0x13, 0xdc, 0x33, 0x0a, 0xa8, 0x3e, 0x18, 0xcd, 0xf7, 0x21,
+	0xdd, 0x57, 0x48, 0xb5, 0xec, 0x3f, 0x81, 0xbd, 0x0a, 0x64, 0xbe, 0x66, 0xb4, 0x10, 0xb5, 0xd5,
+	0xfd, 0x6a, 0xe6, 0x46, 0x5c, 0x89, 0x66, 0x4d, 0x9a, 0x16, 0xa2, 0xbc, 0xe6, 0x60, 0xff, 0x76,
+	0xcc, 0xf8, 0x56, 0xf8, 0xfc, 0x7e, 0xd4, 0x86, 0x16, 0xe5, 0xc1, 0x57, 0x87, 0xc3, 0x47, 0xd0,
+	0xf1, 0x38, 0x8f, 0x45, 0xba, 0xac, 0xbd, 0xc0, 0xf8, 0x37, 0x23, 0x18, 0x7b, 0x96, 0x36, 0x37,
+	0x04, 0xc9, 0xa7, 0xff, 0x00, 0xa1, 0xe7, 0x65, 0xd9, 0x5c, 0x09, 0x00, 0x00,
 }
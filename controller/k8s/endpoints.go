@@ -18,6 +18,7 @@ import (
 const (
 	kubeSystem       = "kube-system"
 	endpointResource = "endpoints"
+	clusterIPIndex   = "clusterIP"
 )
 
 type EndpointsListener interface {
@@ -29,6 +30,7 @@ type EndpointsListener interface {
 
 type EndpointsWatcher interface {
 	GetService(service string) (*v1.Service, bool, error)
+	GetServiceByClusterIP(ip string) (*v1.Service, bool, error)
 	Subscribe(service string, port uint32, listener EndpointsListener) error
 	Unsubscribe(service string, port uint32, listener EndpointsListener) error
 	Run() error
@@ -147,13 +149,25 @@ func (e *endpointsWatcher) GetService(service string) (*v1.Service, bool, error)
 	return obj.(*v1.Service), exists, err
 }
 
+// GetServiceByClusterIP returns the service that has been allocated the
+// cluster IP `ip`, if there is one.
+func (e *endpointsWatcher) GetServiceByClusterIP(ip string) (*v1.Service, bool, error) {
+	objs, err := e.serviceInformer.indexer.ByIndex(clusterIPIndex, ip)
+	if err != nil || len(objs) == 0 {
+		return nil, false, err
+	}
+	return objs[0].(*v1.Service), true, nil
+}
+
 /// informer ///
 
 // Watches a Kubernetes resource type
 type informer struct {
 	informer cache.Controller
 	store    cache.Store
-	stopCh   chan struct{}
+	// indexer is the same as store, for informers whose store is indexed.
+	indexer cache.Indexer
+	stopCh  chan struct{}
 }
 
 func (i *informer) run() error {
@@ -245,7 +259,7 @@ func newServiceInformer(clientset *kubernetes.Clientset, servicePorts *map[strin
 
 	serviceListWatcher := cache.NewListWatchFromClient(clientset.CoreV1().RESTClient(), "services", v1.NamespaceAll, fields.Everything())
 
-	store, inf := cache.NewInformer(
+	indexer, inf := cache.NewIndexerInformer(
 		serviceListWatcher,
 		&v1.Service{},
 		time.Duration(0),
@@ -283,17 +297,36 @@ func newServiceInformer(clientset *kubernetes.Clientset, servicePorts *map[strin
 				mutex.RUnlock()
 			},
 		},
+		cache.Indexers{clusterIPIndex: clusterIPIndexFunc},
 	)
 
 	stopCh := make(chan struct{})
 
 	return informer{
 		informer: inf,
-		store:    store,
+		store:    indexer,
+		indexer:  indexer,
 		stopCh:   stopCh,
 	}
 }
 
+// clusterIPIndexFunc indexes services by their cluster IPs. Headless services
+// and services in the kube-system namespace aren't indexed.
+func clusterIPIndexFunc(obj interface{}) ([]string, error) {
+	service, ok := obj.(*v1.Service)
+	if !ok {
+		return nil, fmt.Errorf("%v is not a Service", obj)
+	}
+	if service.Namespace == kubeSystem {
+		return nil, nil
+	}
+	ip := service.Spec.ClusterIP
+	if ip == "" || ip == v1.ClusterIPNone {
+		return nil, nil
+	}
+	return []string{ip}, nil
+}
+
 /// servicePort ///
 
 // servicePort represents a service along with a port number.  Multiple
@@ -15,6 +15,10 @@ func (m *MockEndpointsWatcher) GetService(service string) (*v1.Service, bool, er
 	m.HostReceived = service
 	return m.ServiceToReturn, m.ExistsToReturn, m.ErrToReturn
 }
+func (m *MockEndpointsWatcher) GetServiceByClusterIP(ip string) (*v1.Service, bool, error) {
+	m.HostReceived = ip
+	return m.ServiceToReturn, m.ExistsToReturn, m.ErrToReturn
+}
 func (m *MockEndpointsWatcher) Subscribe(service string, port uint32, listener EndpointsListener) error {
 	m.ListenerSubscribed = listener
 	return m.ErrToReturn
@@ -0,0 +1,298 @@
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures::{Async, Future, Poll, Stream};
+use futures::task::AtomicTask;
+use tokio_core::reactor::{Handle, Timeout};
+
+use backoff;
+use config::{self, OverLimit};
+use ctx;
+use telemetry;
+
+/// Limits how many of a listener's connections may be open at once.
+///
+/// Each connection that the listener accepts holds a `Permit` until it's
+/// closed.
+#[derive(Clone)]
+pub struct Limit(Arc<Shared>);
+
+/// Counts a connection against its listener's limit while it's open.
+pub struct Permit(Arc<Shared>);
+
+/// Accepts connections from a listener's stream of incoming connections,
+/// subject to the listener's connection limit, if it has one.
+///
+/// When the listener fails to accept a connection, e.g. because the process
+/// has run out of file descriptors, it waits before accepting again rather
+/// than failing, if it has a backoff policy.
+pub struct Accept<S> {
+    incoming: S,
+    limit: Option<Limit>,
+    backoff: Option<backoff::Policy>,
+    handle: Handle,
+    /// The number of times that accepting a connection has failed since a
+    /// connection was last accepted.
+    failures: u32,
+    timer: Option<Timeout>,
+}
+
+struct Shared {
+    config: config::ConnectionLimit,
+    open: AtomicUsize,
+    /// The accept task, which is notified when a connection closes while
+    /// it's paused at the limit.
+    task: AtomicTask,
+    sensors: telemetry::Sensors,
+    proxy: Arc<ctx::Proxy>,
+}
+
+// ===== impl Limit =====
+
+impl Limit {
+    pub fn new(
+        config: config::ConnectionLimit,
+        sensors: &telemetry::Sensors,
+        proxy: &Arc<ctx::Proxy>,
+    ) -> Self {
+        let shared = Shared {
+            config,
+            open: AtomicUsize::new(0),
+            task: AtomicTask::new(),
+            sensors: sensors.clone(),
+            proxy: proxy.clone(),
+        };
+        shared.record(0);
+        Limit(Arc::new(shared))
+    }
+
+    /// Returns true if the listener may accept another connection. Otherwise,
+    /// the current task is notified when one of its connections closes.
+    fn poll_capacity(&self) -> bool {
+        let shared = &self.0;
+        if shared.open.load(Ordering::Acquire) < shared.config.max {
+            return true;
+        }
+
+        shared.task.register();
+        // A connection may have closed before the task was registered.
+        shared.open.load(Ordering::Acquire) < shared.config.max
+    }
+
+    /// Counts an accepted connection against the limit, unless the limit has
+    /// been reached.
+    fn acquire(&self) -> Option<Permit> {
+        let shared = &self.0;
+        // Only the accept task opens connections, so the count can't grow
+        // between the check and the increment.
+        if shared.open.load(Ordering::Acquire) >= shared.config.max {
+            return None;
+        }
+
+        let open = shared.open.fetch_add(1, Ordering::AcqRel) + 1;
+        shared.record(open);
+        Some(Permit(self.0.clone()))
+    }
+}
+
+// ===== impl Permit =====
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let open = self.0.open.fetch_sub(1, Ordering::AcqRel) - 1;
+        self.0.record(open);
+        self.0.task.notify();
+    }
+}
+
+impl fmt::Debug for Permit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Permit")
+            .field("max", &self.0.config.max)
+            .finish()
+    }
+}
+
+// ===== impl Accept =====
+
+impl<S> Accept<S> {
+    pub fn new(
+        incoming: S,
+        limit: Option<Limit>,
+        backoff: Option<backoff::Policy>,
+        handle: &Handle,
+    ) -> Self {
+        Self {
+            incoming,
+            limit,
+            backoff,
+            handle: handle.clone(),
+            failures: 0,
+            timer: None,
+        }
+    }
+
+    fn back_off(&mut self, error: io::Error) -> Result<(), io::Error> {
+        let policy = match self.backoff {
+            Some(ref policy) => policy,
+            None => return Err(error),
+        };
+
+        self.failures = self.failures.saturating_add(1);
+        let backoff = policy.backoff_after(self.failures);
+        warn!("failed to accept connection: {}; waiting {:?}", error, backoff);
+        self.timer = Some(Timeout::new(backoff, &self.handle)?);
+        Ok(())
+    }
+}
+
+impl<S> Stream for Accept<S>
+where
+    S: Stream<Error = io::Error>,
+{
+    type Item = (S::Item, Option<Permit>);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            if let Some(ref mut timer) = self.timer {
+                if timer.poll().expect("accept backoff timer").is_not_ready() {
+                    return Ok(Async::NotReady);
+                }
+            }
+            self.timer = None;
+
+            // While a paused listener is at its limit, new connections wait
+            // in its backlog.
+            if let Some(ref limit) = self.limit {
+                if limit.0.config.over_limit == OverLimit::Pause && !limit.poll_capacity() {
+                    trace!("listener is at its connection limit; pausing");
+                    return Ok(Async::NotReady);
+                }
+            }
+
+            let conn = match self.incoming.poll() {
+                Ok(Async::Ready(Some(conn))) => conn,
+                Ok(Async::Ready(None)) => return Ok(Async::Ready(None)),
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
+                Err(e) => {
+                    self.back_off(e)?;
+                    continue;
+                },
+            };
+            self.failures = 0;
+
+            let permit = match self.limit {
+                Some(ref limit) => match limit.acquire() {
+                    Some(permit) => Some(permit),
+                    None => {
+                        debug!("listener is at its connection limit; closing connection");
+                        drop(conn);
+                        continue;
+                    },
+                },
+                None => None,
+            };
+            return Ok(Async::Ready(Some((conn, permit))));
+        }
+    }
+}
+
+// ===== impl Shared =====
+
+impl Shared {
+    fn record(&self, open: usize) {
+        self.sensors.listener(&self.proxy, open, self.config.max);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::{future, stream};
+    use tokio_core::reactor::Core;
+
+    use super::*;
+
+    fn limit(max: usize, over_limit: OverLimit) -> Limit {
+        let proxy = ctx::Proxy::inbound(&ctx::Process::test("test"));
+        Limit::new(
+            config::ConnectionLimit { max, over_limit },
+            &telemetry::Sensors::null(),
+            &proxy,
+        )
+    }
+
+    fn ready<T>(poll: Async<T>) -> T {
+        match poll {
+            Async::Ready(t) => t,
+            Async::NotReady => panic!("not ready"),
+        }
+    }
+
+    #[test]
+    fn permits_are_limited() {
+        let limit = limit(2, OverLimit::Close);
+
+        let first = limit.acquire().expect("first permit");
+        let _second = limit.acquire().expect("second permit");
+        assert!(limit.acquire().is_none());
+
+        drop(first);
+        assert!(limit.acquire().is_some());
+    }
+
+    #[test]
+    fn connections_over_limit_are_closed() {
+        let core = Core::new().unwrap();
+        let incoming = stream::iter_ok::<_, io::Error>(vec![1, 2, 3]);
+        let mut accept = Accept::new(incoming, Some(limit(1, OverLimit::Close)), None, &core.handle());
+
+        let (conn, permit) = ready(accept.poll().unwrap()).expect("first connection");
+        assert_eq!(conn, 1);
+
+        // The other connections are closed while the first is open.
+        let next = accept.poll().unwrap().map(|conn| conn.map(|(conn, _)| conn));
+        assert_eq!(next, Async::Ready(None));
+        drop(permit);
+    }
+
+    #[test]
+    fn listener_pauses_at_limit() {
+        let core = Core::new().unwrap();
+        let incoming = stream::iter_ok::<_, io::Error>(vec![1, 2]);
+        let mut accept = Accept::new(incoming, Some(limit(1, OverLimit::Pause)), None, &core.handle());
+
+        future::poll_fn(|| {
+            let (_, permit) = ready(accept.poll().unwrap()).expect("first connection");
+            assert!(accept.poll().unwrap().is_not_ready());
+
+            // The second connection is accepted once the first closes.
+            drop(permit);
+            let (conn, _) = ready(accept.poll().unwrap()).expect("second connection");
+            assert_eq!(conn, 2);
+            Ok::<_, ()>(Async::Ready(()))
+        }).wait().unwrap();
+    }
+
+    #[test]
+    fn accept_errors_back_off() {
+        let mut core = Core::new().unwrap();
+        let incoming = stream::iter_result(vec![
+            Err(io::Error::new(io::ErrorKind::Other, "too many open files")),
+            Ok(1),
+        ]);
+        let policy = backoff::Policy::new(config::ReconnectBackoff {
+            base: Duration::from_millis(10),
+            max: Duration::from_millis(10),
+            jitter: 0.0,
+        });
+        let mut accept = Accept::new(incoming, None, Some(policy), &core.handle());
+
+        let conn = core.run(future::poll_fn(|| accept.poll())).unwrap();
+        assert_eq!(conn.map(|(conn, _)| conn), Some(1));
+    }
+}
@@ -0,0 +1,266 @@
+use std::{cmp, fmt, u64};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{Async, Future, Poll};
+use rand::{self, Rng};
+use tokio_core::reactor::{Handle, Timeout};
+use tower_service::Service;
+
+use config;
+use ctx;
+use telemetry;
+
+/// Waits before polling an inner service's readiness again after it fails.
+///
+/// Each consecutive failure waits twice as long as the last, up to a limit,
+/// and the wait is shortened by a random jitter. The service reports that
+/// it isn't ready while it waits, so that a service that can't connect isn't
+/// reconnected in a loop. Without a policy, failures are returned to the
+/// caller.
+///
+/// Only failures to become ready are backed off from. An HTTP/1 client
+/// connects lazily, when each request is dispatched, so its connection
+/// failures are returned as errors from the responses and aren't delayed.
+pub struct Backoff<S> {
+    inner: S,
+    policy: Option<Policy>,
+    handle: Handle,
+    /// The number of times that the inner service has failed since it was
+    /// last ready.
+    failures: u32,
+    timer: Option<Timeout>,
+}
+
+/// Decides how long to wait after each failure.
+#[derive(Clone, Debug)]
+pub struct Policy {
+    config: config::ReconnectBackoff,
+    /// The endpoint that's reconnected, if its reconnects are recorded.
+    endpoint: Option<(telemetry::Sensors, Arc<ctx::transport::Client>)>,
+}
+
+// ===== impl Backoff =====
+
+impl<S> Backoff<S> {
+    pub fn new(inner: S, policy: Option<Policy>, handle: &Handle) -> Self {
+        Self {
+            inner,
+            policy,
+            handle: handle.clone(),
+            failures: 0,
+            timer: None,
+        }
+    }
+
+    /// Starts waiting after a failure, returning whether the service is
+    /// now backing off.
+    fn back_off(&mut self) -> bool {
+        let policy = match self.policy {
+            Some(ref policy) => policy,
+            None => return false,
+        };
+
+        self.failures = self.failures.saturating_add(1);
+        let backoff = policy.backoff_after(self.failures);
+        trace!("backoff: failed {} times, waiting {:?}", self.failures, backoff);
+        policy.record(backoff);
+
+        match Timeout::new(backoff, &self.handle) {
+            Ok(mut timer) => {
+                // The timer is polled so that the task is notified when the
+                // wait is over.
+                let _ = timer.poll();
+                self.timer = Some(timer);
+                true
+            },
+            Err(e) => {
+                warn!("backoff: failed to start timer: {}", e);
+                false
+            },
+        }
+    }
+}
+
+impl<S> Service for Backoff<S>
+where
+    S: Service,
+    S::Error: fmt::Debug,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        if let Some(ref mut timer) = self.timer {
+            if timer.poll().expect("backoff timer").is_not_ready() {
+                return Ok(Async::NotReady);
+            }
+        }
+        self.timer = None;
+
+        match self.inner.poll_ready() {
+            Ok(Async::Ready(())) => {
+                self.failures = 0;
+                Ok(Async::Ready(()))
+            },
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(e) => {
+                if self.back_off() {
+                    debug!("backoff: service failed: {:?}", e);
+                    Ok(Async::NotReady)
+                } else {
+                    Err(e)
+                }
+            },
+        }
+    }
+
+    fn call(&mut self, req: Self::Request) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+// ===== impl Policy =====
+
+impl Policy {
+    pub fn new(config: config::ReconnectBackoff) -> Self {
+        Self {
+            config,
+            endpoint: None,
+        }
+    }
+
+    /// Returns a policy that records each reconnect to `endpoint`.
+    pub fn for_endpoint(
+        config: config::ReconnectBackoff,
+        sensors: &telemetry::Sensors,
+        endpoint: &Arc<ctx::transport::Client>,
+    ) -> Self {
+        Self {
+            config,
+            endpoint: Some((sensors.clone(), Arc::clone(endpoint))),
+        }
+    }
+
+    /// Returns how long to wait after the given number of consecutive
+    /// failures.
+    pub fn backoff_after(&self, failures: u32) -> Duration {
+        self.backoff(failures, rand::thread_rng().gen())
+    }
+
+    /// Returns how long to wait after the given number of consecutive
+    /// failures, where `sample` is uniformly distributed in [0, 1).
+    fn backoff(&self, failures: u32, sample: f64) -> Duration {
+        let base = nanos(self.config.base);
+        let max = nanos(self.config.max);
+
+        // The exponent is bounded so that the wait can't overflow before
+        // it's limited.
+        let exponent = cmp::min(failures.saturating_sub(1), 32) as i32;
+        let backoff = (base * 2f64.powi(exponent)).min(max);
+
+        let jitter = (self.config.jitter as f64).max(0.0).min(1.0);
+        from_nanos(backoff * (1.0 - jitter * sample))
+    }
+
+    fn record(&self, backoff: Duration) {
+        if let Some((ref sensors, ref endpoint)) = self.endpoint {
+            sensors.reconnect(endpoint, backoff);
+        }
+    }
+}
+
+fn nanos(d: Duration) -> f64 {
+    d.as_secs() as f64 * 1e9 + d.subsec_nanos() as f64
+}
+
+fn from_nanos(nanos: f64) -> Duration {
+    if nanos >= u64::MAX as f64 * 1e9 {
+        return Duration::from_secs(u64::MAX);
+    }
+    let secs = (nanos / 1e9).trunc();
+    Duration::new(secs as u64, (nanos - secs * 1e9) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future;
+    use tokio_core::reactor::Core;
+
+    use super::*;
+
+    /// A service that always fails to become ready.
+    struct Failing;
+
+    impl Service for Failing {
+        type Request = ();
+        type Response = ();
+        type Error = ();
+        type Future = future::FutureResult<(), ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Err(())
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::err(())
+        }
+    }
+
+    fn policy(jitter: f32) -> Policy {
+        Policy::new(config::ReconnectBackoff {
+            base: Duration::from_millis(100),
+            max: Duration::from_secs(1),
+            jitter,
+        })
+    }
+
+    #[test]
+    fn backoff_doubles_after_each_failure() {
+        let policy = policy(0.0);
+        assert_eq!(policy.backoff(1, 0.5), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2, 0.5), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3, 0.5), Duration::from_millis(400));
+    }
+
+    #[test]
+    fn backoff_is_limited() {
+        let policy = policy(0.0);
+        assert_eq!(policy.backoff(5, 0.5), Duration::from_secs(1));
+        assert_eq!(policy.backoff(u32::max_value(), 0.5), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn backoff_is_shortened_by_jitter() {
+        let policy = policy(0.5);
+        assert_eq!(policy.backoff(1, 0.0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1, 0.5), Duration::from_millis(75));
+        assert_eq!(policy.backoff(1, 1.0), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn not_ready_while_backing_off() {
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+        core.run(future::lazy(|| {
+            let mut backoff = Backoff::new(Failing, Some(policy(0.0)), &handle);
+            assert!(backoff.poll_ready().expect("backing off").is_not_ready());
+            assert!(backoff.poll_ready().expect("backing off").is_not_ready());
+            assert_eq!(backoff.failures, 1);
+            Ok::<(), ()>(())
+        })).unwrap();
+    }
+
+    #[test]
+    fn failures_are_returned_without_a_policy() {
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+        core.run(future::lazy(|| {
+            let mut backoff = Backoff::new(Failing, None, &handle);
+            assert!(backoff.poll_ready().is_err());
+            Ok::<(), ()>(())
+        })).unwrap();
+    }
+}
@@ -0,0 +1,500 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future, Poll};
+use futures_watch::Watch;
+use rand::Rng;
+use tower_balance::{Balance, Load};
+use tower_balance::choose::{Choose, Replicas};
+use tower_discover::{Change, Discover};
+use tower_service::Service;
+
+use config::LoadMetric;
+use control::destination::DstLabelsWatch;
+use locality::HasDstLabels;
+use telemetry::metrics::DstLabels;
+
+/// The weight of an endpoint for which the Destination service gave none.
+pub const DEFAULT_WEIGHT: u32 = 1;
+
+pub type WeightWatch = Watch<u32>;
+
+/// A service that receives a share of a load balancer's requests in
+/// proportion to its weight.
+pub trait HasWeight {
+    fn weight(&self) -> u32;
+}
+
+/// A service with a weight and metric labels from the Destination service.
+///
+/// The weight is watched so that it may change without the service being
+/// re-bound.
+#[derive(Debug)]
+pub struct Weighted<S> {
+    inner: S,
+    weight: WeightWatch,
+    dst_labels: Option<DstLabelsWatch>,
+}
+
+/// Wraps each discovered service so that its load is measured by a
+/// `LoadMetric`.
+#[derive(Debug)]
+pub struct WithLoad<D> {
+    inner: D,
+    metric: LoadMetric,
+}
+
+/// A service whose load is measured by its pending requests and, with
+/// peak-EWMA, its response latency.
+#[derive(Debug)]
+pub struct Loaded<S> {
+    inner: S,
+    /// Each pending request holds a reference to this, so that the number of
+    /// pending requests is one less than its strong count.
+    pending: Arc<()>,
+    rtt: Option<Arc<Mutex<PeakEwma>>>,
+}
+
+pub struct ResponseFuture<F> {
+    inner: F,
+    _pending: Arc<()>,
+    rtt: Option<(Instant, Arc<Mutex<PeakEwma>>)>,
+}
+
+/// Chooses the less loaded of two endpoints, each drawn at random with a
+/// probability proportional to its weight.
+///
+/// Endpoints with equal loads, e.g. idle endpoints, receive requests in
+/// proportion to their weights.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct WeightedPowerOfTwoChoices<R> {
+    rng: R,
+}
+
+/// A moving average of an endpoint's response latency.
+///
+/// A latency greater than the average replaces it, so that the average
+/// reacts to a slow endpoint immediately, and only recovers gradually.
+#[derive(Debug)]
+struct PeakEwma {
+    /// The average, in nanoseconds.
+    estimate: f64,
+    updated_at: Instant,
+    decay: f64,
+}
+
+/// Balances requests across the services discovered by `discover`, in
+/// proportion to their weights.
+pub fn weighted_power_of_two_choices<D, R>(
+    discover: D,
+    rng: R,
+) -> Balance<D, WeightedPowerOfTwoChoices<R>>
+where
+    D: Discover,
+    WeightedPowerOfTwoChoices<R>: Choose<D::Key, D::Service>,
+{
+    Balance::new(discover, WeightedPowerOfTwoChoices { rng })
+}
+
+// ===== impl Weighted =====
+
+impl<S> Weighted<S> {
+    pub fn new(inner: S, weight: WeightWatch, dst_labels: Option<DstLabelsWatch>) -> Self {
+        Self { inner, weight, dst_labels }
+    }
+
+    /// Returns a service with the default weight, which never changes, and
+    /// no labels.
+    pub fn with_default_weight(inner: S) -> Self {
+        let (weight, _) = Watch::new(DEFAULT_WEIGHT);
+        Self::new(inner, weight, None)
+    }
+}
+
+impl<S> HasWeight for Weighted<S> {
+    fn weight(&self) -> u32 {
+        *self.weight.borrow()
+    }
+}
+
+impl<S> HasDstLabels for Weighted<S> {
+    fn dst_labels(&self) -> Option<DstLabels> {
+        self.dst_labels.as_ref().and_then(|labels| labels.borrow().clone())
+    }
+}
+
+impl<S: Service> Service for Weighted<S> {
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, req: Self::Request) -> Self::Future {
+        self.inner.call(req)
+    }
+}
+
+// ===== impl WithLoad =====
+
+impl<D> WithLoad<D> {
+    pub fn new(inner: D, metric: LoadMetric) -> Self {
+        Self { inner, metric }
+    }
+}
+
+impl<D> Discover for WithLoad<D>
+where
+    D: Discover,
+    D::Service: HasWeight,
+{
+    type Key = D::Key;
+    type Request = D::Request;
+    type Response = D::Response;
+    type Error = D::Error;
+    type Service = Loaded<D::Service>;
+    type DiscoverError = D::DiscoverError;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::DiscoverError> {
+        let change = match try_ready!(self.inner.poll()) {
+            Change::Insert(key, inner) => Change::Insert(key, Loaded::new(inner, self.metric)),
+            Change::Remove(key) => Change::Remove(key),
+        };
+        Ok(Async::Ready(change))
+    }
+}
+
+// ===== impl Loaded =====
+
+impl<S: HasWeight> Loaded<S> {
+    fn new(inner: S, metric: LoadMetric) -> Self {
+        let rtt = match metric {
+            LoadMetric::PendingRequests => None,
+            LoadMetric::PeakEwma { default_rtt, decay } => {
+                let ewma = PeakEwma::new(default_rtt, decay, Instant::now());
+                Some(Arc::new(Mutex::new(ewma)))
+            },
+        };
+        Self {
+            inner,
+            pending: Arc::new(()),
+            rtt,
+        }
+    }
+
+    /// The load isn't divided by the endpoint's weight, since the weight
+    /// already determines how often the endpoint is considered.
+    fn load_at(&self, now: Instant) -> f64 {
+        // The request that's being dispatched is counted, so that an idle
+        // endpoint's load still depends on its latency.
+        let pending = Arc::strong_count(&self.pending) as f64;
+        match self.rtt {
+            None => pending,
+            // A nanosecond is added so that pending requests are still
+            // counted once the average has decayed to nothing.
+            Some(ref rtt) => (rtt.lock().expect("rtt lock").estimate(now) + 1.0) * pending,
+        }
+    }
+}
+
+impl<S: HasWeight> Load for Loaded<S> {
+    type Metric = f64;
+
+    fn load(&self) -> Self::Metric {
+        self.load_at(Instant::now())
+    }
+}
+
+impl<S: Service> Service for Loaded<S> {
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, req: Self::Request) -> Self::Future {
+        ResponseFuture {
+            inner: self.inner.call(req),
+            _pending: self.pending.clone(),
+            rtt: self.rtt.as_ref().map(|rtt| (Instant::now(), rtt.clone())),
+        }
+    }
+}
+
+// ===== impl ResponseFuture =====
+
+impl<F: Future> Future for ResponseFuture<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let rsp = try_ready!(self.inner.poll());
+
+        // Like the response latency that's reported by telemetry, this is
+        // the time until the response's headers were received, but it's
+        // measured from when the request was dispatched to this endpoint.
+        if let Some((dispatched_at, rtt)) = self.rtt.take() {
+            let now = Instant::now();
+            rtt.lock().expect("rtt lock").observe(now - dispatched_at, now);
+        }
+
+        Ok(Async::Ready(rsp))
+    }
+}
+
+// ===== impl WeightedPowerOfTwoChoices =====
+
+impl<K, S, R> Choose<K, Loaded<S>> for WeightedPowerOfTwoChoices<R>
+where
+    S: HasWeight,
+    R: Rng,
+{
+    fn choose(&mut self, replicas: Replicas<K, Loaded<S>>) -> usize {
+        let now = Instant::now();
+        choose(
+            &mut self.rng,
+            replicas.len(),
+            |idx| replicas[idx].inner.weight(),
+            |idx| replicas[idx].load_at(now),
+        )
+    }
+}
+
+/// Draws two of `len` endpoints by weight, and returns the less loaded.
+fn choose<R, W, L>(rng: &mut R, len: usize, weight: W, load: L) -> usize
+where
+    R: Rng,
+    W: Fn(usize) -> u32,
+    L: Fn(usize) -> f64,
+{
+    let a = pick(rng, len, &weight, None);
+    let b = pick(rng, len, &weight, Some(a));
+
+    // Ties go to the first endpoint, which was drawn from all of them.
+    if load(b) < load(a) {
+        b
+    } else {
+        a
+    }
+}
+
+/// Draws one of `len` endpoints, other than `except`, with a probability
+/// proportional to its weight.
+fn pick<R, W>(rng: &mut R, len: usize, weight: &W, except: Option<usize>) -> usize
+where
+    R: Rng,
+    W: Fn(usize) -> u32,
+{
+    let weight_of = |idx: usize| -> u64 {
+        if Some(idx) == except {
+            0
+        } else {
+            u64::from(weight(idx))
+        }
+    };
+
+    let total = (0..len).map(&weight_of).sum::<u64>();
+    if total == 0 {
+        // None of the endpoints has a weight, so each is as likely.
+        let idx = rng.gen_range(0, len - except.map_or(0, |_| 1));
+        return match except {
+            Some(except) if idx >= except => idx + 1,
+            _ => idx,
+        };
+    }
+
+    let mut n = rng.gen_range(0, total);
+    for idx in 0..len {
+        let weight = weight_of(idx);
+        if n < weight {
+            return idx;
+        }
+        n -= weight;
+    }
+
+    unreachable!("weights sum to the total");
+}
+
+// ===== impl PeakEwma =====
+
+impl PeakEwma {
+    fn new(default_rtt: Duration, decay: Duration, now: Instant) -> Self {
+        Self {
+            estimate: nanos(default_rtt),
+            updated_at: now,
+            decay: nanos(decay),
+        }
+    }
+
+    /// Returns the average, decayed by the time since it was last updated, so
+    /// that an endpoint that hasn't been chosen since it was slow is
+    /// eventually tried again.
+    fn estimate(&self, now: Instant) -> f64 {
+        self.estimate * self.decay_since_update(now)
+    }
+
+    fn observe(&mut self, rtt: Duration, now: Instant) {
+        let rtt = nanos(rtt);
+        self.estimate = if rtt > self.estimate {
+            rtt
+        } else {
+            let decay = self.decay_since_update(now);
+            self.estimate * decay + rtt * (1.0 - decay)
+        };
+        self.updated_at = now;
+    }
+
+    fn decay_since_update(&self, now: Instant) -> f64 {
+        if now <= self.updated_at || self.decay <= 0.0 {
+            return 1.0;
+        }
+        (-nanos(now - self.updated_at) / self.decay).exp()
+    }
+}
+
+fn nanos(d: Duration) -> f64 {
+    d.as_secs() as f64 * 1e9 + d.subsec_nanos() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, FutureResult};
+    use rand;
+
+    use super::*;
+
+    struct Echo;
+
+    impl Service for Echo {
+        type Request = ();
+        type Response = ();
+        type Error = ();
+        type Future = FutureResult<(), ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: ()) -> Self::Future {
+            future::ok(())
+        }
+    }
+
+    fn weighted(weight: u32) -> Loaded<Weighted<Echo>> {
+        let (weight, _) = Watch::new(weight);
+        Loaded::new(Weighted::new(Echo, weight, None), LoadMetric::PendingRequests)
+    }
+
+    #[test]
+    fn load_counts_pending_requests() {
+        let mut svc = weighted(1);
+        let now = Instant::now();
+        assert_eq!(svc.load_at(now), 1.0);
+
+        let rsp = svc.call(());
+        assert_eq!(svc.load_at(now), 2.0);
+
+        drop(rsp);
+        assert_eq!(svc.load_at(now), 1.0);
+    }
+
+    #[test]
+    fn load_is_not_divided_by_weight() {
+        let svc = weighted(4);
+        assert_eq!(svc.load_at(Instant::now()), 1.0);
+    }
+
+    #[test]
+    fn weight_changes_without_rebinding() {
+        let (watch, mut store) = Watch::new(1);
+        let svc = Loaded::new(Weighted::new(Echo, watch, None), LoadMetric::PendingRequests);
+        assert_eq!(svc.inner.weight(), 1);
+
+        store.store(2).expect("store weight");
+        assert_eq!(svc.inner.weight(), 2);
+    }
+
+    #[test]
+    fn idle_endpoints_are_chosen_by_weight() {
+        let weights = [3, 1, 1, 1];
+        let mut rng = rand::thread_rng();
+        let mut chosen = [0u32; 4];
+        for _ in 0..60_000 {
+            let idx = choose(&mut rng, weights.len(), |idx| weights[idx], |_| 1.0);
+            chosen[idx] += 1;
+        }
+
+        for &light in &chosen[1..] {
+            let ratio = f64::from(chosen[0]) / f64::from(light);
+            assert!(2.7 < ratio && ratio < 3.3, "expected 3:1, chosen {:?}", chosen);
+        }
+    }
+
+    #[test]
+    fn less_loaded_endpoint_is_chosen() {
+        let loads = [2.0, 1.0];
+        let mut rng = rand::thread_rng();
+        for _ in 0..1_000 {
+            assert_eq!(choose(&mut rng, loads.len(), |_| 1, |idx| loads[idx]), 1);
+        }
+    }
+
+    #[test]
+    fn unweighted_endpoints_are_chosen() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..1_000 {
+            let a = pick(&mut rng, 2, &|_| 0, None);
+            assert_eq!(pick(&mut rng, 2, &|_| 0, Some(a)), 1 - a);
+        }
+    }
+
+    #[test]
+    fn peak_ewma_takes_peaks_immediately() {
+        let now = Instant::now();
+        let mut ewma = PeakEwma::new(Duration::from_millis(10), Duration::from_secs(10), now);
+        assert_eq!(ewma.estimate(now), 10e6);
+
+        ewma.observe(Duration::from_millis(100), now);
+        assert_eq!(ewma.estimate(now), 100e6);
+    }
+
+    #[test]
+    fn peak_ewma_recovers_gradually() {
+        let now = Instant::now();
+        let mut ewma = PeakEwma::new(Duration::from_millis(100), Duration::from_secs(10), now);
+
+        let later = now + Duration::from_secs(10);
+        ewma.observe(Duration::from_millis(10), later);
+        let estimate = ewma.estimate(later);
+        assert!(estimate < 100e6, "estimate should fall: {}", estimate);
+        assert!(estimate > 10e6, "estimate should not fall to the last latency: {}", estimate);
+    }
+
+    #[test]
+    fn peak_ewma_decays_while_idle() {
+        let now = Instant::now();
+        let ewma = PeakEwma::new(Duration::from_millis(100), Duration::from_secs(10), now);
+        assert!(ewma.estimate(now + Duration::from_secs(10)) < 100e6);
+    }
+
+    #[test]
+    fn peak_ewma_load_prefers_faster_endpoints() {
+        let (weight, _) = Watch::new(1);
+        let metric = LoadMetric::PeakEwma {
+            default_rtt: Duration::from_millis(10),
+            decay: Duration::from_secs(10),
+        };
+        let fast = Loaded::new(Weighted::new(Echo, weight.clone(), None), metric);
+        let slow = Loaded::new(Weighted::new(Echo, weight, None), metric);
+
+        let now = Instant::now();
+        slow.rtt.as_ref().unwrap().lock().unwrap().observe(Duration::from_millis(500), now);
+        assert!(fast.load_at(now) < slow.load_at(now));
+    }
+}
@@ -2,22 +2,30 @@ use std::error::Error;
 use std::fmt;
 use std::default::Default;
 use std::marker::PhantomData;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::sync::atomic::AtomicUsize;
+use std::time::Duration;
 
-use futures::{Future, Poll, future};
+use futures::{Async, Future, Poll, future};
 use http::{self, uri};
+use tokio_connect::Connect;
 use tokio_core::reactor::Handle;
 use tower_service as tower;
 use tower_h2;
 use tower_reconnect::Reconnect;
 
+use backoff::{self, Backoff};
+use config;
 use control;
 use control::destination::Endpoint;
 use ctx;
+use fault;
+use orig_proto;
 use telemetry::{self, sensor};
+use timeout::{Timeout, TimeoutError};
 use transparency::{self, HttpBody, h1};
-use transport;
+use transport::{self, tls};
 
 /// Binds a `Service` from a `SocketAddr`.
 ///
@@ -31,6 +39,9 @@ pub struct Bind<C, B> {
     sensors: telemetry::Sensors,
     executor: Handle,
     req_ids: Arc<AtomicUsize>,
+    tls_client_config: Option<tls::ClientConfig>,
+    reconnect_backoff: Option<config::ReconnectBackoff>,
+    faults: Arc<Vec<config::Fault>>,
     _p: PhantomData<B>,
 }
 
@@ -40,6 +51,26 @@ pub struct BindProtocol<C, B> {
     protocol: Protocol,
 }
 
+/// Binds a TCP connector to each discovered endpoint.
+#[derive(Clone, Debug)]
+pub struct BindTcp {
+    ctx: Arc<ctx::Proxy>,
+    sensors: telemetry::Sensors,
+    executor: Handle,
+    tls_client_config: Option<tls::ClientConfig>,
+    connect_timeout: Duration,
+}
+
+/// Opens connections to a single endpoint.
+///
+/// Each request is the original destination of a connection being forwarded,
+/// and is answered with a connection to the endpoint.
+#[derive(Clone, Debug)]
+pub struct TcpService {
+    addr: config::Addr,
+    connect: TcpConnect,
+}
+
 /// A type of service binding
 ///
 /// Some services, for various reasons, may not be able to be used to serve multiple
@@ -76,6 +107,12 @@ pub enum Protocol {
         /// absolute URIs be bound to separate service stacks. It is also
         /// used to determine what URI normalization will be necessary.
         was_absolute_form: bool,
+        /// Whether or not the request asks to take over its connection, as
+        /// WebSocket handshakes and `CONNECT` requests do.
+        ///
+        /// Such requests need connections of their own, so they can't be
+        /// multiplexed over HTTP/2 with other requests.
+        is_upgrade: bool,
     },
     Http2
 }
@@ -102,9 +139,9 @@ pub struct NormalizeUri<S> {
 
 pub type Service<B> = Binding<B>;
 
-pub type Stack<B> = Reconnect<NormalizeUri<NewHttp<B>>>;
+pub type Stack<B> = Backoff<Reconnect<NormalizeUri<orig_proto::Upgrade<NewHttp<B>>>>>;
 
-pub type NewHttp<B> = sensor::NewHttp<Client<B>, B, HttpBody>;
+pub type NewHttp<B> = sensor::NewHttp<fault::Inject<Client<B>>, B, HttpBody>;
 
 pub type HttpResponse = http::Response<sensor::http::ResponseBody<HttpBody>>;
 
@@ -112,6 +149,8 @@ pub type HttpRequest<B> = http::Request<sensor::http::RequestBody<B>>;
 
 pub type Client<B> = transparency::Client<sensor::Connect<transport::Connect>, B>;
 
+pub type TcpConnect = sensor::Connect<Timeout<transport::Connect>>;
+
 #[derive(Copy, Clone, Debug)]
 pub enum BufferSpawnError {
     Inbound,
@@ -145,6 +184,9 @@ impl<B> Bind<(), B> {
             ctx: (),
             sensors: telemetry::Sensors::null(),
             req_ids: Default::default(),
+            tls_client_config: None,
+            reconnect_backoff: None,
+            faults: Arc::new(Vec::new()),
             _p: PhantomData,
         }
     }
@@ -156,12 +198,33 @@ impl<B> Bind<(), B> {
         }
     }
 
+    /// Configures TLS to be originated on connections to endpoints that have
+    /// a TLS identity.
+    pub fn with_tls_client_config(self, tls_client_config: Option<tls::ClientConfig>) -> Self {
+        Self {
+            tls_client_config,
+            ..self
+        }
+    }
+
+    /// Configures how long to wait before reconnecting to an endpoint after a
+    /// connection to it fails.
+    pub fn with_reconnect_backoff(self, reconnect_backoff: config::ReconnectBackoff) -> Self {
+        Self {
+            reconnect_backoff: Some(reconnect_backoff),
+            ..self
+        }
+    }
+
     pub fn with_ctx<C>(self, ctx: C) -> Bind<C, B> {
         Bind {
             ctx,
             sensors: self.sensors,
             executor: self.executor,
             req_ids: self.req_ids,
+            tls_client_config: self.tls_client_config,
+            reconnect_backoff: self.reconnect_backoff,
+            faults: self.faults,
             _p: PhantomData,
         }
     }
@@ -174,6 +237,9 @@ impl<C: Clone, B> Clone for Bind<C, B> {
             sensors: self.sensors.clone(),
             executor: self.executor.clone(),
             req_ids: self.req_ids.clone(),
+            tls_client_config: self.tls_client_config.clone(),
+            reconnect_backoff: self.reconnect_backoff,
+            faults: self.faults.clone(),
             _p: PhantomData,
         }
     }
@@ -181,9 +247,44 @@ impl<C: Clone, B> Clone for Bind<C, B> {
 
 
 impl<C, B> Bind<C, B> {
+    /// Returns a `Bind` for services whose requests have bodies of type `B2`.
+    pub fn with_body<B2>(self) -> Bind<C, B2> {
+        Bind {
+            ctx: self.ctx,
+            sensors: self.sensors,
+            executor: self.executor,
+            req_ids: self.req_ids,
+            tls_client_config: self.tls_client_config,
+            reconnect_backoff: self.reconnect_backoff,
+            faults: self.faults,
+            _p: PhantomData,
+        }
+    }
+
+    /// Configures the faults that are injected into requests, for chaos
+    /// testing.
+    pub fn with_faults(self, faults: Vec<config::Fault>) -> Self {
+        Self {
+            faults: Arc::new(faults),
+            ..self
+        }
+    }
+
     pub fn executor(&self) -> &Handle {
         &self.executor
     }
+
+    pub fn ctx(&self) -> &C {
+        &self.ctx
+    }
+
+    pub fn sensors(&self) -> &telemetry::Sensors {
+        &self.sensors
+    }
+
+    pub fn faults(&self) -> &Arc<Vec<config::Fault>> {
+        &self.faults
+    }
 }
 
 impl<B> Bind<Arc<ctx::Proxy>, B>
@@ -193,42 +294,77 @@ where
     fn bind_stack(&self, ep: &Endpoint, protocol: &Protocol) -> Stack<B> {
         debug!("bind_stack endpoint={:?}, protocol={:?}", ep, protocol);
         let addr = ep.address();
+        let tls = tls_connection_config(ep, self.tls_client_config.as_ref());
+
         let client_ctx = ctx::transport::Client::new(
             &self.ctx,
-            &addr,
+            addr,
             ep.dst_labels().cloned(),
+            tls.as_ref().map(|tls| tls.server_identity.clone()),
         );
 
         // Map a socket address to a connection.
         let connect = self.sensors.connect(
-            transport::Connect::new(addr, &self.executor),
+            transport::Connect::new(addr.clone(), tls, &self.executor),
             &client_ctx,
         );
 
+        // HTTP/1 requests to meshed endpoints are multiplexed over HTTP/2,
+        // and are downgraded again by the endpoint's proxy.
+        let upgrade_to_http2 = upgrades_to_http2(ep, protocol, self.tls_client_config.as_ref());
+        let client_protocol = if upgrade_to_http2 {
+            &Protocol::Http2
+        } else {
+            protocol
+        };
+
         let client = transparency::Client::new(
-            protocol,
+            client_protocol,
             connect,
             self.executor.clone()
         );
 
+        // Faults are injected beneath the sensors, so that they're observed
+        // like the endpoint's own responses and errors.
+        let client = fault::Inject::new(client, &self.executor);
+
         let sensors = self.sensors.http(
             self.req_ids.clone(),
             client,
             &client_ctx
         );
 
+        let upgrade = orig_proto::Upgrade::new(sensors, upgrade_to_http2);
+
         // Rewrite the HTTP/1 URI, if the authorities in the Host header
         // and request URI are not in agreement, or are not present.
-        let proxy = NormalizeUri::new(sensors, protocol.was_absolute_form());
+        let proxy = NormalizeUri::new(upgrade, protocol.was_absolute_form());
+
+        // Automatically perform reconnects if the connection fails, waiting
+        // longer after each consecutive failure. HTTP/1 connections are made
+        // as requests are dispatched, so they're retried without waiting.
+        let policy = self.reconnect_backoff.map(|config| {
+            backoff::Policy::for_endpoint(config, &self.sensors, &client_ctx)
+        });
+        Backoff::new(Reconnect::new(proxy), policy, &self.executor)
+    }
 
-        // Automatically perform reconnects if the connection fails.
-        //
-        // TODO: Add some sort of backoff logic.
-        Reconnect::new(proxy)
+    /// Returns a `BindTcp` that opens connections with this `Bind`'s context,
+    /// sensors, and TLS configuration.
+    pub fn tcp(&self, connect_timeout: Duration) -> BindTcp {
+        BindTcp {
+            ctx: self.ctx.clone(),
+            sensors: self.sensors.clone(),
+            executor: self.executor.clone(),
+            tls_client_config: self.tls_client_config.clone(),
+            connect_timeout,
+        }
     }
 
     pub fn new_binding(&self, ep: &Endpoint, protocol: &Protocol) -> Binding<B> {
-        if protocol.can_reuse_clients() {
+        // An HTTP/2 connection may be shared by requests for any host.
+        let tls_client_config = self.tls_client_config.as_ref();
+        if protocol.can_reuse_clients() || upgrades_to_http2(ep, protocol, tls_client_config) {
             Binding::Bound(self.bind_stack(ep, protocol))
         } else {
             Binding::BindsPerRequest {
@@ -241,6 +377,33 @@ where
     }
 }
 
+/// Requests are only upgraded over TLS, since the endpoint's proxy only
+/// downgrades requests from peers that it has authenticated.
+fn upgrades_to_http2(
+    ep: &Endpoint,
+    protocol: &Protocol,
+    tls_client_config: Option<&tls::ClientConfig>,
+) -> bool {
+    protocol.can_upgrade_to_http2() &&
+        ep.can_use_orig_proto() &&
+        tls_connection_config(ep, tls_client_config).is_some()
+}
+
+/// Only meshed endpoints have an identity, so TLS is originated only when
+/// talking to another proxy.
+fn tls_connection_config(
+    ep: &Endpoint,
+    tls_client_config: Option<&tls::ClientConfig>,
+) -> Option<tls::ConnectionConfig> {
+    match (ep.tls_identity(), tls_client_config) {
+        (Some(identity), Some(config)) => Some(tls::ConnectionConfig {
+            server_identity: identity.clone(),
+            config: config.clone(),
+        }),
+        _ => None,
+    }
+}
+
 // ===== impl BindProtocol =====
 
 
@@ -270,6 +433,76 @@ where
 }
 
 
+// ===== impl BindTcp =====
+
+impl BindTcp {
+    pub fn executor(&self) -> &Handle {
+        &self.executor
+    }
+
+    /// Returns a `BindTcp` that never originates TLS, e.g. for connections
+    /// that the peer's proxy forwards without detecting their protocol.
+    pub fn without_tls(&self) -> BindTcp {
+        BindTcp {
+            tls_client_config: None,
+            ..self.clone()
+        }
+    }
+}
+
+impl control::destination::Bind for BindTcp {
+    type Endpoint = Endpoint;
+    type Request = SocketAddr;
+    type Response = <TcpConnect as Connect>::Connected;
+    type Error = TimeoutError<::std::io::Error>;
+    type Service = TcpService;
+    type BindError = ();
+
+    fn bind(&self, ep: &Endpoint) -> Result<Self::Service, Self::BindError> {
+        debug!("bind tcp endpoint={:?}", ep);
+        let addr = ep.address();
+        let tls = tls_connection_config(ep, self.tls_client_config.as_ref());
+
+        // The endpoint's metric labels are attached to the transport metrics
+        // of each connection.
+        let client_ctx = ctx::transport::Client::new(
+            &self.ctx,
+            addr,
+            ep.dst_labels().cloned(),
+            tls.as_ref().map(|tls| tls.server_identity.clone()),
+        );
+
+        let connect = Timeout::new(
+            transport::Connect::new(addr.clone(), tls, &self.executor),
+            self.connect_timeout,
+            &self.executor,
+        );
+
+        Ok(TcpService {
+            addr: addr.clone(),
+            connect: self.sensors.connect(connect, &client_ctx),
+        })
+    }
+}
+
+// ===== impl TcpService =====
+
+impl tower::Service for TcpService {
+    type Request = SocketAddr;
+    type Response = <TcpConnect as Connect>::Connected;
+    type Error = TimeoutError<::std::io::Error>;
+    type Future = <TcpConnect as Connect>::Future;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        Ok(Async::Ready(()))
+    }
+
+    fn call(&mut self, orig_dst: SocketAddr) -> Self::Future {
+        debug!("tcp connecting to {} for {}", self.addr, orig_dst);
+        self.connect.connect()
+    }
+}
+
 // ===== impl NormalizeUri =====
 
 
@@ -401,7 +634,9 @@ impl Protocol {
             .unwrap_or_else(|| Host::NoAuthority);
 
 
-        Protocol::Http1 { host, was_absolute_form }
+        let is_upgrade = h1::is_upgrade(req);
+
+        Protocol::Http1 { host, was_absolute_form, is_upgrade }
     }
 
     /// Returns true if the request was originally received in absolute form.
@@ -418,4 +653,13 @@ impl Protocol {
             _ => false,
         }
     }
+
+    /// Returns true if requests may be upgraded to HTTP/2 when they're sent
+    /// to a meshed endpoint.
+    pub fn can_upgrade_to_http2(&self) -> bool {
+        match *self {
+            Protocol::Http1 { is_upgrade, .. } => !is_upgrade,
+            Protocol::Http2 => false,
+        }
+    }
 }
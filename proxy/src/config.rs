@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 use std::env;
+use std::fmt;
+use std::hash::Hash;
 use std::iter::FromIterator;
 use std::net::SocketAddr;
 use std::path::PathBuf;
@@ -9,7 +11,7 @@ use std::time::Duration;
 use http;
 use indexmap::IndexSet;
 
-use transport::{Host, HostAndPort, HostAndPortError};
+use transport::{Host, HostAndPort, HostAndPortError, tls};
 use convert::TryFrom;
 
 // TODO:
@@ -34,16 +36,45 @@ pub struct Config {
     /// Where to forward externally received connections.
     pub private_forward: Option<Addr>,
 
+    /// The inbound ports whose requests are forwarded to `private_forward`
+    /// when it's a Unix socket. Requests to other ports are forwarded to
+    /// their original destinations.
+    pub private_forward_ports: IndexSet<u16>,
+
     /// The maximum amount of time to wait for a connection to the public peer.
     pub public_connect_timeout: Duration,
 
     /// The maximum amount of time to wait for a connection to the private peer.
     pub private_connect_timeout: Duration,
 
+    /// The maximum amount of time to wait for a client's first bytes before
+    /// treating its connection as opaque TCP, if any.
+    ///
+    /// The same deadline also bounds reading a PROXY protocol header and
+    /// terminating TLS; a connection that hasn't finished either by then is
+    /// closed.
+    ///
+    /// By default, connections wait indefinitely, since server-speaks-first
+    /// protocols should be listed in the ports that disable protocol
+    /// detection instead.
+    pub protocol_detection_timeout: Option<Duration>,
+
     pub inbound_ports_disable_protocol_detection: IndexSet<u16>,
 
     pub outbound_ports_disable_protocol_detection: IndexSet<u16>,
 
+    /// Whether connections to the public listener begin with a PROXY
+    /// protocol header, as sent by an L4 load balancer.
+    pub inbound_accept_proxy_protocol: bool,
+
+    /// Whether connections to the private listener begin with a PROXY
+    /// protocol header.
+    pub outbound_accept_proxy_protocol: bool,
+
+    /// Whether inbound connections that are forwarded as TCP are prefixed
+    /// with a PROXY protocol header describing the original client.
+    pub inbound_send_proxy_protocol: bool,
+
     pub inbound_router_capacity: usize,
 
     pub outbound_router_capacity: usize,
@@ -52,12 +83,48 @@ pub struct Config {
 
     pub outbound_router_max_idle_age: Duration,
 
+    /// How many inbound requests may wait for each route's service, and for
+    /// how long.
+    pub inbound_queue: Queue,
+
+    /// How many outbound requests may wait for each route's service, and for
+    /// how long.
+    pub outbound_queue: Queue,
+
+    /// The most inbound requests that each route may be processing at once.
+    pub inbound_max_in_flight: usize,
+
+    /// The most outbound requests that each route may be processing at once.
+    pub outbound_max_in_flight: usize,
+
+    /// The rate limits of inbound requests, by the port of their original
+    /// destination.
+    pub inbound_rate_limits: RateLimits<u16>,
+
+    /// The rate limits of outbound requests, by the authority of their
+    /// destination.
+    pub outbound_rate_limits: RateLimits<http::uri::Authority>,
+
+    /// The largest outbound request body that's mirrored to a destination's
+    /// shadow. Larger requests are sent only to their destination.
+    pub outbound_mirror_max_body_bytes: usize,
+
+    /// Bounds how long each mirrored request may take, including the time
+    /// that it waits for the body that it copies.
+    pub outbound_mirror_timeout: Duration,
+
     /// The path to "/etc/resolv.conf"
     pub resolv_conf_path: PathBuf,
 
     /// Where to talk to the control plane.
     pub control_host_and_port: HostAndPort,
 
+    /// Whether destinations' profiles are requested from the controller.
+    ///
+    /// Profiles are disabled by default, since not every controller serves
+    /// them.
+    pub destination_profiles_enabled: bool,
+
     /// Event queue capacity.
     pub event_buffer_capacity: usize,
 
@@ -67,9 +134,243 @@ pub struct Config {
     /// Timeout after which to cancel binding a request.
     pub bind_timeout: Duration,
 
+    /// How failed outbound requests are retried, if retries are enabled.
+    pub outbound_retries: Option<Retries>,
+
+    /// How failing endpoints are ejected from outbound load balancers, if
+    /// outlier detection is enabled.
+    pub outbound_outlier_detection: Option<OutlierDetection>,
+
+    /// How outbound load balancers measure the load of each endpoint.
+    pub outbound_load_metric: LoadMetric,
+
+    /// The proxy's locality, if it's known, which outbound load balancers
+    /// prefer endpoints to share.
+    pub locality: Option<Locality>,
+
+    /// How long to wait before reconnecting to an endpoint, or to the
+    /// controller, after a connection to it fails.
+    pub reconnect_backoff: ReconnectBackoff,
+
+    /// The most connections that the inbound listener may have open at once,
+    /// if it's limited.
+    pub inbound_connection_limit: Option<ConnectionLimit>,
+
+    /// The most connections that the outbound listener may have open at
+    /// once, if it's limited.
+    pub outbound_connection_limit: Option<ConnectionLimit>,
+
+    /// How long a listener waits before accepting connections again after it
+    /// fails to accept one.
+    pub accept_backoff: ReconnectBackoff,
+
+    /// Faults injected into inbound requests, for chaos testing.
+    pub inbound_faults: Vec<Fault>,
+
+    /// Faults injected into outbound requests, for chaos testing.
+    pub outbound_faults: Vec<Fault>,
+
+    /// Where to load TLS certificates from, if TLS is enabled.
+    pub tls_settings: Option<tls::CommonSettings>,
+
     pub pod_namespace: String,
 }
 
+/// Configures which failed requests are retried, and how many retries are
+/// allowed.
+#[derive(Clone, Debug)]
+pub struct Retries {
+    /// The number of retries allowed for each request that is dispatched.
+    pub budget_ratio: f32,
+
+    /// The number of retries allowed each second regardless of how many
+    /// requests are dispatched.
+    pub budget_min_per_second: u32,
+
+    /// Response statuses after which a request may be retried.
+    pub http_statuses: IndexSet<u16>,
+
+    /// `grpc-status` codes after which a request may be retried.
+    pub grpc_statuses: IndexSet<u32>,
+}
+
+/// Configures when failing endpoints are ejected from a load balancer, and
+/// for how long.
+#[derive(Clone, Debug)]
+pub struct OutlierDetection {
+    /// The number of consecutive failures after which an endpoint is ejected,
+    /// or 0 if endpoints aren't ejected for consecutive failures.
+    pub consecutive_failures: u32,
+
+    /// The ratio of failed requests in a window after which an endpoint is
+    /// ejected, or 0 if endpoints aren't ejected for their failure rate.
+    pub failure_rate: f32,
+
+    /// The number of requests that must be made in a window before its
+    /// failure rate is considered.
+    pub min_requests: u32,
+
+    /// The period over which failure rates are measured.
+    pub window: Duration,
+
+    /// How long an endpoint is ejected the first time. Each consecutive
+    /// ejection is twice as long as the last.
+    pub base_ejection_time: Duration,
+
+    /// The longest that an endpoint is ejected.
+    pub max_ejection_time: Duration,
+
+    /// The largest ratio of a load balancer's endpoints that may be ejected.
+    pub max_ejected_ratio: f32,
+}
+
+/// The measure of an endpoint's load by which load balancers choose between
+/// endpoints.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum LoadMetric {
+    /// The number of requests that the endpoint hasn't yet responded to.
+    PendingRequests,
+
+    /// The number of pending requests, multiplied by a moving average of the
+    /// endpoint's response latency that is biased toward its peaks.
+    PeakEwma {
+        /// The latency that's assumed for an endpoint that hasn't responded
+        /// yet.
+        default_rtt: Duration,
+
+        /// The period over which past latencies lose their influence on the
+        /// average.
+        decay: Duration,
+    },
+}
+
+/// The name of a `LoadMetric`, whose parameters are configured separately.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum LoadMetricName {
+    PendingRequests,
+    PeakEwma,
+}
+
+/// Configures how outbound load balancers prefer endpoints in the proxy's own
+/// locality.
+#[derive(Clone, Debug)]
+pub struct Locality {
+    /// The name of the destination metric label that identifies an
+    /// endpoint's locality.
+    pub label: String,
+
+    /// The value of that label for endpoints in the proxy's locality.
+    pub value: String,
+
+    /// The ratio of the local endpoints that must be healthy for all requests
+    /// to be sent to them. Below it, requests spill over to other localities
+    /// in proportion.
+    pub min_healthy_ratio: f32,
+}
+
+/// Configures how many requests may wait for a route's service to become
+/// ready, and for how long. Requests beyond either limit are shed.
+///
+/// Each route has a queue of its own, but all of a listener's routes are
+/// configured alike; there are no per-route overrides.
+#[derive(Copy, Clone, Debug)]
+pub struct Queue {
+    /// The most requests that may wait at once.
+    pub capacity: usize,
+
+    /// The longest that a request may wait.
+    pub max_wait: Duration,
+}
+
+/// Configures the rate limits of routes, each of which has a token bucket.
+///
+/// Requests that find their route's bucket empty wait for it to refill, for
+/// up to `max_wait`. Requests that would wait longer are rejected.
+#[derive(Clone, Debug)]
+pub struct RateLimits<K: Hash + Eq> {
+    /// The limits of the routes that have one. Other routes aren't limited.
+    pub limits: HashMap<K, RateLimit>,
+
+    /// The longest that a request may wait for its route's bucket to refill.
+    pub max_wait: Duration,
+}
+
+/// The rate limit of a single route.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RateLimit {
+    /// The number of requests per second at which the bucket refills.
+    pub rate: u32,
+
+    /// The capacity of the bucket, i.e. the most requests that may be sent
+    /// at once after the route was idle.
+    pub burst: u32,
+}
+
+/// Configures how long to wait before reconnecting after a connection fails.
+#[derive(Copy, Clone, Debug)]
+pub struct ReconnectBackoff {
+    /// How long to wait after the first failure. Each consecutive failure
+    /// waits twice as long as the last.
+    pub base: Duration,
+
+    /// The longest to wait after a failure.
+    pub max: Duration,
+
+    /// The ratio of each wait that's randomized, so that connections that
+    /// failed together aren't all retried together.
+    pub jitter: f32,
+}
+
+/// Limits how many connections a listener may have open at once.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ConnectionLimit {
+    pub max: usize,
+
+    /// What the listener does while it's at the limit.
+    pub over_limit: OverLimit,
+}
+
+/// What a listener does while it has as many connections open as it may.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OverLimit {
+    /// The listener stops accepting connections until one of its connections
+    /// closes, so that new connections wait in the listen backlog.
+    Pause,
+
+    /// The listener accepts new connections and closes them immediately.
+    Close,
+}
+
+/// A fault that's injected into a share of the requests to an authority.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fault {
+    /// The authority of the requests into which the fault is injected,
+    /// normalized so that it has a port.
+    pub authority: http::uri::Authority,
+
+    /// The percentage of the authority's requests into which the fault is
+    /// injected, from 0 to 100.
+    pub percent: f32,
+
+    pub kind: FaultKind,
+}
+
+/// How a request is disrupted by an injected fault.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FaultKind {
+    /// The response is delayed by a random duration between `min` and `max`.
+    Delay { min: Duration, max: Duration },
+
+    /// The request isn't sent, and is answered with this status.
+    Abort(http::StatusCode),
+
+    /// The request isn't sent, and is answered with this `grpc-status`.
+    GrpcAbort(u32),
+
+    /// The request isn't sent, and its stream is reset.
+    Reset,
+}
+
 /// Configuration settings for binding a listener.
 ///
 /// TODO: Rename this to be more inline with the actual types.
@@ -81,8 +382,16 @@ pub struct Listener {
 
 /// A logical address. This abstracts over the various strategies for cross
 /// process communication.
-#[derive(Clone, Copy, Debug)]
-pub struct Addr(SocketAddr);
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Addr {
+    /// A TCP socket address, written as `tcp://<ip>:<port>`.
+    Tcp(SocketAddr),
+
+    /// The path of a Unix domain socket, written as `unix:///<path>`.
+    ///
+    /// Accepted connections from unnamed Unix sockets have an empty path.
+    Unix(PathBuf),
+}
 
 /// Errors produced when loading a `Config` struct.
 #[derive(Clone, Debug)]
@@ -95,6 +404,14 @@ pub enum ParseError {
     EnvironmentUnsupported,
     NotADuration,
     NotANumber,
+    NotABoolean,
+    NotALoadMetric,
+    NotAnOverLimitAction,
+    NotAConnectionLimit,
+    NotAQueueCapacity,
+    NotARatio,
+    NotAFault,
+    NotARateLimit,
     HostIsNotAnIpAddress,
     NotUnicode,
     UrlError(UrlError),
@@ -114,6 +431,9 @@ pub enum UrlError {
     /// The URL is missing the authority part.
     AuthorityError(HostAndPortError),
 
+    /// A Unix socket URL is not an absolute path.
+    UnixPathNotAbsolute,
+
     /// The URL contains a path component that isn't "/", which isn't allowed.
     PathNotAllowed,
 }
@@ -134,17 +454,85 @@ pub struct TestEnv {
 }
 
 // Environment variables to look at when loading the configuration
+//
+// The private forward, control, and metrics addresses may be Unix sockets.
+// The public and private listeners must be TCP, since their original
+// destinations are read from the TCP socket.
 const ENV_EVENT_BUFFER_CAPACITY: &str = "CONDUIT_PROXY_EVENT_BUFFER_CAPACITY";
 pub const ENV_PRIVATE_LISTENER: &str = "CONDUIT_PROXY_PRIVATE_LISTENER";
 pub const ENV_PRIVATE_FORWARD: &str = "CONDUIT_PROXY_PRIVATE_FORWARD";
+pub const ENV_PRIVATE_FORWARD_PORTS: &str = "CONDUIT_PROXY_PRIVATE_FORWARD_PORTS";
 pub const ENV_PUBLIC_LISTENER: &str = "CONDUIT_PROXY_PUBLIC_LISTENER";
 pub const ENV_CONTROL_LISTENER: &str = "CONDUIT_PROXY_CONTROL_LISTENER";
 pub const ENV_METRICS_LISTENER: &str = "CONDUIT_PROXY_METRICS_LISTENER";
 pub const ENV_METRICS_RETAIN_IDLE: &str = "CONDUIT_PROXY_METRICS_RETAIN_IDLE";
 const ENV_PRIVATE_CONNECT_TIMEOUT: &str = "CONDUIT_PROXY_PRIVATE_CONNECT_TIMEOUT";
 const ENV_PUBLIC_CONNECT_TIMEOUT: &str = "CONDUIT_PROXY_PUBLIC_CONNECT_TIMEOUT";
+pub const ENV_PROTOCOL_DETECTION_TIMEOUT: &str = "CONDUIT_PROXY_PROTOCOL_DETECTION_TIMEOUT";
 pub const ENV_BIND_TIMEOUT: &str = "CONDUIT_PROXY_BIND_TIMEOUT";
 
+pub const ENV_OUTBOUND_RETRIES_ENABLED: &str = "CONDUIT_PROXY_OUTBOUND_RETRIES_ENABLED";
+pub const ENV_OUTBOUND_RETRY_BUDGET_RATIO: &str = "CONDUIT_PROXY_OUTBOUND_RETRY_BUDGET_RATIO";
+pub const ENV_OUTBOUND_RETRY_BUDGET_MIN_PER_SECOND: &str = "CONDUIT_PROXY_OUTBOUND_RETRY_BUDGET_MIN_PER_SECOND";
+pub const ENV_OUTBOUND_RETRY_HTTP_STATUSES: &str = "CONDUIT_PROXY_OUTBOUND_RETRY_HTTP_STATUSES";
+pub const ENV_OUTBOUND_RETRY_GRPC_STATUSES: &str = "CONDUIT_PROXY_OUTBOUND_RETRY_GRPC_STATUSES";
+
+pub const ENV_OUTBOUND_OUTLIER_DETECTION_ENABLED: &str = "CONDUIT_PROXY_OUTBOUND_OUTLIER_DETECTION_ENABLED";
+pub const ENV_OUTBOUND_OUTLIER_CONSECUTIVE_FAILURES: &str = "CONDUIT_PROXY_OUTBOUND_OUTLIER_CONSECUTIVE_FAILURES";
+pub const ENV_OUTBOUND_OUTLIER_FAILURE_RATE: &str = "CONDUIT_PROXY_OUTBOUND_OUTLIER_FAILURE_RATE";
+pub const ENV_OUTBOUND_OUTLIER_MIN_REQUESTS: &str = "CONDUIT_PROXY_OUTBOUND_OUTLIER_MIN_REQUESTS";
+pub const ENV_OUTBOUND_OUTLIER_WINDOW: &str = "CONDUIT_PROXY_OUTBOUND_OUTLIER_WINDOW";
+pub const ENV_OUTBOUND_OUTLIER_BASE_EJECTION_TIME: &str = "CONDUIT_PROXY_OUTBOUND_OUTLIER_BASE_EJECTION_TIME";
+pub const ENV_OUTBOUND_OUTLIER_MAX_EJECTION_TIME: &str = "CONDUIT_PROXY_OUTBOUND_OUTLIER_MAX_EJECTION_TIME";
+pub const ENV_OUTBOUND_OUTLIER_MAX_EJECTED_RATIO: &str = "CONDUIT_PROXY_OUTBOUND_OUTLIER_MAX_EJECTED_RATIO";
+
+// Either `pending-requests` or `peak-ewma`. The peak-EWMA settings have no
+// effect when pending requests are used.
+pub const ENV_OUTBOUND_LOAD_METRIC: &str = "CONDUIT_PROXY_OUTBOUND_LOAD_METRIC";
+pub const ENV_OUTBOUND_PEAK_EWMA_DEFAULT_RTT: &str = "CONDUIT_PROXY_OUTBOUND_PEAK_EWMA_DEFAULT_RTT";
+pub const ENV_OUTBOUND_PEAK_EWMA_DECAY: &str = "CONDUIT_PROXY_OUTBOUND_PEAK_EWMA_DECAY";
+
+// Locality-aware load balancing is enabled only when the proxy's locality is
+// set. Endpoints are in the same locality when the Destination service labels
+// them with the same value for the locality label.
+pub const ENV_LOCALITY: &str = "CONDUIT_PROXY_LOCALITY";
+pub const ENV_LOCALITY_LABEL: &str = "CONDUIT_PROXY_LOCALITY_LABEL";
+pub const ENV_OUTBOUND_LOCALITY_MIN_HEALTHY_RATIO: &str = "CONDUIT_PROXY_OUTBOUND_LOCALITY_MIN_HEALTHY_RATIO";
+
+// Connections to endpoints and to the controller are retried after
+// exponentially longer waits while they keep failing.
+pub const ENV_RECONNECT_BACKOFF_BASE: &str = "CONDUIT_PROXY_RECONNECT_BACKOFF_BASE";
+pub const ENV_RECONNECT_BACKOFF_MAX: &str = "CONDUIT_PROXY_RECONNECT_BACKOFF_MAX";
+pub const ENV_RECONNECT_BACKOFF_JITTER: &str = "CONDUIT_PROXY_RECONNECT_BACKOFF_JITTER";
+
+// Limits the number of connections that each proxy listener may have open at
+// once. While a listener is at its limit, it either stops accepting connections
+// (`pause`, the default) or accepts and immediately closes them (`close`).
+pub const ENV_INBOUND_MAX_CONNECTIONS: &str = "CONDUIT_PROXY_INBOUND_MAX_CONNECTIONS";
+pub const ENV_OUTBOUND_MAX_CONNECTIONS: &str = "CONDUIT_PROXY_OUTBOUND_MAX_CONNECTIONS";
+pub const ENV_INBOUND_CONNECTIONS_OVER_LIMIT: &str = "CONDUIT_PROXY_INBOUND_CONNECTIONS_OVER_LIMIT";
+pub const ENV_OUTBOUND_CONNECTIONS_OVER_LIMIT: &str = "CONDUIT_PROXY_OUTBOUND_CONNECTIONS_OVER_LIMIT";
+
+// When a listener fails to accept a connection, e.g. because the process has
+// run out of file descriptors, it waits before accepting again. The wait
+// doubles after each consecutive failure.
+pub const ENV_ACCEPT_BACKOFF_BASE: &str = "CONDUIT_PROXY_ACCEPT_BACKOFF_BASE";
+pub const ENV_ACCEPT_BACKOFF_MAX: &str = "CONDUIT_PROXY_ACCEPT_BACKOFF_MAX";
+
+// Faults are injected into a percentage of the requests to each authority
+// that's listed, e.g.
+// `web.prod.svc.cluster.local:80=delay:100ms-1s@10,books:8080=abort:503@5`.
+// A fault is one of `delay:<duration>`, `delay:<min>-<max>`,
+// `abort:<http-status>`, `grpc-abort:<grpc-status>`, or `reset`.
+pub const ENV_INBOUND_FAULTS: &str = "CONDUIT_PROXY_INBOUND_FAULTS";
+pub const ENV_OUTBOUND_FAULTS: &str = "CONDUIT_PROXY_OUTBOUND_FAULTS";
+
+// TLS is enabled only when all three of these are set. They are paths to
+// PEM-encoded files.
+pub const ENV_TLS_TRUST_ANCHORS: &str = "CONDUIT_PROXY_TLS_TRUST_ANCHORS";
+pub const ENV_TLS_CERT: &str = "CONDUIT_PROXY_TLS_CERT";
+pub const ENV_TLS_PRIVATE_KEY: &str = "CONDUIT_PROXY_TLS_PRIVATE_KEY";
+
 // Limits the number of HTTP routes that may be active in the proxy at any time. There is
 // an inbound route for each local port that receives connections. There is an outbound
 // route for each protocol and authority.
@@ -154,11 +542,42 @@ pub const ENV_OUTBOUND_ROUTER_CAPACITY: &str = "CONDUIT_PROXY_OUTBOUND_ROUTER_CA
 pub const ENV_INBOUND_ROUTER_MAX_IDLE_AGE: &str = "CONDUIT_PROXY_INBOUND_ROUTER_MAX_IDLE_AGE";
 pub const ENV_OUTBOUND_ROUTER_MAX_IDLE_AGE: &str = "CONDUIT_PROXY_OUTBOUND_ROUTER_MAX_IDLE_AGE";
 
+// Requests wait in a queue for each route's service. When a queue is full, or
+// a request waits in it too long, the request fails with a 503. The capacity
+// and maximum wait apply to each of a listener's routes alike.
+pub const ENV_INBOUND_QUEUE_CAPACITY: &str = "CONDUIT_PROXY_INBOUND_QUEUE_CAPACITY";
+pub const ENV_OUTBOUND_QUEUE_CAPACITY: &str = "CONDUIT_PROXY_OUTBOUND_QUEUE_CAPACITY";
+pub const ENV_INBOUND_QUEUE_MAX_WAIT: &str = "CONDUIT_PROXY_INBOUND_QUEUE_MAX_WAIT";
+pub const ENV_OUTBOUND_QUEUE_MAX_WAIT: &str = "CONDUIT_PROXY_OUTBOUND_QUEUE_MAX_WAIT";
+
+pub const ENV_INBOUND_MAX_IN_FLIGHT: &str = "CONDUIT_PROXY_INBOUND_MAX_IN_FLIGHT";
+pub const ENV_OUTBOUND_MAX_IN_FLIGHT: &str = "CONDUIT_PROXY_OUTBOUND_MAX_IN_FLIGHT";
+
+pub const ENV_DESTINATION_PROFILES_ENABLED: &str = "CONDUIT_PROXY_DESTINATION_PROFILES_ENABLED";
+pub const ENV_OUTBOUND_MIRROR_MAX_BODY_BYTES: &str = "CONDUIT_PROXY_OUTBOUND_MIRROR_MAX_BODY_BYTES";
+pub const ENV_OUTBOUND_MIRROR_TIMEOUT: &str = "CONDUIT_PROXY_OUTBOUND_MIRROR_TIMEOUT";
+
+// Requests are rate limited by the port of their original destination when
+// they're inbound, e.g. `8080=100,9090=10:50`, and by the authority of their
+// destination when they're outbound, e.g. `books.default.svc.cluster.local:8080=100`.
+// Each limit is written as `<requests-per-second>[:<burst>]`; the burst is the
+// rate by default. Requests over the limit wait up to the max wait for it
+// before they're rejected with a 429.
+pub const ENV_INBOUND_RATE_LIMITS: &str = "CONDUIT_PROXY_INBOUND_RATE_LIMITS";
+pub const ENV_OUTBOUND_RATE_LIMITS: &str = "CONDUIT_PROXY_OUTBOUND_RATE_LIMITS";
+pub const ENV_INBOUND_RATE_LIMIT_MAX_WAIT: &str = "CONDUIT_PROXY_INBOUND_RATE_LIMIT_MAX_WAIT";
+pub const ENV_OUTBOUND_RATE_LIMIT_MAX_WAIT: &str = "CONDUIT_PROXY_OUTBOUND_RATE_LIMIT_MAX_WAIT";
+
 // These *disable* our protocol detection for connections whose SO_ORIGINAL_DST
 // has a port in the provided list.
 pub const ENV_INBOUND_PORTS_DISABLE_PROTOCOL_DETECTION: &str = "CONDUIT_PROXY_INBOUND_PORTS_DISABLE_PROTOCOL_DETECTION";
 pub const ENV_OUTBOUND_PORTS_DISABLE_PROTOCOL_DETECTION: &str = "CONDUIT_PROXY_OUTBOUND_PORTS_DISABLE_PROTOCOL_DETECTION";
 
+// These enable HAProxy's PROXY protocol, which is disabled by default.
+pub const ENV_INBOUND_ACCEPT_PROXY_PROTOCOL: &str = "CONDUIT_PROXY_INBOUND_ACCEPT_PROXY_PROTOCOL";
+pub const ENV_OUTBOUND_ACCEPT_PROXY_PROTOCOL: &str = "CONDUIT_PROXY_OUTBOUND_ACCEPT_PROXY_PROTOCOL";
+pub const ENV_INBOUND_SEND_PROXY_PROTOCOL: &str = "CONDUIT_PROXY_INBOUND_SEND_PROXY_PROTOCOL";
+
 pub const ENV_POD_NAMESPACE: &str = "CONDUIT_PROXY_POD_NAMESPACE";
 
 pub const ENV_CONTROL_URL: &str = "CONDUIT_PROXY_CONTROL_URL";
@@ -176,6 +595,38 @@ const DEFAULT_PUBLIC_CONNECT_TIMEOUT: Duration = Duration::from_millis(300);
 const DEFAULT_BIND_TIMEOUT: Duration = Duration::from_secs(10); // same as in Linkerd
 const DEFAULT_RESOLV_CONF: &str = "/etc/resolv.conf";
 
+// Allow retries to add up to 20% more load to a destination, as well as a
+// trickle of retries when it's receiving little traffic.
+const DEFAULT_OUTBOUND_RETRY_BUDGET_RATIO: f32 = 0.2;
+const DEFAULT_OUTBOUND_RETRY_BUDGET_MIN_PER_SECOND: u32 = 10;
+const DEFAULT_OUTBOUND_RETRY_HTTP_STATUSES: &[u16] = &[502, 503];
+const DEFAULT_OUTBOUND_RETRY_GRPC_STATUSES: &[u32] = &[
+    14, // UNAVAILABLE
+];
+
+// Outlier detection never ejects more than half of a load balancer's
+// endpoints, so it has no effect on destinations with a single endpoint.
+const DEFAULT_OUTBOUND_OUTLIER_CONSECUTIVE_FAILURES: u32 = 5;
+const DEFAULT_OUTBOUND_OUTLIER_FAILURE_RATE: f32 = 0.5;
+const DEFAULT_OUTBOUND_OUTLIER_MIN_REQUESTS: u32 = 10;
+const DEFAULT_OUTBOUND_OUTLIER_WINDOW: Duration = Duration::from_secs(10);
+const DEFAULT_OUTBOUND_OUTLIER_BASE_EJECTION_TIME: Duration = Duration::from_secs(30);
+const DEFAULT_OUTBOUND_OUTLIER_MAX_EJECTION_TIME: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_OUTBOUND_OUTLIER_MAX_EJECTED_RATIO: f32 = 0.5;
+
+const DEFAULT_OUTBOUND_PEAK_EWMA_DEFAULT_RTT: Duration = Duration::from_millis(30);
+const DEFAULT_OUTBOUND_PEAK_EWMA_DECAY: Duration = Duration::from_secs(10);
+
+const DEFAULT_LOCALITY_LABEL: &str = "zone";
+const DEFAULT_OUTBOUND_LOCALITY_MIN_HEALTHY_RATIO: f32 = 0.7;
+
+const DEFAULT_RECONNECT_BACKOFF_BASE: Duration = Duration::from_millis(100);
+const DEFAULT_RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(10);
+const DEFAULT_RECONNECT_BACKOFF_JITTER: f32 = 0.5;
+
+const DEFAULT_ACCEPT_BACKOFF_BASE: Duration = Duration::from_millis(10);
+const DEFAULT_ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(1);
+
 /// It's assumed that a typical proxy can serve inbound traffic for up to 100 pod-local
 /// HTTP services and may communicate with up to 10K external HTTP domains.
 const DEFAULT_INBOUND_ROUTER_CAPACITY:  usize = 100;
@@ -184,6 +635,21 @@ const DEFAULT_OUTBOUND_ROUTER_CAPACITY: usize = 10_000;
 const DEFAULT_INBOUND_ROUTER_MAX_IDLE_AGE:  Duration = Duration::from_secs(60);
 const DEFAULT_OUTBOUND_ROUTER_MAX_IDLE_AGE: Duration = Duration::from_secs(60);
 
+const DEFAULT_INBOUND_QUEUE_CAPACITY: usize = 1_000;
+const DEFAULT_OUTBOUND_QUEUE_CAPACITY: usize = 1_000;
+const DEFAULT_INBOUND_QUEUE_MAX_WAIT: Duration = Duration::from_secs(10);
+const DEFAULT_OUTBOUND_QUEUE_MAX_WAIT: Duration = Duration::from_secs(10);
+
+const DEFAULT_INBOUND_MAX_IN_FLIGHT: usize = 10_000;
+const DEFAULT_OUTBOUND_MAX_IN_FLIGHT: usize = 10_000;
+
+// By default, requests over a rate limit are rejected without waiting.
+const DEFAULT_INBOUND_RATE_LIMIT_MAX_WAIT: Duration = Duration::from_secs(0);
+const DEFAULT_OUTBOUND_RATE_LIMIT_MAX_WAIT: Duration = Duration::from_secs(0);
+
+const DEFAULT_OUTBOUND_MIRROR_MAX_BODY_BYTES: usize = 64 * 1024;
+const DEFAULT_OUTBOUND_MIRROR_TIMEOUT: Duration = Duration::from_secs(10);
+
 // By default, we keep a list of known assigned ports of server-first protocols.
 //
 // https://www.iana.org/assignments/service-names-port-numbers/service-names-port-numbers.txt
@@ -201,20 +667,75 @@ impl<'a> TryFrom<&'a Strings> for Config {
         // Parse all the environment variables. `env_var` and `env_var_parse`
         // will log any errors so defer returning any errors until all of them
         // have been parsed.
-        let private_listener_addr = parse(strings, ENV_PRIVATE_LISTENER, str::parse);
-        let public_listener_addr = parse(strings, ENV_PUBLIC_LISTENER, str::parse);
+        let private_listener_addr = parse(strings, ENV_PRIVATE_LISTENER, parse_tcp_addr);
+        let public_listener_addr = parse(strings, ENV_PUBLIC_LISTENER, parse_tcp_addr);
         let control_listener_addr = parse(strings, ENV_CONTROL_LISTENER, str::parse);
         let metrics_listener_addr = parse(strings, ENV_METRICS_LISTENER, str::parse);
         let private_forward = parse(strings, ENV_PRIVATE_FORWARD, str::parse);
+        let private_forward_ports = parse(strings, ENV_PRIVATE_FORWARD_PORTS, parse_port_set);
         let public_connect_timeout = parse(strings, ENV_PUBLIC_CONNECT_TIMEOUT, parse_duration);
         let private_connect_timeout = parse(strings, ENV_PRIVATE_CONNECT_TIMEOUT, parse_duration);
+        let protocol_detection_timeout = parse(strings, ENV_PROTOCOL_DETECTION_TIMEOUT, parse_duration);
         let inbound_disable_ports = parse(strings, ENV_INBOUND_PORTS_DISABLE_PROTOCOL_DETECTION, parse_port_set);
         let outbound_disable_ports = parse(strings, ENV_OUTBOUND_PORTS_DISABLE_PROTOCOL_DETECTION, parse_port_set);
+        let inbound_accept_proxy_protocol = parse(strings, ENV_INBOUND_ACCEPT_PROXY_PROTOCOL, parse_bool);
+        let outbound_accept_proxy_protocol = parse(strings, ENV_OUTBOUND_ACCEPT_PROXY_PROTOCOL, parse_bool);
+        let inbound_send_proxy_protocol = parse(strings, ENV_INBOUND_SEND_PROXY_PROTOCOL, parse_bool);
         let inbound_router_capacity = parse(strings, ENV_INBOUND_ROUTER_CAPACITY, parse_number);
         let outbound_router_capacity = parse(strings, ENV_OUTBOUND_ROUTER_CAPACITY, parse_number);
         let inbound_router_max_idle_age = parse(strings, ENV_INBOUND_ROUTER_MAX_IDLE_AGE, parse_duration);
         let outbound_router_max_idle_age = parse(strings, ENV_OUTBOUND_ROUTER_MAX_IDLE_AGE, parse_duration);
+        let inbound_queue_capacity = parse(strings, ENV_INBOUND_QUEUE_CAPACITY, parse_queue_capacity);
+        let outbound_queue_capacity = parse(strings, ENV_OUTBOUND_QUEUE_CAPACITY, parse_queue_capacity);
+        let inbound_queue_max_wait = parse(strings, ENV_INBOUND_QUEUE_MAX_WAIT, parse_duration);
+        let outbound_queue_max_wait = parse(strings, ENV_OUTBOUND_QUEUE_MAX_WAIT, parse_duration);
+        let inbound_max_in_flight = parse(strings, ENV_INBOUND_MAX_IN_FLIGHT, parse_number);
+        let outbound_max_in_flight = parse(strings, ENV_OUTBOUND_MAX_IN_FLIGHT, parse_number);
+        let inbound_rate_limits = parse(strings, ENV_INBOUND_RATE_LIMITS, |s| {
+            parse_rate_limits(s, parse_number::<u16>)
+        });
+        let outbound_rate_limits = parse(strings, ENV_OUTBOUND_RATE_LIMITS, |s| {
+            parse_rate_limits(s, parse_authority)
+        });
+        let inbound_rate_limit_max_wait = parse(strings, ENV_INBOUND_RATE_LIMIT_MAX_WAIT, parse_duration);
+        let outbound_rate_limit_max_wait = parse(strings, ENV_OUTBOUND_RATE_LIMIT_MAX_WAIT, parse_duration);
+        let destination_profiles_enabled = parse(strings, ENV_DESTINATION_PROFILES_ENABLED, parse_bool);
+        let outbound_mirror_max_body_bytes = parse(strings, ENV_OUTBOUND_MIRROR_MAX_BODY_BYTES, parse_number);
+        let outbound_mirror_timeout = parse(strings, ENV_OUTBOUND_MIRROR_TIMEOUT, parse_duration);
         let bind_timeout = parse(strings, ENV_BIND_TIMEOUT, parse_duration);
+        let outbound_retries_enabled = parse(strings, ENV_OUTBOUND_RETRIES_ENABLED, parse_bool);
+        let outbound_retry_budget_ratio = parse(strings, ENV_OUTBOUND_RETRY_BUDGET_RATIO, parse_unbounded_ratio);
+        let outbound_retry_budget_min_per_second = parse(strings, ENV_OUTBOUND_RETRY_BUDGET_MIN_PER_SECOND, parse_number);
+        let outbound_retry_http_statuses = parse(strings, ENV_OUTBOUND_RETRY_HTTP_STATUSES, parse_number_set);
+        let outbound_retry_grpc_statuses = parse(strings, ENV_OUTBOUND_RETRY_GRPC_STATUSES, parse_number_set);
+        let outbound_outlier_detection_enabled = parse(strings, ENV_OUTBOUND_OUTLIER_DETECTION_ENABLED, parse_bool);
+        let outbound_outlier_consecutive_failures = parse(strings, ENV_OUTBOUND_OUTLIER_CONSECUTIVE_FAILURES, parse_number);
+        let outbound_outlier_failure_rate = parse(strings, ENV_OUTBOUND_OUTLIER_FAILURE_RATE, parse_ratio);
+        let outbound_outlier_min_requests = parse(strings, ENV_OUTBOUND_OUTLIER_MIN_REQUESTS, parse_number);
+        let outbound_outlier_window = parse(strings, ENV_OUTBOUND_OUTLIER_WINDOW, parse_duration);
+        let outbound_outlier_base_ejection_time = parse(strings, ENV_OUTBOUND_OUTLIER_BASE_EJECTION_TIME, parse_duration);
+        let outbound_outlier_max_ejection_time = parse(strings, ENV_OUTBOUND_OUTLIER_MAX_EJECTION_TIME, parse_duration);
+        let outbound_outlier_max_ejected_ratio = parse(strings, ENV_OUTBOUND_OUTLIER_MAX_EJECTED_RATIO, parse_ratio);
+        let outbound_load_metric = parse(strings, ENV_OUTBOUND_LOAD_METRIC, parse_load_metric);
+        let outbound_peak_ewma_default_rtt = parse(strings, ENV_OUTBOUND_PEAK_EWMA_DEFAULT_RTT, parse_duration);
+        let outbound_peak_ewma_decay = parse(strings, ENV_OUTBOUND_PEAK_EWMA_DECAY, parse_duration);
+        let locality = strings.get(ENV_LOCALITY);
+        let locality_label = strings.get(ENV_LOCALITY_LABEL);
+        let outbound_locality_min_healthy_ratio = parse(strings, ENV_OUTBOUND_LOCALITY_MIN_HEALTHY_RATIO, parse_ratio);
+        let reconnect_backoff_base = parse(strings, ENV_RECONNECT_BACKOFF_BASE, parse_duration);
+        let reconnect_backoff_max = parse(strings, ENV_RECONNECT_BACKOFF_MAX, parse_duration);
+        let reconnect_backoff_jitter = parse(strings, ENV_RECONNECT_BACKOFF_JITTER, parse_ratio);
+        let inbound_max_connections = parse(strings, ENV_INBOUND_MAX_CONNECTIONS, parse_max_connections);
+        let outbound_max_connections = parse(strings, ENV_OUTBOUND_MAX_CONNECTIONS, parse_max_connections);
+        let inbound_connections_over_limit = parse(strings, ENV_INBOUND_CONNECTIONS_OVER_LIMIT, parse_over_limit);
+        let outbound_connections_over_limit = parse(strings, ENV_OUTBOUND_CONNECTIONS_OVER_LIMIT, parse_over_limit);
+        let accept_backoff_base = parse(strings, ENV_ACCEPT_BACKOFF_BASE, parse_duration);
+        let accept_backoff_max = parse(strings, ENV_ACCEPT_BACKOFF_MAX, parse_duration);
+        let inbound_faults = parse(strings, ENV_INBOUND_FAULTS, parse_faults);
+        let outbound_faults = parse(strings, ENV_OUTBOUND_FAULTS, parse_faults);
+        let tls_trust_anchors = parse(strings, ENV_TLS_TRUST_ANCHORS, parse_path);
+        let tls_end_entity_cert = parse(strings, ENV_TLS_CERT, parse_path);
+        let tls_private_key = parse(strings, ENV_TLS_PRIVATE_KEY, parse_path);
         let resolv_conf_path = strings.get(ENV_RESOLV_CONF);
         let event_buffer_capacity = parse(strings, ENV_EVENT_BUFFER_CAPACITY, parse_number);
         let metrics_retain_idle = parse(strings, ENV_METRICS_RETAIN_IDLE, parse_duration);
@@ -226,6 +747,91 @@ impl<'a> TryFrom<&'a Strings> for Config {
             })
         });
 
+        let private_forward = private_forward?;
+        let private_forward_ports = match (&private_forward, private_forward_ports?) {
+            (_, Some(ports)) => Ok(ports),
+            (&Some(Addr::Unix(_)), None) => {
+                error!(
+                    "{} is not set; it is required when {} is a Unix socket",
+                    ENV_PRIVATE_FORWARD_PORTS,
+                    ENV_PRIVATE_FORWARD,
+                );
+                Err(Error::InvalidEnvVar)
+            },
+            (_, None) => Ok(IndexSet::new()),
+        };
+
+        let tls_settings = match (tls_trust_anchors?, tls_end_entity_cert?, tls_private_key?) {
+            (Some(trust_anchors), Some(end_entity_cert), Some(private_key)) => {
+                Ok(Some(tls::CommonSettings {
+                    trust_anchors,
+                    end_entity_cert,
+                    private_key,
+                }))
+            },
+            (None, None, None) => Ok(None),
+            (trust_anchors, end_entity_cert, private_key) => {
+                if trust_anchors.is_none() {
+                    error!("{} is not set; it is required when TLS is enabled", ENV_TLS_TRUST_ANCHORS);
+                }
+                if end_entity_cert.is_none() {
+                    error!("{} is not set; it is required when TLS is enabled", ENV_TLS_CERT);
+                }
+                if private_key.is_none() {
+                    error!("{} is not set; it is required when TLS is enabled", ENV_TLS_PRIVATE_KEY);
+                }
+                Err(Error::InvalidEnvVar)
+            },
+        };
+
+        let reconnect_backoff = check_backoff(
+            ReconnectBackoff {
+                base: reconnect_backoff_base?.unwrap_or(DEFAULT_RECONNECT_BACKOFF_BASE),
+                max: reconnect_backoff_max?.unwrap_or(DEFAULT_RECONNECT_BACKOFF_MAX),
+                jitter: reconnect_backoff_jitter?.unwrap_or(DEFAULT_RECONNECT_BACKOFF_JITTER),
+            },
+            ENV_RECONNECT_BACKOFF_BASE,
+            ENV_RECONNECT_BACKOFF_MAX,
+        );
+
+        let accept_backoff = check_backoff(
+            ReconnectBackoff {
+                base: accept_backoff_base?.unwrap_or(DEFAULT_ACCEPT_BACKOFF_BASE),
+                max: accept_backoff_max?.unwrap_or(DEFAULT_ACCEPT_BACKOFF_MAX),
+                jitter: DEFAULT_RECONNECT_BACKOFF_JITTER,
+            },
+            ENV_ACCEPT_BACKOFF_BASE,
+            ENV_ACCEPT_BACKOFF_MAX,
+        );
+
+        let outbound_retries = Retries {
+            budget_ratio: outbound_retry_budget_ratio?
+                .unwrap_or(DEFAULT_OUTBOUND_RETRY_BUDGET_RATIO),
+            budget_min_per_second: outbound_retry_budget_min_per_second?
+                .unwrap_or(DEFAULT_OUTBOUND_RETRY_BUDGET_MIN_PER_SECOND),
+            http_statuses: outbound_retry_http_statuses?
+                .unwrap_or_else(|| IndexSet::from_iter(DEFAULT_OUTBOUND_RETRY_HTTP_STATUSES.iter().cloned())),
+            grpc_statuses: outbound_retry_grpc_statuses?
+                .unwrap_or_else(|| IndexSet::from_iter(DEFAULT_OUTBOUND_RETRY_GRPC_STATUSES.iter().cloned())),
+        };
+
+        let outbound_outlier_detection = OutlierDetection {
+            consecutive_failures: outbound_outlier_consecutive_failures?
+                .unwrap_or(DEFAULT_OUTBOUND_OUTLIER_CONSECUTIVE_FAILURES),
+            failure_rate: outbound_outlier_failure_rate?
+                .unwrap_or(DEFAULT_OUTBOUND_OUTLIER_FAILURE_RATE),
+            min_requests: outbound_outlier_min_requests?
+                .unwrap_or(DEFAULT_OUTBOUND_OUTLIER_MIN_REQUESTS),
+            window: outbound_outlier_window?
+                .unwrap_or(DEFAULT_OUTBOUND_OUTLIER_WINDOW),
+            base_ejection_time: outbound_outlier_base_ejection_time?
+                .unwrap_or(DEFAULT_OUTBOUND_OUTLIER_BASE_EJECTION_TIME),
+            max_ejection_time: outbound_outlier_max_ejection_time?
+                .unwrap_or(DEFAULT_OUTBOUND_OUTLIER_MAX_EJECTION_TIME),
+            max_ejected_ratio: outbound_outlier_max_ejected_ratio?
+                .unwrap_or(DEFAULT_OUTBOUND_OUTLIER_MAX_EJECTED_RATIO),
+        };
+
         // There is no default controller URL because a default would make it
         // too easy to connect to the wrong controller, which would be dangerous.
         let control_host_and_port = match parse(strings, ENV_CONTROL_URL, parse_url) {
@@ -254,18 +860,25 @@ impl<'a> TryFrom<&'a Strings> for Config {
                 addr: metrics_listener_addr?
                     .unwrap_or_else(|| Addr::from_str(DEFAULT_METRICS_LISTENER).unwrap()),
             },
-            private_forward: private_forward?,
+            private_forward,
+            private_forward_ports: private_forward_ports?,
 
             public_connect_timeout: public_connect_timeout?
                 .unwrap_or(DEFAULT_PUBLIC_CONNECT_TIMEOUT),
             private_connect_timeout: private_connect_timeout?
                 .unwrap_or(DEFAULT_PRIVATE_CONNECT_TIMEOUT),
 
+            protocol_detection_timeout: protocol_detection_timeout?,
+
             inbound_ports_disable_protocol_detection: inbound_disable_ports?
                 .unwrap_or_else(|| default_disable_ports_protocol_detection()),
             outbound_ports_disable_protocol_detection: outbound_disable_ports?
                 .unwrap_or_else(|| default_disable_ports_protocol_detection()),
 
+            inbound_accept_proxy_protocol: inbound_accept_proxy_protocol?.unwrap_or(false),
+            outbound_accept_proxy_protocol: outbound_accept_proxy_protocol?.unwrap_or(false),
+            inbound_send_proxy_protocol: inbound_send_proxy_protocol?.unwrap_or(false),
+
             inbound_router_capacity: inbound_router_capacity?
                 .unwrap_or(DEFAULT_INBOUND_ROUTER_CAPACITY),
             outbound_router_capacity: outbound_router_capacity?
@@ -276,6 +889,37 @@ impl<'a> TryFrom<&'a Strings> for Config {
             outbound_router_max_idle_age: outbound_router_max_idle_age?
                 .unwrap_or(DEFAULT_OUTBOUND_ROUTER_MAX_IDLE_AGE),
 
+            inbound_queue: Queue {
+                capacity: inbound_queue_capacity?.unwrap_or(DEFAULT_INBOUND_QUEUE_CAPACITY),
+                max_wait: inbound_queue_max_wait?.unwrap_or(DEFAULT_INBOUND_QUEUE_MAX_WAIT),
+            },
+            outbound_queue: Queue {
+                capacity: outbound_queue_capacity?.unwrap_or(DEFAULT_OUTBOUND_QUEUE_CAPACITY),
+                max_wait: outbound_queue_max_wait?.unwrap_or(DEFAULT_OUTBOUND_QUEUE_MAX_WAIT),
+            },
+
+            inbound_max_in_flight: inbound_max_in_flight?
+                .unwrap_or(DEFAULT_INBOUND_MAX_IN_FLIGHT),
+            outbound_max_in_flight: outbound_max_in_flight?
+                .unwrap_or(DEFAULT_OUTBOUND_MAX_IN_FLIGHT),
+
+            inbound_rate_limits: RateLimits {
+                limits: inbound_rate_limits?.unwrap_or_default(),
+                max_wait: inbound_rate_limit_max_wait?
+                    .unwrap_or(DEFAULT_INBOUND_RATE_LIMIT_MAX_WAIT),
+            },
+            outbound_rate_limits: RateLimits {
+                limits: outbound_rate_limits?.unwrap_or_default(),
+                max_wait: outbound_rate_limit_max_wait?
+                    .unwrap_or(DEFAULT_OUTBOUND_RATE_LIMIT_MAX_WAIT),
+            },
+
+            destination_profiles_enabled: destination_profiles_enabled?.unwrap_or(false),
+            outbound_mirror_max_body_bytes: outbound_mirror_max_body_bytes?
+                .unwrap_or(DEFAULT_OUTBOUND_MIRROR_MAX_BODY_BYTES),
+            outbound_mirror_timeout: outbound_mirror_timeout?
+                .unwrap_or(DEFAULT_OUTBOUND_MIRROR_TIMEOUT),
+
             resolv_conf_path: resolv_conf_path?
                 .unwrap_or(DEFAULT_RESOLV_CONF.into())
                 .into(),
@@ -286,6 +930,63 @@ impl<'a> TryFrom<&'a Strings> for Config {
 
             bind_timeout: bind_timeout?.unwrap_or(DEFAULT_BIND_TIMEOUT),
 
+            outbound_retries: if outbound_retries_enabled?.unwrap_or(false) {
+                Some(outbound_retries)
+            } else {
+                None
+            },
+
+            outbound_outlier_detection: if outbound_outlier_detection_enabled?.unwrap_or(false) {
+                Some(outbound_outlier_detection)
+            } else {
+                None
+            },
+
+            outbound_load_metric: match outbound_load_metric? {
+                Some(LoadMetricName::PeakEwma) => LoadMetric::PeakEwma {
+                    default_rtt: outbound_peak_ewma_default_rtt?
+                        .unwrap_or(DEFAULT_OUTBOUND_PEAK_EWMA_DEFAULT_RTT),
+                    decay: outbound_peak_ewma_decay?
+                        .unwrap_or(DEFAULT_OUTBOUND_PEAK_EWMA_DECAY),
+                },
+                Some(LoadMetricName::PendingRequests) | None => LoadMetric::PendingRequests,
+            },
+
+            locality: match locality? {
+                Some(value) => Some(Locality {
+                    label: locality_label?
+                        .unwrap_or_else(|| DEFAULT_LOCALITY_LABEL.to_owned()),
+                    value,
+                    min_healthy_ratio: outbound_locality_min_healthy_ratio?
+                        .unwrap_or(DEFAULT_OUTBOUND_LOCALITY_MIN_HEALTHY_RATIO),
+                }),
+                None => None,
+            },
+
+            reconnect_backoff: reconnect_backoff?,
+
+            inbound_connection_limit: match inbound_max_connections? {
+                Some(max) => Some(ConnectionLimit {
+                    max,
+                    over_limit: inbound_connections_over_limit?.unwrap_or(OverLimit::Pause),
+                }),
+                None => None,
+            },
+            outbound_connection_limit: match outbound_max_connections? {
+                Some(max) => Some(ConnectionLimit {
+                    max,
+                    over_limit: outbound_connections_over_limit?.unwrap_or(OverLimit::Pause),
+                }),
+                None => None,
+            },
+
+            accept_backoff: accept_backoff?,
+
+            inbound_faults: inbound_faults?.unwrap_or_default(),
+            outbound_faults: outbound_faults?.unwrap_or_default(),
+
+            tls_settings: tls_settings?,
+
             pod_namespace: pod_namespace?,
         })
     }
@@ -297,21 +998,50 @@ fn default_disable_ports_protocol_detection() -> IndexSet<u16> {
 
 // ===== impl Addr =====
 
+const UNIX_URL_PREFIX: &str = "unix://";
+
+impl Addr {
+    /// Returns the socket address, if this is a TCP address.
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        match *self {
+            Addr::Tcp(addr) => Some(addr),
+            Addr::Unix(_) => None,
+        }
+    }
+}
+
 impl FromStr for Addr {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with(UNIX_URL_PREFIX) {
+            let path = &s[UNIX_URL_PREFIX.len()..];
+            if !path.starts_with('/') {
+                return Err(ParseError::UrlError(UrlError::UnixPathNotAbsolute));
+            }
+            return Ok(Addr::Unix(PathBuf::from(path)));
+        }
+
         let a = parse_url(s)?;
         if let Host::Ip(ip) = a.host {
-            return Ok(Addr(SocketAddr::from((ip, a.port))));
+            return Ok(Addr::Tcp(SocketAddr::from((ip, a.port))));
         }
         Err(ParseError::HostIsNotAnIpAddress)
     }
 }
 
-impl From<Addr> for SocketAddr {
-    fn from(addr: Addr) -> SocketAddr {
-        addr.0
+impl From<SocketAddr> for Addr {
+    fn from(addr: SocketAddr) -> Self {
+        Addr::Tcp(addr)
+    }
+}
+
+impl fmt::Display for Addr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Addr::Tcp(ref addr) => write!(f, "tcp://{}", addr),
+            Addr::Unix(ref path) => write!(f, "{}{}", UNIX_URL_PREFIX, path.display()),
+        }
     }
 }
 
@@ -356,6 +1086,33 @@ fn parse_number<T>(s: &str) -> Result<T, ParseError> where T: FromStr {
     s.parse().map_err(|_| ParseError::NotANumber)
 }
 
+/// Parses a ratio between 0 and 1, inclusive.
+fn parse_ratio(s: &str) -> Result<f32, ParseError> {
+    let ratio = parse_number::<f32>(s)?;
+    if !(0.0 <= ratio && ratio <= 1.0) {
+        return Err(ParseError::NotARatio);
+    }
+    Ok(ratio)
+}
+
+/// Parses a ratio that may be greater than 1, like the number of retries
+/// allowed for each request.
+fn parse_unbounded_ratio(s: &str) -> Result<f32, ParseError> {
+    let ratio = parse_number::<f32>(s)?;
+    if !(ratio.is_finite() && ratio >= 0.0) {
+        return Err(ParseError::NotARatio);
+    }
+    Ok(ratio)
+}
+
+fn parse_bool(s: &str) -> Result<bool, ParseError> {
+    match s {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(ParseError::NotABoolean),
+    }
+}
+
 fn parse_duration(s: &str) -> Result<Duration, ParseError> {
     use regex::Regex;
 
@@ -377,6 +1134,144 @@ fn parse_duration(s: &str) -> Result<Duration, ParseError> {
     }
 }
 
+/// Parses the name of a load metric.
+fn parse_load_metric(s: &str) -> Result<LoadMetricName, ParseError> {
+    match s {
+        "pending-requests" => Ok(LoadMetricName::PendingRequests),
+        "peak-ewma" => Ok(LoadMetricName::PeakEwma),
+        _ => Err(ParseError::NotALoadMetric),
+    }
+}
+
+/// Parses how many connections a listener may have open at once.
+///
+/// A listener that may have no connections open would never accept one, so
+/// a limit of zero is rejected.
+fn parse_max_connections(s: &str) -> Result<usize, ParseError> {
+    match parse_number(s)? {
+        0 => Err(ParseError::NotAConnectionLimit),
+        max => Ok(max),
+    }
+}
+
+/// Parses how many requests may wait in a route's queue.
+///
+/// A queue that can't hold any requests would shed every request that
+/// arrives while its service isn't ready, so a capacity of zero is rejected.
+fn parse_queue_capacity(s: &str) -> Result<usize, ParseError> {
+    match parse_number(s)? {
+        0 => Err(ParseError::NotAQueueCapacity),
+        capacity => Ok(capacity),
+    }
+}
+
+/// Parses what a listener does while it's at its connection limit.
+fn parse_over_limit(s: &str) -> Result<OverLimit, ParseError> {
+    match s {
+        "pause" => Ok(OverLimit::Pause),
+        "close" => Ok(OverLimit::Close),
+        _ => Err(ParseError::NotAnOverLimitAction),
+    }
+}
+
+/// Parses a comma-separated list of faults, each written as
+/// `<authority>=<fault>@<percent>`.
+fn parse_faults(s: &str) -> Result<Vec<Fault>, ParseError> {
+    s.split(',').map(|f| parse_fault(f.trim())).collect()
+}
+
+fn parse_fault(s: &str) -> Result<Fault, ParseError> {
+    let mut parts = s.splitn(2, '=');
+    let authority = parts.next().ok_or(ParseError::NotAFault)?;
+    let fault = parts.next().ok_or(ParseError::NotAFault)?;
+
+    let authority = parse_authority(authority)
+        .map_err(|_| ParseError::NotAFault)?;
+
+    let mut parts = fault.rsplitn(2, '@');
+    let percent = parse_number::<f32>(parts.next().ok_or(ParseError::NotAFault)?)?;
+    if !(0.0 <= percent && percent <= 100.0) {
+        return Err(ParseError::NotAFault);
+    }
+    let kind = parts.next().ok_or(ParseError::NotAFault)?;
+
+    let mut parts = kind.splitn(2, ':');
+    let kind = match (parts.next(), parts.next()) {
+        (Some("delay"), Some(delay)) => {
+            let mut bounds = delay.splitn(2, '-');
+            let min = parse_duration(bounds.next().ok_or(ParseError::NotAFault)?)?;
+            let max = match bounds.next() {
+                Some(max) => parse_duration(max)?,
+                None => min,
+            };
+            if max < min {
+                return Err(ParseError::NotAFault);
+            }
+            FaultKind::Delay { min, max }
+        },
+        (Some("abort"), Some(status)) => {
+            let status = http::StatusCode::from_u16(parse_number(status)?)
+                .map_err(|_| ParseError::NotAFault)?;
+            FaultKind::Abort(status)
+        },
+        (Some("grpc-abort"), Some(code)) => FaultKind::GrpcAbort(parse_number(code)?),
+        (Some("reset"), None) => FaultKind::Reset,
+        _ => return Err(ParseError::NotAFault),
+    };
+
+    Ok(Fault {
+        authority,
+        percent,
+        kind,
+    })
+}
+
+/// Parses a comma-separated list of rate limits, each written as
+/// `<key>=<rate>[:<burst>]`.
+fn parse_rate_limits<K, F>(s: &str, parse_key: F) -> Result<HashMap<K, RateLimit>, ParseError>
+where
+    K: Hash + Eq,
+    F: Fn(&str) -> Result<K, ParseError>,
+{
+    let mut limits = HashMap::new();
+    for limit in s.split(',') {
+        let mut parts = limit.trim().splitn(2, '=');
+        let key = parts.next().ok_or(ParseError::NotARateLimit)?;
+        let limit = parts.next().ok_or(ParseError::NotARateLimit)?;
+        let key = parse_key(key).map_err(|_| ParseError::NotARateLimit)?;
+
+        let mut parts = limit.splitn(2, ':');
+        let rate = parse_number(parts.next().ok_or(ParseError::NotARateLimit)?)?;
+        let burst = match parts.next() {
+            Some(burst) => parse_number(burst)?,
+            None => rate,
+        };
+        if rate == 0 || burst == 0 {
+            return Err(ParseError::NotARateLimit);
+        }
+
+        limits.insert(key, RateLimit { rate, burst });
+    }
+    Ok(limits)
+}
+
+/// Parses an authority, normalized so that its port is 80 if it doesn't have
+/// one.
+fn parse_authority(s: &str) -> Result<http::uri::Authority, ParseError> {
+    let authority = http::uri::Authority::from_str(s)
+        .map_err(|_| ParseError::UrlError(UrlError::SyntaxError))?;
+    HostAndPort::normalize(&authority, Some(80))
+        .map(|a| http::uri::Authority::from(&a))
+        .map_err(|e| ParseError::UrlError(UrlError::AuthorityError(e)))
+}
+
+fn parse_tcp_addr(s: &str) -> Result<Addr, ParseError> {
+    match s.parse()? {
+        Addr::Unix(_) => Err(ParseError::UrlError(UrlError::UnsupportedScheme)),
+        addr => Ok(addr),
+    }
+}
+
 fn parse_url(s: &str) -> Result<HostAndPort, ParseError> {
     let url = s.parse::<http::Uri>().map_err(|_| ParseError::UrlError(UrlError::SyntaxError))?;
     if url.scheme_part().map(|s| s.as_str()) != Some("tcp") {
@@ -396,14 +1291,40 @@ fn parse_url(s: &str) -> Result<HostAndPort, ParseError> {
         .map_err(|e| ParseError::UrlError(UrlError::AuthorityError(e)))
 }
 
+fn parse_path(s: &str) -> Result<PathBuf, ParseError> {
+    Ok(PathBuf::from(s))
+}
+
 fn parse_port_set(s: &str) -> Result<IndexSet<u16>, ParseError> {
+    parse_number_set(s)
+}
+
+fn parse_number_set<T>(s: &str) -> Result<IndexSet<T>, ParseError>
+where
+    T: FromStr + Hash + Eq,
+{
     let mut set = IndexSet::new();
     for num in s.split(',') {
-        set.insert(parse_number::<u16>(num)?);
+        set.insert(parse_number::<T>(num)?);
     }
     Ok(set)
 }
 
+/// Checks that a backoff's base isn't longer than its maximum, logging the
+/// variables that configure them if it is.
+fn check_backoff(backoff: ReconnectBackoff, base_name: &str, max_name: &str)
+    -> Result<ReconnectBackoff, Error>
+{
+    if backoff.base > backoff.max {
+        error!(
+            "{}={:?} is longer than {}={:?}",
+            base_name, backoff.base, max_name, backoff.max,
+        );
+        return Err(Error::InvalidEnvVar);
+    }
+    Ok(backoff)
+}
+
 fn parse<T, Parse>(strings: &Strings, name: &str, parse: Parse) -> Result<Option<T>, Error>
     where Parse: FnOnce(&str) -> Result<T, ParseError> {
     match strings.get(name)? {
@@ -489,4 +1410,345 @@ mod tests {
     fn parse_duration_number_without_unit_is_invalid() {
         assert_eq!(parse_duration("1"), Err(ParseError::NotADuration));
     }
+
+    #[test]
+    fn parse_tcp_addr_url() {
+        assert_eq!(
+            "tcp://127.0.0.1:4140".parse::<Addr>(),
+            Ok(Addr::Tcp(([127, 0, 0, 1], 4140).into()))
+        );
+        assert_eq!(
+            "tcp://localhost:4140".parse::<Addr>(),
+            Err(ParseError::HostIsNotAnIpAddress)
+        );
+    }
+
+    #[test]
+    fn parse_unix_addr_url() {
+        assert_eq!(
+            "unix:///var/run/app.sock".parse::<Addr>(),
+            Ok(Addr::Unix(PathBuf::from("/var/run/app.sock")))
+        );
+        assert_eq!(
+            "unix://var/run/app.sock".parse::<Addr>(),
+            Err(ParseError::UrlError(UrlError::UnixPathNotAbsolute))
+        );
+    }
+
+    #[test]
+    fn addr_display_round_trips() {
+        for s in &["tcp://127.0.0.1:4140", "unix:///var/run/app.sock"] {
+            let addr = s.parse::<Addr>().expect("addr");
+            assert_eq!(&addr.to_string(), s);
+        }
+    }
+
+    #[test]
+    fn listeners_must_be_tcp() {
+        assert_eq!(
+            parse_tcp_addr("unix:///var/run/proxy.sock"),
+            Err(ParseError::UrlError(UrlError::UnsupportedScheme))
+        );
+
+        let mut env = test_env();
+        env.put(ENV_PUBLIC_LISTENER, "unix:///var/run/proxy.sock".to_owned());
+        assert!(Config::try_from(&env).is_err());
+
+        let mut env = test_env();
+        env.put(ENV_PRIVATE_FORWARD, "unix:///var/run/app.sock".to_owned());
+        env.put(ENV_PRIVATE_FORWARD_PORTS, "8080".to_owned());
+        env.put(ENV_METRICS_LISTENER, "unix:///var/run/metrics.sock".to_owned());
+        let config = Config::try_from(&env).expect("config");
+        assert_eq!(
+            config.private_forward,
+            Some(Addr::Unix(PathBuf::from("/var/run/app.sock")))
+        );
+        assert_eq!(
+            config.metrics_listener.addr,
+            Addr::Unix(PathBuf::from("/var/run/metrics.sock"))
+        );
+    }
+
+    #[test]
+    fn unix_private_forward_requires_ports() {
+        let mut env = test_env();
+        env.put(ENV_PRIVATE_FORWARD, "unix:///var/run/app.sock".to_owned());
+        assert!(Config::try_from(&env).is_err());
+
+        env.put(ENV_PRIVATE_FORWARD_PORTS, "8080,8081".to_owned());
+        let config = Config::try_from(&env).expect("config");
+        assert_eq!(
+            config.private_forward_ports.iter().cloned().collect::<Vec<_>>(),
+            vec![8080, 8081]
+        );
+    }
+
+    #[test]
+    fn parse_bool_values() {
+        assert_eq!(parse_bool("true"), Ok(true));
+        assert_eq!(parse_bool("false"), Ok(false));
+        assert_eq!(parse_bool("yes"), Err(ParseError::NotABoolean));
+        assert_eq!(parse_bool("TRUE"), Err(ParseError::NotABoolean));
+    }
+
+    #[test]
+    fn parse_ratios() {
+        assert_eq!(parse_ratio("0"), Ok(0.0));
+        assert_eq!(parse_ratio("0.5"), Ok(0.5));
+        assert_eq!(parse_ratio("1"), Ok(1.0));
+        assert_eq!(parse_ratio("1.5"), Err(ParseError::NotARatio));
+        assert_eq!(parse_ratio("-0.5"), Err(ParseError::NotARatio));
+        assert_eq!(parse_ratio("NaN"), Err(ParseError::NotARatio));
+    }
+
+    #[test]
+    fn parse_unbounded_ratios() {
+        assert_eq!(parse_unbounded_ratio("0.2"), Ok(0.2));
+        assert_eq!(parse_unbounded_ratio("1.5"), Ok(1.5));
+        assert_eq!(parse_unbounded_ratio("-0.2"), Err(ParseError::NotARatio));
+        assert_eq!(parse_unbounded_ratio("inf"), Err(ParseError::NotARatio));
+        assert_eq!(parse_unbounded_ratio("NaN"), Err(ParseError::NotARatio));
+        assert_eq!(parse_unbounded_ratio("lots"), Err(ParseError::NotANumber));
+    }
+
+    #[test]
+    fn parse_queue_capacities() {
+        assert_eq!(parse_queue_capacity("100"), Ok(100));
+        assert_eq!(parse_queue_capacity("0"), Err(ParseError::NotAQueueCapacity));
+
+        let mut env = test_env();
+        env.put(ENV_OUTBOUND_QUEUE_CAPACITY, "0".to_owned());
+        assert!(Config::try_from(&env).is_err());
+    }
+
+    #[test]
+    fn parse_load_metric_names() {
+        assert_eq!(parse_load_metric("pending-requests"), Ok(LoadMetricName::PendingRequests));
+        assert_eq!(parse_load_metric("peak-ewma"), Ok(LoadMetricName::PeakEwma));
+        assert_eq!(parse_load_metric("round-robin"), Err(ParseError::NotALoadMetric));
+    }
+
+    #[test]
+    fn parse_faults_by_authority() {
+        let faults = parse_faults(
+            "web.prod.svc.cluster.local=delay:100ms-1s@10, books:8080=abort:503@2.5,\
+             books:8080=grpc-abort:14@5,authors:8080=reset@100"
+        ).expect("faults");
+        let authority = |s: &str| http::uri::Authority::from_str(s).unwrap();
+        assert_eq!(faults, vec![
+            Fault {
+                authority: authority("web.prod.svc.cluster.local:80"),
+                percent: 10.0,
+                kind: FaultKind::Delay {
+                    min: Duration::from_millis(100),
+                    max: Duration::from_secs(1),
+                },
+            },
+            Fault {
+                authority: authority("books:8080"),
+                percent: 2.5,
+                kind: FaultKind::Abort(http::StatusCode::SERVICE_UNAVAILABLE),
+            },
+            Fault {
+                authority: authority("books:8080"),
+                percent: 5.0,
+                kind: FaultKind::GrpcAbort(14),
+            },
+            Fault {
+                authority: authority("authors:8080"),
+                percent: 100.0,
+                kind: FaultKind::Reset,
+            },
+        ]);
+    }
+
+    #[test]
+    fn parse_faults_invalid() {
+        for s in &[
+            "books:8080",
+            "books:8080=reset",
+            "books:8080=reset@101",
+            "books:8080=reset:1@10",
+            "books:8080=delay:1s-100ms@10",
+            "books:8080=abort:1000@10",
+            "books:8080=explode@10",
+        ] {
+            assert_eq!(parse_faults(s), Err(ParseError::NotAFault), "{}", s);
+        }
+    }
+
+    #[test]
+    fn parse_rate_limits_by_port_and_authority() {
+        let limits = parse_rate_limits("8080=100, 9090=10:50", parse_number::<u16>).unwrap();
+        assert_eq!(limits[&8080], RateLimit { rate: 100, burst: 100 });
+        assert_eq!(limits[&9090], RateLimit { rate: 10, burst: 50 });
+
+        let limits = parse_rate_limits("books=5:1", parse_authority).unwrap();
+        let books = http::uri::Authority::from_str("books:80").unwrap();
+        assert_eq!(limits[&books], RateLimit { rate: 5, burst: 1 });
+
+        for s in &["8080", "8080=0", "8080=10:0", "books=10"] {
+            assert!(parse_rate_limits(s, parse_number::<u16>).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn rate_limits_are_disabled_by_default() {
+        let config = Config::try_from(&test_env()).expect("config");
+        assert!(config.inbound_rate_limits.limits.is_empty());
+        assert!(config.outbound_rate_limits.limits.is_empty());
+        assert_eq!(config.outbound_rate_limits.max_wait, Duration::from_secs(0));
+    }
+
+    #[test]
+    fn protocol_detection_does_not_time_out_by_default() {
+        let config = Config::try_from(&test_env()).expect("config");
+        assert_eq!(config.protocol_detection_timeout, None);
+
+        let mut env = test_env();
+        env.put(ENV_PROTOCOL_DETECTION_TIMEOUT, "10s".to_owned());
+        let config = Config::try_from(&env).expect("config");
+        assert_eq!(config.protocol_detection_timeout, Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn outlier_detection_is_disabled_by_default() {
+        let config = Config::try_from(&test_env()).expect("config");
+        assert!(config.outbound_outlier_detection.is_none());
+
+        let mut env = test_env();
+        env.put(ENV_OUTBOUND_OUTLIER_DETECTION_ENABLED, "true".to_owned());
+        let config = Config::try_from(&env).expect("config");
+        assert!(config.outbound_outlier_detection.is_some());
+
+        env.put(ENV_OUTBOUND_OUTLIER_MAX_EJECTED_RATIO, "1.5".to_owned());
+        assert!(Config::try_from(&env).is_err());
+    }
+
+    #[test]
+    fn locality_min_healthy_ratio_is_a_ratio() {
+        let mut env = test_env();
+        env.put(ENV_LOCALITY, "us-west-1a".to_owned());
+        env.put(ENV_OUTBOUND_LOCALITY_MIN_HEALTHY_RATIO, "0.5".to_owned());
+        let config = Config::try_from(&env).expect("config");
+        assert_eq!(config.locality.map(|l| l.min_healthy_ratio), Some(0.5));
+
+        env.put(ENV_OUTBOUND_LOCALITY_MIN_HEALTHY_RATIO, "70".to_owned());
+        assert!(Config::try_from(&env).is_err());
+    }
+
+    #[test]
+    fn destination_profiles_are_disabled_by_default() {
+        let config = Config::try_from(&test_env()).expect("config");
+        assert!(!config.destination_profiles_enabled);
+
+        let mut env = test_env();
+        env.put(ENV_DESTINATION_PROFILES_ENABLED, "true".to_owned());
+        let config = Config::try_from(&env).expect("config");
+        assert!(config.destination_profiles_enabled);
+    }
+
+    #[test]
+    fn peak_ewma_is_configured() {
+        let mut env = test_env();
+        env.put(ENV_OUTBOUND_LOAD_METRIC, "peak-ewma".to_owned());
+        env.put(ENV_OUTBOUND_PEAK_EWMA_DEFAULT_RTT, "100ms".to_owned());
+        let config = Config::try_from(&env).expect("config");
+        assert_eq!(config.outbound_load_metric, LoadMetric::PeakEwma {
+            default_rtt: Duration::from_millis(100),
+            decay: DEFAULT_OUTBOUND_PEAK_EWMA_DECAY,
+        });
+    }
+
+    #[test]
+    fn connection_limits_pause_by_default() {
+        let mut env = test_env();
+        env.put(ENV_INBOUND_MAX_CONNECTIONS, "100".to_owned());
+        env.put(ENV_OUTBOUND_MAX_CONNECTIONS, "200".to_owned());
+        env.put(ENV_OUTBOUND_CONNECTIONS_OVER_LIMIT, "close".to_owned());
+        let config = Config::try_from(&env).expect("config");
+        assert_eq!(config.inbound_connection_limit, Some(ConnectionLimit {
+            max: 100,
+            over_limit: OverLimit::Pause,
+        }));
+        assert_eq!(config.outbound_connection_limit, Some(ConnectionLimit {
+            max: 200,
+            over_limit: OverLimit::Close,
+        }));
+
+        let config = Config::try_from(&test_env()).expect("config");
+        assert_eq!(config.inbound_connection_limit, None);
+        assert_eq!(parse_over_limit("drop"), Err(ParseError::NotAnOverLimitAction));
+
+        let mut env = test_env();
+        env.put(ENV_INBOUND_MAX_CONNECTIONS, "0".to_owned());
+        assert!(Config::try_from(&env).is_err());
+        assert_eq!(parse_max_connections("0"), Err(ParseError::NotAConnectionLimit));
+        assert_eq!(parse_max_connections("-1"), Err(ParseError::NotANumber));
+    }
+
+    #[test]
+    fn reconnect_backoff_is_validated() {
+        let mut env = test_env();
+        env.put(ENV_RECONNECT_BACKOFF_BASE, "1s".to_owned());
+        env.put(ENV_RECONNECT_BACKOFF_MAX, "1s".to_owned());
+        env.put(ENV_RECONNECT_BACKOFF_JITTER, "0.5".to_owned());
+        let config = Config::try_from(&env).expect("config");
+        assert_eq!(config.reconnect_backoff.base, Duration::from_secs(1));
+        assert_eq!(config.reconnect_backoff.jitter, 0.5);
+
+        env.put(ENV_RECONNECT_BACKOFF_MAX, "500ms".to_owned());
+        assert!(Config::try_from(&env).is_err());
+
+        let mut env = test_env();
+        env.put(ENV_RECONNECT_BACKOFF_JITTER, "1.5".to_owned());
+        assert!(Config::try_from(&env).is_err());
+    }
+
+    #[test]
+    fn proxy_protocol_is_disabled_by_default() {
+        let config = Config::try_from(&test_env()).expect("config");
+        assert!(!config.inbound_accept_proxy_protocol);
+        assert!(!config.outbound_accept_proxy_protocol);
+        assert!(!config.inbound_send_proxy_protocol);
+    }
+
+    #[test]
+    fn mirror_timeout_is_configured() {
+        let config = Config::try_from(&test_env()).expect("config");
+        assert_eq!(config.outbound_mirror_timeout, DEFAULT_OUTBOUND_MIRROR_TIMEOUT);
+
+        let mut env = test_env();
+        env.put(ENV_OUTBOUND_MIRROR_TIMEOUT, "250ms".to_owned());
+        let config = Config::try_from(&env).expect("config");
+        assert_eq!(config.outbound_mirror_timeout, Duration::from_millis(250));
+    }
+
+    fn test_env() -> TestEnv {
+        let mut env = TestEnv::new();
+        env.put(ENV_CONTROL_URL, "tcp://127.0.0.1:8086".to_owned());
+        env.put(ENV_POD_NAMESPACE, "test".to_owned());
+        env
+    }
+
+    #[test]
+    fn tls_is_disabled_by_default() {
+        let config = Config::try_from(&test_env()).expect("config");
+        assert!(config.tls_settings.is_none());
+    }
+
+    #[test]
+    fn tls_settings_require_all_paths() {
+        let mut env = test_env();
+        env.put(ENV_TLS_TRUST_ANCHORS, "/var/conduit-io/trust-anchors.pem".to_owned());
+        env.put(ENV_TLS_CERT, "/var/conduit-io/cert.pem".to_owned());
+        assert!(Config::try_from(&env).is_err());
+
+        env.put(ENV_TLS_PRIVATE_KEY, "/var/conduit-io/private-key.pem".to_owned());
+        let config = Config::try_from(&env).expect("config");
+        let tls = config.tls_settings.expect("tls settings");
+        assert_eq!(tls.trust_anchors, PathBuf::from("/var/conduit-io/trust-anchors.pem"));
+        assert_eq!(tls.end_entity_cert, PathBuf::from("/var/conduit-io/cert.pem"));
+        assert_eq!(tls.private_key, PathBuf::from("/var/conduit-io/private-key.pem"));
+    }
 }
@@ -1,30 +1,90 @@
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
 use futures::*;
 use std;
 use std::cmp;
+use std::fmt;
 use std::io;
 use std::net::SocketAddr;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+use std::sync::Arc;
 use tokio_core::net::{TcpListener, TcpStreamNew, TcpStream};
 use tokio_core::reactor::Handle;
 use tokio_io::{AsyncRead, AsyncWrite};
+use tokio_uds::{UnixListener, UnixStream};
 
-use config::Addr;
+use accept::{Accept, Limit, Permit};
+use backoff;
+use config::{self, Addr};
+use ctx;
+use telemetry;
 use transport::GetOriginalDst;
+use transport::proxy_protocol;
+use transport::tls;
 
 pub type PlaintextSocket = TcpStream;
 
 pub struct BoundPort {
-    inner: std::net::TcpListener,
-    local_addr: SocketAddr,
+    inner: Listener,
+    local_addr: Addr,
+    limit: Option<Limit>,
+    accept_backoff: Option<config::ReconnectBackoff>,
+}
+
+enum Listener {
+    Tcp(std::net::TcpListener),
+    Unix(std::os::unix::net::UnixListener),
 }
 
 /// Initiates a client connection to the given address.
-pub fn connect(addr: &SocketAddr, executor: &Handle) -> Connecting {
-    Connecting(PlaintextSocket::connect(addr, executor))
+///
+/// If `tls` is set, the connection is upgraded to TLS once it has been
+/// established. TLS is only originated over TCP, so it is ignored when `addr`
+/// is a Unix socket.
+pub fn connect(
+    addr: &Addr,
+    tls: Option<tls::ConnectionConfig>,
+    executor: &Handle,
+) -> Connecting {
+    match *addr {
+        Addr::Tcp(ref addr) => Connecting::Plaintext {
+            connect: PlaintextSocket::connect(addr, executor),
+            tls,
+        },
+        Addr::Unix(ref path) => {
+            let connect = UnixStream::connect(path, executor).map_err(|e| {
+                // Rather than waiting, connecting to a Unix socket fails with
+                // `WouldBlock` when the listener's backlog is full. Report
+                // that as a refusal so it isn't mistaken for a pending
+                // operation, which would never be woken.
+                if e.kind() == io::ErrorKind::WouldBlock {
+                    io::Error::new(io::ErrorKind::ConnectionRefused, e)
+                } else {
+                    e
+                }
+            });
+            Connecting::Unix(Some(connect))
+        },
+    }
 }
 
 /// A socket that is in the process of connecting.
-pub struct Connecting(TcpStreamNew);
+pub enum Connecting {
+    Plaintext {
+        connect: TcpStreamNew,
+        tls: Option<tls::ConnectionConfig>,
+    },
+    UpgradeToTls(tls::ConnectAsync<PlaintextSocket>),
+    /// A Unix socket, which may still be connecting when it's returned by
+    /// `connect`.
+    Unix(Option<io::Result<UnixStream>>),
+}
+
+/// A future of a `Connection` on which TLS has been terminated.
+pub struct AcceptTls {
+    accept: tls::AcceptAsync<Prefixed<PlaintextSocket>>,
+    permit: Option<Permit>,
+}
 
 /// Abstracts a plaintext socket vs. a TLS decorated one.
 ///
@@ -42,11 +102,26 @@ pub struct Connection {
     /// When calling `read`, it's important to consume bytes from this buffer
     /// before calling `io.read`.
     peek_buf: BytesMut,
+    /// Counts this connection against its listener's connection limit until
+    /// it's dropped, if it was accepted by a limited listener.
+    permit: Option<Permit>,
 }
 
-#[derive(Debug)]
 enum Io {
     Plain(PlaintextSocket),
+    TlsServer(Box<tls::TlsStream<Prefixed<PlaintextSocket>, tls::ServerSession>>),
+    TlsClient(Box<tls::TlsStream<PlaintextSocket, tls::ClientSession>>),
+    Unix(UnixStream),
+}
+
+/// An I/O type that replays bytes that have already been read from `io`
+/// before reading from `io` again.
+///
+/// TLS is terminated on connections that have already been peeked, so the
+/// peeked bytes must be handed to the TLS session before anything else.
+pub struct Prefixed<T> {
+    prefix: Bytes,
+    io: T,
 }
 
 /// A trait describing that a type can peek bytes.
@@ -63,6 +138,13 @@ pub trait Peek {
     // buffer.
     fn peeked(&self) -> &[u8];
 
+    /// An async attempt to peek more bytes, after those that have already
+    /// been peeked.
+    ///
+    /// Returns the number of bytes that were newly peeked, which is 0 once
+    /// the connection has been closed.
+    fn poll_peek_more(&mut self) -> Poll<usize, io::Error>;
+
     /// A `Future` around `poll_peek`, returning this type instead.
     fn peek(self) -> PeekFuture<Self> where Self: Sized {
         PeekFuture {
@@ -71,6 +153,13 @@ pub trait Peek {
     }
 }
 
+/// A future of a `Connection` and the PROXY protocol header that it began
+/// with.
+#[derive(Debug)]
+pub struct ReadProxyHeader {
+    inner: Option<Connection>,
+}
+
 /// A future of when some `Peek` fulfills with some bytes.
 #[derive(Debug)]
 pub struct PeekFuture<T> {
@@ -81,44 +170,104 @@ pub struct PeekFuture<T> {
 
 impl BoundPort {
     pub fn new(addr: Addr) -> Result<Self, io::Error> {
-        let inner = std::net::TcpListener::bind(SocketAddr::from(addr))?;
-        let local_addr = inner.local_addr()?;
-        Ok(BoundPort {
-            inner,
-            local_addr,
-        })
+        match addr {
+            Addr::Tcp(addr) => {
+                let inner = std::net::TcpListener::bind(addr)?;
+                let local_addr = inner.local_addr()?;
+                Ok(BoundPort {
+                    inner: Listener::Tcp(inner),
+                    local_addr: Addr::Tcp(local_addr),
+                    limit: None,
+                    accept_backoff: None,
+                })
+            },
+            Addr::Unix(path) => {
+                remove_stale_socket(&path)?;
+                let inner = std::os::unix::net::UnixListener::bind(&path)?;
+                Ok(BoundPort {
+                    inner: Listener::Unix(inner),
+                    local_addr: Addr::Unix(path),
+                    limit: None,
+                    accept_backoff: None,
+                })
+            },
+        }
+    }
+
+    pub fn local_addr(&self) -> &Addr {
+        &self.local_addr
     }
 
-    pub fn local_addr(&self) -> SocketAddr {
-        self.local_addr
+    /// Limits how many of this port's connections may be open at once, if a
+    /// limit is given.
+    pub fn with_connection_limit(
+        mut self,
+        limit: Option<config::ConnectionLimit>,
+        sensors: &telemetry::Sensors,
+        proxy: &Arc<ctx::Proxy>,
+    ) -> Self {
+        self.limit = limit.map(|limit| Limit::new(limit, sensors, proxy));
+        self
+    }
+
+    /// Waits before accepting connections again after accepting one fails,
+    /// rather than failing the listener.
+    pub fn with_accept_backoff(mut self, backoff: config::ReconnectBackoff) -> Self {
+        self.accept_backoff = Some(backoff);
+        self
     }
 
     // Listen for incoming connections and dispatch them to the handler `f`.
     //
     // This ensures that every incoming connection has the correct options set.
-    // In the future it will also ensure that the connection is upgraded with
-    // TLS when needed.
+    // TLS isn't terminated here; the handler does that with `accept_tls` once
+    // it has peeked a ClientHello on the connection.
     pub fn listen_and_fold<T, F, Fut>(self, executor: &Handle, initial: T, f: F)
         -> Box<Future<Item = (), Error = io::Error> + 'static>
         where
-        F: Fn(T, (Connection, SocketAddr)) -> Fut + 'static,
+        F: Fn(T, (Connection, Addr)) -> Fut + 'static,
         T: 'static,
         Fut: IntoFuture<Item = T, Error = std::io::Error> + 'static {
-        let fut = TcpListener::from_listener(self.inner, &self.local_addr, &executor)
-            .expect("from_listener") // TODO: get rid of this `expect()`.
-            .incoming()
-            .fold(initial, move |b, (socket, remote_addr)| {
-                // TODO: On Linux and most other platforms it would be better
-                // to set the `TCP_NODELAY` option on the bound socket and
-                // then have the listening sockets inherit it. However, that
-                // doesn't work on all platforms and also the underlying
-                // libraries don't have the necessary API for that, so just
-                // do it here.
-                set_nodelay_or_warn(&socket);
-                f(b, (Connection::plain(socket), remote_addr))
-            });
-
-        Box::new(fut.map(|_| ()))
+        let limit = self.limit;
+        let backoff = self.accept_backoff.map(backoff::Policy::new);
+        match self.inner {
+            Listener::Tcp(inner) => {
+                let local_addr = self.local_addr.socket_addr()
+                    .expect("TCP listener must have a socket address");
+                let incoming = TcpListener::from_listener(inner, &local_addr, &executor)
+                    .expect("from_listener") // TODO: get rid of this `expect()`.
+                    .incoming();
+                let fut = Accept::new(incoming, limit, backoff, executor)
+                    .fold(initial, move |b, ((socket, remote_addr), permit)| {
+                        // TODO: On Linux and most other platforms it would be better
+                        // to set the `TCP_NODELAY` option on the bound socket and
+                        // then have the listening sockets inherit it. However, that
+                        // doesn't work on all platforms and also the underlying
+                        // libraries don't have the necessary API for that, so just
+                        // do it here.
+                        set_nodelay_or_warn(&socket);
+                        let connection = Connection::plain(socket).with_permit(permit);
+                        f(b, (connection, Addr::Tcp(remote_addr)))
+                    });
+
+                Box::new(fut.map(|_| ()))
+            },
+            Listener::Unix(inner) => {
+                let incoming = UnixListener::from_listener(inner, &executor)
+                    .expect("from_listener") // TODO: get rid of this `expect()`.
+                    .incoming();
+                let fut = Accept::new(incoming, limit, backoff, executor)
+                    .fold(initial, move |b, ((socket, remote_addr), permit)| {
+                        let remote_addr = remote_addr.as_pathname()
+                            .map(Path::to_path_buf)
+                            .unwrap_or_default();
+                        let connection = Connection::unix(socket).with_permit(permit);
+                        f(b, (connection, Addr::Unix(remote_addr)))
+                    });
+
+                Box::new(fut.map(|_| ()))
+            },
+        }
     }
 }
 
@@ -129,9 +278,57 @@ impl Future for Connecting {
     type Error = io::Error;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        let socket = try_ready!(self.0.poll());
-        set_nodelay_or_warn(&socket);
-        Ok(Async::Ready(Connection::plain(socket)))
+        loop {
+            let upgrade = match *self {
+                Connecting::Plaintext { ref mut connect, ref mut tls } => {
+                    let socket = try_ready!(connect.poll());
+                    set_nodelay_or_warn(&socket);
+                    match tls.take() {
+                        None => return Ok(Async::Ready(Connection::plain(socket))),
+                        Some(tls) => tls.config.connect(&tls.server_identity, socket),
+                    }
+                },
+                Connecting::UpgradeToTls(ref mut upgrade) => {
+                    let stream = try_ready!(upgrade.poll());
+                    return Ok(Async::Ready(Connection {
+                        io: Io::TlsClient(Box::new(stream)),
+                        peek_buf: BytesMut::new(),
+                        permit: None,
+                    }));
+                },
+                Connecting::Unix(ref mut connect) => {
+                    // The connection is established once the socket becomes
+                    // writable.
+                    if let Some(Ok(ref socket)) = *connect {
+                        if socket.poll_write().is_not_ready() {
+                            return Ok(Async::NotReady);
+                        }
+                        if let Some(e) = socket.take_error()? {
+                            return Err(e);
+                        }
+                    }
+                    let socket = connect.take().expect("polled after completed")?;
+                    return Ok(Async::Ready(Connection::unix(socket)));
+                },
+            };
+            *self = Connecting::UpgradeToTls(upgrade);
+        }
+    }
+}
+
+// ===== impl AcceptTls =====
+
+impl Future for AcceptTls {
+    type Item = Connection;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let stream = try_ready!(self.accept.poll());
+        Ok(Async::Ready(Connection {
+            io: Io::TlsServer(Box::new(stream)),
+            peek_buf: BytesMut::new(),
+            permit: self.permit.take(),
+        }))
     }
 }
 
@@ -143,15 +340,83 @@ impl Connection {
         Connection {
             io: Io::Plain(socket),
             peek_buf: BytesMut::new(),
+            permit: None,
+        }
+    }
+
+    /// A constructor of `Connection` with a Unix domain socket.
+    pub fn unix(socket: UnixStream) -> Self {
+        Connection {
+            io: Io::Unix(socket),
+            peek_buf: BytesMut::new(),
+            permit: None,
+        }
+    }
+
+    fn with_permit(mut self, permit: Option<Permit>) -> Self {
+        self.permit = permit;
+        self
+    }
+
+    /// Terminates TLS on this plaintext connection.
+    ///
+    /// Any bytes that have already been peeked are treated as the start of
+    /// the TLS handshake.
+    ///
+    /// # Panics
+    ///
+    /// If TLS has already been established on this connection, or if it is
+    /// not a TCP connection.
+    pub fn accept_tls(self, config: &tls::ServerConfig) -> AcceptTls {
+        let socket = match self.io {
+            Io::Plain(socket) => socket,
+            _ => panic!("TLS can only be accepted on a plaintext TCP connection"),
+        };
+        let io = Prefixed {
+            prefix: self.peek_buf.freeze(),
+            io: socket,
+        };
+        AcceptTls {
+            accept: config.accept(io),
+            permit: self.permit,
+        }
+    }
+
+    /// Reads the PROXY protocol header that this connection must begin with.
+    ///
+    /// Any bytes read past the end of the header are treated as having been
+    /// peeked.
+    pub fn read_proxy_header(self) -> ReadProxyHeader {
+        ReadProxyHeader {
+            inner: Some(self),
         }
     }
 
+    /// The identity of the peer, if TLS has been established.
+    pub fn tls_peer_identity(&self) -> Option<tls::Identity> {
+        match self.io {
+            Io::Plain(_) | Io::Unix(_) => None,
+            Io::TlsServer(ref stream) => tls::peer_identity(stream.get_ref().1),
+            Io::TlsClient(ref stream) => tls::peer_identity(stream.get_ref().1),
+        }
+    }
+
+    /// The original destination of a TCP connection, if it was redirected.
     pub fn original_dst_addr<T: GetOriginalDst>(&self, get: &T) -> Option<SocketAddr> {
-        get.get_original_dst(self.socket())
+        self.socket().and_then(|socket| get.get_original_dst(socket))
     }
 
+    /// The local address of a TCP connection.
+    ///
+    /// Fails for connections over Unix sockets, which have no socket address.
     pub fn local_addr(&self) -> Result<SocketAddr, std::io::Error> {
-        self.socket().local_addr()
+        match self.socket() {
+            Some(socket) => socket.local_addr(),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Unix socket has no socket address",
+            )),
+        }
     }
 
     // This must never be made public so that in the future `Connection` can
@@ -160,9 +425,22 @@ impl Connection {
     // data is read from the socket. Each piece of information needed about the
     // underlying socket should be exposed by its own minimal accessor function
     // as is done above.
-    fn socket(&self) -> &PlaintextSocket {
+    fn socket(&self) -> Option<&PlaintextSocket> {
         match self.io {
-            Io::Plain(ref socket) => socket
+            Io::Plain(ref socket) => Some(socket),
+            Io::TlsServer(ref stream) => Some(&stream.get_ref().0.io),
+            Io::TlsClient(ref stream) => Some(stream.get_ref().0),
+            Io::Unix(_) => None,
+        }
+    }
+
+    /// Reads from the socket into `peek_buf`, bypassing any peeked bytes.
+    fn read_into_peek_buf(&mut self) -> Poll<usize, io::Error> {
+        match self.io {
+            Io::Plain(ref mut t) => t.read_buf(&mut self.peek_buf),
+            Io::TlsServer(ref mut t) => t.read_buf(&mut self.peek_buf),
+            Io::TlsClient(ref mut t) => t.read_buf(&mut self.peek_buf),
+            Io::Unix(ref mut t) => t.read_buf(&mut self.peek_buf),
         }
     }
 }
@@ -176,6 +454,9 @@ impl io::Read for Connection {
         if peeked_len == 0 {
             match self.io {
                 Io::Plain(ref mut t) => t.read(buf),
+                Io::TlsServer(ref mut t) => t.read(buf),
+                Io::TlsClient(ref mut t) => t.read(buf),
+                Io::Unix(ref mut t) => t.read(buf),
             }
         } else {
             let len = cmp::min(buf.len(), peeked_len);
@@ -196,6 +477,9 @@ impl AsyncRead for Connection {
     unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [u8]) -> bool {
         match self.io {
             Io::Plain(ref t) => t.prepare_uninitialized_buffer(buf),
+            Io::TlsServer(ref t) => t.prepare_uninitialized_buffer(buf),
+            Io::TlsClient(ref t) => t.prepare_uninitialized_buffer(buf),
+            Io::Unix(ref t) => t.prepare_uninitialized_buffer(buf),
         }
     }
 }
@@ -204,12 +488,18 @@ impl io::Write for Connection {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         match self.io {
             Io::Plain(ref mut t) => t.write(buf),
+            Io::TlsServer(ref mut t) => t.write(buf),
+            Io::TlsClient(ref mut t) => t.write(buf),
+            Io::Unix(ref mut t) => t.write(buf),
         }
     }
 
     fn flush(&mut self) -> io::Result<()> {
         match self.io {
             Io::Plain(ref mut t) => t.flush(),
+            Io::TlsServer(ref mut t) => t.flush(),
+            Io::TlsClient(ref mut t) => t.flush(),
+            Io::Unix(ref mut t) => t.flush(),
         }
     }
 }
@@ -217,16 +507,25 @@ impl io::Write for Connection {
 impl AsyncWrite for Connection {
     fn shutdown(&mut self) -> Poll<(), io::Error> {
         use std::net::Shutdown;
+        // Shut down the TLS session (if any) first, so that the peer
+        // receives a close_notify alert before the TCP FIN.
+        match self.io {
+            Io::Plain(ref mut t) => try_ready!(AsyncWrite::shutdown(t)),
+            Io::TlsServer(ref mut t) => try_ready!(t.shutdown()),
+            Io::TlsClient(ref mut t) => try_ready!(t.shutdown()),
+            Io::Unix(ref mut t) => try_ready!(AsyncWrite::shutdown(t)),
+        }
+        // Shutdown the write side of the socket.
+        //
+        // If we're shutting down, then we definitely won't write
+        // anymore. So, we should tell the remote about this. This
+        // is relied upon in our TCP proxy, to start shutting down
+        // the pipe if one side closes.
         match self.io {
-            Io::Plain(ref mut t) => {
-                try_ready!(AsyncWrite::shutdown(t));
-                // TCP shutdown the write side.
-                //
-                // If we're shutting down, then we definitely won't write
-                // anymore. So, we should tell the remote about this. This
-                // is relied upon in our TCP proxy, to start shutting down
-                // the pipe if one side closes.
-                TcpStream::shutdown(t, Shutdown::Write).map(Async::Ready)
+            Io::Unix(ref t) => t.shutdown(Shutdown::Write).map(Async::Ready),
+            _ => {
+                let socket = self.socket().expect("TCP connection must have a socket");
+                TcpStream::shutdown(socket, Shutdown::Write).map(Async::Ready)
             },
         }
     }
@@ -234,6 +533,9 @@ impl AsyncWrite for Connection {
     fn write_buf<B: Buf>(&mut self, buf: &mut B) -> Poll<usize, io::Error> {
         match self.io {
             Io::Plain(ref mut t) => t.write_buf(buf),
+            Io::TlsServer(ref mut t) => t.write_buf(buf),
+            Io::TlsClient(ref mut t) => t.write_buf(buf),
+            Io::Unix(ref mut t) => t.write_buf(buf),
         }
     }
 }
@@ -242,9 +544,7 @@ impl Peek for Connection {
     fn poll_peek(&mut self) -> Poll<usize, io::Error> {
         if self.peek_buf.is_empty() {
             self.peek_buf.reserve(8192);
-            match self.io {
-                Io::Plain(ref mut t) => t.read_buf(&mut self.peek_buf),
-            }
+            self.read_into_peek_buf()
         } else {
             Ok(Async::Ready(self.peek_buf.len()))
         }
@@ -253,6 +553,93 @@ impl Peek for Connection {
     fn peeked(&self) -> &[u8] {
         self.peek_buf.as_ref()
     }
+
+    fn poll_peek_more(&mut self) -> Poll<usize, io::Error> {
+        if self.peek_buf.len() == self.peek_buf.capacity() {
+            self.peek_buf.reserve(8192);
+        }
+        self.read_into_peek_buf()
+    }
+}
+
+// ===== impl Io =====
+
+impl fmt::Debug for Io {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Io::Plain(ref t) => f.debug_tuple("Plain").field(t).finish(),
+            Io::TlsServer(ref t) => f.debug_tuple("TlsServer").field(&t.get_ref().0.io).finish(),
+            Io::TlsClient(ref t) => f.debug_tuple("TlsClient").field(t.get_ref().0).finish(),
+            Io::Unix(ref t) => f.debug_tuple("Unix").field(t).finish(),
+        }
+    }
+}
+
+// ===== impl Prefixed =====
+
+impl<T: io::Read> io::Read for Prefixed<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.prefix.is_empty() {
+            return self.io.read(buf);
+        }
+
+        let len = cmp::min(buf.len(), self.prefix.len());
+        buf[..len].copy_from_slice(&self.prefix[..len]);
+        self.prefix.advance(len);
+        Ok(len)
+    }
+}
+
+impl<T: AsyncRead> AsyncRead for Prefixed<T> {
+    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [u8]) -> bool {
+        self.io.prepare_uninitialized_buffer(buf)
+    }
+}
+
+impl<T: io::Write> io::Write for Prefixed<T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.io.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.io.flush()
+    }
+}
+
+impl<T: AsyncWrite> AsyncWrite for Prefixed<T> {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        self.io.shutdown()
+    }
+}
+
+// ===== impl ReadProxyHeader =====
+
+impl Future for ReadProxyHeader {
+    type Item = (Connection, proxy_protocol::Header);
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            let parsed = {
+                let connection = self.inner.as_mut().expect("polled after completed");
+                let parsed = proxy_protocol::parse(connection.peek_buf.as_ref())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                if parsed.is_none() {
+                    connection.peek_buf.reserve(512);
+                    if try_ready!(connection.read_into_peek_buf()) == 0 {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+                }
+                parsed
+            };
+
+            if let Some((header, len)) = parsed {
+                let mut connection = self.inner.take().expect("polled after completed");
+                connection.peek_buf.advance(len);
+                return Ok(Async::Ready((connection, header)));
+            }
+        }
+    }
 }
 
 // impl PeekFuture
@@ -276,6 +663,18 @@ impl<T: Peek> Future for PeekFuture<T> {
 
 // Misc.
 
+/// Removes a socket file left behind by a previous process, so that the path
+/// can be bound again. Anything other than a socket is left alone, so that
+/// binding fails instead.
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(ref meta) if meta.file_type().is_socket() => std::fs::remove_file(path),
+        Ok(_) => Ok(()),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
 fn set_nodelay_or_warn(socket: &PlaintextSocket) {
     if let Err(e) = socket.set_nodelay(true) {
         warn!(
@@ -5,8 +5,8 @@ use std::collections::{
     VecDeque,
 };
 use std::fmt;
-use std::iter::IntoIterator;
-use std::net::SocketAddr;
+use std::iter::{self, IntoIterator};
+use std::net::{Ipv4Addr, SocketAddr};
 use std::time::Duration;
 use tokio_core::reactor::Handle;
 use tower_grpc as grpc;
@@ -16,14 +16,16 @@ use conduit_proxy_controller_grpc::common::{Destination, TcpAddress};
 use conduit_proxy_controller_grpc::destination::client::Destination as DestinationSvc;
 use conduit_proxy_controller_grpc::destination::update::Update as PbUpdate2;
 use conduit_proxy_controller_grpc::destination::{Update as PbUpdate, WeightedAddr};
+use conduit_proxy_controller_grpc::destination::protocol_hint::Protocol;
 
-use super::{Metadata, ResolveRequest, Update};
+use super::{Metadata, ProtocolHint, ResolveRequest, Update};
+use balance::DEFAULT_WEIGHT;
 use control::cache::{Cache, CacheChange, Exists};
 use control::fully_qualified_authority::FullyQualifiedAuthority;
 use control::remote_stream::{Receiver, Remote};
 use dns::{self, IpAddrListFuture};
 use telemetry::metrics::DstLabels;
-use transport::DnsNameAndPort;
+use transport::{tls, DnsNameAndPort};
 
 type DestinationServiceQuery<T> = Remote<PbUpdate, T>;
 type UpdateRx<T> = Receiver<PbUpdate, T>;
@@ -249,9 +251,18 @@ where
                     // Stop polling DNS on any active update from the Destination service.
                     set.dns_query = None;
                 },
-                Exists::No => {
+                Exists::No => match addr_authority(auth) {
+                    // An address that doesn't belong to a service is its own
+                    // only endpoint, until the Destination service reports
+                    // otherwise.
+                    Some(addr) => {
+                        set.add(auth, iter::once((addr, Metadata::no_metadata())));
+                        set.reset_on_next_modification();
+                    },
                     // Fall back to DNS.
-                    set.reset_dns_query(&self.dns_resolver, Duration::from_secs(0), auth);
+                    None => {
+                        set.reset_dns_query(&self.dns_resolver, Duration::from_secs(0), auth);
+                    },
                 },
                 Exists::Unknown => (), // No change from Destination service's perspective.
             }
@@ -276,10 +287,15 @@ where
             connect_or_reconnect,
             auth
         );
-        FullyQualifiedAuthority::normalize(auth, default_destination_namespace).map(|auth| {
+        let path = match addr_authority(auth) {
+            Some(addr) => Some(addr.to_string()),
+            None => FullyQualifiedAuthority::normalize(auth, default_destination_namespace)
+                .map(|auth| auth.without_trailing_dot().to_owned()),
+        };
+        path.map(|path| {
             let req = Destination {
                 scheme: "k8s".into(),
-                path: auth.without_trailing_dot().to_owned(),
+                path,
             };
             let mut svc = DestinationSvc::new(client.lift_ref());
             let response = svc.get(grpc::Request::new(req));
@@ -501,6 +517,19 @@ impl<T: HttpService<ResponseBody = RecvBody>> DestinationSet<T> {
     }
 }
 
+/// Returns the address that `auth` was built from by `Control::resolve_addr`,
+/// if it names an address rather than a DNS name.
+///
+/// The Destination service resolves a service's cluster IP to the service's
+/// endpoints. Only IPv4 addresses are resolved this way, since the
+/// Destination service's path syntax can't express IPv6 addresses.
+fn addr_authority(auth: &DnsNameAndPort) -> Option<SocketAddr> {
+    let host: &str = auth.host.as_ref();
+    host.parse::<Ipv4Addr>()
+        .ok()
+        .map(|ip| SocketAddr::from((ip, auth.port)))
+}
+
 /// Construct a new labeled `SocketAddr `from a protobuf `WeightedAddr`.
 fn pb_to_addr_meta(
     pb: WeightedAddr,
@@ -508,8 +537,23 @@ fn pb_to_addr_meta(
 ) -> Option<(SocketAddr, Metadata)> {
     let addr = pb.addr.and_then(pb_to_sock_addr)?;
     let label_iter = set_labels.iter().chain(pb.metric_labels.iter());
+    let tls_identity = pb.tls_identity.and_then(|pb| {
+        tls::Identity::from_dns_name(&pb.server_name)
+            .map_err(|()| warn!("ignoring invalid TLS identity: {:?}", pb.server_name))
+            .ok()
+    });
+    let protocol_hint = match pb.protocol_hint.and_then(|pb| pb.protocol) {
+        Some(Protocol::H2(..)) => ProtocolHint::Http2,
+        None => ProtocolHint::Unknown,
+    };
+    // An endpoint without a weight is indistinguishable from one with a
+    // weight of 0, which would never be chosen.
+    let weight = if pb.weight == 0 { DEFAULT_WEIGHT } else { pb.weight };
     let meta = Metadata {
         metric_labels: DstLabels::new(label_iter),
+        tls_identity,
+        protocol_hint,
+        weight,
     };
     Some((addr, meta))
 }
@@ -1,7 +1,9 @@
 use futures_watch;
 use std::{cmp, hash, net::SocketAddr};
 
+use config::Addr;
 use telemetry::metrics::DstLabels;
+use transport::tls;
 
 pub type DstLabelsWatch = futures_watch::Watch<Option<DstLabels>>;
 
@@ -10,34 +12,75 @@ pub type DstLabelsWatch = futures_watch::Watch<Option<DstLabels>>;
 /// Equality, Ordering, and hashability is determined soley by the Endpoint's address.
 #[derive(Clone, Debug)]
 pub struct Endpoint {
-    address: SocketAddr,
+    address: Addr,
     dst_labels: Option<DstLabelsWatch>,
+    tls_identity: Option<tls::Identity>,
+    protocol_hint: ProtocolHint,
+}
+
+/// A hint from the Destination service about the protocols that an endpoint
+/// accepts.
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum ProtocolHint {
+    /// Nothing is known about the endpoint's protocols.
+    Unknown,
+    /// The endpoint's proxy accepts HTTP/1 requests carried over HTTP/2.
+    Http2,
 }
 
 // ==== impl Endpoint =====
 
 impl Endpoint {
-    pub fn new(address: SocketAddr, dst_labels: DstLabelsWatch) -> Self {
+    pub fn new(
+        address: SocketAddr,
+        dst_labels: DstLabelsWatch,
+        tls_identity: Option<tls::Identity>,
+        protocol_hint: ProtocolHint,
+    ) -> Self {
         Self {
-            address,
+            address: Addr::Tcp(address),
             dst_labels: Some(dst_labels),
+            tls_identity,
+            protocol_hint,
         }
     }
 
-    pub fn address(&self) -> SocketAddr {
-        self.address
+    pub fn address(&self) -> &Addr {
+        &self.address
     }
 
     pub fn dst_labels(&self) -> Option<&DstLabelsWatch> {
         self.dst_labels.as_ref()
     }
+
+    /// The identity of the endpoint's proxy, if the endpoint is meshed.
+    pub fn tls_identity(&self) -> Option<&tls::Identity> {
+        self.tls_identity.as_ref()
+    }
+
+    /// Returns true if HTTP/1 requests to this endpoint may be carried over
+    /// HTTP/2, to be downgraded by the endpoint's proxy.
+    pub fn can_use_orig_proto(&self) -> bool {
+        match self.protocol_hint {
+            ProtocolHint::Unknown => false,
+            ProtocolHint::Http2 => true,
+        }
+    }
 }
 
 impl From<SocketAddr> for Endpoint {
     fn from(address: SocketAddr) -> Self {
+        Addr::Tcp(address).into()
+    }
+}
+
+impl From<Addr> for Endpoint {
+    fn from(address: Addr) -> Self {
         Self {
             address,
             dst_labels: None,
+            tls_identity: None,
+            protocol_hint: ProtocolHint::Unknown,
         }
     }
 }
@@ -4,18 +4,18 @@ use std::net::SocketAddr;
 use futures::sync::mpsc;
 use futures::{Async, Poll, Stream};
 use futures_watch::{Store, Watch};
-use http;
 use tower_discover::{Change, Discover};
 use tower_service::Service;
 
+use balance::{Weighted, DEFAULT_WEIGHT};
 use dns;
 use telemetry::metrics::DstLabels;
-use transport::DnsNameAndPort;
+use transport::{tls, DnsNameAndPort};
 
 pub mod background;
 mod endpoint;
 
-pub use self::endpoint::{DstLabelsWatch, Endpoint};
+pub use self::endpoint::{DstLabelsWatch, Endpoint, ProtocolHint};
 
 /// A handle to request resolutions from a `Background`.
 #[derive(Clone, Debug)]
@@ -39,6 +39,14 @@ pub struct Resolution<B> {
     /// This is used to update the `Labeled` middleware on those services
     /// without requiring the service stack to be re-bound.
     metric_labels: HashMap<SocketAddr, Store<Option<DstLabels>>>,
+    /// Map associating addresses with the `Store` for the watch on that
+    /// service's weight, so that the balancer may observe weight changes.
+    weights: HashMap<SocketAddr, Store<u32>>,
+    /// The TLS identity and protocol hint with which each endpoint was bound.
+    bound_metadata: HashMap<SocketAddr, (Option<tls::Identity>, ProtocolHint)>,
+    /// An endpoint that was removed so that it may be re-bound with new
+    /// metadata on the next poll.
+    pending_insert: Option<(SocketAddr, Metadata)>,
     bind: B,
 }
 
@@ -47,6 +55,16 @@ pub struct Resolution<B> {
 struct Metadata {
     /// A set of Prometheus metric labels describing the destination.
     metric_labels: Option<DstLabels>,
+
+    /// The identity of the destination's proxy, if it is meshed.
+    tls_identity: Option<tls::Identity>,
+
+    /// A hint about the protocols that the destination accepts.
+    protocol_hint: ProtocolHint,
+
+    /// The destination's share of requests, relative to the weights of the
+    /// other endpoints.
+    weight: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -114,6 +132,9 @@ impl Resolver {
         Resolution {
             update_rx,
             metric_labels: HashMap::new(),
+            weights: HashMap::new(),
+            bound_metadata: HashMap::new(),
+            pending_insert: None,
             bind,
         }
     }
@@ -122,7 +143,26 @@ impl Resolver {
 // ==== impl Resolution =====
 
 impl<B> Resolution<B> {
+    /// Returns true if the endpoint was bound with metadata that changes how
+    /// its connections are established.
+    fn bound_metadata_changed(&self, addr: &SocketAddr, meta: &Metadata) -> bool {
+        self.bound_metadata
+            .get(addr)
+            .map(|&(ref tls_identity, protocol_hint)| {
+                *tls_identity != meta.tls_identity || protocol_hint != meta.protocol_hint
+            })
+            .unwrap_or(false)
+    }
+
     fn update_metadata(&mut self, addr: SocketAddr, meta: Metadata) -> Result<(), ()> {
+        if let Some(store) = self.weights.get_mut(&addr) {
+            store
+                .store(meta.weight)
+                .map_err(|e| {
+                    error!("update_metadata: weight store error: {:?}", e);
+                })?;
+        }
+
         if let Some(store) = self.metric_labels.get_mut(&addr) {
             store
                 .store(meta.metric_labels)
@@ -144,22 +184,27 @@ impl<B> Resolution<B> {
     }
 }
 
-impl<B, A> Discover for Resolution<B>
+impl<B> Discover for Resolution<B>
 where
-    B: Bind<Endpoint = Endpoint, Request = http::Request<A>>,
+    B: Bind<Endpoint = Endpoint>,
 {
     type Key = SocketAddr;
     type Request = B::Request;
     type Response = B::Response;
     type Error = B::Error;
-    type Service = B::Service;
+    type Service = Weighted<B::Service>;
     type DiscoverError = ();
 
     fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::DiscoverError> {
         loop {
-            let up = self.update_rx.poll();
-            trace!("watch: {:?}", up);
-            let update = try_ready!(up).expect("destination stream must be infinite");
+            let update = match self.pending_insert.take() {
+                Some((addr, meta)) => Update::Insert(addr, meta),
+                None => {
+                    let up = self.update_rx.poll();
+                    trace!("watch: {:?}", up);
+                    try_ready!(up).expect("destination stream must be infinite")
+                },
+            };
 
             match update {
                 Update::Insert(addr, meta) => {
@@ -168,13 +213,35 @@ where
                     // so it can be updated later.
                     let (labels_watch, labels_store) = Watch::new(meta.metric_labels);
                     self.metric_labels.insert(addr, labels_store);
+                    let (weight_watch, weight_store) = Watch::new(meta.weight);
+                    self.weights.insert(addr, weight_store);
+                    self.bound_metadata.insert(
+                        addr,
+                        (meta.tls_identity.clone(), meta.protocol_hint),
+                    );
 
-                    let endpoint = Endpoint::new(addr, labels_watch.clone());
+                    let endpoint = Endpoint::new(
+                        addr,
+                        labels_watch.clone(),
+                        meta.tls_identity,
+                        meta.protocol_hint,
+                    );
 
                     let service = self.bind.bind(&endpoint).map_err(|_| ())?;
+                    let service = Weighted::new(service, weight_watch, Some(labels_watch));
 
                     return Ok(Async::Ready(Change::Insert(addr, service)));
                 },
+                Update::ChangeMetadata(addr, ref meta) if self.bound_metadata_changed(&addr, meta) => {
+                    // The endpoint's connections were established with the
+                    // old identity or protocol, so the service must be
+                    // re-bound.
+                    self.metric_labels.remove(&addr);
+                    self.weights.remove(&addr);
+                    self.bound_metadata.remove(&addr);
+                    self.pending_insert = Some((addr, meta.clone()));
+                    return Ok(Async::Ready(Change::Remove(addr)));
+                },
                 Update::ChangeMetadata(addr, meta) => {
                     // Update metadata and continue polling `rx`.
                     self.update_metadata(addr, meta)?;
@@ -185,6 +252,8 @@ where
                     // still exists --- it will simply read the final
                     // value from the watch.
                     self.metric_labels.remove(&addr);
+                    self.weights.remove(&addr);
+                    self.bound_metadata.remove(&addr);
                     return Ok(Async::Ready(Change::Remove(addr)));
                 },
             }
@@ -198,6 +267,9 @@ impl Metadata {
     fn no_metadata() -> Self {
         Metadata {
             metric_labels: None,
+            tls_identity: None,
+            protocol_hint: ProtocolHint::Unknown,
+            weight: DEFAULT_WEIGHT,
         }
     }
 }
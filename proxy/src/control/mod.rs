@@ -1,21 +1,19 @@
 use std::fmt;
 use std::io;
-use std::time::{Duration, Instant};
+use std::net::SocketAddrV4;
+use std::time::Duration;
 
 use bytes::Bytes;
 use futures::{future, Async, Future, Poll};
 use h2;
 use http;
-use tokio_core::reactor::{
-    Handle,
-    // TODO: would rather just have Backoff in a separate file so this
-    //       renaming import is not necessary.
-    Timeout as ReactorTimeout
-};
+use tokio_core::reactor::Handle;
 use tower_service::Service;
 use tower_h2;
 use tower_reconnect::{Error as ReconnectError, Reconnect};
 
+use backoff::{self, Backoff};
+use config;
 use dns;
 use transport::{DnsNameAndPort, HostAndPort, LookupAddressAndConnect};
 use timeout::{Timeout, TimeoutError};
@@ -25,31 +23,50 @@ pub mod destination;
 mod fully_qualified_authority;
 mod observe;
 pub mod pb;
+mod profiles;
 mod remote_stream;
 
 use self::destination::{Resolver, Resolution};
+use self::fully_qualified_authority::FullyQualifiedAuthority;
 pub use self::destination::Bind;
 pub use self::observe::Observe;
 
 #[derive(Clone)]
 pub struct Control {
     disco: Resolver,
+    /// Watches destinations' profiles, if profiles are enabled.
+    profiles: Option<profiles::Watcher>,
+    default_destination_namespace: String,
 }
 
 pub struct Background {
     disco: destination::background::Config,
+    profiles: Option<profiles::Config>,
 }
 
-pub fn new(dns_config: dns::Config, default_destination_namespace: String) -> (Control, Background)
+pub fn new(
+    dns_config: dns::Config,
+    default_destination_namespace: String,
+    profiles_enabled: bool,
+) -> (Control, Background)
 {
-    let (tx, rx) = self::destination::new(dns_config, default_destination_namespace);
+    let (tx, rx) = self::destination::new(dns_config, default_destination_namespace.clone());
+    let (profiles_tx, profiles_rx) = if profiles_enabled {
+        let (tx, rx) = self::profiles::new();
+        (Some(tx), Some(rx))
+    } else {
+        (None, None)
+    };
 
     let c = Control {
         disco: tx,
+        profiles: profiles_tx,
+        default_destination_namespace,
     };
 
     let b = Background {
         disco: rx,
+        profiles: profiles_rx,
     };
 
     (c, b)
@@ -61,6 +78,29 @@ impl Control {
     pub fn resolve<B>(&self, auth: &DnsNameAndPort, bind: B) -> Resolution<B> {
         self.disco.resolve(auth, bind)
     }
+
+    /// Start watching for changes to the profile of `auth`, if profiles are
+    /// enabled and it names a service that is resolved through the
+    /// Destination service.
+    pub fn profile(&self, auth: &DnsNameAndPort) -> Option<::profiles::Receiver> {
+        let profiles = self.profiles.as_ref()?;
+        FullyQualifiedAuthority::normalize(auth, &self.default_destination_namespace)
+            .map(|auth| profiles.watch(auth))
+    }
+
+    /// Resolves the endpoints of the service that has been allocated the
+    /// address `addr`, e.g. the original destination of an opaque TCP
+    /// connection.
+    ///
+    /// The service is looked up by the Destination service. An address that
+    /// isn't a service's resolves to itself.
+    pub fn resolve_addr<B>(&self, addr: SocketAddrV4, bind: B) -> Resolution<B> {
+        let auth = DnsNameAndPort {
+            host: dns::Name::from(addr.ip().to_string().as_str()),
+            port: addr.port(),
+        };
+        self.disco.resolve(&auth, bind)
+    }
 }
 
 // ===== impl Background =====
@@ -70,6 +110,7 @@ impl Background {
         self,
         host_and_port: HostAndPort,
         dns_config: dns::Config,
+        reconnect_backoff: config::ReconnectBackoff,
         executor: &Handle,
     ) -> Box<Future<Item = (), Error = ()>> {
         // Build up the Controller Client Stack
@@ -92,15 +133,23 @@ impl Background {
 
             let reconnect = Reconnect::new(h2_client);
             let log_errors = LogErrors::new(reconnect);
-            let backoff = Backoff::new(log_errors, Duration::from_secs(5), executor);
+            let backoff = Backoff::new(
+                log_errors,
+                Some(backoff::Policy::new(reconnect_backoff)),
+                executor,
+            );
             // TODO: Use AddOrigin in tower-http
             AddOrigin::new(scheme, authority, backoff)
         };
 
         let mut disco = self.disco.process(executor);
+        let mut profiles = self.profiles.map(|p| p.process(executor));
 
         let fut = future::poll_fn(move || {
             disco.poll_rpc(&mut client);
+            if let Some(ref mut profiles) = profiles {
+                profiles.poll_rpc(&mut client);
+            }
 
             Ok(Async::NotReady)
         });
@@ -109,62 +158,7 @@ impl Background {
     }
 }
 
-// ===== Backoff =====
-
-/// Wait a duration if inner `poll_ready` returns an error.
-//TODO: move to tower-backoff
-struct Backoff<S> {
-    inner: S,
-    timer: ReactorTimeout,
-    waiting: bool,
-    wait_dur: Duration,
-}
-
-impl<S> Backoff<S> {
-    fn new(inner: S, wait_dur: Duration, handle: &Handle) -> Self {
-        Backoff {
-            inner,
-            timer: ReactorTimeout::new(wait_dur, handle).unwrap(),
-            waiting: false,
-            wait_dur,
-        }
-    }
-}
-
-impl<S> Service for Backoff<S>
-where
-    S: Service,
-    S::Error: ::std::fmt::Debug,
-{
-    type Request = S::Request;
-    type Response = S::Response;
-    type Error = S::Error;
-    type Future = S::Future;
-
-    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
-        if self.waiting {
-            if self.timer.poll().unwrap().is_not_ready() {
-                return Ok(Async::NotReady);
-            }
-
-            self.waiting = false;
-        }
-
-        match self.inner.poll_ready() {
-            Err(_err) => {
-                trace!("backoff: controller error, waiting {:?}", self.wait_dur);
-                self.waiting = true;
-                self.timer.reset(Instant::now() + self.wait_dur);
-                Ok(Async::NotReady)
-            }
-            ok => ok,
-        }
-    }
-
-    fn call(&mut self, req: Self::Request) -> Self::Future {
-        self.inner.call(req)
-    }
-}
+// ===== impl AddOrigin =====
 
 /// Wraps an HTTP service, injecting authority and scheme on every request.
 struct AddOrigin<S> {
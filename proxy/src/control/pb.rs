@@ -6,6 +6,7 @@ use std::fmt;
 use std::sync::Arc;
 
 use conduit_proxy_controller_grpc::*;
+use config;
 use convert::*;
 use ctx;
 use telemetry::{event, Event};
@@ -53,7 +54,8 @@ impl event::StreamResponseEnd {
 
         common::TapEvent {
             source: Some((&ctx.server.remote).into()),
-            destination: Some((&ctx.client.remote).into()),
+            source_tls_identity: tls_identity_string(&ctx.server),
+            destination: tcp_address(&ctx.client.remote),
             destination_meta,
             event: Some(tap_event::Event::Http(tap_event::Http {
                 event: Some(tap_event::http::Event::ResponseEnd(end)),
@@ -85,7 +87,8 @@ impl event::StreamResponseFail {
 
         common::TapEvent {
             source: Some((&ctx.server.remote).into()),
-            destination: Some((&ctx.client.remote).into()),
+            source_tls_identity: tls_identity_string(&ctx.server),
+            destination: tcp_address(&ctx.client.remote),
             destination_meta,
             event: Some(tap_event::Event::Http(tap_event::Http {
                 event: Some(tap_event::http::Event::ResponseEnd(end)),
@@ -117,7 +120,8 @@ impl event::StreamRequestFail {
 
         common::TapEvent {
             source: Some((&ctx.server.remote).into()),
-            destination: Some((&ctx.client.remote).into()),
+            source_tls_identity: tls_identity_string(&ctx.server),
+            destination: tcp_address(&ctx.client.remote),
             destination_meta,
             event: Some(tap_event::Event::Http(tap_event::Http {
                 event: Some(tap_event::http::Event::ResponseEnd(end)),
@@ -147,6 +151,10 @@ impl<'a> TryFrom<&'a Event> for common::TapEvent {
                         .unwrap_or_default()
                         .into(),
                     path: ctx.uri.path().into(),
+                    fault: ctx.fault.as_ref()
+                        .map(|f| f.as_str())
+                        .unwrap_or_default()
+                        .into(),
                 };
 
                 let destination_meta = ctx.dst_labels()
@@ -157,7 +165,8 @@ impl<'a> TryFrom<&'a Event> for common::TapEvent {
 
                 common::TapEvent {
                     source: Some((&ctx.server.remote).into()),
-                    destination: Some((&ctx.client.remote).into()),
+                    source_tls_identity: tls_identity_string(&ctx.server),
+                    destination: tcp_address(&ctx.client.remote),
                     destination_meta,
                     event: Some(tap_event::Event::Http(tap_event::Http {
                         event: Some(tap_event::http::Event::RequestInit(init)),
@@ -184,7 +193,8 @@ impl<'a> TryFrom<&'a Event> for common::TapEvent {
 
                 common::TapEvent {
                     source: Some((&ctx.request.server.remote).into()),
-                    destination: Some((&ctx.request.client.remote).into()),
+                    source_tls_identity: tls_identity_string(&ctx.request.server),
+                    destination: tcp_address(&ctx.request.client.remote),
                     destination_meta,
                     event: Some(tap_event::Event::Http(tap_event::Http {
                         event: Some(tap_event::http::Event::ResponseInit(init)),
@@ -210,3 +220,15 @@ impl<'a> TryFrom<&'a Event> for common::TapEvent {
         Ok(tap_ev)
     }
 }
+
+fn tls_identity_string(ctx: &ctx::transport::Server) -> String {
+    ctx.tls_identity
+        .as_ref()
+        .map(|identity| identity.to_string())
+        .unwrap_or_default()
+}
+
+/// Tap events can only describe TCP peers, so Unix sockets are omitted.
+fn tcp_address(addr: &config::Addr) -> Option<common::TcpAddress> {
+    addr.socket_addr().map(|addr| (&addr).into())
+}
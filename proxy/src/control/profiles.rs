@@ -0,0 +1,564 @@
+use std::collections::{
+    hash_map::{Entry, HashMap},
+    VecDeque,
+};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use convert::TryFrom;
+use futures::sync::mpsc;
+use futures::{Async, Future, Stream};
+use http;
+use tokio_core::reactor::{Handle, Timeout};
+use tower_grpc as grpc;
+use tower_h2::{BoxBody, HttpService, RecvBody};
+
+use conduit_proxy_controller_grpc::common::Destination;
+use conduit_proxy_controller_grpc::destination::client::Destination as DestinationSvc;
+use conduit_proxy_controller_grpc::destination::{
+    DestinationProfile,
+    Mirror as PbMirror,
+    ResponseClass as PbResponseClass,
+    Route as PbRoute,
+    WeightedDst as PbWeightedDst,
+};
+
+use super::fully_qualified_authority::FullyQualifiedAuthority;
+use super::remote_stream::{Receiver, Remote};
+use profiles::{self, Mirror, Profile, ResponseClass, Route, RouteName, WeightedDst};
+
+/// How long to wait before querying a destination's profile again after its
+/// stream ends.
+///
+/// Profiles are optional, so a controller that fails to serve them shouldn't
+/// be asked for them continuously. A controller that doesn't implement them
+/// isn't asked again at all.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+type ProfileQuery<T> = Remote<DestinationProfile, T>;
+
+/// A handle to request profiles from a `Process`.
+#[derive(Clone, Debug)]
+pub struct Watcher {
+    request_tx: mpsc::UnboundedSender<WatchRequest>,
+}
+
+/// Stores the configuration for a profile background worker.
+#[derive(Debug)]
+pub struct Config {
+    request_rx: mpsc::UnboundedReceiver<WatchRequest>,
+}
+
+/// Satisfies profile watches as requested via `request_rx`.
+///
+/// Each watched destination's profile is queried from the Destination
+/// service, and its updates are propagated to all of its watchers. A
+/// destination is no longer queried once all of its watchers are dropped.
+pub struct Process<T: HttpService<ResponseBody = RecvBody>> {
+    watches: HashMap<FullyQualifiedAuthority, Watch<T>>,
+    /// Whether the Destination service responded that it doesn't implement
+    /// `GetProfile`, so that profiles are no longer requested.
+    unimplemented: bool,
+    /// A queue of authorities that need to be reconnected.
+    reconnects: VecDeque<FullyQualifiedAuthority>,
+    /// Each poll, records whether the rpc service was still ready.
+    rpc_ready: bool,
+    /// A receiver of new watch requests.
+    request_rx: mpsc::UnboundedReceiver<WatchRequest>,
+    handle: Handle,
+}
+
+#[derive(Debug)]
+struct WatchRequest {
+    authority: FullyQualifiedAuthority,
+    profile_tx: profiles::Sender,
+}
+
+/// Holds the state of a single destination's profile.
+struct Watch<T: HttpService<ResponseBody = RecvBody>> {
+    query: Option<ProfileQuery<T>>,
+    /// Fires when the profile should be queried again.
+    reconnect_after: Option<Timeout>,
+    profile: Option<Profile>,
+    txs: Vec<profiles::Sender>,
+}
+
+/// Creates a "channel" of `Watcher` to `Process` handles.
+pub fn new() -> (Watcher, Config) {
+    let (request_tx, request_rx) = mpsc::unbounded();
+    (Watcher { request_tx }, Config { request_rx })
+}
+
+// ===== impl Watcher =====
+
+impl Watcher {
+    /// Start watching for changes to the profile of a certain authority.
+    pub fn watch(&self, authority: FullyQualifiedAuthority) -> profiles::Receiver {
+        trace!("watch profile; authority={:?}", authority);
+        let (profile_tx, profile_rx) = profiles::channel();
+        self.request_tx
+            .unbounded_send(WatchRequest { authority, profile_tx })
+            .expect("unbounded can't fail");
+        profile_rx
+    }
+}
+
+// ===== impl Config =====
+
+impl Config {
+    /// Bind this handle to start talking to the controller API.
+    pub fn process<T>(self, executor: &Handle) -> Process<T>
+    where
+        T: HttpService<RequestBody = BoxBody, ResponseBody = RecvBody>,
+        T::Error: fmt::Debug,
+    {
+        Process {
+            watches: HashMap::new(),
+            unimplemented: false,
+            reconnects: VecDeque::new(),
+            rpc_ready: false,
+            request_rx: self.request_rx,
+            handle: executor.clone(),
+        }
+    }
+}
+
+// ===== impl Process =====
+
+impl<T> Process<T>
+where
+    T: HttpService<RequestBody = BoxBody, ResponseBody = RecvBody>,
+    T::Error: fmt::Debug,
+{
+    pub fn poll_rpc(&mut self, client: &mut T) {
+        if self.unimplemented {
+            self.drop_new_watches();
+            return;
+        }
+
+        // As in the destination process, streams that must be reconnected
+        // while the `rpc` service is ready are reconnected now, rather than
+        // on the next wakeup.
+        loop {
+            self.poll_new_watches(client);
+            self.poll_watches();
+
+            if self.reconnects.is_empty() || !self.rpc_ready {
+                break;
+            }
+        }
+    }
+
+    fn poll_new_watches(&mut self, client: &mut T) {
+        loop {
+            match client.poll_ready() {
+                Ok(Async::Ready(())) => {
+                    self.rpc_ready = true;
+                },
+                Ok(Async::NotReady) => {
+                    self.rpc_ready = false;
+                    break;
+                },
+                Err(err) => {
+                    warn!("Destination.GetProfile poll_ready error: {:?}", err);
+                    self.rpc_ready = false;
+                    break;
+                },
+            }
+
+            // handle any pending reconnects first
+            if let Some(auth) = self.reconnects.pop_front() {
+                if let Some(watch) = self.watches.get_mut(&auth) {
+                    watch.query = Some(Self::query(client, &auth, "reconnect"));
+                }
+                continue;
+            }
+
+            match self.request_rx.poll() {
+                Ok(Async::Ready(Some(WatchRequest { authority, profile_tx }))) => {
+                    match self.watches.entry(authority) {
+                        Entry::Occupied(mut occ) => {
+                            let watch = occ.get_mut();
+                            // The current profile, if it's known, is sent
+                            // to the new watcher first.
+                            if let Some(ref profile) = watch.profile {
+                                profile_tx.send(profile.clone());
+                            }
+                            watch.txs.push(profile_tx);
+                        },
+                        Entry::Vacant(vac) => {
+                            let query = Self::query(client, vac.key(), "connect");
+                            vac.insert(Watch {
+                                query: Some(query),
+                                reconnect_after: None,
+                                profile: None,
+                                txs: vec![profile_tx],
+                            });
+                        },
+                    }
+                },
+                Ok(Async::Ready(None)) => {
+                    trace!("profile watcher is dropped, shutdown?");
+                    return;
+                },
+                Ok(Async::NotReady) => break,
+                Err(_) => unreachable!("unbounded receiver doesn't error"),
+            }
+        }
+    }
+
+    fn poll_watches(&mut self) {
+        // Watches without any watchers are dropped, canceling their queries.
+        self.watches.retain(|auth, watch| {
+            watch.txs.retain(profiles::Sender::is_watching);
+            if watch.txs.is_empty() {
+                trace!("profile of {:?} is no longer watched", auth);
+                return false;
+            }
+            true
+        });
+
+        for (auth, watch) in &mut self.watches {
+            match watch.query.take() {
+                Some(Remote::ConnectedOrConnecting { rx }) => {
+                    match watch.poll_query(auth, rx) {
+                        Some(Remote::NeedsReconnect) => {
+                            let timer = Timeout::new(RECONNECT_DELAY, &self.handle)
+                                .expect("reactor gone");
+                            watch.reconnect_after = Some(timer);
+                            watch.query = Some(Remote::NeedsReconnect);
+                        },
+                        Some(query) => watch.query = Some(query),
+                        None => self.unimplemented = true,
+                    }
+                },
+                query => watch.query = query,
+            }
+
+            // The timer is polled as soon as it's set, so that the task is
+            // notified when it fires.
+            if let Some(mut timer) = watch.reconnect_after.take() {
+                match timer.poll() {
+                    Ok(Async::NotReady) => {
+                        watch.reconnect_after = Some(timer);
+                    },
+                    Ok(Async::Ready(())) | Err(_) => {
+                        self.reconnects.push_back(auth.clone());
+                    },
+                }
+            }
+        }
+
+        if self.unimplemented {
+            warn!("Destination.GetProfile is not implemented by the controller; \
+                   destination profiles are disabled");
+            // Dropping the watches ends their receivers, which continue to
+            // use the last profile that they received, if any.
+            self.watches.clear();
+            self.reconnects.clear();
+            self.drop_new_watches();
+        }
+    }
+
+    /// Drops watch requests once profiles are no longer requested.
+    fn drop_new_watches(&mut self) {
+        while let Ok(Async::Ready(Some(_))) = self.request_rx.poll() {}
+    }
+
+    fn query(client: &mut T, auth: &FullyQualifiedAuthority, connect_or_reconnect: &str)
+        -> ProfileQuery<T>
+    {
+        trace!("ProfileQuery {} {:?}", connect_or_reconnect, auth);
+        let req = Destination {
+            scheme: "k8s".into(),
+            path: auth.without_trailing_dot().to_owned(),
+        };
+        let mut svc = DestinationSvc::new(client.lift_ref());
+        let response = svc.get_profile(grpc::Request::new(req));
+        Remote::ConnectedOrConnecting {
+            rx: Receiver::new(response),
+        }
+    }
+}
+
+// ===== impl Watch =====
+
+impl<T> Watch<T>
+where
+    T: HttpService<RequestBody = BoxBody, ResponseBody = RecvBody>,
+    T::Error: fmt::Debug,
+{
+    /// Polls the profile query, returning `None` if the Destination service
+    /// doesn't implement it.
+    fn poll_query(
+        &mut self,
+        auth: &FullyQualifiedAuthority,
+        mut rx: Receiver<DestinationProfile, T>,
+    ) -> Option<ProfileQuery<T>> {
+        loop {
+            match rx.poll() {
+                Ok(Async::Ready(Some(pb))) => {
+                    let profile = pb_to_profile(pb);
+                    trace!("profile for {:?}: {:?}", auth, profile);
+                    // retain is used to drop any senders that are dead
+                    self.txs.retain(|tx| tx.send(profile.clone()));
+                    self.profile = Some(profile);
+                },
+                Ok(Async::Ready(None)) => {
+                    trace!("Destination.GetProfile stream ended for {:?}", auth);
+                    return Some(Remote::NeedsReconnect);
+                },
+                Ok(Async::NotReady) => {
+                    return Some(Remote::ConnectedOrConnecting { rx });
+                },
+                Err(grpc::Error::Grpc(ref status, _)) if *status == grpc::Status::UNIMPLEMENTED => {
+                    debug!("Destination.GetProfile is unimplemented for {:?}", auth);
+                    return None;
+                },
+                Err(err) => {
+                    debug!("Destination.GetProfile stream errored for {:?}: {:?}", auth, err);
+                    return Some(Remote::NeedsReconnect);
+                },
+            }
+        }
+    }
+}
+
+/// Constructs a `Profile` from a protobuf `DestinationProfile`, skipping any
+/// routes or destinations that can't be used.
+fn pb_to_profile(pb: DestinationProfile) -> Profile {
+    let routes = pb.routes
+        .into_iter()
+        .filter_map(pb_to_route)
+        .map(Arc::new)
+        .collect();
+    let dst_overrides = pb.dst_overrides
+        .into_iter()
+        .filter_map(pb_to_weighted_dst)
+        .collect();
+    let mirror = pb.mirror.and_then(pb_to_mirror);
+    Profile { routes, dst_overrides, mirror }
+}
+
+fn pb_to_mirror(pb: PbMirror) -> Option<Mirror> {
+    // A mirror that receives no requests needn't be bound.
+    if pb.percent == 0 {
+        return None;
+    }
+    if pb.percent > 100 {
+        warn!("ignoring mirror with invalid percentage: {}", pb.percent);
+        return None;
+    }
+    let authority = http::uri::Authority::from_str(&pb.authority)
+        .map_err(|_| warn!("ignoring invalid mirror: {:?}", pb.authority))
+        .ok()?;
+    Some(Mirror {
+        authority,
+        percent: pb.percent,
+    })
+}
+
+fn pb_to_weighted_dst(pb: PbWeightedDst) -> Option<WeightedDst> {
+    // A destination without a weight would never be chosen.
+    if pb.weight == 0 {
+        return None;
+    }
+    let authority = http::uri::Authority::from_str(&pb.authority)
+        .map_err(|_| warn!("ignoring invalid destination override: {:?}", pb.authority))
+        .ok()?;
+    Some(WeightedDst {
+        authority,
+        weight: pb.weight,
+    })
+}
+
+fn pb_to_route(pb: PbRoute) -> Option<Route> {
+    // A route without a condition applies to all requests.
+    let (method, path) = match pb.condition {
+        Some(condition) => {
+            let method = match condition.method.and_then(|m| m.type_) {
+                Some(ref m) => {
+                    let method = http::Method::try_from(m)
+                        .map_err(|_| warn!("ignoring route with invalid method: {:?}", m))
+                        .ok()?;
+                    Some(method)
+                },
+                None => None,
+            };
+            let path = if condition.path_regex.is_empty() {
+                None
+            } else {
+                let path = Route::path_regex(&condition.path_regex)
+                    .map_err(|e| warn!("ignoring route with invalid path regex: {}", e))
+                    .ok()?;
+                Some(path)
+            };
+            (method, path)
+        },
+        None => (None, None),
+    };
+
+    let timeout = pb.timeout.and_then(|d| {
+        if d.seconds < 0 || d.nanos < 0 || (d.seconds == 0 && d.nanos == 0) {
+            return None;
+        }
+        Some(Duration::new(d.seconds as u64, d.nanos as u32))
+    });
+
+    let response_classes = pb.response_classes
+        .into_iter()
+        .filter_map(pb_to_response_class)
+        .collect();
+
+    let name = if pb.name.is_empty() {
+        None
+    } else {
+        Some(RouteName::new(&pb.name))
+    };
+
+    Some(Route {
+        name,
+        method,
+        path,
+        timeout,
+        is_retryable: pb.is_retryable,
+        response_classes,
+    })
+}
+
+fn pb_to_response_class(pb: PbResponseClass) -> Option<ResponseClass> {
+    let status = pb.condition.and_then(|c| c.status)?;
+    if status.min > status.max || status.max > u32::from(::std::u16::MAX) {
+        warn!("ignoring response class with invalid status range: {:?}", status);
+        return None;
+    }
+    Some(ResponseClass {
+        min_status: status.min as u16,
+        max_status: status.max as u16,
+        is_failure: pb.is_failure,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use conduit_proxy_controller_grpc::common::{http_method, HttpMethod};
+    use conduit_proxy_controller_grpc::destination::{
+        HttpStatusRange,
+        RequestMatch,
+        ResponseMatch,
+    };
+    use prost_types;
+
+    use super::*;
+
+    fn pb_route(method: Option<http_method::Type>, path_regex: &str) -> PbRoute {
+        PbRoute {
+            condition: Some(RequestMatch {
+                method: method.map(|type_| HttpMethod { type_: Some(type_) }),
+                path_regex: path_regex.into(),
+            }),
+            response_classes: vec![],
+            timeout: None,
+            is_retryable: false,
+            name: String::new(),
+        }
+    }
+
+    #[test]
+    fn converts_routes() {
+        let mut pb = pb_route(
+            Some(http_method::Type::Unregistered("POST".into())),
+            "/books/[0-9]+",
+        );
+        pb.timeout = Some(prost_types::Duration { seconds: 1, nanos: 500_000_000 });
+        pb.is_retryable = true;
+        pb.name = "books".into();
+        pb.response_classes = vec![PbResponseClass {
+            condition: Some(ResponseMatch {
+                status: Some(HttpStatusRange { min: 500, max: 599 }),
+            }),
+            is_failure: true,
+        }];
+
+        let route = pb_to_route(pb).expect("route");
+        assert_eq!(route.method, Some(http::Method::POST));
+        assert!(route.path.as_ref().unwrap().is_match("/books/1"));
+        assert_eq!(route.timeout, Some(Duration::from_millis(1500)));
+        assert!(route.is_retryable);
+        assert_eq!(route.name, Some(RouteName::new("books")));
+        assert_eq!(route.response_classes, vec![ResponseClass {
+            min_status: 500,
+            max_status: 599,
+            is_failure: true,
+        }]);
+    }
+
+    #[test]
+    fn skips_invalid_routes() {
+        let profile = pb_to_profile(DestinationProfile {
+            routes: vec![
+                pb_route(None, "/books/("),
+                pb_route(Some(http_method::Type::Unregistered("".into())), ""),
+                pb_route(None, "/authors/.*"),
+            ],
+            dst_overrides: vec![],
+            mirror: None,
+        });
+        assert_eq!(profile.routes.len(), 1);
+        assert!(profile.routes[0].path.as_ref().unwrap().is_match("/authors/1"));
+    }
+
+    #[test]
+    fn skips_invalid_response_classes() {
+        let class = |min, max| PbResponseClass {
+            condition: Some(ResponseMatch {
+                status: Some(HttpStatusRange { min, max }),
+            }),
+            is_failure: true,
+        };
+        assert!(pb_to_response_class(class(500, 400)).is_none());
+        assert!(pb_to_response_class(class(500, 70_000)).is_none());
+        assert!(pb_to_response_class(class(500, 500)).is_some());
+    }
+
+    #[test]
+    fn skips_unusable_dst_overrides() {
+        let dst = |authority: &str, weight| PbWeightedDst {
+            authority: authority.into(),
+            weight,
+        };
+        let profile = pb_to_profile(DestinationProfile {
+            routes: vec![],
+            dst_overrides: vec![
+                dst("web-v1.prod.svc.cluster.local", 0),
+                dst("web v2", 10),
+                dst("web-v2.prod.svc.cluster.local:8080", 10),
+            ],
+            mirror: None,
+        });
+        assert_eq!(profile.dst_overrides, vec![WeightedDst {
+            authority: http::uri::Authority::from_static("web-v2.prod.svc.cluster.local:8080"),
+            weight: 10,
+        }]);
+    }
+
+    #[test]
+    fn skips_unusable_mirrors() {
+        let mirror = |authority: &str, percent| PbMirror {
+            authority: authority.into(),
+            percent,
+        };
+        assert!(pb_to_mirror(mirror("web-v2.prod.svc.cluster.local", 0)).is_none());
+        assert!(pb_to_mirror(mirror("web-v2.prod.svc.cluster.local", 101)).is_none());
+        assert!(pb_to_mirror(mirror("web v2", 10)).is_none());
+        assert_eq!(
+            pb_to_mirror(mirror("web-v2.prod.svc.cluster.local", 10)),
+            Some(Mirror {
+                authority: http::uri::Authority::from_static("web-v2.prod.svc.cluster.local"),
+                percent: 10,
+            })
+        );
+    }
+}
@@ -3,6 +3,10 @@ use std::sync::Arc;
 
 use control::destination::DstLabelsWatch;
 use ctx;
+use fault::Injected;
+use mirror::ShadowAuthority;
+use profiles::RouteName;
+use split::ConcreteAuthority;
 
 
 /// Describes a stream's request headers.
@@ -19,6 +23,21 @@ pub struct Request {
 
     /// Identifies the proxy client that dispatched the request.
     pub client: Arc<ctx::transport::Client>,
+
+    /// The route of the destination's profile that the request matched, if
+    /// the destination's routes are named.
+    pub route: Option<RouteName>,
+
+    /// The destination to which the request was sent, if it was split
+    /// from the destination named by its authority.
+    pub concrete_authority: Option<ConcreteAuthority>,
+
+    /// The shadow destination to which the request was sent, if it's a copy
+    /// of another request.
+    pub shadow_authority: Option<ShadowAuthority>,
+
+    /// The fault injected into the request, if any.
+    pub fault: Option<Injected>,
 }
 
 /// Describes a stream's response headers.
@@ -49,6 +68,10 @@ impl Request {
             method: request.method().clone(),
             server: Arc::clone(server),
             client: Arc::clone(client),
+            route: request.extensions().get::<RouteName>().cloned(),
+            concrete_authority: request.extensions().get::<ConcreteAuthority>().cloned(),
+            shadow_authority: request.extensions().get::<ShadowAuthority>().cloned(),
+            fault: request.extensions().get::<Injected>().cloned(),
         };
 
         Arc::new(r)
@@ -88,6 +88,7 @@ pub mod test_util {
 
     use ctx;
     use telemetry::metrics::DstLabels;
+    use transport::tls;
 
     fn addr() -> SocketAddr {
         ([1, 2, 3, 4], 5678).into()
@@ -101,7 +102,12 @@ pub mod test_util {
     }
 
     pub fn server(proxy: &Arc<ctx::Proxy>) -> Arc<ctx::transport::Server> {
-        ctx::transport::Server::new(&proxy, &addr(), &addr(), &Some(addr()))
+        ctx::transport::Server::new(&proxy, &addr(), &addr(), &Some(addr()), None)
+    }
+
+    pub fn tls_server(proxy: &Arc<ctx::Proxy>, identity: &str) -> Arc<ctx::transport::Server> {
+        let identity = tls::Identity::from_dns_name(identity).expect("valid identity");
+        ctx::transport::Server::new(&proxy, &addr(), &addr(), &Some(addr()), Some(identity))
     }
 
     pub fn client<L, S>(proxy: &Arc<ctx::Proxy>, labels: L) -> Arc<ctx::transport::Client>
@@ -110,7 +116,7 @@ pub mod test_util {
         S: fmt::Display,
     {
         let (labels_watch, _store) = futures_watch::Watch::new(DstLabels::new(labels));
-        ctx::transport::Client::new(&proxy, &addr(), Some(labels_watch))
+        ctx::transport::Client::new(&proxy, &addr().into(), Some(labels_watch), None)
     }
 
     pub fn request(
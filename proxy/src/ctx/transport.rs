@@ -2,8 +2,10 @@ use std::{cmp, hash};
 use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 
+use config::Addr;
 use control::destination::DstLabelsWatch;
 use ctx;
+use transport::tls;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Ctx {
@@ -18,14 +20,20 @@ pub struct Server {
     pub remote: SocketAddr,
     pub local: SocketAddr,
     pub orig_dst: Option<SocketAddr>,
+    /// The verified identity of the remote peer, if it connected with TLS.
+    pub tls_identity: Option<tls::Identity>,
 }
 
 /// Identifies a connection from the proxy to another process.
 #[derive(Clone, Debug)]
 pub struct Client {
     pub proxy: Arc<ctx::Proxy>,
-    pub remote: SocketAddr,
+    /// The address of the remote peer, which may be a Unix socket.
+    pub remote: Addr,
     pub dst_labels: Option<DstLabelsWatch>,
+    /// The identity that the remote peer is required to present, if TLS is
+    /// originated on the connection.
+    pub tls_identity: Option<tls::Identity>,
 }
 
 impl Ctx {
@@ -35,6 +43,13 @@ impl Ctx {
             Ctx::Server(ref ctx) => &ctx.proxy,
         }
     }
+
+    pub fn tls_identity(&self) -> Option<&tls::Identity> {
+        match *self {
+            Ctx::Client(ref ctx) => ctx.tls_identity.as_ref(),
+            Ctx::Server(ref ctx) => ctx.tls_identity.as_ref(),
+        }
+    }
 }
 
 impl Server {
@@ -43,12 +58,14 @@ impl Server {
         local: &SocketAddr,
         remote: &SocketAddr,
         orig_dst: &Option<SocketAddr>,
+        tls_identity: Option<tls::Identity>,
     ) -> Arc<Server> {
         let s = Server {
             proxy: Arc::clone(proxy),
             local: *local,
             remote: *remote,
             orig_dst: *orig_dst,
+            tls_identity,
         };
 
         Arc::new(s)
@@ -81,13 +98,15 @@ fn same_addr(a0: &SocketAddr, a1: &SocketAddr) -> bool {
 impl Client {
     pub fn new(
         proxy: &Arc<ctx::Proxy>,
-        remote: &SocketAddr,
+        remote: &Addr,
         dst_labels: Option<DstLabelsWatch>,
+        tls_identity: Option<tls::Identity>,
     ) -> Arc<Client> {
         let c = Client {
             proxy: Arc::clone(proxy),
-            remote: *remote,
+            remote: remote.clone(),
             dst_labels,
+            tls_identity,
         };
 
         Arc::new(c)
@@ -99,13 +118,15 @@ impl hash::Hash for Client {
         self.proxy.hash(state);
         self.remote.hash(state);
         // ignore dst_labels
+        self.tls_identity.hash(state);
     }
 }
 
 impl cmp::PartialEq for Client {
     fn eq(&self, other: &Self) -> bool {
         self.proxy.eq(&other.proxy) &&
-        self.remote.eq(&other.remote)
+        self.remote.eq(&other.remote) &&
+        self.tls_identity.eq(&other.tls_identity)
     }
 }
 
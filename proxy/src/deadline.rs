@@ -0,0 +1,202 @@
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future, Poll};
+use http;
+use http::header::HeaderValue;
+use tokio_core::reactor::{Handle, Timeout};
+use tower_service::Service;
+
+use telemetry::sensor::http::RequestOpen;
+use timeout::TimeoutError;
+
+const GRPC_TIMEOUT: &str = "grpc-timeout";
+
+/// A `grpc-timeout` value has at most 8 digits.
+const MAX_GRPC_TIMEOUT_DIGITS: u64 = 100_000_000;
+
+/// Enforces the deadline of a request that has a `grpc-timeout` header.
+///
+/// The deadline is measured from when the proxy received the request, so it
+/// covers the time that the request waits to be dispatched. The request's
+/// `grpc-timeout` is decremented by the time already spent in the proxy
+/// before it's forwarded, so that the next hop sees what remains of it.
+#[derive(Clone, Debug)]
+pub struct Deadline<S> {
+    inner: S,
+    handle: Handle,
+}
+
+pub struct ResponseFuture<F> {
+    /// The inner future, or `None` if the deadline passed before the request
+    /// was dispatched.
+    inner: Option<F>,
+    expiry: Option<(Timeout, Duration)>,
+}
+
+// ===== impl Deadline =====
+
+impl<S> Deadline<S> {
+    pub fn new(inner: S, handle: &Handle) -> Self {
+        Self {
+            inner,
+            handle: handle.clone(),
+        }
+    }
+}
+
+impl<S, B> Service for Deadline<S>
+where
+    S: Service<Request = http::Request<B>>,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = TimeoutError<S::Error>;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready().map_err(TimeoutError::Error)
+    }
+
+    fn call(&mut self, mut req: Self::Request) -> Self::Future {
+        let timeout = match req.headers().get(GRPC_TIMEOUT).and_then(parse_grpc_timeout) {
+            Some(timeout) => timeout,
+            None => return ResponseFuture {
+                inner: Some(self.inner.call(req)),
+                expiry: None,
+            },
+        };
+
+        let opened_at = req.extensions().get::<RequestOpen>()
+            .map(|&RequestOpen(at)| at)
+            .unwrap_or_else(Instant::now);
+        let spent = opened_at.elapsed();
+        if spent >= timeout {
+            debug!("grpc-timeout of {:?} passed before request was dispatched", timeout);
+            return ResponseFuture {
+                inner: None,
+                expiry: Some((
+                    Timeout::new(Duration::from_secs(0), &self.handle).expect("reactor gone"),
+                    timeout,
+                )),
+            };
+        }
+
+        let remaining = timeout - spent;
+        req.headers_mut().insert(GRPC_TIMEOUT, encode_grpc_timeout(remaining));
+
+        let timer = Timeout::new(remaining, &self.handle).expect("reactor gone");
+        ResponseFuture {
+            inner: Some(self.inner.call(req)),
+            expiry: Some((timer, timeout)),
+        }
+    }
+}
+
+// ===== impl ResponseFuture =====
+
+impl<F: Future> Future for ResponseFuture<F> {
+    type Item = F::Item;
+    type Error = TimeoutError<F::Error>;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Some(ref mut inner) = self.inner {
+            if let Async::Ready(rsp) = inner.poll().map_err(TimeoutError::Error)? {
+                return Ok(Async::Ready(rsp));
+            }
+        }
+
+        if let Some((ref mut timer, timeout)) = self.expiry {
+            if timer.poll().expect("deadline timer").is_ready() {
+                return Err(TimeoutError::Timeout(timeout));
+            }
+        }
+
+        Ok(Async::NotReady)
+    }
+}
+
+/// Parses a `grpc-timeout` header value, e.g. `100m` for 100 milliseconds.
+fn parse_grpc_timeout(value: &HeaderValue) -> Option<Duration> {
+    let value = value.to_str().ok()?;
+    if value.len() < 2 || value.len() > 9 {
+        return None;
+    }
+
+    let (digits, unit) = value.split_at(value.len() - 1);
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n = digits.parse::<u64>().ok()?;
+
+    match unit {
+        "H" => Some(Duration::from_secs(n * 60 * 60)),
+        "M" => Some(Duration::from_secs(n * 60)),
+        "S" => Some(Duration::from_secs(n)),
+        "m" => Some(Duration::from_millis(n)),
+        "u" => Some(Duration::new(n / 1_000_000, (n % 1_000_000) as u32 * 1_000)),
+        "n" => Some(Duration::new(n / 1_000_000_000, (n % 1_000_000_000) as u32)),
+        _ => None,
+    }
+}
+
+/// Encodes a duration as a `grpc-timeout` header value, in the finest unit
+/// that fits.
+///
+/// The duration is truncated, so the deadline is never extended.
+fn encode_grpc_timeout(d: Duration) -> HeaderValue {
+    let nanos = d.as_secs()
+        .saturating_mul(1_000_000_000)
+        .saturating_add(d.subsec_nanos() as u64);
+
+    let units = [
+        ("n", 1),
+        ("u", 1_000),
+        ("m", 1_000_000),
+        ("S", 1_000_000_000),
+        ("M", 60 * 1_000_000_000),
+        ("H", 60 * 60 * 1_000_000_000),
+    ];
+    let (unit, value) = units.iter()
+        .map(|&(unit, per)| (unit, nanos / per))
+        .find(|&(_, value)| value < MAX_GRPC_TIMEOUT_DIGITS)
+        .unwrap_or(("H", MAX_GRPC_TIMEOUT_DIGITS - 1));
+
+    HeaderValue::from_str(&format!("{}{}", value, unit))
+        .expect("grpc-timeout is a valid header value")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Option<Duration> {
+        parse_grpc_timeout(&HeaderValue::from_str(s).unwrap())
+    }
+
+    #[test]
+    fn parses_grpc_timeouts() {
+        assert_eq!(parse("1H"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse("2M"), Some(Duration::from_secs(120)));
+        assert_eq!(parse("3S"), Some(Duration::from_secs(3)));
+        assert_eq!(parse("100m"), Some(Duration::from_millis(100)));
+        assert_eq!(parse("1500000u"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse("250n"), Some(Duration::new(0, 250)));
+    }
+
+    #[test]
+    fn rejects_invalid_grpc_timeouts() {
+        assert_eq!(parse("m"), None);
+        assert_eq!(parse("10"), None);
+        assert_eq!(parse("10s"), None);
+        assert_eq!(parse("-10m"), None);
+        assert_eq!(parse("123456789m"), None);
+    }
+
+    #[test]
+    fn encodes_grpc_timeouts_in_finest_unit() {
+        assert_eq!(encode_grpc_timeout(Duration::new(0, 250)), "250n");
+        assert_eq!(encode_grpc_timeout(Duration::from_millis(1500)), "1500000u");
+        assert_eq!(encode_grpc_timeout(Duration::from_secs(3600)), "3600000m");
+        assert_eq!(encode_grpc_timeout(Duration::from_secs(1_000_000_000)), "16666666M");
+    }
+}
@@ -0,0 +1,402 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{Async, Future, Poll};
+use h2;
+use http::{self, header::{CONTENT_LENGTH, CONTENT_TYPE}};
+use rand::{self, Rng};
+use tokio_core::reactor::{Handle, Timeout};
+use tower_h2::client;
+use tower_service::{NewService, Service};
+
+use config::{Fault, FaultKind};
+use transparency::{h1, HttpBody};
+use transport::HostAndPort;
+
+const GRPC_STATUS: &str = "grpc-status";
+const GRPC_MESSAGE: &str = "grpc-message";
+
+/// A fault that's injected into a request.
+///
+/// It's inserted into the extensions of requests by `Choose`, and acted on by
+/// `Inject`. Requests' metrics and tap events are labeled by it, so that the
+/// failures it causes aren't mistaken for real ones, and requests into which
+/// it's injected are neither retried nor counted against their endpoints.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Injected {
+    Delay(Duration),
+    Abort(http::StatusCode),
+    GrpcAbort(u32),
+    Reset,
+}
+
+/// Chooses the fault, if any, that's injected into each request, according to
+/// the faults configured for the request's authority.
+///
+/// It sits above a destination's routes, so that the fault is chosen once for
+/// each request, however many times it's dispatched.
+#[derive(Clone, Debug)]
+pub struct Choose<S> {
+    inner: S,
+    faults: Arc<Vec<Fault>>,
+}
+
+/// Injects the faults chosen by `Choose` into requests.
+///
+/// It sits beneath the telemetry sensors, so that injected faults are
+/// observed like the responses and errors of the endpoint itself.
+#[derive(Clone)]
+pub struct Inject<N> {
+    inner: N,
+    handle: Handle,
+}
+
+pub struct NewInject<F> {
+    future: F,
+    handle: Handle,
+}
+
+/// The service built by `Inject`.
+///
+/// Its inner service is shared with the requests that are delayed, which are
+/// dispatched once their delays pass.
+pub struct InjectService<S> {
+    inner: Rc<RefCell<S>>,
+    handle: Handle,
+}
+
+pub enum ResponseFuture<S: Service> {
+    Inner(S::Future),
+    /// The request isn't dispatched until the timer fires.
+    Delayed(Rc<RefCell<S>>, Option<S::Request>, Timeout),
+    Abort(Option<http::Response<HttpBody>>),
+    Reset,
+}
+
+// ===== impl Injected =====
+
+impl Injected {
+    fn new(kind: &FaultKind) -> Self {
+        match *kind {
+            FaultKind::Delay { min, max } => {
+                let range = max - min;
+                let range_ms = range.as_secs() * 1_000 + u64::from(range.subsec_nanos() / 1_000_000);
+                if range_ms == 0 {
+                    return Injected::Delay(min);
+                }
+                let jitter = rand::thread_rng().gen_range(0, range_ms + 1);
+                Injected::Delay(min + Duration::from_millis(jitter))
+            },
+            FaultKind::Abort(status) => Injected::Abort(status),
+            FaultKind::GrpcAbort(code) => Injected::GrpcAbort(code),
+            FaultKind::Reset => Injected::Reset,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Injected::Delay(_) => "delay",
+            Injected::Abort(_) | Injected::GrpcAbort(_) => "abort",
+            Injected::Reset => "reset",
+        }
+    }
+
+    fn response(&self) -> Option<http::Response<HttpBody>> {
+        let mut rsp = http::Response::builder();
+        match *self {
+            Injected::Abort(status) => {
+                rsp.status(status)
+                    .header(CONTENT_LENGTH, "0");
+            },
+            Injected::GrpcAbort(code) => {
+                rsp.status(http::StatusCode::OK)
+                    .header(CONTENT_TYPE, "application/grpc")
+                    .header(GRPC_STATUS, &*code.to_string())
+                    .header(GRPC_MESSAGE, "fault injected by proxy");
+            },
+            Injected::Delay(_) | Injected::Reset => return None,
+        }
+        let rsp = rsp.body(HttpBody::default())
+            .expect("injected fault response is valid");
+        Some(rsp)
+    }
+}
+
+impl fmt::Display for Injected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// ===== impl Choose =====
+
+impl<S> Choose<S> {
+    pub fn new(inner: S, faults: Arc<Vec<Fault>>) -> Self {
+        Self { inner, faults }
+    }
+
+    fn choose<B>(&self, req: &http::Request<B>) -> Option<Injected> {
+        if self.faults.is_empty() {
+            return None;
+        }
+
+        let authority = req.uri().authority_part().cloned()
+            .or_else(|| h1::authority_from_host(req))?;
+        let authority = HostAndPort::normalize(&authority, Some(80)).ok()?;
+        let authority = http::uri::Authority::from(&authority);
+
+        let fault = self.faults.iter()
+            .filter(|f| f.authority == authority)
+            .find(|f| rand::thread_rng().gen::<f32>() * 100.0 < f.percent)?;
+        Some(Injected::new(&fault.kind))
+    }
+}
+
+impl<S, B> Service for Choose<S>
+where
+    S: Service<Request = http::Request<B>>,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, mut req: Self::Request) -> Self::Future {
+        if let Some(injected) = self.choose(&req) {
+            debug!("injecting {:?} into request to {}", injected, req.uri());
+            req.extensions_mut().insert(injected);
+        }
+        self.inner.call(req)
+    }
+}
+
+// ===== impl Inject =====
+
+impl<N> Inject<N> {
+    pub fn new(inner: N, handle: &Handle) -> Self {
+        Self {
+            inner,
+            handle: handle.clone(),
+        }
+    }
+}
+
+impl<N, B> NewService for Inject<N>
+where
+    N: NewService<
+        Request = http::Request<B>,
+        Response = http::Response<HttpBody>,
+        Error = client::Error,
+    >,
+{
+    type Request = N::Request;
+    type Response = N::Response;
+    type Error = N::Error;
+    type Service = InjectService<N::Service>;
+    type InitError = N::InitError;
+    type Future = NewInject<N::Future>;
+
+    fn new_service(&self) -> Self::Future {
+        NewInject {
+            future: self.inner.new_service(),
+            handle: self.handle.clone(),
+        }
+    }
+}
+
+impl<F: Future> Future for NewInject<F> {
+    type Item = InjectService<F::Item>;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let inner = try_ready!(self.future.poll());
+        Ok(Async::Ready(InjectService {
+            inner: Rc::new(RefCell::new(inner)),
+            handle: self.handle.clone(),
+        }))
+    }
+}
+
+// ===== impl InjectService =====
+
+impl<S, B> Service for InjectService<S>
+where
+    S: Service<
+        Request = http::Request<B>,
+        Response = http::Response<HttpBody>,
+        Error = client::Error,
+    >,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.borrow_mut().poll_ready()
+    }
+
+    fn call(&mut self, req: Self::Request) -> Self::Future {
+        let injected = req.extensions().get::<Injected>().cloned();
+        let injected = match injected {
+            Some(injected) => injected,
+            None => return ResponseFuture::Inner(self.inner.borrow_mut().call(req)),
+        };
+
+        match injected {
+            Injected::Delay(delay) => {
+                let timer = Timeout::new(delay, &self.handle).expect("reactor gone");
+                ResponseFuture::Delayed(self.inner.clone(), Some(req), timer)
+            },
+            Injected::Abort(_) | Injected::GrpcAbort(_) => {
+                ResponseFuture::Abort(injected.response())
+            },
+            Injected::Reset => ResponseFuture::Reset,
+        }
+    }
+}
+
+// ===== impl ResponseFuture =====
+
+impl<S> Future for ResponseFuture<S>
+where
+    S: Service<Response = http::Response<HttpBody>, Error = client::Error>,
+{
+    type Item = S::Response;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let f = match *self {
+            ResponseFuture::Inner(ref mut f) => return f.poll(),
+            ResponseFuture::Delayed(ref service, ref mut req, ref mut timer) => {
+                if timer.poll().expect("fault timer").is_not_ready() {
+                    return Ok(Async::NotReady);
+                }
+                let mut service = service.borrow_mut();
+                try_ready!(service.poll_ready());
+                let req = req.take().expect("polled after ready");
+                service.call(req)
+            },
+            ResponseFuture::Abort(ref mut rsp) => {
+                let rsp = rsp.take().expect("polled after ready");
+                return Ok(Async::Ready(rsp));
+            },
+            ResponseFuture::Reset => return Err(h2::Reason::CANCEL.into()),
+        };
+        *self = ResponseFuture::Inner(f);
+        self.poll()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::str::FromStr;
+
+    use futures::future;
+    use tokio_core::reactor::Core;
+
+    use super::*;
+
+    /// Counts the requests that it's called with.
+    struct Count(Rc<Cell<usize>>);
+
+    impl Service for Count {
+        type Request = http::Request<()>;
+        type Response = http::Response<HttpBody>;
+        type Error = client::Error;
+        type Future = future::FutureResult<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: Self::Request) -> Self::Future {
+            self.0.set(self.0.get() + 1);
+            future::ok(http::Response::new(HttpBody::default()))
+        }
+    }
+
+    fn fault(authority: &str, percent: f32, kind: FaultKind) -> Fault {
+        Fault {
+            authority: http::uri::Authority::from_str(authority).unwrap(),
+            percent,
+            kind,
+        }
+    }
+
+    fn request(uri: &str) -> http::Request<()> {
+        http::Request::get(uri).body(()).unwrap()
+    }
+
+    #[test]
+    fn faults_are_chosen_by_authority() {
+        let choose = Choose::new((), Arc::new(vec![
+            fault("books:80", 0.0, FaultKind::Reset),
+            fault("books:80", 100.0, FaultKind::Abort(http::StatusCode::SERVICE_UNAVAILABLE)),
+            fault("authors:8080", 100.0, FaultKind::GrpcAbort(14)),
+        ]));
+
+        assert_eq!(
+            choose.choose(&request("http://books/")),
+            Some(Injected::Abort(http::StatusCode::SERVICE_UNAVAILABLE))
+        );
+        assert_eq!(
+            choose.choose(&request("http://books:80/")),
+            Some(Injected::Abort(http::StatusCode::SERVICE_UNAVAILABLE))
+        );
+        assert_eq!(choose.choose(&request("http://authors:8080/")), Some(Injected::GrpcAbort(14)));
+        assert_eq!(choose.choose(&request("http://authors/")), None);
+    }
+
+    #[test]
+    fn delays_are_within_bounds() {
+        let kind = FaultKind::Delay {
+            min: Duration::from_millis(100),
+            max: Duration::from_millis(200),
+        };
+        for _ in 0..100 {
+            match Injected::new(&kind) {
+                Injected::Delay(d) => {
+                    assert!(d >= Duration::from_millis(100) && d <= Duration::from_millis(200));
+                },
+                injected => panic!("unexpected fault: {:?}", injected),
+            }
+        }
+    }
+
+    #[test]
+    fn delayed_requests_are_dispatched_after_their_delay() {
+        let mut core = Core::new().unwrap();
+        let calls = Rc::new(Cell::new(0));
+        let mut inject = InjectService {
+            inner: Rc::new(RefCell::new(Count(calls.clone()))),
+            handle: core.handle(),
+        };
+
+        let mut req = request("http://books/");
+        req.extensions_mut().insert(Injected::Delay(Duration::from_millis(10)));
+        let rsp = inject.call(req);
+        assert_eq!(calls.get(), 0);
+
+        core.run(rsp).unwrap();
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn aborts_respond_with_status() {
+        let rsp = Injected::Abort(http::StatusCode::SERVICE_UNAVAILABLE).response().unwrap();
+        assert_eq!(rsp.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+
+        let rsp = Injected::GrpcAbort(14).response().unwrap();
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+        assert_eq!(rsp.headers()[GRPC_STATUS], "14");
+    }
+}
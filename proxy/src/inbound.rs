@@ -1,7 +1,9 @@
-use std::net::{SocketAddr};
+use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::Arc;
 
 use http;
+use indexmap::IndexSet;
 use tower_service as tower;
 use tower_buffer::{self, Buffer};
 use tower_in_flight_limit::{self, InFlightLimit};
@@ -9,26 +11,65 @@ use tower_h2;
 use conduit_proxy_router::Recognize;
 
 use bind;
+use config::{self, Addr};
 use ctx;
+use deadline::Deadline;
+use fault;
+use queue::{Dequeue, Enqueue, Queue};
+use rate_limit::{Limiter, RateLimit};
+use timeout::TimeoutError;
 
 type Bind<B> = bind::Bind<Arc<ctx::Proxy>, B>;
 
 pub struct Inbound<B> {
-    default_addr: Option<SocketAddr>,
+    default_addr: Option<Addr>,
+    /// The ports whose requests are forwarded to `default_addr` when it's a
+    /// Unix socket.
+    forward_ports: Arc<IndexSet<u16>>,
     bind: Bind<B>,
+    queue: config::Queue,
+    max_in_flight: usize,
+    /// The rate limiters of the ports that have rate limits.
+    limiters: Arc<HashMap<u16, Limiter>>,
 }
 
-const MAX_IN_FLIGHT: usize = 10_000;
+/// Identifies an inbound route by the address to which its requests are
+/// sent, the port of their original destination, if they have one, and their
+/// protocol.
+///
+/// Requests to all of the ports that are forwarded to a Unix socket are sent
+/// to the same address, so the port distinguishes their routes, which may
+/// have different rate limits.
+type Key = (Addr, Option<u16>, bind::Protocol);
 
 // ===== impl Inbound =====
 
 impl<B> Inbound<B> {
-    pub fn new(default_addr: Option<SocketAddr>, bind: Bind<B>) -> Self {
+    pub fn new(
+        default_addr: Option<Addr>,
+        forward_ports: IndexSet<u16>,
+        bind: Bind<B>,
+        queue: config::Queue,
+        max_in_flight: usize,
+        rate_limits: config::RateLimits<u16>,
+    ) -> Self {
+        let limiters = Limiter::from_config(&rate_limits, bind.sensors(), bind.ctx());
         Self {
             default_addr,
+            forward_ports: Arc::new(forward_ports),
             bind,
+            queue,
+            max_in_flight,
+            limiters: Arc::new(limiters),
         }
     }
+
+    /// Returns the rate limiter of the port of the route's original
+    /// destination, if it has one.
+    fn limiter(&self, key: &Key) -> Option<Limiter> {
+        let &(_, port, _) = key;
+        port.and_then(|port| self.limiters.get(&port)).cloned()
+    }
 }
 
 impl<B> Clone for Inbound<B>
@@ -39,6 +80,10 @@ where
         Self {
             bind: self.bind.clone(),
             default_addr: self.default_addr.clone(),
+            forward_ports: self.forward_ports.clone(),
+            queue: self.queue,
+            max_in_flight: self.max_in_flight,
+            limiters: self.limiters.clone(),
         }
     }
 }
@@ -50,26 +95,39 @@ where
     type Request = http::Request<B>;
     type Response = bind::HttpResponse;
     type Error = tower_in_flight_limit::Error<
-        tower_buffer::Error<
-            <bind::Service<B> as tower::Service>::Error
+        TimeoutError<
+            tower_buffer::Error<
+                <bind::Service<B> as tower::Service>::Error
+            >
         >
     >;
-    type Key = (SocketAddr, bind::Protocol);
+    type Key = Key;
     type RouteError = bind::BufferSpawnError;
-    type Service = InFlightLimit<Buffer<bind::Service<B>>>;
+    type Service = fault::Choose<InFlightLimit<Deadline<RateLimit<
+        Enqueue<Buffer<Dequeue<bind::Service<B>>>>
+    >>>>;
 
     fn recognize(&self, req: &Self::Request) -> Option<Self::Key> {
-        let key = req.extensions()
+        let orig_dst = req.extensions()
             .get::<Arc<ctx::transport::Server>>()
             .and_then(|ctx| {
                 trace!("recognize local={} orig={:?}", ctx.local, ctx.orig_dst);
                 ctx.orig_dst_if_not_local()
-            })
-            .or_else(|| self.default_addr);
+            });
+
+        // The original destination can never name a Unix socket, so requests
+        // to the ports that the application serves on one are sent there.
+        let port = orig_dst.map(|addr| addr.port());
+        let key = match (orig_dst, &self.default_addr) {
+            (Some(orig_dst), &Some(ref addr @ Addr::Unix(_)))
+                if self.forward_ports.contains(&orig_dst.port()) => Some(addr.clone()),
+            (Some(orig_dst), _) => Some(Addr::Tcp(orig_dst)),
+            (None, default_addr) => default_addr.clone(),
+        };
 
         let proto = bind::Protocol::detect(req);
 
-        let key = key.map(move |addr| (addr, proto));
+        let key = key.map(move |addr| (addr, port, proto));
         trace!("recognize key={:?}", key);
 
         key
@@ -77,49 +135,96 @@ where
 
     /// Builds a static service to a single endpoint.
     ///
-    /// # TODO
-    ///
-    /// Buffering is currently unbounded and does not apply timeouts. This must be
-    /// changed.
+    /// Requests that can't be buffered, or that wait in the buffer too long,
+    /// are shed. Requests that have a `grpc-timeout` fail when it passes.
+    /// Requests whose original destination is a port that has a rate limit
+    /// wait for it, or are rejected, when they exceed it.
+    /// Faults configured for a request's authority may be injected into it.
     fn bind_service(&self, key: &Self::Key) -> Result<Self::Service, Self::RouteError> {
-        let &(ref addr, ref proto) = key;
+        let &(ref addr, _, ref proto) = key;
         debug!("building inbound {:?} client to {}", proto, addr);
 
-        let endpoint = (*addr).into();
+        let endpoint = addr.clone().into();
         let binding = self.bind.new_binding(&endpoint, proto);
-        Buffer::new(binding, self.bind.executor())
+        let queue = Queue::new(
+            self.queue,
+            self.bind.sensors(),
+            self.bind.ctx(),
+            authority(addr),
+        );
+        let limiter = self.limiter(key);
+        let executor = self.bind.executor();
+        Buffer::new(queue.dequeue(binding), executor)
             .map(|buffer| {
-                InFlightLimit::new(buffer, MAX_IN_FLIGHT)
+                let limit = RateLimit::new(queue.enqueue(buffer, executor), limiter, executor);
+                let deadline = Deadline::new(limit, executor);
+                let limit = InFlightLimit::new(deadline, self.max_in_flight);
+                fault::Choose::new(limit, self.bind.faults().clone())
             })
             .map_err(|_| bind::BufferSpawnError::Inbound)
     }
 }
 
+/// Returns the authority by which an inbound route's metrics are labeled.
+///
+/// An application that listens on a Unix socket is only reachable locally.
+fn authority(addr: &Addr) -> http::uri::Authority {
+    let authority = match *addr {
+        Addr::Tcp(addr) => addr.to_string(),
+        Addr::Unix(_) => "localhost".to_owned(),
+    };
+    http::uri::Authority::from_str(&authority)
+        .expect("inbound address is a valid authority")
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use std::net;
+    use std::path::PathBuf;
     use std::sync::Arc;
+    use std::time::Duration;
 
     use http;
+    use indexmap::IndexSet;
     use tokio_core::reactor::Core;
     use conduit_proxy_router::Recognize;
 
-    use super::Inbound;
+    use super::{Inbound, Key};
     use bind::{self, Bind, Host};
+    use config::{self, Addr};
     use ctx;
 
     fn new_inbound(default: Option<net::SocketAddr>, ctx: &Arc<ctx::Proxy>) -> Inbound<()> {
+        new_inbound_with_addr(default.map(Addr::Tcp), IndexSet::new(), HashMap::new(), ctx)
+    }
+
+    fn new_inbound_with_addr(
+        default: Option<Addr>,
+        forward_ports: IndexSet<u16>,
+        limits: HashMap<u16, config::RateLimit>,
+        ctx: &Arc<ctx::Proxy>,
+    ) -> Inbound<()> {
         let core = Core::new().unwrap();
         let bind = Bind::new(core.handle()).with_ctx(ctx.clone());
-        Inbound::new(default, bind)
+        let queue = config::Queue {
+            capacity: 100,
+            max_wait: Duration::from_secs(10),
+        };
+        let rate_limits = config::RateLimits {
+            limits,
+            max_wait: Duration::from_secs(0),
+        };
+        Inbound::new(default, forward_ports, bind, queue, 10_000, rate_limits)
     }
 
-    fn make_key_http1(addr: net::SocketAddr) -> (net::SocketAddr, bind::Protocol) {
+    fn make_key_http1<A: Into<Addr>>(addr: A, port: Option<u16>) -> Key {
         let protocol = bind::Protocol::Http1 {
             host: Host::NoAuthority,
             was_absolute_form: false,
+            is_upgrade: false,
         };
-        (addr, protocol)
+        (addr.into(), port, protocol)
     }
 
     quickcheck! {
@@ -132,9 +237,10 @@ mod tests {
 
             let inbound = new_inbound(None, &ctx);
 
-            let srv_ctx = ctx::transport::Server::new(&ctx, &local, &remote, &Some(orig_dst));
+            let srv_ctx = ctx::transport::Server::new(&ctx, &local, &remote, &Some(orig_dst), None);
 
-            let rec = srv_ctx.orig_dst_if_not_local().map(make_key_http1);
+            let rec = srv_ctx.orig_dst_if_not_local()
+                .map(|addr| make_key_http1(addr, Some(addr.port())));
 
             let mut req = http::Request::new(());
             req.extensions_mut()
@@ -159,9 +265,10 @@ mod tests {
                     &local,
                     &remote,
                     &None,
+                    None,
                 ));
 
-            inbound.recognize(&req) == default.map(make_key_http1)
+            inbound.recognize(&req) == default.map(|addr| make_key_http1(addr, None))
         }
 
         fn recognize_default_no_ctx(default: Option<net::SocketAddr>) -> bool {
@@ -171,7 +278,7 @@ mod tests {
 
             let req = http::Request::new(());
 
-            inbound.recognize(&req) == default.map(make_key_http1)
+            inbound.recognize(&req) == default.map(|addr| make_key_http1(addr, None))
         }
 
         fn recognize_default_no_loop(
@@ -190,9 +297,100 @@ mod tests {
                     &local,
                     &remote,
                     &Some(local),
+                    None,
                 ));
 
-            inbound.recognize(&req) == default.map(make_key_http1)
+            inbound.recognize(&req) == default.map(|addr| make_key_http1(addr, None))
+        }
+
+        fn recognize_unix_default_for_forward_ports(
+            orig_dst: net::SocketAddr,
+            local: net::SocketAddr,
+            remote: net::SocketAddr
+        ) -> bool {
+            let ctx = ctx::Proxy::inbound(&ctx::Process::test("test"));
+
+            let default = Addr::Unix(PathBuf::from("/var/run/app.sock"));
+            let mut forward_ports = IndexSet::new();
+            forward_ports.insert(orig_dst.port());
+            let inbound = new_inbound_with_addr(
+                Some(default.clone()),
+                forward_ports,
+                HashMap::new(),
+                &ctx,
+            );
+
+            let srv_ctx = ctx::transport::Server::new(&ctx, &local, &remote, &Some(orig_dst), None);
+
+            let port = srv_ctx.orig_dst_if_not_local().map(|addr| addr.port());
+
+            let mut req = http::Request::new(());
+            req.extensions_mut()
+                .insert(srv_ctx);
+
+            inbound.recognize(&req) == Some(make_key_http1(default, port))
+        }
+
+        fn recognize_orig_dst_for_other_ports(
+            orig_dst: net::SocketAddr,
+            local: net::SocketAddr,
+            remote: net::SocketAddr
+        ) -> bool {
+            let ctx = ctx::Proxy::inbound(&ctx::Process::test("test"));
+
+            let default = Addr::Unix(PathBuf::from("/var/run/app.sock"));
+            let mut forward_ports = IndexSet::new();
+            forward_ports.insert(orig_dst.port().wrapping_add(1));
+            let inbound = new_inbound_with_addr(
+                Some(default.clone()),
+                forward_ports,
+                HashMap::new(),
+                &ctx,
+            );
+
+            let srv_ctx = ctx::transport::Server::new(&ctx, &local, &remote, &Some(orig_dst), None);
+
+            let rec = match srv_ctx.orig_dst_if_not_local() {
+                Some(addr) => make_key_http1(addr, Some(addr.port())),
+                None => make_key_http1(default, None),
+            };
+
+            let mut req = http::Request::new(());
+            req.extensions_mut()
+                .insert(srv_ctx);
+
+            inbound.recognize(&req) == Some(rec)
         }
     }
+
+    #[test]
+    fn forwarded_ports_are_rate_limited_by_original_destination() {
+        let ctx = ctx::Proxy::inbound(&ctx::Process::test("test"));
+
+        let default = Addr::Unix(PathBuf::from("/var/run/app.sock"));
+        let mut forward_ports = IndexSet::new();
+        forward_ports.insert(8080);
+        forward_ports.insert(9090);
+        let mut limits = HashMap::new();
+        limits.insert(8080, config::RateLimit { rate: 100, burst: 100 });
+        let inbound = new_inbound_with_addr(Some(default.clone()), forward_ports, limits, &ctx);
+
+        let local = net::SocketAddr::from(([10, 0, 0, 1], 4143));
+        let remote = net::SocketAddr::from(([10, 0, 0, 2], 50000));
+        let recognize = |port: u16| {
+            let orig_dst = net::SocketAddr::from(([10, 0, 0, 1], port));
+            let mut req = http::Request::new(());
+            req.extensions_mut()
+                .insert(ctx::transport::Server::new(&ctx, &local, &remote, &Some(orig_dst), None));
+            inbound.recognize(&req).expect("request is recognized")
+        };
+
+        let limited = recognize(8080);
+        assert_eq!(limited, make_key_http1(default.clone(), Some(8080)));
+        assert!(inbound.limiter(&limited).is_some());
+
+        let unlimited = recognize(9090);
+        assert_eq!(unlimited, make_key_http1(default, Some(9090)));
+        assert!(inbound.limiter(&unlimited).is_none());
+    }
 }
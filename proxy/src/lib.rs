@@ -29,9 +29,13 @@ extern crate prost_types;
 extern crate quickcheck;
 extern crate rand;
 extern crate regex;
+extern crate ring;
+extern crate rustls;
 extern crate tokio_connect;
 extern crate tokio_core;
 extern crate tokio_io;
+extern crate tokio_rustls;
+extern crate tokio_uds;
 extern crate tower_balance;
 extern crate tower_buffer;
 extern crate tower_discover;
@@ -43,6 +47,8 @@ extern crate conduit_proxy_router;
 extern crate tower_util;
 extern crate tower_in_flight_limit;
 extern crate trust_dns_resolver;
+extern crate untrusted;
+extern crate webpki;
 
 use futures::*;
 
@@ -59,18 +65,34 @@ use tower_service::NewService;
 use tower_fn::*;
 use conduit_proxy_router::{Recognize, Router, Error as RouteError};
 
+mod accept;
 pub mod app;
+mod backoff;
+mod balance;
 mod bind;
 pub mod config;
 mod connection;
 pub mod control;
 pub mod ctx;
+mod deadline;
 mod dns;
 mod drain;
+mod fault;
 mod inbound;
+mod locality;
 mod logging;
 mod map_err;
+mod mirror;
+mod orig_proto;
 mod outbound;
+mod outlier;
+mod profiles;
+mod proxy_error;
+mod queue;
+mod rate_limit;
+mod retry;
+mod split;
+mod tcp_outbound;
 pub mod telemetry;
 mod transparency;
 mod transport;
@@ -82,9 +104,12 @@ use bind::Bind;
 use connection::BoundPort;
 use inbound::Inbound;
 use map_err::MapErr;
+use proxy_error::{Classify, Reason};
 use transparency::{HttpBody, Server};
 pub use transport::{GetOriginalDst, SoOriginalDst};
+use transport::tls;
 use outbound::Outbound;
+use tcp_outbound::TcpOutbound;
 
 /// Runs a sidecar proxy.
 ///
@@ -107,6 +132,9 @@ pub struct Main<G> {
     outbound_listener: BoundPort,
     metrics_listener: BoundPort,
 
+    tls_server_config: Option<tls::ServerConfig>,
+    tls_client_config: Option<tls::ClientConfig>,
+
     get_original_dst: G,
 
     reactor: Core,
@@ -118,47 +146,68 @@ where
 {
     pub fn new(config: config::Config, get_original_dst: G) -> Self {
 
-        let control_listener = BoundPort::new(config.control_listener.addr)
-            .expect("controller listener bind");
-        let inbound_listener = BoundPort::new(config.public_listener.addr)
-            .expect("public listener bind");
-        let outbound_listener = BoundPort::new(config.private_listener.addr)
-            .expect("private listener bind");
+        let control_listener = BoundPort::new(config.control_listener.addr.clone())
+            .expect("controller listener bind")
+            .with_accept_backoff(config.accept_backoff);
+        let inbound_listener = BoundPort::new(config.public_listener.addr.clone())
+            .expect("public listener bind")
+            .with_accept_backoff(config.accept_backoff);
+        let outbound_listener = BoundPort::new(config.private_listener.addr.clone())
+            .expect("private listener bind")
+            .with_accept_backoff(config.accept_backoff);
 
         let reactor = Core::new().expect("reactor");
 
-        let metrics_listener = BoundPort::new(config.metrics_listener.addr)
-            .expect("metrics listener bind");
+        let metrics_listener = BoundPort::new(config.metrics_listener.addr.clone())
+            .expect("metrics listener bind")
+            .with_accept_backoff(config.accept_backoff);
+
+        let (tls_server_config, tls_client_config) = match config.tls_settings {
+            Some(ref settings) => {
+                let (server, client) = tls::CommonConfig::load_from_disk(settings)
+                    .and_then(|common| Ok((common.server_config()?, common.client_config())))
+                    .unwrap_or_else(|e| panic!("invalid TLS configuration: {}", e));
+                (Some(server), Some(client))
+            },
+            None => (None, None),
+        };
+
         Main {
             config,
             control_listener,
             inbound_listener,
             outbound_listener,
             metrics_listener,
+            tls_server_config,
+            tls_client_config,
             get_original_dst,
             reactor,
         }
     }
 
 
-    pub fn control_addr(&self) -> SocketAddr {
-        self.control_listener.local_addr()
+    pub fn control_addr(&self) -> config::Addr {
+        self.control_listener.local_addr().clone()
     }
 
     pub fn inbound_addr(&self) -> SocketAddr {
         self.inbound_listener.local_addr()
+            .socket_addr()
+            .expect("public listener must be TCP")
     }
 
     pub fn outbound_addr(&self) -> SocketAddr {
         self.outbound_listener.local_addr()
+            .socket_addr()
+            .expect("private listener must be TCP")
     }
 
     pub fn handle(&self) -> Handle {
         self.reactor.handle()
     }
 
-    pub fn metrics_addr(&self) -> SocketAddr {
-        self.metrics_listener.local_addr()
+    pub fn metrics_addr(&self) -> config::Addr {
+        self.metrics_listener.local_addr().clone()
     }
 
     pub fn run_until<F>(self, shutdown_signal: F)
@@ -173,11 +222,14 @@ where
             inbound_listener,
             outbound_listener,
             metrics_listener,
+            tls_server_config,
+            tls_client_config,
             get_original_dst,
             reactor: mut core,
         } = self;
 
         let control_host_and_port = config.control_host_and_port.clone();
+        let reconnect_backoff = config.reconnect_backoff;
 
         info!("using controller at {:?}", control_host_and_port);
         info!("routing on {:?}", outbound_listener.local_addr());
@@ -186,6 +238,13 @@ where
             inbound_listener.local_addr(),
             config.private_forward
         );
+        if let Some(config::Addr::Unix(_)) = config.private_forward {
+            info!(
+                "forwarding inbound ports {:?} to {:?}",
+                config.private_forward_ports,
+                config.private_forward,
+            );
+        }
         info!(
             "serving Prometheus metrics on {:?}",
             metrics_listener.local_addr(),
@@ -198,6 +257,21 @@ where
             "protocol detection disabled for outbound ports {:?}",
             config.outbound_ports_disable_protocol_detection,
         );
+        if config.inbound_accept_proxy_protocol {
+            info!("accepting PROXY protocol on {:?}", inbound_listener.local_addr());
+        }
+        if config.outbound_accept_proxy_protocol {
+            info!("accepting PROXY protocol on {:?}", outbound_listener.local_addr());
+        }
+        if tls_server_config.is_some() {
+            info!("terminating TLS on {:?}", inbound_listener.local_addr());
+        }
+        if let Some(ref limit) = config.inbound_connection_limit {
+            info!("limiting {:?} to {} connections", inbound_listener.local_addr(), limit.max);
+        }
+        if let Some(ref limit) = config.outbound_connection_limit {
+            info!("limiting {:?} to {} connections", outbound_listener.local_addr(), limit.max);
+        }
 
         let (sensors, telemetry) = telemetry::new(
             &process_ctx,
@@ -211,25 +285,43 @@ where
                 panic!("invalid DNS configuration: {:?}", e);
             });
 
-        let (control, control_bg) = control::new(dns_config.clone(), config.pod_namespace.clone());
+        let (control, control_bg) = control::new(
+            dns_config.clone(),
+            config.pod_namespace.clone(),
+            config.destination_profiles_enabled,
+        );
 
         let executor = core.handle();
         let (drain_tx, drain_rx) = drain::channel();
 
-        let bind = Bind::new(executor.clone()).with_sensors(sensors.clone());
+        let bind = Bind::new(executor.clone())
+            .with_sensors(sensors.clone())
+            .with_tls_client_config(tls_client_config)
+            .with_reconnect_backoff(config.reconnect_backoff);
 
         // Setup the public listener. This will listen on a publicly accessible
         // address and listen for inbound connections that should be forwarded
         // to the managed application (private destination).
         let inbound = {
             let ctx = ctx::Proxy::inbound(&process_ctx);
+            let inbound_listener = inbound_listener
+                .with_connection_limit(config.inbound_connection_limit, &sensors, &ctx);
 
-            let bind = bind.clone().with_ctx(ctx.clone());
+            let bind = bind.clone()
+                .with_ctx(ctx.clone())
+                .with_faults(config.inbound_faults.clone());
 
-            let default_addr = config.private_forward.map(|a| a.into());
+            let default_addr = config.private_forward.clone();
 
             let router = Router::new(
-                Inbound::new(default_addr, bind),
+                Inbound::new(
+                    default_addr,
+                    config.private_forward_ports.clone(),
+                    bind,
+                    config.inbound_queue,
+                    config.inbound_max_in_flight,
+                    config.inbound_rate_limits.clone(),
+                ),
                 config.inbound_router_capacity,
                 config.inbound_router_max_idle_age,
             );
@@ -237,7 +329,12 @@ where
                 inbound_listener,
                 router,
                 config.private_connect_timeout,
+                None,
+                config.protocol_detection_timeout,
                 config.inbound_ports_disable_protocol_detection,
+                config.inbound_accept_proxy_protocol,
+                config.inbound_send_proxy_protocol,
+                tls_server_config,
                 ctx,
                 sensors.clone(),
                 get_original_dst.clone(),
@@ -252,9 +349,40 @@ where
         // to a remote service (public destination).
         let outbound = {
             let ctx = ctx::Proxy::outbound(&process_ctx);
-            let bind = bind.clone().with_ctx(ctx.clone());
+            let outbound_listener = outbound_listener
+                .with_connection_limit(config.outbound_connection_limit, &sensors, &ctx);
+            let bind = bind.clone()
+                .with_ctx(ctx.clone())
+                .with_faults(config.outbound_faults.clone());
+
+            // Opaque TCP connections are balanced across the endpoints of
+            // their original destination's service.
+            let tcp_router = Router::new(
+                TcpOutbound::new(
+                    bind.tcp(config.public_connect_timeout),
+                    control.clone(),
+                    config.outbound_ports_disable_protocol_detection.clone(),
+                    config.bind_timeout,
+                ),
+                config.outbound_router_capacity,
+                config.outbound_router_max_idle_age,
+            );
+
             let router = Router::new(
-                Outbound::new(bind, control, config.bind_timeout),
+                Outbound::new(
+                    bind,
+                    control,
+                    config.bind_timeout,
+                    config.outbound_retries.clone(),
+                    config.outbound_outlier_detection.clone(),
+                    config.outbound_load_metric,
+                    config.locality.clone(),
+                    config.outbound_queue,
+                    config.outbound_max_in_flight,
+                    config.outbound_mirror_max_body_bytes,
+                    config.outbound_mirror_timeout,
+                    config.outbound_rate_limits.clone(),
+                ),
                 config.outbound_router_capacity,
                 config.outbound_router_max_idle_age,
             );
@@ -262,7 +390,12 @@ where
                 outbound_listener,
                 router,
                 config.public_connect_timeout,
+                Some(tcp_router),
+                config.protocol_detection_timeout,
                 config.outbound_ports_disable_protocol_detection,
+                config.outbound_accept_proxy_protocol,
+                false,
+                None,
                 ctx,
                 sensors,
                 get_original_dst,
@@ -303,6 +436,7 @@ where
                     let client = control_bg.bind(
                         control_host_and_port,
                         dns_config,
+                        reconnect_backoff,
                         &executor
                     );
 
@@ -338,7 +472,12 @@ fn serve<R, B, E, F, G>(
     bound_port: BoundPort,
     router: Router<R>,
     tcp_connect_timeout: Duration,
+    tcp_router: Option<Router<TcpOutbound>>,
+    protocol_detection_timeout: Option<Duration>,
     disable_protocol_detection_ports: IndexSet<u16>,
+    accept_proxy_protocol: bool,
+    send_proxy_protocol: bool,
+    tls_server_config: Option<tls::ServerConfig>,
     proxy_ctx: Arc<ctx::Proxy>,
     sensors: telemetry::Sensors,
     get_orig_dst: G,
@@ -347,7 +486,7 @@ fn serve<R, B, E, F, G>(
 ) -> Box<Future<Item = (), Error = io::Error> + 'static>
 where
     B: tower_h2::Body + Default + 'static,
-    E: Error + 'static,
+    E: Error + Classify + 'static,
     F: Error + 'static,
     R: Recognize<
         Request = http::Request<HttpBody>,
@@ -358,41 +497,57 @@ where
         + 'static,
     G: GetOriginalDst + 'static,
 {
+    // Requests that a peer proxy upgraded to HTTP/2 are downgraded before
+    // they're forwarded to the application.
+    let downgrade = proxy_ctx.is_inbound();
+
+    let errors_ctx = Arc::clone(&proxy_ctx);
+    let errors_sensors = sensors.clone();
     let stack = Arc::new(NewServiceFn::new(move || {
         // Clone the router handle
         let router = router.clone();
 
-        // Map errors to appropriate response error codes.
-        let map_err = MapErr::new(router, |e| {
-            match e {
+        // Map errors to responses that describe why the proxy failed the
+        // request, so that they can be told apart from the application's.
+        let ctx = Arc::clone(&errors_ctx);
+        let sensors = errors_sensors.clone();
+        let map_err = MapErr::new(router, move |e| {
+            let reason = match e {
                 RouteError::Route(r) => {
-                    error!(" turning route error: {} into 500", r);
-                    http::StatusCode::INTERNAL_SERVER_ERROR
+                    error!("turning route error: {} into 500", r);
+                    Reason::Internal
                 }
                 RouteError::Inner(i) => {
-                    error!("turning {} into 500", i);
-                    http::StatusCode::INTERNAL_SERVER_ERROR
+                    let reason = i.reason();
+                    error!("turning {} into {}", i, reason.status());
+                    reason
                 }
                 RouteError::NotRecognized => {
-                    error!("turning route not recognized error into 500");
-                    http::StatusCode::INTERNAL_SERVER_ERROR
+                    error!("turning route not recognized error into 400");
+                    Reason::NotRecognized
                 }
                 RouteError::NoCapacity(capacity) => {
                     // TODO For H2 streams, we should probably signal a protocol-level
                     // capacity change.
                     error!("router at capacity ({}); returning a 503", capacity);
-                    http::StatusCode::SERVICE_UNAVAILABLE
+                    Reason::NoCapacity
                 }
-            }
+            };
+            sensors.proxy_error(&ctx, reason);
+            reason
         });
 
+        let downgrade = orig_proto::Downgrade::new(map_err, downgrade);
+
         // Install the request open timestamp module at the very top
         // of the stack, in order to take the timestamp as close as
         // possible to the beginning of the request's lifetime.
-        telemetry::sensor::http::TimestampRequestOpen::new(map_err)
+        telemetry::sensor::http::TimestampRequestOpen::new(downgrade)
     }));
 
-    let listen_addr = bound_port.local_addr();
+    let listen_addr = bound_port.local_addr()
+        .socket_addr()
+        .expect("proxy listeners must be TCP");
     let server = Server::new(
         listen_addr,
         proxy_ctx,
@@ -400,7 +555,12 @@ where
         get_orig_dst,
         stack,
         tcp_connect_timeout,
+        tcp_router,
+        protocol_detection_timeout,
         disable_protocol_detection_ports,
+        accept_proxy_protocol,
+        send_proxy_protocol,
+        tls_server_config,
         drain_rx.clone(),
         executor.clone(),
     );
@@ -410,7 +570,10 @@ where
         executor,
         (),
         move |(), (connection, remote_addr)| {
-            server.serve(connection, remote_addr);
+            match remote_addr {
+                config::Addr::Tcp(remote_addr) => server.serve(connection, remote_addr),
+                config::Addr::Unix(_) => unreachable!("proxy listeners must be TCP"),
+            }
             Ok(())
         },
     );
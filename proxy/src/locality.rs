@@ -0,0 +1,380 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::Hash;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use futures::{Async, Poll};
+use http;
+use indexmap::IndexMap;
+use rand::{self, Rng};
+use tower_discover::{Change, Discover};
+use tower_service::Service;
+
+use config;
+use outlier;
+use telemetry::metrics::DstLabels;
+
+/// A service with metric labels from the Destination service, one of which
+/// may identify the service's locality.
+pub trait HasDstLabels {
+    fn dst_labels(&self) -> Option<DstLabels>;
+}
+
+/// Sends requests to the balancer of the endpoints in the proxy's locality
+/// while enough of them are healthy.
+///
+/// When too few of them are healthy, a share of requests proportional to the
+/// shortfall spills over to the balancer of the remote endpoints.
+pub struct Prefer<S> {
+    local: S,
+    remote: S,
+    health: Health,
+    /// The side that was chosen for the next request.
+    next: Option<Side>,
+}
+
+/// A destination's endpoints, partitioned by their locality.
+pub struct Split<D: Discover> {
+    pub local: Partition<D>,
+    pub remote: Partition<D>,
+    pub health: Health,
+}
+
+/// Discovers one side of a destination's endpoints.
+///
+/// Both sides share the destination's discovery, so changes for one side may
+/// be queued while the other side is polled.
+pub struct Partition<D: Discover> {
+    shared: Rc<RefCell<Shared<D>>>,
+    side: Side,
+}
+
+/// Describes how many of a destination's endpoints are healthy on each side.
+#[derive(Clone)]
+pub struct Health {
+    counts: Rc<Cell<Counts>>,
+    min_healthy_ratio: f32,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+struct Counts {
+    local: usize,
+    local_healthy: usize,
+    remote_healthy: usize,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Side {
+    Local,
+    Remote,
+}
+
+struct Shared<D: Discover> {
+    /// Endpoints that are ejected by outlier detection are removed from their
+    /// side's balancer, but they're still counted as unhealthy endpoints.
+    inner: outlier::Detect<D>,
+    locality: Option<Arc<config::Locality>>,
+    endpoints: IndexMap<D::Key, Tracked>,
+    local_changes: VecDeque<Change<D::Key, outlier::Endpoint<D::Service>>>,
+    remote_changes: VecDeque<Change<D::Key, outlier::Endpoint<D::Service>>>,
+    counts: Rc<Cell<Counts>>,
+}
+
+struct Tracked {
+    side: Side,
+    healthy: bool,
+}
+
+/// Partitions a destination's endpoints into those in the proxy's locality
+/// and the rest.
+///
+/// If the proxy's locality isn't known, all endpoints are local.
+pub fn split<D>(inner: outlier::Detect<D>, locality: Option<Arc<config::Locality>>) -> Split<D>
+where
+    D: Discover,
+{
+    let min_healthy_ratio = locality.as_ref()
+        .map(|l| l.min_healthy_ratio)
+        .unwrap_or(0.0);
+    let counts = Rc::new(Cell::new(Counts::default()));
+    let shared = Rc::new(RefCell::new(Shared {
+        inner,
+        locality,
+        endpoints: IndexMap::new(),
+        local_changes: VecDeque::new(),
+        remote_changes: VecDeque::new(),
+        counts: counts.clone(),
+    }));
+
+    Split {
+        local: Partition {
+            shared: shared.clone(),
+            side: Side::Local,
+        },
+        remote: Partition {
+            shared,
+            side: Side::Remote,
+        },
+        health: Health {
+            counts,
+            min_healthy_ratio,
+        },
+    }
+}
+
+// ===== impl Prefer =====
+
+impl<S> Prefer<S> {
+    pub fn new(local: S, remote: S, health: Health) -> Self {
+        Self {
+            local,
+            remote,
+            health,
+            next: None,
+        }
+    }
+}
+
+impl<S: Service> Service for Prefer<S> {
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        // The local balancer is always polled, so that the destination's
+        // endpoints have been discovered before a side is chosen.
+        let local_ready = self.local.poll_ready()?.is_ready();
+
+        // A side stays chosen until the request is dispatched, unless it
+        // loses all of its healthy endpoints while waiting.
+        let side = match self.next {
+            Some(side) if self.health.has_healthy(side) => side,
+            _ => self.health.choose(),
+        };
+        self.next = Some(side);
+
+        let ready = match side {
+            Side::Local => local_ready,
+            Side::Remote => self.remote.poll_ready()?.is_ready(),
+        };
+        if ready {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+
+    fn call(&mut self, req: Self::Request) -> Self::Future {
+        match self.next.take() {
+            Some(Side::Remote) => self.remote.call(req),
+            Some(Side::Local) | None => self.local.call(req),
+        }
+    }
+}
+
+// ===== impl Health =====
+
+impl Health {
+    /// Returns the share of requests that are sent to local endpoints.
+    fn local_share(&self) -> f32 {
+        let counts = self.counts.get();
+        if counts.local_healthy == 0 {
+            return 0.0;
+        }
+        if counts.remote_healthy == 0 {
+            return 1.0;
+        }
+
+        let healthy_ratio = counts.local_healthy as f32 / counts.local as f32;
+        (healthy_ratio / self.min_healthy_ratio).min(1.0)
+    }
+
+    fn has_healthy(&self, side: Side) -> bool {
+        let counts = self.counts.get();
+        match side {
+            Side::Local => counts.local_healthy > 0,
+            Side::Remote => counts.remote_healthy > 0,
+        }
+    }
+
+    fn choose(&self) -> Side {
+        let share = self.local_share();
+        if share >= 1.0 || rand::thread_rng().gen::<f32>() < share {
+            Side::Local
+        } else {
+            Side::Remote
+        }
+    }
+}
+
+// ===== impl Partition =====
+
+impl<D, A, B> Discover for Partition<D>
+where
+    D: Discover<Request = http::Request<A>, Response = http::Response<B>>,
+    D::Key: Hash + Eq + Clone + fmt::Debug,
+    D::Service: HasDstLabels,
+{
+    type Key = D::Key;
+    type Request = D::Request;
+    type Response = D::Response;
+    type Error = D::Error;
+    type Service = outlier::Endpoint<D::Service>;
+    type DiscoverError = D::DiscoverError;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::DiscoverError> {
+        self.shared.borrow_mut().poll(self.side)
+    }
+}
+
+// ===== impl Shared =====
+
+impl<D, A, B> Shared<D>
+where
+    D: Discover<Request = http::Request<A>, Response = http::Response<B>>,
+    D::Key: Hash + Eq + Clone + fmt::Debug,
+    D::Service: HasDstLabels,
+{
+    fn poll(
+        &mut self,
+        side: Side,
+    ) -> Poll<Change<D::Key, outlier::Endpoint<D::Service>>, D::DiscoverError> {
+        // All of the destination's changes are routed, so that the health of
+        // both sides is current.
+        while let Async::Ready(change) = self.inner.poll()? {
+            self.route(change);
+        }
+        self.forget_removed();
+        self.count();
+
+        let changes = match side {
+            Side::Local => &mut self.local_changes,
+            Side::Remote => &mut self.remote_changes,
+        };
+        match changes.pop_front() {
+            Some(change) => Ok(Async::Ready(change)),
+            None => Ok(Async::NotReady),
+        }
+    }
+
+    fn route(&mut self, change: Change<D::Key, outlier::Endpoint<D::Service>>) {
+        let (side, change) = match change {
+            Change::Insert(key, svc) => {
+                // An ejected endpoint is readmitted to the side that it was
+                // discovered on.
+                let side = match self.endpoints.get(&key) {
+                    Some(tracked) => tracked.side,
+                    None => self.side_of(&svc),
+                };
+                self.endpoints.insert(key.clone(), Tracked { side, healthy: true });
+                (side, Change::Insert(key, svc))
+            },
+            Change::Remove(key) => {
+                let side = match self.endpoints.get(&key) {
+                    Some(tracked) => tracked.side,
+                    None => return,
+                };
+                if self.inner.is_ejected(&key) {
+                    if let Some(tracked) = self.endpoints.get_mut(&key) {
+                        tracked.healthy = false;
+                    }
+                } else {
+                    self.endpoints.swap_remove(&key);
+                }
+                (side, Change::Remove(key))
+            },
+        };
+
+        match side {
+            Side::Local => self.local_changes.push_back(change),
+            Side::Remote => self.remote_changes.push_back(change),
+        }
+    }
+
+    /// Forgets ejected endpoints that are no longer discovered.
+    ///
+    /// Outlier detection doesn't report the removal of an ejected endpoint,
+    /// since it was already removed from the balancer.
+    fn forget_removed(&mut self) {
+        let inner = &self.inner;
+        self.endpoints.retain(|key, tracked| tracked.healthy || inner.is_ejected(key));
+    }
+
+    fn count(&self) {
+        let mut counts = Counts::default();
+        for tracked in self.endpoints.values() {
+            match tracked.side {
+                Side::Local => {
+                    counts.local += 1;
+                    if tracked.healthy {
+                        counts.local_healthy += 1;
+                    }
+                },
+                Side::Remote => if tracked.healthy {
+                    counts.remote_healthy += 1;
+                },
+            }
+        }
+        self.counts.set(counts);
+    }
+
+    fn side_of<S: HasDstLabels>(&self, svc: &S) -> Side {
+        let locality = match self.locality {
+            Some(ref locality) => locality,
+            None => return Side::Local,
+        };
+
+        let is_local = svc.dst_labels()
+            .map(|labels| labels.as_map().get(&locality.label) == Some(&locality.value))
+            .unwrap_or(false);
+        if is_local {
+            Side::Local
+        } else {
+            Side::Remote
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn health(local: usize, local_healthy: usize, remote_healthy: usize) -> Health {
+        let counts = Counts {
+            local,
+            local_healthy,
+            remote_healthy,
+        };
+        Health {
+            counts: Rc::new(Cell::new(counts)),
+            min_healthy_ratio: 0.5,
+        }
+    }
+
+    #[test]
+    fn prefers_local_while_enough_are_healthy() {
+        assert_eq!(health(4, 4, 4).local_share(), 1.0);
+        assert_eq!(health(4, 2, 4).local_share(), 1.0);
+    }
+
+    #[test]
+    fn spills_over_in_proportion() {
+        assert_eq!(health(4, 1, 4).local_share(), 0.5);
+        assert_eq!(health(4, 0, 4).local_share(), 0.0);
+    }
+
+    #[test]
+    fn stays_local_without_remote_endpoints() {
+        assert_eq!(health(4, 1, 0).local_share(), 1.0);
+        assert_eq!(health(4, 1, 0).choose(), Side::Local);
+    }
+
+    #[test]
+    fn goes_remote_without_local_endpoints() {
+        assert_eq!(health(0, 0, 4).local_share(), 0.0);
+        assert_eq!(health(0, 0, 4).choose(), Side::Remote);
+    }
+}
@@ -4,20 +4,25 @@ use std::sync::Arc;
 use futures::{Future, Poll};
 use h2;
 use http;
-use http::header::CONTENT_LENGTH;
 use tower_service::Service;
 
-/// Map an HTTP service's error to an appropriate 500 response.
+use proxy_error::{self, Reason};
+
+/// Map an HTTP service's error to a response that describes why the request
+/// failed.
 pub struct MapErr<T, E, F> {
     inner: T,
     f: Arc<F>,
     _p: PhantomData<E>,
 }
 
-/// Catches errors from the inner future and maps them to 500 responses.
+/// Catches errors from the inner future and maps them to error responses.
 pub struct ResponseFuture<T, E, F> {
     inner: T,
     f: Arc<F>,
+    /// Whether the request is a gRPC request, which is failed with a
+    /// `grpc-status` rather than an HTTP status.
+    grpc: bool,
     _p: PhantomData<E>,
 }
 
@@ -27,7 +32,7 @@ pub struct ResponseFuture<T, E, F> {
 impl<T, E, F> MapErr<T, E, F>
 where
     T: Service<Error = E>,
-    F: Fn(E) -> http::StatusCode,
+    F: Fn(E) -> Reason,
 {
     /// Crete a new `MapErr`
     pub fn new(inner: T, f: F) -> Self {
@@ -39,11 +44,11 @@ where
     }
 }
 
-impl<T, B, E, F> Service for MapErr<T, E, F>
+impl<T, A, B, E, F> Service for MapErr<T, E, F>
 where
-    T: Service<Response = http::Response<B>, Error = E>,
+    T: Service<Request = http::Request<A>, Response = http::Response<B>, Error = E>,
     B: Default,
-    F: Fn(E) -> http::StatusCode,
+    F: Fn(E) -> Reason,
 {
     type Request = T::Request;
     type Response = T::Response;
@@ -57,10 +62,12 @@ where
     }
 
     fn call(&mut self, request: Self::Request) -> Self::Future {
+        let grpc = proxy_error::is_grpc(&request);
         let inner = self.inner.call(request);
         ResponseFuture {
             inner,
             f: self.f.clone(),
+            grpc,
             _p: PhantomData,
         }
     }
@@ -72,21 +79,18 @@ impl<T, B, E, F> Future for ResponseFuture<T, E, F>
 where
     T: Future<Item = http::Response<B>, Error = E>,
     B: Default,
-    F: Fn(E) -> http::StatusCode,
+    F: Fn(E) -> Reason,
 {
     type Item = T::Item;
     type Error = h2::Error;
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
         self.inner.poll().or_else(|e| {
-            let status = (self.f)(e);
-            let response = http::Response::builder()
-                .status(status)
-                .header(CONTENT_LENGTH, "0")
-                .body(Default::default())
-                .unwrap();
-
-            Ok(response.into())
+            let reason = (self.f)(e);
+            if reason == Reason::Reset {
+                return Err(h2::Reason::CANCEL.into());
+            }
+            Ok(reason.response(self.grpc).into())
         })
     }
 }
@@ -0,0 +1,680 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use futures::{Async, Future, Poll, Stream};
+use futures::sync::mpsc;
+use h2;
+use http::{self, header::CONTENT_LENGTH};
+use rand::{self, Rng};
+use tokio_core::reactor::{Handle, Timeout};
+use tower_buffer::Buffer;
+use tower_h2::Body;
+use tower_service::Service;
+
+use ctx;
+use profiles;
+use split::{self, Bind};
+use telemetry::sensor::http::RequestOpen;
+use transparency::h1;
+use transport::DnsNameAndPort;
+
+/// The authority of the shadow destination to which a request was mirrored.
+///
+/// It's inserted into the extensions of mirrored requests, so that their
+/// metrics are distinguished from those of the requests they copy.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShadowAuthority(http::uri::Authority);
+
+/// Mirrors a share of a destination's requests to the shadow destination
+/// named by its profile.
+///
+/// Each mirrored request's body is copied as it's sent to the destination,
+/// and the copy is sent to the shadow once it's complete. A copy is abandoned,
+/// without ever being sent, if its body grows beyond `max_body_bytes` or the
+/// request being mirrored fails. Responses from the shadow are discarded, and
+/// the shadow's failures never affect the request being mirrored: a request
+/// is only mirrored if the shadow is ready for it.
+pub struct Mirror<S, T: Bind> {
+    inner: S,
+    bind: T,
+    /// The destination's port, which is used for a shadow that doesn't name
+    /// one.
+    default_port: u16,
+    shadow: Option<Shadow<T::Service>>,
+    profile_rx: Option<profiles::Receiver>,
+    max_body_bytes: usize,
+    /// Bounds how long each mirrored request may take.
+    timeout: Duration,
+    handle: Handle,
+}
+
+struct Shadow<S: Service> {
+    dst: DnsNameAndPort,
+    authority: ShadowAuthority,
+    percent: u32,
+    /// Shared by the shadow's mirrored requests, each of which is sent once
+    /// its body has been copied.
+    service: Buffer<S>,
+}
+
+/// A request body that may be copied to, or copied from, another request.
+pub struct MirrorBody<B> {
+    inner: Inner<B>,
+}
+
+enum Inner<B> {
+    /// A body that isn't mirrored.
+    Passthru(B),
+    /// A body whose frames are copied to a mirrored request.
+    Teed(B, Tee),
+    /// The body of a mirrored request, copied from another request.
+    Mirrored(Mirrored),
+}
+
+/// Copies a body's frames to a mirrored request.
+struct Tee {
+    /// Dropped once the copy is abandoned.
+    tx: Option<mpsc::UnboundedSender<Frame>>,
+    /// How many more bytes may be copied.
+    remaining: usize,
+    /// Whether all of the body's data has been copied.
+    is_end_data: bool,
+}
+
+struct Mirrored {
+    rx: mpsc::UnboundedReceiver<Frame>,
+    /// Data that's been received but not yet read.
+    buffered: VecDeque<Bytes>,
+    /// Set once the body's data has been received.
+    trailers: Option<Option<http::HeaderMap>>,
+}
+
+enum Frame {
+    Data(Bytes),
+    End(Option<http::HeaderMap>),
+    Abort,
+}
+
+/// Drives a mirrored request to completion, discarding its response.
+struct Discard<S: Service, B> {
+    state: DiscardState<S, B>,
+    timer: Timeout,
+}
+
+enum DiscardState<S: Service, B> {
+    /// The request is held until its body has been copied, so that a copy
+    /// that's abandoned is never sent.
+    Buffering(S, Option<S::Request>),
+    Pending(S::Future),
+    Body(B),
+}
+
+// ===== impl ShadowAuthority =====
+
+impl ShadowAuthority {
+    pub fn new(authority: http::uri::Authority) -> Self {
+        ShadowAuthority(authority)
+    }
+}
+
+impl fmt::Display for ShadowAuthority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+// ===== impl Mirror =====
+
+impl<S, T> Mirror<S, T>
+where
+    T: Bind,
+    T::Service: 'static,
+    <T::Service as Service>::Request: 'static,
+    <T::Service as Service>::Future: 'static,
+{
+    pub fn new(
+        inner: S,
+        bind: T,
+        default_port: u16,
+        profile_rx: Option<profiles::Receiver>,
+        max_body_bytes: usize,
+        timeout: Duration,
+        handle: &Handle,
+    ) -> Self {
+        Self {
+            inner,
+            bind,
+            default_port,
+            shadow: None,
+            profile_rx,
+            max_body_bytes,
+            timeout,
+            handle: handle.clone(),
+        }
+    }
+
+    fn poll_profile(&mut self) {
+        if let Some(profile) = profiles::poll_latest(&mut self.profile_rx) {
+            self.update(profile.mirror);
+        }
+    }
+
+    /// Replaces the shadow, reusing its service if its destination is
+    /// unchanged.
+    fn update(&mut self, mirror: Option<profiles::Mirror>) {
+        let prior = self.shadow.take();
+
+        let profiles::Mirror { authority, percent } = match mirror {
+            Some(mirror) => mirror,
+            None => return,
+        };
+
+        let (dst, authority) = match split::dns_dst(&authority, self.default_port) {
+            Some(dst) => dst,
+            None => {
+                warn!("ignoring mirror {}: not a DNS name", authority);
+                return;
+            },
+        };
+
+        let prior = prior.and_then(|p| if p.dst == dst { Some(p.service) } else { None });
+        let service = match prior {
+            Some(service) => service,
+            None => {
+                let service = match self.bind.bind(&dst) {
+                    Ok(service) => service,
+                    Err(_) => {
+                        warn!("failed to bind mirror {:?}", dst);
+                        return;
+                    },
+                };
+                match Buffer::new(service, &self.handle) {
+                    Ok(service) => service,
+                    Err(_) => {
+                        warn!("failed to spawn mirror {:?}", dst);
+                        return;
+                    },
+                }
+            },
+        };
+
+        debug!("mirroring {}% of requests to {}", percent, authority);
+        self.shadow = Some(Shadow {
+            dst,
+            authority: ShadowAuthority::new(authority),
+            percent,
+            service,
+        });
+    }
+}
+
+impl<S, T, B, RB> Service for Mirror<S, T>
+where
+    S: Service<Request = http::Request<MirrorBody<B>>>,
+    T: Bind,
+    T::Service: Service<
+        Request = http::Request<MirrorBody<B>>,
+        Response = http::Response<RB>,
+    > + 'static,
+    <T::Service as Service>::Future: 'static,
+    B: Body<Data = Bytes> + Default + 'static,
+    RB: Body + 'static,
+{
+    type Request = http::Request<B>;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.poll_profile();
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, req: Self::Request) -> Self::Future {
+        let mirror = match self.shadow {
+            Some(ref mut shadow) => shadow.should_mirror(&req, self.max_body_bytes),
+            None => false,
+        };
+        if !mirror {
+            return self.inner.call(req.map(MirrorBody::new));
+        }
+        let shadow = self.shadow.as_mut().expect("request is mirrored to shadow");
+
+        let mut mirrored = http::Request::new(MirrorBody::new(B::default()));
+        *mirrored.method_mut() = req.method().clone();
+        *mirrored.uri_mut() = req.uri().clone();
+        *mirrored.version_mut() = req.version();
+        *mirrored.headers_mut() = req.headers().clone();
+
+        // Telemetry describes the mirrored request as a request from the
+        // same client, to the shadow.
+        if let Some(server_ctx) = req.extensions().get::<Arc<ctx::transport::Server>>() {
+            mirrored.extensions_mut().insert(server_ctx.clone());
+        }
+        mirrored.extensions_mut().insert(RequestOpen(Instant::now()));
+        mirrored.extensions_mut().insert(shadow.authority.clone());
+
+        let (parts, body) = req.into_parts();
+        let body = if body.is_end_stream() {
+            MirrorBody::new(body)
+        } else {
+            let (body, copy) = MirrorBody::tee(body, self.max_body_bytes);
+            *mirrored.body_mut() = copy;
+            body
+        };
+        let req = http::Request::from_parts(parts, body);
+
+        let timer = Timeout::new(self.timeout, &self.handle).expect("reactor gone");
+        self.handle.spawn(Discard {
+            state: DiscardState::Buffering(shadow.service.clone(), Some(mirrored)),
+            timer,
+        });
+
+        self.inner.call(req)
+    }
+}
+
+// ===== impl Shadow =====
+
+impl<S: Service> Shadow<S> {
+    /// Chooses whether `req` is mirrored to the shadow.
+    fn should_mirror<B>(&mut self, req: &http::Request<B>, max_body_bytes: usize) -> bool {
+        if rand::thread_rng().gen_range(0, 100) >= self.percent {
+            return false;
+        }
+
+        // Requests that take over their connections can't be copied.
+        if h1::is_upgrade(req) {
+            return false;
+        }
+
+        let content_length = req.headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok());
+        if content_length.map(|len| len > max_body_bytes).unwrap_or(false) {
+            trace!("not mirroring request with {:?} byte body", content_length);
+            return false;
+        }
+
+        // The request is never delayed by the shadow.
+        match self.service.poll_ready() {
+            Ok(Async::Ready(())) => true,
+            Ok(Async::NotReady) => {
+                trace!("not mirroring request: {} is not ready", self.authority);
+                false
+            },
+            Err(_) => {
+                debug!("not mirroring request: {} failed", self.authority);
+                false
+            },
+        }
+    }
+}
+
+// ===== impl MirrorBody =====
+
+impl<B> MirrorBody<B> {
+    pub fn new(body: B) -> Self {
+        MirrorBody {
+            inner: Inner::Passthru(body),
+        }
+    }
+
+    /// Returns `body`, and a copy of it that's abandoned if `body` grows
+    /// beyond `max_bytes`.
+    fn tee(body: B, max_bytes: usize) -> (Self, Self) {
+        let (tx, rx) = mpsc::unbounded();
+        let tee = Tee {
+            tx: Some(tx),
+            remaining: max_bytes,
+            is_end_data: false,
+        };
+        let body = MirrorBody {
+            inner: Inner::Teed(body, tee),
+        };
+        let copy = MirrorBody {
+            inner: Inner::Mirrored(Mirrored {
+                rx,
+                buffered: VecDeque::new(),
+                trailers: None,
+            }),
+        };
+        (body, copy)
+    }
+
+    /// Receives all of a mirrored body's data, failing if its copy is
+    /// abandoned.
+    fn poll_buffer(&mut self) -> Poll<(), h2::Error> {
+        match self.inner {
+            Inner::Mirrored(ref mut mirrored) => mirrored.poll_buffer(),
+            Inner::Passthru(_) | Inner::Teed(..) => Ok(Async::Ready(())),
+        }
+    }
+}
+
+impl<B: Default> Default for MirrorBody<B> {
+    fn default() -> Self {
+        MirrorBody::new(B::default())
+    }
+}
+
+impl<B: Body<Data = Bytes>> Body for MirrorBody<B> {
+    type Data = Bytes;
+
+    fn is_end_stream(&self) -> bool {
+        match self.inner {
+            Inner::Passthru(ref body) | Inner::Teed(ref body, _) => body.is_end_stream(),
+            Inner::Mirrored(_) => false,
+        }
+    }
+
+    fn poll_data(&mut self) -> Poll<Option<Bytes>, h2::Error> {
+        match self.inner {
+            Inner::Passthru(ref mut body) => body.poll_data(),
+            Inner::Teed(ref mut body, ref mut tee) => {
+                let data = try_ready!(body.poll_data());
+                match data {
+                    Some(ref bytes) => tee.data(bytes),
+                    None => tee.is_end_data = true,
+                }
+                Ok(Async::Ready(data))
+            },
+            Inner::Mirrored(ref mut mirrored) => mirrored.poll_data(),
+        }
+    }
+
+    fn poll_trailers(&mut self) -> Poll<Option<http::HeaderMap>, h2::Error> {
+        match self.inner {
+            Inner::Passthru(ref mut body) => body.poll_trailers(),
+            Inner::Teed(ref mut body, ref mut tee) => {
+                let trailers = try_ready!(body.poll_trailers());
+                tee.end(trailers.clone());
+                Ok(Async::Ready(trailers))
+            },
+            Inner::Mirrored(ref mut mirrored) => {
+                while mirrored.trailers.is_none() {
+                    // Any data that wasn't read is discarded.
+                    try_ready!(mirrored.poll_data());
+                }
+                Ok(Async::Ready(mirrored.trailers.take().and_then(|t| t)))
+            },
+        }
+    }
+}
+
+// ===== impl Tee =====
+
+impl Tee {
+    fn data(&mut self, bytes: &Bytes) {
+        if let Some(tx) = self.tx.take() {
+            if bytes.len() > self.remaining {
+                debug!("abandoning mirrored request: body is too large");
+                let _ = tx.unbounded_send(Frame::Abort);
+                return;
+            }
+            self.remaining -= bytes.len();
+
+            // If the mirrored request has completed, there's no need to
+            // continue copying its body.
+            if tx.unbounded_send(Frame::Data(bytes.clone())).is_ok() {
+                self.tx = Some(tx);
+            }
+        }
+    }
+
+    fn end(&mut self, trailers: Option<http::HeaderMap>) {
+        if let Some(tx) = self.tx.take() {
+            let _ = tx.unbounded_send(Frame::End(trailers));
+        }
+    }
+}
+
+impl Drop for Tee {
+    fn drop(&mut self) {
+        // HTTP/1 bodies' trailers aren't necessarily polled, so a body whose
+        // data was all copied is complete. Otherwise, the request being
+        // mirrored failed, and so its copy is abandoned as well.
+        if let Some(tx) = self.tx.take() {
+            let frame = if self.is_end_data {
+                Frame::End(None)
+            } else {
+                Frame::Abort
+            };
+            let _ = tx.unbounded_send(frame);
+        }
+    }
+}
+
+// ===== impl Mirrored =====
+
+impl Mirrored {
+    fn poll_buffer(&mut self) -> Poll<(), h2::Error> {
+        while let Some(bytes) = try_ready!(self.poll_rx()) {
+            self.buffered.push_back(bytes);
+        }
+        Ok(Async::Ready(()))
+    }
+
+    fn poll_data(&mut self) -> Poll<Option<Bytes>, h2::Error> {
+        if let Some(bytes) = self.buffered.pop_front() {
+            return Ok(Async::Ready(Some(bytes)));
+        }
+        self.poll_rx()
+    }
+
+    fn poll_rx(&mut self) -> Poll<Option<Bytes>, h2::Error> {
+        if self.trailers.is_some() {
+            return Ok(Async::Ready(None));
+        }
+
+        match self.rx.poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Ok(Async::Ready(Some(Frame::Data(bytes)))) => Ok(Async::Ready(Some(bytes))),
+            Ok(Async::Ready(Some(Frame::End(trailers)))) => {
+                self.trailers = Some(trailers);
+                Ok(Async::Ready(None))
+            },
+            Ok(Async::Ready(Some(Frame::Abort))) | Ok(Async::Ready(None)) | Err(_) => {
+                Err(h2::Reason::CANCEL.into())
+            },
+        }
+    }
+}
+
+// ===== impl Discard =====
+
+impl<S, A, B> Discard<S, B>
+where
+    S: Service<Request = http::Request<MirrorBody<A>>, Response = http::Response<B>>,
+    A: Body<Data = Bytes>,
+    B: Body,
+{
+    fn poll_response(&mut self) -> Poll<(), ()> {
+        loop {
+            let state = match self.state {
+                DiscardState::Buffering(ref mut service, ref mut req) => {
+                    if let Some(ref mut req) = *req {
+                        try_ready!(req.body_mut()
+                            .poll_buffer()
+                            .map_err(|_| debug!("mirrored request abandoned")));
+                    }
+                    try_ready!(service.poll_ready().map_err(|_| debug!("shadow failed")));
+                    let req = req.take().expect("mirrored request is sent once");
+                    DiscardState::Pending(service.call(req))
+                },
+                DiscardState::Pending(ref mut fut) => {
+                    let rsp = try_ready!(fut.poll().map_err(|_| debug!("mirrored request failed")));
+                    trace!("mirrored request responded with {}", rsp.status());
+                    let (_, body) = rsp.into_parts();
+                    DiscardState::Body(body)
+                },
+                DiscardState::Body(ref mut body) => {
+                    while try_ready!(body.poll_data().map_err(|_| ())).is_some() {}
+                    try_ready!(body.poll_trailers().map_err(|_| ()));
+                    return Ok(Async::Ready(()));
+                },
+            };
+            self.state = state;
+        }
+    }
+}
+
+impl<S, A, B> Future for Discard<S, B>
+where
+    S: Service<Request = http::Request<MirrorBody<A>>, Response = http::Response<B>>,
+    A: Body<Data = Bytes>,
+    B: Body,
+{
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<(), ()> {
+        // The mirrored request's outcome is of no consequence.
+        if self.poll_response() != Ok(Async::NotReady) {
+            return Ok(Async::Ready(()));
+        }
+
+        // Mirrored requests that take too long are canceled.
+        match self.timer.poll() {
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Ok(Async::Ready(())) | Err(_) => {
+                debug!("mirrored request timed out");
+                Ok(Async::Ready(()))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use futures::future;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct TestBody {
+        data: VecDeque<Bytes>,
+        trailers: Option<http::HeaderMap>,
+    }
+
+    impl Body for TestBody {
+        type Data = Bytes;
+
+        fn is_end_stream(&self) -> bool {
+            self.data.is_empty() && self.trailers.is_none()
+        }
+
+        fn poll_data(&mut self) -> Poll<Option<Bytes>, h2::Error> {
+            Ok(Async::Ready(self.data.pop_front()))
+        }
+
+        fn poll_trailers(&mut self) -> Poll<Option<http::HeaderMap>, h2::Error> {
+            Ok(Async::Ready(self.trailers.take()))
+        }
+    }
+
+    fn body(chunks: &[&'static str], trailers: Option<http::HeaderMap>) -> TestBody {
+        TestBody {
+            data: chunks.iter().map(|c| Bytes::from_static(c.as_bytes())).collect(),
+            trailers,
+        }
+    }
+
+    /// Reads a body's data and trailers.
+    fn read<B>(body: &mut MirrorBody<B>) -> Result<(Vec<Bytes>, Option<http::HeaderMap>), h2::Error>
+    where
+        B: Body<Data = Bytes>,
+    {
+        future::poll_fn(|| {
+            let mut data = Vec::new();
+            while let Some(bytes) = try_ready!(body.poll_data()) {
+                data.push(bytes);
+            }
+            let trailers = try_ready!(body.poll_trailers());
+            Ok(Async::Ready((data, trailers)))
+        }).wait()
+    }
+
+    #[test]
+    fn teed_bodies_are_copied() {
+        let mut trailers = http::HeaderMap::new();
+        trailers.insert("grpc-status", "0".parse().unwrap());
+
+        let (mut primary, mut copy) = MirrorBody::tee(
+            body(&["hello", " world"], Some(trailers.clone())),
+            1024,
+        );
+
+        let (data, primary_trailers) = read(&mut primary).unwrap();
+        assert_eq!(data, vec![Bytes::from("hello"), Bytes::from(" world")]);
+        assert_eq!(primary_trailers, Some(trailers.clone()));
+
+        let (data, copy_trailers) = read(&mut copy).unwrap();
+        assert_eq!(data, vec![Bytes::from("hello"), Bytes::from(" world")]);
+        assert_eq!(copy_trailers, Some(trailers));
+    }
+
+    #[test]
+    fn teed_bodies_are_complete_without_polling_trailers() {
+        let (mut primary, mut copy) = MirrorBody::tee(body(&["hello"], None), 1024);
+
+        let data = future::poll_fn(|| primary.poll_data()).wait().unwrap();
+        assert_eq!(data, Some(Bytes::from("hello")));
+        assert_eq!(future::poll_fn(|| primary.poll_data()).wait().unwrap(), None);
+        drop(primary);
+
+        let (data, trailers) = read(&mut copy).unwrap();
+        assert_eq!(data, vec![Bytes::from("hello")]);
+        assert_eq!(trailers, None);
+    }
+
+    #[test]
+    fn copies_of_large_bodies_are_abandoned() {
+        let (mut primary, mut copy) = MirrorBody::tee(body(&["hello", " world"], None), 8);
+
+        // The body itself is unaffected.
+        let (data, _) = read(&mut primary).unwrap();
+        assert_eq!(data, vec![Bytes::from("hello"), Bytes::from(" world")]);
+
+        assert!(read(&mut copy).is_err());
+    }
+
+    #[test]
+    fn copies_of_incomplete_bodies_are_abandoned() {
+        let (mut primary, mut copy) = MirrorBody::tee(body(&["hello", " world"], None), 1024);
+
+        let data = future::poll_fn(|| primary.poll_data()).wait().unwrap();
+        assert_eq!(data, Some(Bytes::from("hello")));
+        drop(primary);
+
+        assert!(read(&mut copy).is_err());
+    }
+
+    #[test]
+    fn copies_are_buffered_until_complete() {
+        let (mut primary, mut copy) = MirrorBody::tee(body(&["hello", " world"], None), 1024);
+
+        read(&mut primary).unwrap();
+        drop(primary);
+
+        future::poll_fn(|| copy.poll_buffer()).wait().unwrap();
+        let (data, _) = read(&mut copy).unwrap();
+        assert_eq!(data, vec![Bytes::from("hello"), Bytes::from(" world")]);
+    }
+
+    #[test]
+    fn abandoned_copies_are_never_complete() {
+        let (mut primary, mut copy) = MirrorBody::tee(body(&["hello", " world"], None), 8);
+
+        read(&mut primary).unwrap();
+        drop(primary);
+
+        assert!(future::poll_fn(|| copy.poll_buffer()).wait().is_err());
+    }
+}
@@ -0,0 +1,312 @@
+use std::mem;
+use std::sync::Arc;
+
+use futures::{future, Future, Poll};
+use http;
+use http::header::{HeaderValue, HOST, TRANSFER_ENCODING};
+use http::uri::{Parts, Uri};
+use tower_service::{NewService, Service};
+
+use ctx;
+use transparency::h1;
+
+/// The header in which an upgraded request's original protocol is recorded.
+///
+/// Its value is the request's HTTP/1 version, followed by `; absolute-form`
+/// if the request's URI was received in absolute-form.
+pub const ORIG_PROTO: &str = "conduit-orig-proto";
+
+const ABSOLUTE_FORM: &str = "absolute-form";
+
+/// Upgrades HTTP/1 requests to HTTP/2, so that they may be multiplexed over a
+/// single connection to a peer proxy.
+///
+/// The request's original protocol is recorded in the `conduit-orig-proto`
+/// header, so that the peer can `Downgrade` it before it is forwarded to the
+/// application.
+#[derive(Copy, Clone, Debug)]
+pub struct Upgrade<S> {
+    inner: S,
+    enabled: bool,
+}
+
+/// Restores the HTTP/1 version of responses to upgraded requests.
+pub struct UpgradeFuture<F> {
+    inner: F,
+    version: Option<http::Version>,
+}
+
+/// Downgrades requests that were upgraded by a peer proxy back to HTTP/1.
+///
+/// Only requests from a meshed peer, whose identity was authenticated by TLS,
+/// are downgraded. The `conduit-orig-proto` header is removed from all other
+/// requests, so that it can't be forged by an unmeshed client.
+#[derive(Copy, Clone, Debug)]
+pub struct Downgrade<S> {
+    inner: S,
+    enabled: bool,
+}
+
+/// Removes connection-specific headers from responses to downgraded requests,
+/// so that they may be sent over HTTP/2.
+pub struct DowngradeFuture<F> {
+    inner: F,
+    was_downgraded: bool,
+}
+
+// ===== impl Upgrade =====
+
+impl<S> Upgrade<S> {
+    /// Returns an `Upgrade` that upgrades requests only if `enabled` is set.
+    pub fn new(inner: S, enabled: bool) -> Self {
+        Self { inner, enabled }
+    }
+}
+
+impl<S, A, B> NewService for Upgrade<S>
+where
+    S: NewService<Request = http::Request<A>, Response = http::Response<B>>,
+    S::Service: Service<Request = http::Request<A>, Response = http::Response<B>>,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Service = Upgrade<S::Service>;
+    type InitError = S::InitError;
+    type Future = future::Map<S::Future, fn(S::Service) -> Upgrade<S::Service>>;
+
+    fn new_service(&self) -> Self::Future {
+        let s = self.inner.new_service();
+        // As in `NormalizeUri`, the closures must not capture anything so
+        // that they can be `fn`s.
+        if self.enabled {
+            s.map(|inner| Upgrade::new(inner, true))
+        } else {
+            s.map(|inner| Upgrade::new(inner, false))
+        }
+    }
+}
+
+impl<S, A, B> Service for Upgrade<S>
+where
+    S: Service<Request = http::Request<A>, Response = http::Response<B>>,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = UpgradeFuture<S::Future>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, mut req: Self::Request) -> Self::Future {
+        if !self.enabled || req.version() == http::Version::HTTP_2 {
+            // Only requests that this proxy upgraded may carry the header.
+            req.headers_mut().remove(ORIG_PROTO);
+            return UpgradeFuture {
+                inner: self.inner.call(req),
+                version: None,
+            };
+        }
+
+        let version = req.version();
+        let mut orig_proto = match version {
+            http::Version::HTTP_10 => "HTTP/1.0".to_owned(),
+            _ => "HTTP/1.1".to_owned(),
+        };
+        if req.extensions().get::<h1::UriIsAbsoluteForm>().is_some() {
+            orig_proto.push_str("; ");
+            orig_proto.push_str(ABSOLUTE_FORM);
+        }
+        trace!("upgrading {} request to HTTP/2", orig_proto);
+
+        let orig_proto = HeaderValue::from_str(&orig_proto)
+            .expect("orig-proto is a valid header value");
+        req.headers_mut().insert(ORIG_PROTO, orig_proto);
+
+        // HTTP/2 has its own framing, and the peer will choose the HTTP/1
+        // framing when the request is downgraded.
+        req.headers_mut().remove(TRANSFER_ENCODING);
+        *req.version_mut() = http::Version::HTTP_2;
+
+        UpgradeFuture {
+            inner: self.inner.call(req),
+            version: Some(version),
+        }
+    }
+}
+
+impl<F, B> Future for UpgradeFuture<F>
+where
+    F: Future<Item = http::Response<B>>,
+{
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let mut res = try_ready!(self.inner.poll());
+        if let Some(version) = self.version {
+            *res.version_mut() = version;
+        }
+        Ok(res.into())
+    }
+}
+
+// ===== impl Downgrade =====
+
+impl<S> Downgrade<S> {
+    /// Returns a `Downgrade` that downgrades requests only if `enabled` is
+    /// set.
+    pub fn new(inner: S, enabled: bool) -> Self {
+        Self { inner, enabled }
+    }
+}
+
+impl<S, A, B> Service for Downgrade<S>
+where
+    S: Service<Request = http::Request<A>, Response = http::Response<B>>,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = DowngradeFuture<S::Future>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, mut req: Self::Request) -> Self::Future {
+        let orig_proto = if self.enabled {
+            req.headers_mut().remove(ORIG_PROTO)
+        } else {
+            None
+        };
+        let was_downgraded = match orig_proto {
+            Some(ref orig_proto) if is_from_meshed_peer(&req) => {
+                req.version() == http::Version::HTTP_2 && downgrade(&mut req, orig_proto)
+            },
+            Some(_) => {
+                debug!("ignoring {} from unauthenticated peer", ORIG_PROTO);
+                false
+            },
+            None => false,
+        };
+
+        DowngradeFuture {
+            inner: self.inner.call(req),
+            was_downgraded,
+        }
+    }
+}
+
+/// Returns true if the request was received from a peer whose identity was
+/// authenticated by TLS, i.e. from another proxy.
+fn is_from_meshed_peer<A>(req: &http::Request<A>) -> bool {
+    req.extensions()
+        .get::<Arc<ctx::transport::Server>>()
+        .map_or(false, |ctx| ctx.tls_identity.is_some())
+}
+
+/// Restores a request's HTTP/1 version and URI form, returning false if the
+/// `conduit-orig-proto` header isn't understood.
+fn downgrade<A>(req: &mut http::Request<A>, orig_proto: &HeaderValue) -> bool {
+    let orig_proto = match orig_proto.to_str() {
+        Ok(orig_proto) => orig_proto,
+        Err(_) => return false,
+    };
+    let mut parts = orig_proto.split(';').map(str::trim);
+
+    let version = match parts.next() {
+        Some("HTTP/1.1") => http::Version::HTTP_11,
+        Some("HTTP/1.0") => http::Version::HTTP_10,
+        _ => {
+            debug!("ignoring unknown {}: {:?}", ORIG_PROTO, orig_proto);
+            return false;
+        },
+    };
+    let was_absolute_form = parts.any(|part| part == ABSOLUTE_FORM);
+    trace!("downgrading HTTP/2 request to {}", orig_proto);
+
+    if !was_absolute_form {
+        // The authority is about to be removed from the URI, so it must be
+        // left in the `Host` header.
+        if !req.headers().contains_key(HOST) {
+            let host = req.uri().authority_part()
+                .and_then(|a| HeaderValue::from_str(a.as_str()).ok());
+            if let Some(host) = host {
+                req.headers_mut().insert(HOST, host);
+            }
+        }
+
+        let mut parts = Parts::from(mem::replace(req.uri_mut(), Uri::default()));
+        if parts.path_and_query.is_some() {
+            parts.scheme = None;
+            parts.authority = None;
+        }
+        *req.uri_mut() = Uri::from_parts(parts).expect("origin-form uri");
+    }
+
+    *req.version_mut() = version;
+    true
+}
+
+impl<F, B> Future for DowngradeFuture<F>
+where
+    F: Future<Item = http::Response<B>>,
+{
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let mut res = try_ready!(self.inner.poll());
+        if self.was_downgraded {
+            h1::strip_connection_headers(res.headers_mut());
+            res.headers_mut().remove(TRANSFER_ENCODING);
+            *res.version_mut() = http::Version::HTTP_2;
+        }
+        Ok(res.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upgraded(orig_proto: &str, uri: &str) -> http::Request<()> {
+        http::Request::builder()
+            .version(http::Version::HTTP_2)
+            .uri(uri)
+            .header(ORIG_PROTO, orig_proto)
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn downgrades_to_origin_form() {
+        let mut req = upgraded("HTTP/1.1", "http://example.com/foo?bar");
+        assert!(downgrade(&mut req, &HeaderValue::from_static("HTTP/1.1")));
+        assert_eq!(req.version(), http::Version::HTTP_11);
+        assert_eq!(req.uri().to_string(), "/foo?bar");
+        assert_eq!(req.headers()[HOST], "example.com");
+    }
+
+    #[test]
+    fn downgrades_to_absolute_form() {
+        let mut req = upgraded("HTTP/1.0; absolute-form", "http://example.com/foo");
+        req.headers_mut().insert(HOST, HeaderValue::from_static("other.example.com"));
+        let orig_proto = HeaderValue::from_static("HTTP/1.0; absolute-form");
+        assert!(downgrade(&mut req, &orig_proto));
+        assert_eq!(req.version(), http::Version::HTTP_10);
+        assert_eq!(req.uri().to_string(), "http://example.com/foo");
+        assert_eq!(req.headers()[HOST], "other.example.com");
+    }
+
+    #[test]
+    fn ignores_unknown_versions() {
+        let mut req = upgraded("HTTP/3", "http://example.com/");
+        assert!(!downgrade(&mut req, &HeaderValue::from_static("HTTP/3")));
+        assert_eq!(req.version(), http::Version::HTTP_2);
+        assert_eq!(req.uri().to_string(), "http://example.com/");
+    }
+}
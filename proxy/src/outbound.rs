@@ -1,23 +1,37 @@
 use std::{error, fmt};
+use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::str::FromStr;
 use std::time::Duration;
 use std::sync::Arc;
 
+use bytes::Bytes;
 use http;
 use futures::{Async, Poll};
 use tower_service as tower;
-use tower_balance::{self, choose, load, Balance};
+use tower_balance::Balance;
 use tower_buffer::Buffer;
 use tower_discover::{Change, Discover};
 use tower_in_flight_limit::InFlightLimit;
 use tower_h2;
 use conduit_proxy_router::Recognize;
 
+use balance::{self, Weighted, WeightedPowerOfTwoChoices, WithLoad};
 use bind::{self, Bind, Protocol};
+use config;
 use control;
 use control::destination::{Bind as BindTrait, Resolution};
 use ctx;
-use timeout::Timeout;
+use deadline::Deadline;
+use fault;
+use locality;
+use mirror::{Mirror, MirrorBody};
+use outlier;
+use profiles::{self, Routes};
+use queue::{Dequeue, Enqueue, Queue};
+use rate_limit::{Limiter, RateLimit};
+use retry::{self, Retry};
+use split::{self, Split};
 use transparency::h1;
 use transport::{DnsNameAndPort, Host, HostAndPort};
 use rng::LazyThreadRng;
@@ -25,12 +39,32 @@ use rng::LazyThreadRng;
 type BindProtocol<B> = bind::BindProtocol<Arc<ctx::Proxy>, B>;
 
 pub struct Outbound<B> {
-    bind: Bind<Arc<ctx::Proxy>, B>,
+    bind: Bind<Arc<ctx::Proxy>, MirrorBody<B>>,
     discovery: control::Control,
     bind_timeout: Duration,
+    retries: Option<Arc<config::Retries>>,
+    outlier_detection: Option<Arc<config::OutlierDetection>>,
+    load_metric: config::LoadMetric,
+    locality: Option<Arc<config::Locality>>,
+    queue: config::Queue,
+    max_in_flight: usize,
+    mirror_max_body_bytes: usize,
+    mirror_timeout: Duration,
+    /// The rate limiters of the authorities that have rate limits.
+    limiters: Arc<HashMap<http::uri::Authority, Limiter>>,
 }
 
-const MAX_IN_FLIGHT: usize = 10_000;
+/// Binds the service for each of a destination's concrete destinations.
+pub struct BindBackend<B> {
+    outbound: Outbound<B>,
+    protocol: Protocol,
+}
+
+/// The service stack of a single concrete destination.
+type Backend<B> = Retry<Enqueue<Buffer<Dequeue<locality::Prefer<Balance<
+    WithLoad<locality::Partition<Discovery<MirrorBody<B>>>>,
+    WeightedPowerOfTwoChoices<LazyThreadRng>
+>>>>>>;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Destination {
@@ -43,42 +77,159 @@ pub enum Destination {
 impl<B> Outbound<B> {
     pub fn new(bind: Bind<Arc<ctx::Proxy>, B>,
                discovery: control::Control,
-               bind_timeout: Duration,)
+               bind_timeout: Duration,
+               retries: Option<config::Retries>,
+               outlier_detection: Option<config::OutlierDetection>,
+               load_metric: config::LoadMetric,
+               locality: Option<config::Locality>,
+               queue: config::Queue,
+               max_in_flight: usize,
+               mirror_max_body_bytes: usize,
+               mirror_timeout: Duration,
+               rate_limits: config::RateLimits<http::uri::Authority>)
                -> Outbound<B> {
+        let limiters = Limiter::from_config(&rate_limits, bind.sensors(), bind.ctx());
         Self {
-            bind,
+            bind: bind.with_body(),
             discovery,
             bind_timeout,
+            retries: retries.map(Arc::new),
+            outlier_detection: outlier_detection.map(Arc::new),
+            load_metric,
+            locality: locality.map(Arc::new),
+            queue,
+            max_in_flight,
+            mirror_max_body_bytes,
+            mirror_timeout,
+            limiters: Arc::new(limiters),
+        }
+    }
+}
+
+impl<B> Outbound<B>
+where
+    B: tower_h2::Body<Data = Bytes> + Default + 'static,
+{
+    /// Builds a dynamic, load balancing service for a single destination.
+    ///
+    /// Resolves the destination in service discovery and initializes a service that
+    /// buffers and load balances requests across its endpoints.
+    fn bind_backend(
+        &self,
+        dest: &Destination,
+        protocol: &Protocol,
+    ) -> Result<Backend<B>, bind::BufferSpawnError> {
+        let resolve = match *dest {
+            Destination::Hostname(ref authority) => {
+                Discovery::NamedSvc(self.discovery.resolve(
+                    authority,
+                    self.bind.clone().with_protocol(protocol.clone()),
+                ))
+            },
+            Destination::ImplicitOriginalDst(addr) => {
+                Discovery::ImplicitOriginalDst(Some((addr, self.bind.clone()
+                    .with_protocol(protocol.clone()))))
+            }
+        };
+
+        // Failing endpoints are ejected from the balancer by removing them
+        // from the discovered set.
+        let policy = self.outlier_detection.as_ref().map(|outlier_detection| {
+            outlier::Policy::new(
+                outlier_detection,
+                self.bind.sensors(),
+                self.bind.ctx(),
+                dest.authority(),
+            )
+        });
+        let detect = outlier::Detect::new(resolve, policy);
+
+        // Endpoints in the proxy's locality are balanced separately from the
+        // rest, so that requests may be kept within the locality.
+        let split = locality::split(detect, self.locality.clone());
+        let local = balance::weighted_power_of_two_choices(
+            WithLoad::new(split.local, self.load_metric),
+            LazyThreadRng,
+        );
+        let remote = balance::weighted_power_of_two_choices(
+            WithLoad::new(split.remote, self.load_metric),
+            LazyThreadRng,
+        );
+        let balance = locality::Prefer::new(local, remote, split.health);
+
+        // use the same executor as the underlying `Bind` for the `Buffer`.
+        let handle = self.bind.executor();
+
+        // Requests that can't be buffered, or that wait in the buffer too
+        // long, are shed.
+        let queue = Queue::new(
+            self.queue,
+            self.bind.sensors(),
+            self.bind.ctx(),
+            dest.authority(),
+        );
+        let buffer = Buffer::new(queue.dequeue(balance), handle)
+            .map_err(|_| bind::BufferSpawnError::Outbound)?;
+        let buffer = queue.enqueue(buffer, handle);
+
+        // Failed requests are retried through the balancer, so that they may
+        // be sent to another endpoint. The retry budget is shared by all of
+        // the destination's requests.
+        let policy = self.retries.as_ref().map(|retries| {
+            retry::Policy::new(
+                retries,
+                self.bind.sensors(),
+                self.bind.ctx(),
+                dest.authority(),
+            )
+        });
+        Ok(Retry::new(buffer, policy))
+    }
+
+    /// Watches the destination's profile, if it has one.
+    fn profile(&self, dest: &Destination) -> Option<profiles::Receiver> {
+        match *dest {
+            Destination::Hostname(ref authority) => self.discovery.profile(authority),
+            Destination::ImplicitOriginalDst(_) => None,
         }
     }
 }
 
 impl<B> Clone for Outbound<B>
 where
-    B: tower_h2::Body + 'static,
+    B: tower_h2::Body<Data = Bytes> + 'static,
 {
     fn clone(&self) -> Self {
         Self {
             bind: self.bind.clone(),
             discovery: self.discovery.clone(),
             bind_timeout: self.bind_timeout.clone(),
+            retries: self.retries.clone(),
+            outlier_detection: self.outlier_detection.clone(),
+            load_metric: self.load_metric,
+            locality: self.locality.clone(),
+            queue: self.queue,
+            max_in_flight: self.max_in_flight,
+            mirror_max_body_bytes: self.mirror_max_body_bytes,
+            mirror_timeout: self.mirror_timeout,
+            limiters: self.limiters.clone(),
         }
     }
 }
 
 impl<B> Recognize for Outbound<B>
 where
-    B: tower_h2::Body + 'static,
+    B: tower_h2::Body<Data = Bytes> + Default + 'static,
 {
     type Request = http::Request<B>;
     type Response = bind::HttpResponse;
     type Error = <Self::Service as tower::Service>::Error;
     type Key = (Destination, Protocol);
     type RouteError = bind::BufferSpawnError;
-    type Service = InFlightLimit<Timeout<Buffer<Balance<
-        load::WithPendingRequests<Discovery<B>>,
-        choose::PowerOfTwoChoices<LazyThreadRng>
-    >>>>;
+    type Service = fault::Choose<InFlightLimit<Routes<Deadline<RateLimit<Mirror<
+        Split<BindBackend<B>>,
+        BindBackend<B>,
+    >>>>>>;
 
     fn recognize(&self, req: &Self::Request) -> Option<Self::Key> {
         let proto = bind::Protocol::detect(req);
@@ -93,6 +244,15 @@ where
         // for a valid authority, before we fall back to SO_ORIGINAL_DST.
             .or_else(|| h1::authority_from_host(req));
 
+        // A `CONNECT` request's authority is the target of the tunnel that
+        // it asks the original destination to open, so it's always routed by
+        // SO_ORIGINAL_DST.
+        let authority = if req.method() == &http::Method::CONNECT {
+            None
+        } else {
+            authority
+        };
+
         // TODO: Return error when `HostAndPort::normalize()` fails.
         let mut dest = match authority.as_ref()
             .and_then(|auth| HostAndPort::normalize(auth, Some(80)).ok()) {
@@ -122,15 +282,9 @@ where
         Some((dest, proto))
     }
 
-    /// Builds a dynamic, load balancing service.
-    ///
-    /// Resolves the authority in service discovery and initializes a service that buffers
-    /// and load balances requests across.
-    ///
-    /// # TODO
-    ///
-    /// Buffering is currently unbounded and does not apply timeouts. This must be
-    /// changed.
+    /// Builds a service that splits the destination's requests between the
+    /// destinations that its profile names, or sends them to its own
+    /// endpoints, and mirrors them to the profile's shadow destination.
     fn bind_service(
         &self,
         key: &Self::Key,
@@ -138,34 +292,92 @@ where
         let &(ref dest, ref protocol) = key;
         debug!("building outbound {:?} client to {:?}", protocol, dest);
 
-        let resolve = match *dest {
-            Destination::Hostname(ref authority) => {
-                Discovery::NamedSvc(self.discovery.resolve(
-                    authority,
-                    self.bind.clone().with_protocol(protocol.clone()),
-                ))
-            },
-            Destination::ImplicitOriginalDst(addr) => {
-                Discovery::ImplicitOriginalDst(Some((addr, self.bind.clone()
-                    .with_protocol(protocol.clone()))))
-            }
+        let handle = self.bind.executor();
+
+        // The destination's profile is watched once, and the watch is shared
+        // by the services that apply its routes, splits and mirror.
+        let profile_rx = self.profile(dest);
+
+        let logical = self.bind_backend(dest, protocol)?;
+        let backends = BindBackend {
+            outbound: self.clone(),
+            protocol: protocol.clone(),
         };
+        let split = Split::new(backends, logical, dest.port(), profile_rx.clone());
 
-        let loaded = tower_balance::load::WithPendingRequests::new(resolve);
+        // A share of the destination's requests may be copied to a shadow
+        // destination named by its profile, whose responses are discarded.
+        let shadows = BindBackend {
+            outbound: self.clone(),
+            protocol: protocol.clone(),
+        };
+        let mirror = Mirror::new(
+            split,
+            shadows,
+            dest.port(),
+            profile_rx.clone(),
+            self.mirror_max_body_bytes,
+            self.mirror_timeout,
+            handle,
+        );
+
+        // A destination that has a rate limit applies it to the requests that
+        // are sent to it, before they're split or mirrored, so that the copies
+        // sent to its shadow and the concrete destinations that it's split
+        // between don't count against it. Retries don't count against it
+        // either.
+        let limiter = self.limiters.get(&dest.authority()).cloned();
+        let rate_limit = RateLimit::new(mirror, limiter, handle);
+
+        // A request's `grpc-timeout` bounds all of its attempts, as well as
+        // the time that it waits to be dispatched.
+        let deadline = Deadline::new(rate_limit, handle);
+
+        // The destination's profile configures how each of its requests is
+        // handled, and may bound them by timeouts other than the default.
+        let routes = Routes::new(deadline, profile_rx, self.bind_timeout, handle);
+        let limit = InFlightLimit::new(routes, self.max_in_flight);
+
+        // The fault, if any, that's injected into each request is chosen
+        // before it's routed, so that its retries aren't faulted again.
+        Ok(fault::Choose::new(limit, self.bind.faults().clone()))
+    }
+}
 
-        let balance = tower_balance::power_of_two_choices(loaded, LazyThreadRng);
+// ===== impl Destination =====
 
-        // use the same executor as the underlying `Bind` for the `Buffer` and
-        // `Timeout`.
-        let handle = self.bind.executor();
+impl Destination {
+    /// Returns the authority to which requests for this destination are
+    /// routed.
+    fn authority(&self) -> http::uri::Authority {
+        let authority = match *self {
+            Destination::Hostname(ref dst) => format!("{}:{}", dst.host, dst.port),
+            Destination::ImplicitOriginalDst(addr) => format!("{}", addr),
+        };
+        http::uri::Authority::from_str(&authority)
+            .expect("destination is a valid authority")
+    }
 
-        let buffer = Buffer::new(balance, handle)
-            .map_err(|_| bind::BufferSpawnError::Outbound)?;
+    fn port(&self) -> u16 {
+        match *self {
+            Destination::Hostname(ref dst) => dst.port,
+            Destination::ImplicitOriginalDst(addr) => addr.port(),
+        }
+    }
+}
 
-        let timeout = Timeout::new(buffer, self.bind_timeout, handle);
+// ===== impl BindBackend =====
 
-        Ok(InFlightLimit::new(timeout, MAX_IN_FLIGHT))
+impl<B> split::Bind for BindBackend<B>
+where
+    B: tower_h2::Body<Data = Bytes> + Default + 'static,
+{
+    type Service = Backend<B>;
+    type Error = bind::BufferSpawnError;
 
+    fn bind(&self, dst: &DnsNameAndPort) -> Result<Self::Service, Self::Error> {
+        let dest = Destination::Hostname(dst.clone());
+        self.outbound.bind_backend(&dest, &self.protocol)
     }
 }
 
@@ -182,7 +394,7 @@ where
     type Request = http::Request<B>;
     type Response = bind::HttpResponse;
     type Error = <Self::Service as tower::Service>::Error;
-    type Service = bind::Service<B>;
+    type Service = Weighted<bind::Service<B>>;
     type DiscoverError = BindError;
 
     fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::DiscoverError> {
@@ -191,14 +403,15 @@ where
                 .map_err(|_| BindError::Internal),
             Discovery::ImplicitOriginalDst(ref mut opt) => {
                 // This "discovers" a single address for an external service
-                // that never has another change. This can mean it floats
-                // in the Balancer forever. However, when we finally add
-                // circuit-breaking, this should be able to take care of itself,
-                // closing down when the connection is no longer usable.
+                // that never has another change, so it floats in the
+                // Balancer forever. Outlier detection doesn't take it out:
+                // at most `max_ejected_ratio` of one endpoint, rounded down,
+                // may be ejected, so a failing endpoint is only reconnected
+                // to unless the ratio is 1.
                 if let Some((addr, bind)) = opt.take() {
                     let svc = bind.bind(&addr.into())
                         .map_err(|_| BindError::External{ addr })?;
-                    Ok(Async::Ready(Change::Insert(addr, svc)))
+                    Ok(Async::Ready(Change::Insert(addr, Weighted::with_default_weight(svc))))
                 } else {
                     Ok(Async::NotReady)
                 }
@@ -0,0 +1,514 @@
+use std::{cmp, fmt, u32};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future, Poll};
+use http;
+use indexmap::IndexMap;
+use tower_discover::{Change, Discover};
+use tower_service::Service;
+
+use balance::HasWeight;
+use config;
+use ctx;
+use fault::Injected;
+use locality::HasDstLabels;
+use telemetry::{self, metrics::DstLabels};
+
+/// Ejects failing endpoints from a load balancer.
+///
+/// Endpoints are ejected by removing them from the discovery set, so that the
+/// load balancer can't choose them. Each time an endpoint is ejected it stays
+/// out twice as long as the last time, and then it's inserted again.
+pub struct Detect<D: Discover> {
+    inner: D,
+    policy: Option<Policy>,
+    endpoints: IndexMap<D::Key, Tracked<D::Service>>,
+}
+
+/// Decides when a destination's endpoints are ejected.
+#[derive(Clone, Debug)]
+pub struct Policy {
+    config: Arc<config::OutlierDetection>,
+    sensors: telemetry::Sensors,
+    proxy: Arc<ctx::Proxy>,
+    authority: http::uri::Authority,
+}
+
+/// An endpoint's service, which records the outcome of each request.
+///
+/// The service is shared so that an ejected endpoint may be inserted into
+/// the load balancer again without being re-bound.
+pub struct Endpoint<S> {
+    service: Arc<Mutex<S>>,
+    observer: Option<Observer>,
+}
+
+pub struct ResponseFuture<F> {
+    inner: F,
+    observer: Option<Observer>,
+}
+
+/// The state of an endpoint that may be ejected.
+struct Tracked<S> {
+    service: Arc<Mutex<S>>,
+    observer: Observer,
+    ejected_until: Option<Instant>,
+}
+
+#[derive(Clone)]
+struct Observer {
+    config: Arc<config::OutlierDetection>,
+    health: Arc<Mutex<Health>>,
+}
+
+#[derive(Debug)]
+struct Health {
+    consecutive_failures: u32,
+    window_start: Instant,
+    requests: u32,
+    failures: u32,
+    ejections: u32,
+}
+
+// ===== impl Detect =====
+
+impl<D: Discover> Detect<D> {
+    /// Returns a `Detect` that ejects endpoints according to `policy`, if one
+    /// is given.
+    pub fn new(inner: D, policy: Option<Policy>) -> Self {
+        Self {
+            inner,
+            policy,
+            endpoints: IndexMap::new(),
+        }
+    }
+
+    fn ejected(&self) -> usize {
+        self.endpoints.values().filter(|t| t.ejected_until.is_some()).count()
+    }
+
+    fn record_ejected(&self) {
+        if let Some(ref policy) = self.policy {
+            policy.sensors.endpoints_ejected(&policy.proxy, &policy.authority, self.ejected());
+        }
+    }
+}
+
+impl<D> Detect<D>
+where
+    D: Discover,
+    D::Key: Hash + Eq + Clone + fmt::Debug,
+{
+    /// Returns true if the endpoint is currently ejected, in which case it
+    /// was removed from the load balancer but is still discovered.
+    pub fn is_ejected(&self, key: &D::Key) -> bool {
+        self.endpoints.get(key)
+            .map(|t| t.ejected_until.is_some())
+            .unwrap_or(false)
+    }
+
+    /// Readmits an endpoint whose ejection has expired, or ejects an endpoint
+    /// that's failing.
+    fn poll_ejections(&mut self, now: Instant) -> Option<Change<D::Key, Endpoint<D::Service>>> {
+        let config = match self.policy {
+            Some(ref policy) => policy.config.clone(),
+            None => return None,
+        };
+
+        let readmitted = self.endpoints.iter_mut()
+            .find(|&(_, ref t)| t.ejected_until.map(|until| until <= now).unwrap_or(false))
+            .map(|(key, tracked)| {
+                tracked.ejected_until = None;
+                (key.clone(), tracked.endpoint())
+            });
+        if let Some((key, endpoint)) = readmitted {
+            debug!("readmitting endpoint {:?}", key);
+            self.record_ejected();
+            return Some(Change::Insert(key, endpoint));
+        }
+
+        // Never eject so many endpoints that the rest are overwhelmed.
+        let max_ejected = (self.endpoints.len() as f32 * config.max_ejected_ratio) as usize;
+        if self.ejected() >= max_ejected {
+            return None;
+        }
+
+        let ejected = self.endpoints.iter_mut()
+            .find(|&(_, ref t)| t.ejected_until.is_none() && t.observer.should_eject(now))
+            .map(|(key, tracked)| {
+                let ejection_time = tracked.observer.eject(now);
+                tracked.ejected_until = Some(now + ejection_time);
+                (key.clone(), ejection_time)
+            });
+        if let Some((key, ejection_time)) = ejected {
+            debug!("ejecting endpoint {:?} for {:?}", key, ejection_time);
+            self.record_ejected();
+            return Some(Change::Remove(key));
+        }
+
+        None
+    }
+}
+
+impl<D, A, B> Discover for Detect<D>
+where
+    D: Discover<Request = http::Request<A>, Response = http::Response<B>>,
+    D::Key: Hash + Eq + Clone + fmt::Debug,
+{
+    type Key = D::Key;
+    type Request = D::Request;
+    type Response = D::Response;
+    type Error = D::Error;
+    type Service = Endpoint<D::Service>;
+    type DiscoverError = D::DiscoverError;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::DiscoverError> {
+        while let Async::Ready(change) = self.inner.poll()? {
+            let config = match self.policy {
+                Some(ref policy) => policy.config.clone(),
+                None => {
+                    // Nothing is ejected, so changes needn't be tracked.
+                    let change = match change {
+                        Change::Insert(key, service) =>
+                            Change::Insert(key, Endpoint::new(service)),
+                        Change::Remove(key) => Change::Remove(key),
+                    };
+                    return Ok(Async::Ready(change));
+                },
+            };
+
+            match change {
+                Change::Insert(key, service) => {
+                    let tracked = Tracked::new(service, config);
+                    let endpoint = tracked.endpoint();
+                    let replaced = self.endpoints.insert(key.clone(), tracked);
+                    if replaced.map(|t| t.ejected_until.is_some()).unwrap_or(false) {
+                        self.record_ejected();
+                    }
+                    return Ok(Async::Ready(Change::Insert(key, endpoint)));
+                },
+                Change::Remove(key) => {
+                    let removed = self.endpoints.swap_remove(&key);
+                    if removed.map(|t| t.ejected_until.is_some()).unwrap_or(false) {
+                        // The load balancer already removed the endpoint when
+                        // it was ejected.
+                        self.record_ejected();
+                        continue;
+                    }
+                    return Ok(Async::Ready(Change::Remove(key)));
+                },
+            }
+        }
+
+        if let Some(change) = self.poll_ejections(Instant::now()) {
+            return Ok(Async::Ready(change));
+        }
+
+        Ok(Async::NotReady)
+    }
+}
+
+impl<D: Discover> Drop for Detect<D> {
+    fn drop(&mut self) {
+        // Don't leave the destination's ejections behind in metrics.
+        if let Some(ref policy) = self.policy {
+            if self.ejected() > 0 {
+                policy.sensors.endpoints_ejected(&policy.proxy, &policy.authority, 0);
+            }
+        }
+    }
+}
+
+// ===== impl Policy =====
+
+impl Policy {
+    pub fn new(
+        config: &Arc<config::OutlierDetection>,
+        sensors: &telemetry::Sensors,
+        proxy: &Arc<ctx::Proxy>,
+        authority: http::uri::Authority,
+    ) -> Self {
+        Self {
+            config: config.clone(),
+            sensors: sensors.clone(),
+            proxy: proxy.clone(),
+            authority,
+        }
+    }
+}
+
+// ===== impl Endpoint =====
+
+impl<S> Endpoint<S> {
+    fn new(service: S) -> Self {
+        Self {
+            service: Arc::new(Mutex::new(service)),
+            observer: None,
+        }
+    }
+}
+
+impl<S: HasWeight> HasWeight for Endpoint<S> {
+    fn weight(&self) -> u32 {
+        self.service.lock().expect("endpoint lock").weight()
+    }
+}
+
+impl<S: HasDstLabels> HasDstLabels for Endpoint<S> {
+    fn dst_labels(&self) -> Option<DstLabels> {
+        self.service.lock().expect("endpoint lock").dst_labels()
+    }
+}
+
+impl<S, A, B> Service for Endpoint<S>
+where
+    S: Service<Request = http::Request<A>, Response = http::Response<B>>,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        let ready = self.service.lock().expect("endpoint lock").poll_ready();
+        if ready.is_err() {
+            if let Some(ref observer) = self.observer {
+                observer.record(false, Instant::now());
+            }
+        }
+        ready
+    }
+
+    fn call(&mut self, req: Self::Request) -> Self::Future {
+        // The failures of requests into which faults are injected aren't the
+        // endpoint's own.
+        let observer = if req.extensions().get::<Injected>().is_some() {
+            None
+        } else {
+            self.observer.clone()
+        };
+        let inner = self.service.lock().expect("endpoint lock").call(req);
+        ResponseFuture {
+            inner,
+            observer,
+        }
+    }
+}
+
+impl<F, B> Future for ResponseFuture<F>
+where
+    F: Future<Item = http::Response<B>>,
+{
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let result = self.inner.poll();
+        let success = match result {
+            Ok(Async::NotReady) => return result,
+            Ok(Async::Ready(ref rsp)) => !rsp.status().is_server_error(),
+            Err(_) => false,
+        };
+        if let Some(ref observer) = self.observer {
+            observer.record(success, Instant::now());
+        }
+        result
+    }
+}
+
+// ===== impl Tracked =====
+
+impl<S> Tracked<S> {
+    fn new(service: S, config: Arc<config::OutlierDetection>) -> Self {
+        Self {
+            service: Arc::new(Mutex::new(service)),
+            observer: Observer {
+                config,
+                health: Arc::new(Mutex::new(Health::new(Instant::now()))),
+            },
+            ejected_until: None,
+        }
+    }
+
+    fn endpoint(&self) -> Endpoint<S> {
+        Endpoint {
+            service: self.service.clone(),
+            observer: Some(self.observer.clone()),
+        }
+    }
+}
+
+// ===== impl Observer =====
+
+impl Observer {
+    fn record(&self, success: bool, now: Instant) {
+        let mut health = self.health.lock().expect("health lock");
+        health.record(success, &self.config, now);
+    }
+
+    fn should_eject(&self, now: Instant) -> bool {
+        let mut health = self.health.lock().expect("health lock");
+        health.should_eject(&self.config, now)
+    }
+
+    /// Returns how long the endpoint is ejected.
+    fn eject(&self, now: Instant) -> Duration {
+        let mut health = self.health.lock().expect("health lock");
+        health.eject(&self.config, now)
+    }
+}
+
+// ===== impl Health =====
+
+impl Health {
+    fn new(now: Instant) -> Self {
+        Self {
+            consecutive_failures: 0,
+            window_start: now,
+            requests: 0,
+            failures: 0,
+            ejections: 0,
+        }
+    }
+
+    fn record(&mut self, success: bool, config: &config::OutlierDetection, now: Instant) {
+        self.roll_window(config, now);
+        self.requests += 1;
+        if success {
+            self.consecutive_failures = 0;
+        } else {
+            self.consecutive_failures += 1;
+            self.failures += 1;
+        }
+    }
+
+    fn should_eject(&mut self, config: &config::OutlierDetection, now: Instant) -> bool {
+        self.roll_window(config, now);
+        let too_many_consecutive = config.consecutive_failures > 0 &&
+            self.consecutive_failures >= config.consecutive_failures;
+        too_many_consecutive || self.window_failed(config)
+    }
+
+    fn eject(&mut self, config: &config::OutlierDetection, now: Instant) -> Duration {
+        self.ejections += 1;
+        self.consecutive_failures = 0;
+        self.start_window(now);
+
+        // Each consecutive ejection is twice as long as the last.
+        let factor = 1u32.checked_shl(self.ejections - 1).unwrap_or(u32::MAX);
+        config.base_ejection_time.checked_mul(factor)
+            .map(|t| cmp::min(t, config.max_ejection_time))
+            .unwrap_or(config.max_ejection_time)
+    }
+
+    fn window_failed(&self, config: &config::OutlierDetection) -> bool {
+        config.failure_rate > 0.0 &&
+            self.requests > 0 &&
+            self.requests >= config.min_requests &&
+            self.failures as f32 >= self.requests as f32 * config.failure_rate
+    }
+
+    fn roll_window(&mut self, config: &config::OutlierDetection, now: Instant) {
+        if now < self.window_start + config.window {
+            return;
+        }
+
+        // An endpoint that served a window of requests without failing too
+        // often is forgiven its past ejections.
+        if self.requests > 0 && !self.window_failed(config) {
+            self.ejections = 0;
+        }
+        self.start_window(now);
+    }
+
+    fn start_window(&mut self, now: Instant) {
+        self.window_start = now;
+        self.requests = 0;
+        self.failures = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> config::OutlierDetection {
+        config::OutlierDetection {
+            consecutive_failures: 3,
+            failure_rate: 0.5,
+            min_requests: 4,
+            window: Duration::from_secs(10),
+            base_ejection_time: Duration::from_secs(30),
+            max_ejection_time: Duration::from_secs(100),
+            max_ejected_ratio: 0.5,
+        }
+    }
+
+    #[test]
+    fn ejects_after_consecutive_failures() {
+        let config = config();
+        let now = Instant::now();
+        let mut health = Health::new(now);
+
+        health.record(false, &config, now);
+        health.record(false, &config, now);
+        assert!(!health.should_eject(&config, now));
+
+        health.record(false, &config, now);
+        assert!(health.should_eject(&config, now));
+    }
+
+    #[test]
+    fn ejects_after_failure_rate() {
+        let config = config();
+        let now = Instant::now();
+        let mut health = Health::new(now);
+
+        health.record(true, &config, now);
+        health.record(false, &config, now);
+        health.record(true, &config, now);
+        assert!(!health.should_eject(&config, now));
+
+        health.record(false, &config, now);
+        assert!(health.should_eject(&config, now));
+
+        // Failures from a past window don't count.
+        let later = now + config.window;
+        assert!(!health.should_eject(&config, later));
+    }
+
+    #[test]
+    fn ejection_time_grows_exponentially() {
+        let config = config();
+        let now = Instant::now();
+        let mut health = Health::new(now);
+
+        assert_eq!(health.eject(&config, now), Duration::from_secs(30));
+        assert_eq!(health.eject(&config, now), Duration::from_secs(60));
+        assert_eq!(health.eject(&config, now), Duration::from_secs(100));
+        for _ in 0..40 {
+            health.eject(&config, now);
+        }
+        assert_eq!(health.eject(&config, now), Duration::from_secs(100));
+    }
+
+    #[test]
+    fn healthy_window_resets_ejection_time() {
+        let config = config();
+        let now = Instant::now();
+        let mut health = Health::new(now);
+
+        health.eject(&config, now);
+        health.eject(&config, now);
+
+        let readmitted = now + Duration::from_secs(60);
+        health.record(true, &config, readmitted);
+        health.record(true, &config, readmitted);
+
+        let later = readmitted + config.window;
+        assert!(!health.should_eject(&config, later));
+        assert_eq!(health.eject(&config, later), Duration::from_secs(30));
+    }
+}
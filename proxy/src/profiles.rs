@@ -0,0 +1,537 @@
+use std::fmt;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use futures::{Async, Future, Poll, Stream};
+use futures::sync::mpsc;
+use http;
+use regex::Regex;
+use tokio_core::reactor::{Handle, Timeout};
+use tower_service::Service;
+
+use timeout::TimeoutError;
+
+/// Labels requests that match none of a profile's named routes.
+const UNKNOWN_ROUTE: &str = "unknown";
+
+/// Receives a destination's profile each time that it changes.
+///
+/// A receiver may be cloned, so that the services that apply different parts
+/// of a profile share one watch. Each clone is sent the latest profile that it
+/// hasn't yet returned; the clones are expected to be polled by one task.
+#[derive(Clone, Debug)]
+pub struct Receiver {
+    shared: Arc<Mutex<Shared>>,
+    /// The version of the last profile that this receiver returned.
+    version: usize,
+}
+
+/// Sends a destination's profile to a `Receiver`.
+#[derive(Debug)]
+pub struct Sender {
+    tx: mpsc::UnboundedSender<Profile>,
+    /// Dropped with the last of the receiver's clones, so that the sender can
+    /// tell that the profile is no longer watched.
+    watching: Weak<Mutex<Shared>>,
+}
+
+/// The state shared by a receiver's clones.
+#[derive(Debug)]
+struct Shared {
+    /// Taken once no more profiles will be sent.
+    rx: Option<mpsc::UnboundedReceiver<Profile>>,
+    profile: Profile,
+    /// Incremented each time that a profile is received.
+    version: usize,
+}
+
+/// Configures how requests to a destination are handled.
+///
+/// A destination without a profile has no routes, so its requests are
+/// handled by the proxy's defaults.
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    /// The destination's routes, in order of precedence.
+    pub routes: Vec<Arc<Route>>,
+    /// If not empty, the destinations between which requests are split.
+    pub dst_overrides: Vec<WeightedDst>,
+    /// If set, the destination to which a share of requests are mirrored.
+    pub mirror: Option<Mirror>,
+}
+
+/// A destination to which a share of another destination's requests are
+/// sent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WeightedDst {
+    pub authority: http::uri::Authority,
+    pub weight: u32,
+}
+
+/// A destination to which a share of another destination's requests are
+/// copied, and whose responses are discarded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mirror {
+    pub authority: http::uri::Authority,
+    /// The percentage of requests that are mirrored, from 1 to 100.
+    pub percent: u32,
+}
+
+/// A class of a destination's requests, and how they're handled.
+#[derive(Debug)]
+pub struct Route {
+    /// If set, names the route in metrics.
+    pub name: Option<RouteName>,
+    /// If set, the request's method must be this method.
+    pub method: Option<http::Method>,
+    /// If set, the request's path must match this expression.
+    pub path: Option<Regex>,
+    /// If set, bounds how long the route's requests may take.
+    pub timeout: Option<Duration>,
+    /// Whether the route's requests may be retried.
+    pub is_retryable: bool,
+    /// Classifies the route's responses, in order of precedence.
+    pub response_classes: Vec<ResponseClass>,
+}
+
+/// The name of a route, by which its requests' metrics are labeled.
+///
+/// It's inserted into the extensions of requests to destinations that have
+/// named routes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RouteName(Arc<str>);
+
+/// Classifies responses by their status.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResponseClass {
+    /// The lowest status in the class.
+    pub min_status: u16,
+    /// The highest status in the class.
+    pub max_status: u16,
+    /// Whether the class's responses are failures.
+    pub is_failure: bool,
+}
+
+/// Applies a destination's profile to its requests.
+///
+/// The route that a request matches is inserted into the request's
+/// extensions, so that the services that it's dispatched through may be
+/// configured by it, and the route's timeout is enforced. The profile is
+/// updated as changes to it are received.
+pub struct Routes<S> {
+    inner: S,
+    profile: Profile,
+    profile_rx: Option<Receiver>,
+    default_timeout: Duration,
+    handle: Handle,
+}
+
+pub struct ResponseFuture<F> {
+    inner: F,
+    timer: Timeout,
+    timeout: Duration,
+}
+
+/// Creates a channel over which a destination's profiles are sent.
+pub fn channel() -> (Sender, Receiver) {
+    let (tx, rx) = mpsc::unbounded();
+    let shared = Arc::new(Mutex::new(Shared {
+        rx: Some(rx),
+        profile: Profile::default(),
+        version: 0,
+    }));
+    let sender = Sender {
+        tx,
+        watching: Arc::downgrade(&shared),
+    };
+    (sender, Receiver { shared, version: 0 })
+}
+
+/// Returns the latest profile received on `profile_rx` since it was last
+/// polled, if any.
+///
+/// The receiver is dropped once no more profiles will be sent, so that the
+/// last profile remains in effect.
+pub fn poll_latest(profile_rx: &mut Option<Receiver>) -> Option<Profile> {
+    let mut latest = None;
+    while let Some(mut rx) = profile_rx.take() {
+        match rx.poll() {
+            Ok(Async::Ready(Some(profile))) => {
+                latest = Some(profile);
+                *profile_rx = Some(rx);
+            },
+            Ok(Async::NotReady) => {
+                *profile_rx = Some(rx);
+                break;
+            },
+            Ok(Async::Ready(None)) | Err(_) => break,
+        }
+    }
+    latest
+}
+
+// ===== impl Receiver =====
+
+impl Stream for Receiver {
+    type Item = Profile;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<Profile>, ()> {
+        let mut shared = self.shared.lock().expect("profile receiver lock");
+        shared.poll_rx();
+
+        if self.version != shared.version {
+            self.version = shared.version;
+            return Ok(Async::Ready(Some(shared.profile.clone())));
+        }
+
+        if shared.rx.is_none() {
+            return Ok(Async::Ready(None));
+        }
+
+        Ok(Async::NotReady)
+    }
+}
+
+// ===== impl Shared =====
+
+impl Shared {
+    /// Receives the profiles that have been sent, keeping the latest.
+    fn poll_rx(&mut self) {
+        while let Some(mut rx) = self.rx.take() {
+            match rx.poll() {
+                Ok(Async::Ready(Some(profile))) => {
+                    self.profile = profile;
+                    self.version += 1;
+                },
+                Ok(Async::NotReady) => {
+                    self.rx = Some(rx);
+                    return;
+                },
+                Ok(Async::Ready(None)) | Err(_) => return,
+            }
+            self.rx = Some(rx);
+        }
+    }
+}
+
+// ===== impl Sender =====
+
+impl Sender {
+    /// Sends a profile, returning false if the receiver was dropped.
+    pub fn send(&self, profile: Profile) -> bool {
+        self.tx.unbounded_send(profile).is_ok()
+    }
+
+    /// Returns true if the receiver hasn't been dropped.
+    pub fn is_watching(&self) -> bool {
+        self.watching.upgrade().is_some()
+    }
+}
+
+// ===== impl Profile =====
+
+impl Profile {
+    /// Returns the first route that matches `req`, if any.
+    pub fn route_for<B>(&self, req: &http::Request<B>) -> Option<&Arc<Route>> {
+        self.routes.iter().find(|route| route.matches(req))
+    }
+
+    /// Returns the name by which `route`'s requests are labeled, if any of
+    /// the profile's routes are named.
+    ///
+    /// Requests that don't match a named route are labeled as `unknown`, so
+    /// that the profile bounds how many routes are labeled.
+    fn route_name(&self, route: Option<&Arc<Route>>) -> Option<RouteName> {
+        if !self.routes.iter().any(|r| r.name.is_some()) {
+            return None;
+        }
+
+        let name = route
+            .and_then(|r| r.name.clone())
+            .unwrap_or_else(|| RouteName::new(UNKNOWN_ROUTE));
+        Some(name)
+    }
+}
+
+// ===== impl RouteName =====
+
+impl RouteName {
+    pub fn new(name: &str) -> Self {
+        RouteName(Arc::from(name))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RouteName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+// ===== impl Route =====
+
+impl Route {
+    /// Compiles a path expression so that it must match the entire path.
+    pub fn path_regex(path: &str) -> Result<Regex, ::regex::Error> {
+        Regex::new(&format!("^(?:{})$", path))
+    }
+
+    fn matches<B>(&self, req: &http::Request<B>) -> bool {
+        if let Some(ref method) = self.method {
+            if req.method() != method {
+                return false;
+            }
+        }
+
+        if let Some(ref path) = self.path {
+            if !path.is_match(req.uri().path()) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Classifies a response as a failure or not, if one of the route's
+    /// response classes matches it.
+    pub fn is_failure<B>(&self, rsp: &http::Response<B>) -> Option<bool> {
+        let status = rsp.status().as_u16();
+        self.response_classes.iter()
+            .find(|class| class.min_status <= status && status <= class.max_status)
+            .map(|class| class.is_failure)
+    }
+}
+
+// ===== impl Routes =====
+
+impl<S> Routes<S> {
+    /// Applies the profiles received on `profile_rx`, if there are any.
+    ///
+    /// Requests that don't match a route with a timeout of its own are
+    /// bounded by `default_timeout`.
+    pub fn new(
+        inner: S,
+        profile_rx: Option<Receiver>,
+        default_timeout: Duration,
+        handle: &Handle,
+    ) -> Self {
+        Self {
+            inner,
+            profile: Profile::default(),
+            profile_rx,
+            default_timeout,
+            handle: handle.clone(),
+        }
+    }
+
+    fn poll_profile(&mut self) {
+        if let Some(profile) = poll_latest(&mut self.profile_rx) {
+            trace!("updating profile: {:?}", profile);
+            self.profile = profile;
+        }
+    }
+}
+
+impl<S, B> Service for Routes<S>
+where
+    S: Service<Request = http::Request<B>>,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = TimeoutError<S::Error>;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.poll_profile();
+        self.inner.poll_ready().map_err(TimeoutError::Error)
+    }
+
+    fn call(&mut self, mut req: Self::Request) -> Self::Future {
+        let route = self.profile.route_for(&req).cloned();
+        if let Some(name) = self.profile.route_name(route.as_ref()) {
+            req.extensions_mut().insert(name);
+        }
+
+        let timeout = match route {
+            Some(route) => {
+                let timeout = route.timeout.unwrap_or(self.default_timeout);
+                req.extensions_mut().insert(route);
+                timeout
+            },
+            None => self.default_timeout,
+        };
+
+        let timer = Timeout::new(timeout, &self.handle).expect("reactor gone");
+        ResponseFuture {
+            inner: self.inner.call(req),
+            timer,
+            timeout,
+        }
+    }
+}
+
+// ===== impl ResponseFuture =====
+
+impl<F: Future> Future for ResponseFuture<F> {
+    type Item = F::Item;
+    type Error = TimeoutError<F::Error>;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Async::Ready(rsp) = self.inner.poll().map_err(TimeoutError::Error)? {
+            return Ok(Async::Ready(rsp));
+        }
+
+        if self.timer.poll().expect("route timer").is_ready() {
+            return Err(TimeoutError::Timeout(self.timeout));
+        }
+
+        Ok(Async::NotReady)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future;
+
+    use super::*;
+
+    fn route(method: Option<http::Method>, path: Option<&str>) -> Route {
+        Route {
+            name: None,
+            method,
+            path: path.map(|p| Route::path_regex(p).unwrap()),
+            timeout: None,
+            is_retryable: false,
+            response_classes: vec![],
+        }
+    }
+
+    fn request(method: http::Method, uri: &str) -> http::Request<()> {
+        http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn routes_match_method_and_full_path() {
+        let route = route(Some(http::Method::GET), Some("/books/[0-9]+"));
+        assert!(route.matches(&request(http::Method::GET, "/books/1")));
+        assert!(route.matches(&request(http::Method::GET, "http://example.com/books/42?x=1")));
+        assert!(!route.matches(&request(http::Method::POST, "/books/1")));
+        assert!(!route.matches(&request(http::Method::GET, "/books/1/reviews")));
+        assert!(!route.matches(&request(http::Method::GET, "/api/books/1")));
+    }
+
+    #[test]
+    fn first_matching_route_takes_precedence() {
+        let profile = Profile {
+            routes: vec![
+                Arc::new(route(Some(http::Method::POST), None)),
+                Arc::new(route(None, Some("/books/.*"))),
+            ],
+            ..Profile::default()
+        };
+
+        let matched = profile.route_for(&request(http::Method::POST, "/books/1")).unwrap();
+        assert_eq!(matched.method, Some(http::Method::POST));
+
+        let matched = profile.route_for(&request(http::Method::GET, "/books/1")).unwrap();
+        assert_eq!(matched.method, None);
+
+        assert!(profile.route_for(&request(http::Method::GET, "/authors/1")).is_none());
+    }
+
+    #[test]
+    fn response_classes_classify_by_status() {
+        let mut route = route(None, None);
+        route.response_classes = vec![
+            ResponseClass { min_status: 500, max_status: 503, is_failure: true },
+            ResponseClass { min_status: 500, max_status: 599, is_failure: false },
+        ];
+
+        let rsp = |status: u16| {
+            http::Response::builder().status(status).body(()).unwrap()
+        };
+        assert_eq!(route.is_failure(&rsp(503)), Some(true));
+        assert_eq!(route.is_failure(&rsp(504)), Some(false));
+        assert_eq!(route.is_failure(&rsp(200)), None);
+    }
+
+    #[test]
+    fn requests_are_named_by_named_routes() {
+        let mut books = route(None, Some("/books/.*"));
+        books.name = Some(RouteName::new("books"));
+        let books = Arc::new(books);
+        let authors = Arc::new(route(None, Some("/authors/.*")));
+
+        let profile = Profile {
+            routes: vec![books.clone(), authors.clone()],
+            ..Profile::default()
+        };
+        assert_eq!(profile.route_name(Some(&books)), Some(RouteName::new("books")));
+        assert_eq!(profile.route_name(Some(&authors)), Some(RouteName::new("unknown")));
+        assert_eq!(profile.route_name(None), Some(RouteName::new("unknown")));
+
+        let unnamed = Profile {
+            routes: vec![authors.clone()],
+            ..Profile::default()
+        };
+        assert_eq!(unnamed.route_name(Some(&authors)), None);
+        assert_eq!(unnamed.route_name(None), None);
+    }
+
+    #[test]
+    fn senders_know_when_receivers_are_dropped() {
+        let (tx, rx) = channel();
+        assert!(tx.is_watching());
+        assert!(tx.send(Profile::default()));
+
+        let clone = rx.clone();
+        drop(rx);
+        assert!(tx.is_watching());
+
+        drop(clone);
+        assert!(!tx.is_watching());
+        assert!(!tx.send(Profile::default()));
+    }
+
+    #[test]
+    fn clones_receive_the_latest_profile() {
+        let (tx, mut rx) = channel();
+        let mut clone = rx.clone();
+
+        let dst = |weight| WeightedDst {
+            authority: http::uri::Authority::from_static("books:8080"),
+            weight,
+        };
+        let dst_overrides = |rx: &mut Receiver| match rx.poll() {
+            Ok(Async::Ready(Some(profile))) => Some(profile.dst_overrides),
+            _ => None,
+        };
+
+        future::lazy(move || {
+            assert_eq!(dst_overrides(&mut rx), None);
+
+            for weight in 1..3 {
+                let profile = Profile {
+                    dst_overrides: vec![dst(weight)],
+                    ..Profile::default()
+                };
+                assert!(tx.send(profile));
+            }
+            assert_eq!(dst_overrides(&mut rx), Some(vec![dst(2)]));
+            assert_eq!(dst_overrides(&mut rx), None);
+            assert_eq!(dst_overrides(&mut clone), Some(vec![dst(2)]));
+
+            drop(tx);
+            assert!(rx.poll().unwrap().is_ready());
+            assert!(clone.poll().unwrap().is_ready());
+            Ok::<_, ()>(())
+        }).wait().unwrap();
+    }
+}
@@ -0,0 +1,276 @@
+use std::fmt;
+
+use h2;
+use http;
+use http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use tower_balance;
+use tower_buffer;
+use tower_h2;
+use tower_in_flight_limit;
+use tower_reconnect;
+
+use timeout::TimeoutError;
+
+/// A response header that describes why the proxy failed a request, on
+/// responses that the proxy generated rather than forwarded.
+pub const HEADER: &str = "conduit-proxy-error";
+
+const GRPC_STATUS: &str = "grpc-status";
+const GRPC_MESSAGE: &str = "grpc-message";
+
+// The gRPC status codes with which gRPC requests are failed.
+const GRPC_DEADLINE_EXCEEDED: u32 = 4;
+const GRPC_RESOURCE_EXHAUSTED: u32 = 8;
+const GRPC_INTERNAL: u32 = 13;
+const GRPC_UNAVAILABLE: u32 = 14;
+
+/// Describes why the proxy failed a request, rather than forwarding the
+/// application's response.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Reason {
+    /// The request didn't complete before its timeout.
+    Timeout,
+    /// A connection to the destination couldn't be established.
+    Connect,
+    /// The request couldn't be routed to a destination.
+    NotRecognized,
+    /// The proxy already had too many requests in flight.
+    NoCapacity,
+    /// The route's queue was full.
+    QueueFull,
+    /// The request waited too long in the route's queue.
+    QueueExpired,
+    /// The request exceeded the route's rate limit.
+    RateLimited,
+    /// The request's stream was reset, by the destination or by an injected
+    /// fault. It's reset in turn, rather than answered.
+    Reset,
+    /// The request failed for some other reason.
+    Internal,
+}
+
+/// Classifies the errors of a service stack by the reason that the proxy
+/// failed the request.
+pub trait Classify {
+    fn reason(&self) -> Reason;
+}
+
+/// Returns true if the request is a gRPC request, so that its failures are
+/// described by a `grpc-status` rather than by the HTTP status.
+pub fn is_grpc<B>(req: &http::Request<B>) -> bool {
+    req.headers().get(CONTENT_TYPE)
+        .and_then(|ct| ct.to_str().ok())
+        .map(|ct| ct.starts_with("application/grpc"))
+        .unwrap_or(false)
+}
+
+// ===== impl Reason =====
+
+impl Reason {
+    pub fn status(&self) -> http::StatusCode {
+        match *self {
+            Reason::Timeout => http::StatusCode::GATEWAY_TIMEOUT,
+            Reason::Connect => http::StatusCode::BAD_GATEWAY,
+            Reason::NotRecognized => http::StatusCode::BAD_REQUEST,
+            Reason::NoCapacity |
+            Reason::QueueFull |
+            Reason::QueueExpired => http::StatusCode::SERVICE_UNAVAILABLE,
+            Reason::RateLimited => http::StatusCode::TOO_MANY_REQUESTS,
+            Reason::Reset |
+            Reason::Internal => http::StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Reason::Timeout => "timeout",
+            Reason::Connect => "connect",
+            Reason::NotRecognized => "not_recognized",
+            Reason::NoCapacity => "no_capacity",
+            Reason::QueueFull => "queue_full",
+            Reason::QueueExpired => "queue_expired",
+            Reason::RateLimited => "rate_limited",
+            Reason::Reset => "reset",
+            Reason::Internal => "internal",
+        }
+    }
+
+    pub fn grpc_status(&self) -> u32 {
+        match *self {
+            Reason::Timeout => GRPC_DEADLINE_EXCEEDED,
+            Reason::Connect |
+            Reason::QueueExpired |
+            Reason::Reset |
+            Reason::Internal => GRPC_UNAVAILABLE,
+            Reason::NotRecognized => GRPC_INTERNAL,
+            Reason::NoCapacity |
+            Reason::QueueFull |
+            Reason::RateLimited => GRPC_RESOURCE_EXHAUSTED,
+        }
+    }
+
+    fn message(&self) -> &'static str {
+        match *self {
+            Reason::Timeout => "request timed out in proxy",
+            Reason::Connect => "proxy failed to connect to destination",
+            Reason::NotRecognized => "proxy could not route request",
+            Reason::NoCapacity => "proxy has too many requests in flight",
+            Reason::QueueFull => "proxy queue is full",
+            Reason::QueueExpired => "request waited too long in proxy queue",
+            Reason::RateLimited => "request exceeded proxy rate limit",
+            Reason::Reset => "request was reset",
+            Reason::Internal => "proxy failed request",
+        }
+    }
+
+    /// Returns the response with which the proxy answers a request that it
+    /// failed for this reason.
+    ///
+    /// A gRPC request is answered with a trailers-only response, whose
+    /// `grpc-status` describes the failure, since gRPC clients don't act on
+    /// the HTTP status.
+    pub fn response<B: Default>(&self, grpc: bool) -> http::Response<B> {
+        let mut rsp = http::Response::builder();
+        rsp.header(HEADER, self.as_str());
+        if grpc {
+            rsp.status(http::StatusCode::OK)
+                .header(CONTENT_TYPE, "application/grpc")
+                .header(GRPC_STATUS, &*self.grpc_status().to_string())
+                .header(GRPC_MESSAGE, self.message());
+        } else {
+            rsp.status(self.status())
+                .header(CONTENT_LENGTH, "0");
+        }
+        rsp.body(B::default())
+            .expect("proxy error response is valid")
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// ===== impl Classify =====
+
+impl<E: Classify> Classify for TimeoutError<E> {
+    fn reason(&self) -> Reason {
+        match *self {
+            TimeoutError::Timeout(_) => Reason::Timeout,
+            TimeoutError::Error(ref e) => e.reason(),
+        }
+    }
+}
+
+impl<E: Classify> Classify for tower_in_flight_limit::Error<E> {
+    fn reason(&self) -> Reason {
+        match *self {
+            tower_in_flight_limit::Error::NoCapacity => Reason::NoCapacity,
+            tower_in_flight_limit::Error::Upstream(ref e) => e.reason(),
+        }
+    }
+}
+
+impl<E: Classify> Classify for tower_buffer::Error<E> {
+    fn reason(&self) -> Reason {
+        match *self {
+            tower_buffer::Error::Inner(ref e) => e.reason(),
+            tower_buffer::Error::Closed => Reason::Internal,
+        }
+    }
+}
+
+impl<E: Classify, D> Classify for tower_balance::Error<E, D> {
+    fn reason(&self) -> Reason {
+        match *self {
+            tower_balance::Error::Inner(ref e) => e.reason(),
+            tower_balance::Error::Balance(_) |
+            tower_balance::Error::NotReady => Reason::Internal,
+        }
+    }
+}
+
+impl<E: Classify, C> Classify for tower_reconnect::Error<E, C> {
+    fn reason(&self) -> Reason {
+        match *self {
+            tower_reconnect::Error::Inner(ref e) => e.reason(),
+            tower_reconnect::Error::Connect(_) => Reason::Connect,
+            tower_reconnect::Error::NotReady => Reason::Internal,
+        }
+    }
+}
+
+impl Classify for tower_h2::client::Error {
+    fn reason(&self) -> Reason {
+        match self.reason() {
+            // The HTTP/1 client fails with `CONNECT_ERROR` when it can't
+            // connect to the endpoint, or the connection fails.
+            Some(h2::Reason::CONNECT_ERROR) => Reason::Connect,
+            Some(h2::Reason::CANCEL) => Reason::Reset,
+            _ => Reason::Internal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn responses_name_the_reason() {
+        let rsp = Reason::Timeout.response::<()>(false);
+        assert_eq!(rsp.status(), http::StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(rsp.headers()[HEADER], "timeout");
+
+        let rsp = Reason::QueueFull.response::<()>(false);
+        assert_eq!(rsp.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(rsp.headers()[HEADER], "queue_full");
+
+        let rsp = Reason::RateLimited.response::<()>(false);
+        assert_eq!(rsp.status(), http::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(rsp.headers()[HEADER], "rate_limited");
+    }
+
+    #[test]
+    fn grpc_responses_have_grpc_status() {
+        let rsp = Reason::Timeout.response::<()>(true);
+        assert_eq!(rsp.status(), http::StatusCode::OK);
+        assert_eq!(rsp.headers()[HEADER], "timeout");
+        assert_eq!(rsp.headers()[GRPC_STATUS], "4");
+
+        let rsp = Reason::Connect.response::<()>(true);
+        assert_eq!(rsp.headers()[GRPC_STATUS], "14");
+
+        let rsp = Reason::QueueFull.response::<()>(true);
+        assert_eq!(rsp.headers()[GRPC_STATUS], "8");
+    }
+
+    #[test]
+    fn classifies_connect_failures_under_timeouts() {
+        type Error = TimeoutError<tower_reconnect::Error<tower_h2::client::Error, io::Error>>;
+
+        let timeout: Error = TimeoutError::Timeout(Duration::from_secs(1));
+        assert_eq!(timeout.reason(), Reason::Timeout);
+
+        let connect: Error = TimeoutError::Error(tower_reconnect::Error::Connect(
+            io::Error::new(io::ErrorKind::ConnectionRefused, "refused"),
+        ));
+        assert_eq!(connect.reason(), Reason::Connect);
+    }
+
+    #[test]
+    fn classifies_client_errors_by_h2_reason() {
+        let connect = tower_h2::client::Error::from(h2::Reason::CONNECT_ERROR);
+        assert_eq!(Classify::reason(&connect), Reason::Connect);
+
+        let reset = tower_h2::client::Error::from(h2::Reason::CANCEL);
+        assert_eq!(Classify::reason(&reset), Reason::Reset);
+
+        let internal = tower_h2::client::Error::from(h2::Reason::INTERNAL_ERROR);
+        assert_eq!(Classify::reason(&internal), Reason::Internal);
+    }
+}
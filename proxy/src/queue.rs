@@ -0,0 +1,316 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use futures::{Async, Future, Poll};
+use http;
+use tokio_core::reactor::{Handle, Timeout};
+use tower_service::Service;
+
+use config;
+use ctx;
+use proxy_error::{self, Reason};
+use telemetry;
+
+const QUEUED: usize = 0;
+const DEQUEUED: usize = 1;
+const ABANDONED: usize = 2;
+
+/// Limits how many requests may wait for a route's service, and for how long.
+///
+/// A route's requests wait in a buffer while its service isn't ready. The
+/// buffer is wrapped by an `Enqueue`, which counts each request in, and it
+/// wraps a `Dequeue`, which counts each request out as it's dispatched.
+/// Requests that don't fit in the queue, or that wait in it too long, are
+/// answered with a 503 rather than dispatched.
+#[derive(Clone)]
+pub struct Queue(Arc<Shared>);
+
+/// Admits requests into a route's queue.
+#[derive(Clone)]
+pub struct Enqueue<S> {
+    inner: S,
+    queue: Queue,
+    handle: Handle,
+}
+
+/// Takes requests out of a route's queue as they're dispatched.
+pub struct Dequeue<S> {
+    inner: S,
+    queue: Queue,
+}
+
+pub struct ResponseFuture<F> {
+    state: State<F>,
+    /// Whether the request is a gRPC request, which is shed with a
+    /// `grpc-status` rather than an HTTP status.
+    grpc: bool,
+}
+
+pub struct DequeueFuture<F> {
+    inner: Option<F>,
+}
+
+enum State<F> {
+    Queued {
+        inner: F,
+        ticket: Ticket,
+        queue: Queue,
+        deadline: Timeout,
+    },
+    Shed(Reason),
+}
+
+struct Shared {
+    config: config::Queue,
+    depth: AtomicUsize,
+    sensors: telemetry::Sensors,
+    proxy: Arc<ctx::Proxy>,
+    authority: http::uri::Authority,
+}
+
+/// Marks a request as waiting in the queue.
+///
+/// The ticket travels through the buffer in the request's extensions, so
+/// that the `Dequeue` side knows when the request was enqueued, and whether
+/// it has already been abandoned.
+#[derive(Clone, Debug)]
+struct Ticket {
+    state: Arc<AtomicUsize>,
+    enqueued_at: Instant,
+}
+
+// ===== impl Queue =====
+
+impl Queue {
+    pub fn new(
+        config: config::Queue,
+        sensors: &telemetry::Sensors,
+        proxy: &Arc<ctx::Proxy>,
+        authority: http::uri::Authority,
+    ) -> Self {
+        Queue(Arc::new(Shared {
+            config,
+            depth: AtomicUsize::new(0),
+            sensors: sensors.clone(),
+            proxy: proxy.clone(),
+            authority,
+        }))
+    }
+
+    /// Wraps the service that's buffered, so that requests leave the queue
+    /// when they're dispatched to it.
+    pub fn dequeue<S>(&self, inner: S) -> Dequeue<S> {
+        Dequeue {
+            inner,
+            queue: self.clone(),
+        }
+    }
+
+    /// Wraps the buffer, so that requests enter the queue when they're
+    /// buffered.
+    pub fn enqueue<S>(&self, inner: S, handle: &Handle) -> Enqueue<S> {
+        Enqueue {
+            inner,
+            queue: self.clone(),
+            handle: handle.clone(),
+        }
+    }
+
+    fn enter(&self) -> Option<Ticket> {
+        let queue = &self.0;
+
+        // The depth is only incremented if it's still below the capacity, so
+        // that concurrent requests can't overfill the queue.
+        let mut depth = queue.depth.load(Ordering::Acquire);
+        loop {
+            if depth >= queue.config.capacity {
+                return None;
+            }
+
+            match queue.depth.compare_exchange_weak(
+                depth,
+                depth + 1,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => break,
+                Err(actual) => depth = actual,
+            }
+        }
+
+        queue.sensors.queue(&queue.proxy, &queue.authority, depth + 1, None);
+
+        Some(Ticket {
+            state: Arc::new(AtomicUsize::new(QUEUED)),
+            enqueued_at: Instant::now(),
+        })
+    }
+
+    /// Takes the ticket's request out of the queue, returning false if it
+    /// already left.
+    fn leave(&self, ticket: &Ticket, to: usize) -> bool {
+        if ticket.state.compare_exchange(QUEUED, to, Ordering::AcqRel, Ordering::Acquire).is_err() {
+            return false;
+        }
+
+        let queue = &self.0;
+        let depth = queue.depth.fetch_sub(1, Ordering::AcqRel) - 1;
+        let waited = ticket.enqueued_at.elapsed();
+        queue.sensors.queue(&queue.proxy, &queue.authority, depth, Some(waited));
+        true
+    }
+
+    fn shed(&self, reason: Reason) {
+        self.0.sensors.proxy_error(&self.0.proxy, reason);
+    }
+}
+
+// ===== impl Enqueue =====
+
+impl<S, A, B> Service for Enqueue<S>
+where
+    S: Service<Request = http::Request<A>, Response = http::Response<B>>,
+    B: Default,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, mut req: Self::Request) -> Self::Future {
+        let grpc = proxy_error::is_grpc(&req);
+        let ticket = match self.queue.enter() {
+            Some(ticket) => ticket,
+            None => {
+                debug!("queue for {} is full; shedding request", self.queue.0.authority);
+                self.queue.shed(Reason::QueueFull);
+                return ResponseFuture {
+                    state: State::Shed(Reason::QueueFull),
+                    grpc,
+                };
+            },
+        };
+
+        let deadline = Timeout::new(self.queue.0.config.max_wait, &self.handle)
+            .expect("reactor gone");
+        req.extensions_mut().insert(ticket.clone());
+
+        ResponseFuture {
+            state: State::Queued {
+                inner: self.inner.call(req),
+                ticket,
+                queue: self.queue.clone(),
+                deadline,
+            },
+            grpc,
+        }
+    }
+}
+
+// ===== impl Dequeue =====
+
+impl<S, A, B> Service for Dequeue<S>
+where
+    S: Service<Request = http::Request<A>, Response = http::Response<B>>,
+    B: Default,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = DequeueFuture<S::Future>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, mut req: Self::Request) -> Self::Future {
+        if let Some(ticket) = req.extensions_mut().remove::<Ticket>() {
+            // A request that waited too long was already answered, so it's
+            // not dispatched.
+            if !self.queue.leave(&ticket, DEQUEUED) {
+                return DequeueFuture { inner: None };
+            }
+        }
+
+        DequeueFuture { inner: Some(self.inner.call(req)) }
+    }
+}
+
+// ===== impl ResponseFuture =====
+
+impl<F, B> Future for ResponseFuture<F>
+where
+    F: Future<Item = http::Response<B>>,
+    B: Default,
+{
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.state {
+            State::Shed(reason) => Ok(Async::Ready(reason.response(self.grpc))),
+            State::Queued { ref mut inner, ref ticket, ref queue, ref mut deadline } => {
+                if let Async::Ready(rsp) = inner.poll()? {
+                    return Ok(Async::Ready(rsp));
+                }
+
+                // The deadline only applies while the request is queued.
+                if ticket.is_queued() && deadline.poll().expect("queue deadline").is_ready() {
+                    if queue.leave(ticket, ABANDONED) {
+                        debug!(
+                            "request waited {:?} in queue for {}; shedding request",
+                            queue.0.config.max_wait,
+                            queue.0.authority,
+                        );
+                        queue.shed(Reason::QueueExpired);
+                        return Ok(Async::Ready(Reason::QueueExpired.response(self.grpc)));
+                    }
+                }
+
+                Ok(Async::NotReady)
+            },
+        }
+    }
+}
+
+impl<F> Drop for ResponseFuture<F> {
+    fn drop(&mut self) {
+        // A request that's dropped while it's queued, e.g. because its client
+        // went away, no longer counts against the queue.
+        if let State::Queued { ref ticket, ref queue, .. } = self.state {
+            queue.leave(ticket, ABANDONED);
+        }
+    }
+}
+
+// ===== impl DequeueFuture =====
+
+impl<F, B> Future for DequeueFuture<F>
+where
+    F: Future<Item = http::Response<B>>,
+    B: Default,
+{
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        match self.inner {
+            Some(ref mut inner) => inner.poll(),
+            // No one waits for the response of an abandoned request.
+            None => Ok(Async::Ready(Reason::QueueExpired.response(false))),
+        }
+    }
+}
+
+// ===== impl Ticket =====
+
+impl Ticket {
+    fn is_queued(&self) -> bool {
+        self.state.load(Ordering::Acquire) == QUEUED
+    }
+}
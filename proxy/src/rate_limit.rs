@@ -0,0 +1,366 @@
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::{Async, Future, Poll};
+use http;
+use tokio_core::reactor::{Handle, Timeout};
+use tower_service::Service;
+
+use config;
+use ctx;
+use proxy_error::{self, Reason};
+use telemetry;
+
+/// Limits the rate of a route's requests with a token bucket.
+///
+/// Each request takes a token from the bucket, which refills at the route's
+/// rate. A request that finds the bucket empty reserves the next token and
+/// waits for it, unless it would wait longer than the limit's `max_wait`, in
+/// which case it's answered with a 429 rather than dispatched.
+///
+/// The inner service is shared with the requests that wait, each of which is
+/// dispatched once its token is available and the service is ready.
+pub struct RateLimit<S> {
+    inner: Rc<RefCell<S>>,
+    limiter: Option<Limiter>,
+    handle: Handle,
+}
+
+/// The token bucket of a single rate limit.
+///
+/// A limiter is shared by all of the services of the port or authority that
+/// it limits, e.g. those of each protocol, so that they share its limit.
+#[derive(Clone)]
+pub struct Limiter {
+    bucket: Arc<Mutex<Bucket>>,
+    max_wait: Duration,
+    sensors: telemetry::Sensors,
+    proxy: Arc<ctx::Proxy>,
+}
+
+pub struct ResponseFuture<S: Service> {
+    state: State<S>,
+    /// Whether the request is a gRPC request, which is rejected with a
+    /// `grpc-status` rather than an HTTP status.
+    grpc: bool,
+}
+
+enum State<S: Service> {
+    Dispatched(S::Future),
+    /// The request waits for the token that it reserved.
+    Waiting {
+        service: Rc<RefCell<S>>,
+        request: Option<S::Request>,
+        timer: Timeout,
+    },
+    Rejected,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    rate: f64,
+    burst: f64,
+    /// The number of tokens in the bucket. It's negative while requests wait
+    /// for tokens that they've reserved.
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+// ===== impl RateLimit =====
+
+impl<S> RateLimit<S> {
+    /// Returns a `RateLimit` that limits requests according to `limiter`, if
+    /// one is given.
+    pub fn new(inner: S, limiter: Option<Limiter>, handle: &Handle) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(inner)),
+            limiter,
+            handle: handle.clone(),
+        }
+    }
+}
+
+impl<S, A, B> Service for RateLimit<S>
+where
+    S: Service<Request = http::Request<A>, Response = http::Response<B>>,
+    B: Default,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.borrow_mut().poll_ready()
+    }
+
+    fn call(&mut self, req: Self::Request) -> Self::Future {
+        let grpc = proxy_error::is_grpc(&req);
+        let limiter = match self.limiter {
+            Some(ref limiter) => limiter,
+            None => return ResponseFuture {
+                state: State::Dispatched(self.inner.borrow_mut().call(req)),
+                grpc,
+            },
+        };
+
+        let state = match limiter.reserve() {
+            Some(wait) if wait == Duration::from_secs(0) => {
+                State::Dispatched(self.inner.borrow_mut().call(req))
+            },
+            Some(wait) => {
+                trace!("request to {} waits {:?} for rate limit", req.uri(), wait);
+                State::Waiting {
+                    service: self.inner.clone(),
+                    request: Some(req),
+                    timer: Timeout::new(wait, &self.handle).expect("reactor gone"),
+                }
+            },
+            None => {
+                debug!("request to {} exceeded rate limit; rejecting request", req.uri());
+                limiter.sensors.proxy_error(&limiter.proxy, Reason::RateLimited);
+                State::Rejected
+            },
+        };
+
+        ResponseFuture { state, grpc }
+    }
+}
+
+// ===== impl Limiter =====
+
+impl Limiter {
+    /// Returns a limiter for each of the configured rate limits.
+    pub fn from_config<K>(
+        config: &config::RateLimits<K>,
+        sensors: &telemetry::Sensors,
+        proxy: &Arc<ctx::Proxy>,
+    ) -> HashMap<K, Self>
+    where
+        K: Hash + Eq + Clone,
+    {
+        config.limits.iter()
+            .map(|(key, limit)| {
+                let limiter = Self {
+                    bucket: Arc::new(Mutex::new(Bucket::new(limit, Instant::now()))),
+                    max_wait: config.max_wait,
+                    sensors: sensors.clone(),
+                    proxy: proxy.clone(),
+                };
+                (key.clone(), limiter)
+            })
+            .collect()
+    }
+
+    /// Reserves a token for a request, returning how long the request must
+    /// wait for it, or `None` if the request would wait too long.
+    fn reserve(&self) -> Option<Duration> {
+        let mut bucket = self.bucket.lock().expect("rate limit lock");
+        bucket.reserve(Instant::now(), self.max_wait)
+    }
+}
+
+// ===== impl ResponseFuture =====
+
+impl<S, A, B> Future for ResponseFuture<S>
+where
+    S: Service<Request = http::Request<A>, Response = http::Response<B>>,
+    B: Default,
+{
+    type Item = S::Response;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            let future = match self.state {
+                State::Dispatched(ref mut f) => return f.poll(),
+                State::Waiting { ref mut service, ref mut request, ref mut timer } => {
+                    if timer.poll().expect("rate limit timer").is_not_ready() {
+                        return Ok(Async::NotReady);
+                    }
+                    // The service's readiness may have been taken by other
+                    // requests while this one waited.
+                    let mut service = service.borrow_mut();
+                    try_ready!(service.poll_ready());
+                    let request = request.take().expect("polled after dispatch");
+                    service.call(request)
+                },
+                State::Rejected => {
+                    return Ok(Async::Ready(Reason::RateLimited.response(self.grpc)));
+                },
+            };
+            self.state = State::Dispatched(future);
+        }
+    }
+}
+
+// ===== impl Bucket =====
+
+impl Bucket {
+    fn new(limit: &config::RateLimit, now: Instant) -> Self {
+        let burst = f64::from(limit.burst);
+        Self {
+            rate: f64::from(limit.rate),
+            burst,
+            tokens: burst,
+            refilled_at: now,
+        }
+    }
+
+    fn reserve(&mut self, now: Instant, max_wait: Duration) -> Option<Duration> {
+        let elapsed = now.duration_since(cmp::min(now, self.refilled_at));
+        let elapsed_secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+        self.tokens = (self.tokens + elapsed_secs * self.rate).min(self.burst);
+        self.refilled_at = now;
+
+        let wait = if self.tokens >= 1.0 {
+            Duration::from_secs(0)
+        } else {
+            let wait_nanos = (1.0 - self.tokens) / self.rate * 1e9;
+            Duration::new(
+                (wait_nanos / 1e9) as u64,
+                (wait_nanos % 1e9) as u32,
+            )
+        };
+        if wait > max_wait {
+            return None;
+        }
+
+        self.tokens -= 1.0;
+        Some(wait)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use futures::future;
+    use tokio_core::reactor::Core;
+
+    use super::*;
+
+    /// Counts the requests that it's called with, and is only ready while
+    /// `ready` is set.
+    struct Svc {
+        ready: Rc<Cell<bool>>,
+        calls: Rc<Cell<usize>>,
+    }
+
+    impl Service for Svc {
+        type Request = http::Request<()>;
+        type Response = http::Response<()>;
+        type Error = ();
+        type Future = future::FutureResult<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            if self.ready.get() {
+                Ok(Async::Ready(()))
+            } else {
+                Ok(Async::NotReady)
+            }
+        }
+
+        fn call(&mut self, _: Self::Request) -> Self::Future {
+            self.calls.set(self.calls.get() + 1);
+            future::ok(http::Response::new(()))
+        }
+    }
+
+    fn bucket(rate: u32, burst: u32, now: Instant) -> Bucket {
+        Bucket::new(&config::RateLimit { rate, burst }, now)
+    }
+
+    #[test]
+    fn bucket_allows_bursts() {
+        let now = Instant::now();
+        let mut bucket = bucket(1, 3, now);
+        let max_wait = Duration::from_secs(0);
+
+        for _ in 0..3 {
+            assert_eq!(bucket.reserve(now, max_wait), Some(Duration::from_secs(0)));
+        }
+        assert_eq!(bucket.reserve(now, max_wait), None);
+    }
+
+    #[test]
+    fn bucket_refills_at_rate() {
+        let now = Instant::now();
+        let mut bucket = bucket(10, 1, now);
+        let max_wait = Duration::from_secs(0);
+
+        assert_eq!(bucket.reserve(now, max_wait), Some(Duration::from_secs(0)));
+        assert_eq!(bucket.reserve(now, max_wait), None);
+
+        let now = now + Duration::from_millis(100);
+        assert_eq!(bucket.reserve(now, max_wait), Some(Duration::from_secs(0)));
+        assert_eq!(bucket.reserve(now, max_wait), None);
+
+        // The bucket doesn't refill beyond its burst.
+        let now = now + Duration::from_secs(10);
+        assert_eq!(bucket.reserve(now, max_wait), Some(Duration::from_secs(0)));
+        assert_eq!(bucket.reserve(now, max_wait), None);
+    }
+
+    #[test]
+    fn requests_wait_for_reserved_tokens() {
+        let now = Instant::now();
+        let mut bucket = bucket(10, 1, now);
+        let max_wait = Duration::from_millis(250);
+
+        let mut wait_ms = || bucket.reserve(now, max_wait).map(|d| {
+            (d.as_secs() * 1_000) + u64::from((d.subsec_nanos() + 500_000) / 1_000_000)
+        });
+        assert_eq!(wait_ms(), Some(0));
+        assert_eq!(wait_ms(), Some(100));
+        assert_eq!(wait_ms(), Some(200));
+
+        // Two tokens are already reserved, so another request would wait for
+        // 300ms.
+        assert_eq!(wait_ms(), None);
+    }
+
+    #[test]
+    fn waiting_requests_are_dispatched_once_ready() {
+        let mut core = Core::new().unwrap();
+        let handle = core.handle();
+        let proxy = ctx::Proxy::outbound(&ctx::Process::test("test"));
+        let limits = config::RateLimits {
+            limits: vec![("books", config::RateLimit { rate: 100, burst: 1 })]
+                .into_iter()
+                .collect(),
+            max_wait: Duration::from_secs(1),
+        };
+        let limiter = Limiter::from_config(&limits, &telemetry::Sensors::null(), &proxy)
+            .remove("books");
+
+        let ready = Rc::new(Cell::new(true));
+        let calls = Rc::new(Cell::new(0));
+        let svc = Svc {
+            ready: ready.clone(),
+            calls: calls.clone(),
+        };
+        let mut limit = RateLimit::new(svc, limiter, &handle);
+
+        core.run(limit.call(http::Request::new(()))).unwrap();
+        assert_eq!(calls.get(), 1);
+
+        // The next request's token is available after 10ms, but it isn't
+        // dispatched until the service is ready.
+        ready.set(false);
+        let mut rsp = limit.call(http::Request::new(()));
+        core.run(Timeout::new(Duration::from_millis(50), &handle).unwrap()).unwrap();
+        let poll = core.run(future::lazy(|| rsp.poll())).unwrap();
+        assert!(poll.is_not_ready());
+        assert_eq!(calls.get(), 1);
+
+        ready.set(true);
+        core.run(rsp).unwrap();
+        assert_eq!(calls.get(), 2);
+    }
+}
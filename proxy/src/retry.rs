@@ -0,0 +1,503 @@
+use std::cmp;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use futures::{Async, Future, Poll};
+use http;
+use tower_service::Service;
+use tower_h2;
+
+use config;
+use ctx;
+use fault::Injected;
+use mirror::ShadowAuthority;
+use profiles::{Route, RouteName};
+use proxy_error::{self, Classify};
+use split::ConcreteAuthority;
+use telemetry::{self, sensor::http::RequestOpen};
+use transparency::h1;
+
+/// A request header that marks a request as safe to retry, even if its
+/// method isn't idempotent.
+pub const RETRYABLE: &str = "conduit-retryable";
+
+const GRPC_STATUS: &str = "grpc-status";
+
+/// The most times that a single request is retried.
+const MAX_RETRIES_PER_REQUEST: usize = 2;
+
+/// The most retries that a budget may hold in reserve for a burst of
+/// failures, unless its minimum rate is higher.
+const MAX_BUDGET_BALANCE: f32 = 100.0;
+
+/// Re-dispatches failed requests through the inner service.
+///
+/// The inner service is expected to balance requests across a destination's
+/// endpoints, so a retried request will usually be sent to a different
+/// endpoint than the one that failed it.
+///
+/// Only requests without bodies are retried, since bodies aren't buffered.
+#[derive(Clone, Debug)]
+pub struct Retry<S> {
+    inner: S,
+    policy: Option<Policy>,
+}
+
+/// Decides which failures of a destination's requests may be retried.
+#[derive(Clone, Debug)]
+pub struct Policy {
+    config: Arc<config::Retries>,
+    budget: Arc<Budget>,
+    sensors: telemetry::Sensors,
+    proxy: Arc<ctx::Proxy>,
+    authority: http::uri::Authority,
+}
+
+/// Limits retries to a ratio of the requests that have been dispatched.
+///
+/// Each request deposits `ratio` tokens, and each retry withdraws one.
+/// Tokens are also deposited at `min_per_second`, so that a destination that
+/// receives little traffic may still be retried. A new budget starts with a
+/// second's worth of those tokens.
+#[derive(Debug)]
+pub struct Budget {
+    ratio: f32,
+    min_per_second: f32,
+    max_balance: f32,
+    state: Mutex<BudgetState>,
+}
+
+#[derive(Debug)]
+struct BudgetState {
+    balance: f32,
+    refilled_at: Instant,
+}
+
+pub struct ResponseFuture<S: Service> {
+    state: State<S::Future>,
+    retry: Option<Retrying<S>>,
+}
+
+enum State<F> {
+    Dispatched(F),
+    Redispatching,
+}
+
+/// Holds what's needed to dispatch a retryable request again.
+struct Retrying<S> {
+    service: S,
+    policy: Policy,
+    request: Template,
+    retries: usize,
+}
+
+/// The parts of a request without a body that are copied to each retry.
+struct Template {
+    method: http::Method,
+    uri: http::Uri,
+    version: http::Version,
+    headers: http::HeaderMap,
+    extensions: http::Extensions,
+}
+
+// ===== impl Retry =====
+
+impl<S> Retry<S> {
+    /// Returns a `Retry` that retries requests according to `policy`, if one
+    /// is given.
+    pub fn new(inner: S, policy: Option<Policy>) -> Self {
+        Self { inner, policy }
+    }
+}
+
+impl<S, A, B> Service for Retry<S>
+where
+    S: Service<Request = http::Request<A>, Response = http::Response<B>> + Clone,
+    S::Error: Classify,
+    A: tower_h2::Body + Default,
+{
+    type Request = S::Request;
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = ResponseFuture<S>;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.inner.poll_ready()
+    }
+
+    fn call(&mut self, req: Self::Request) -> Self::Future {
+        let retry = match self.policy {
+            Some(ref policy) => {
+                policy.budget.deposit();
+                if policy.is_retryable_request(&req) {
+                    Some(Retrying {
+                        service: self.inner.clone(),
+                        policy: policy.clone(),
+                        request: Template::new(&req),
+                        retries: 0,
+                    })
+                } else {
+                    None
+                }
+            },
+            None => None,
+        };
+
+        ResponseFuture {
+            state: State::Dispatched(self.inner.call(req)),
+            retry,
+        }
+    }
+}
+
+// ===== impl Policy =====
+
+impl Policy {
+    pub fn new(
+        config: &Arc<config::Retries>,
+        sensors: &telemetry::Sensors,
+        proxy: &Arc<ctx::Proxy>,
+        authority: http::uri::Authority,
+    ) -> Self {
+        let budget = Budget::new(config.budget_ratio, config.budget_min_per_second);
+        Self {
+            config: config.clone(),
+            budget: Arc::new(budget),
+            sensors: sensors.clone(),
+            proxy: proxy.clone(),
+            authority,
+        }
+    }
+
+    fn is_retryable_request<A: tower_h2::Body>(&self, req: &http::Request<A>) -> bool {
+        // Upgrades and requests with bodies can't be replayed.
+        if !req.body().is_end_stream() || h1::is_upgrade(req) {
+            return false;
+        }
+
+        // A request's injected fault must be seen by its client, rather than
+        // hidden by a retry.
+        if req.extensions().get::<Injected>().is_some() {
+            return false;
+        }
+
+        // A route in the destination's profile decides for its requests.
+        if let Some(route) = req.extensions().get::<Arc<Route>>() {
+            return route.is_retryable;
+        }
+
+        is_idempotent(req.method()) ||
+            req.headers().contains_key(RETRYABLE)
+    }
+
+    fn is_retryable_response<B>(&self, rsp: &http::Response<B>, route: Option<&Route>) -> bool {
+        // A request that the proxy shed would only add to the load that
+        // caused it to be shed.
+        if rsp.headers().contains_key(proxy_error::HEADER) {
+            return false;
+        }
+
+        if let Some(is_failure) = route.and_then(|r| r.is_failure(rsp)) {
+            return is_failure;
+        }
+
+        if self.config.http_statuses.contains(&rsp.status().as_u16()) {
+            return true;
+        }
+
+        // A gRPC error is only in the response's headers if it has no
+        // messages, in which case it's safe to discard.
+        rsp.headers().get(GRPC_STATUS)
+            .and_then(|s| s.to_str().ok())
+            .and_then(|s| s.parse::<u32>().ok())
+            .map(|code| self.config.grpc_statuses.contains(&code))
+            .unwrap_or(false)
+    }
+}
+
+fn is_idempotent(method: &http::Method) -> bool {
+    match *method {
+        http::Method::GET |
+        http::Method::HEAD |
+        http::Method::OPTIONS |
+        http::Method::TRACE |
+        http::Method::PUT |
+        http::Method::DELETE => true,
+        _ => false,
+    }
+}
+
+// ===== impl Budget =====
+
+impl Budget {
+    pub fn new(ratio: f32, min_per_second: u32) -> Self {
+        let min_per_second = min_per_second as f32;
+        Self {
+            ratio,
+            min_per_second,
+            max_balance: MAX_BUDGET_BALANCE.max(min_per_second),
+            state: Mutex::new(BudgetState {
+                balance: min_per_second,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Records that a request was dispatched.
+    pub fn deposit(&self) {
+        let mut state = self.state.lock().expect("retry budget lock");
+        state.balance = (state.balance + self.ratio).min(self.max_balance);
+    }
+
+    /// Returns true if a retry may be dispatched.
+    pub fn withdraw(&self) -> bool {
+        self.withdraw_at(Instant::now())
+    }
+
+    fn withdraw_at(&self, now: Instant) -> bool {
+        let mut state = self.state.lock().expect("retry budget lock");
+
+        let elapsed = now.duration_since(cmp::min(now, state.refilled_at));
+        let elapsed_secs = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 / 1e9;
+        state.balance = (state.balance + elapsed_secs * self.min_per_second)
+            .min(self.max_balance);
+        state.refilled_at = now;
+
+        if state.balance < 1.0 {
+            return false;
+        }
+        state.balance -= 1.0;
+        true
+    }
+}
+
+// ===== impl ResponseFuture =====
+
+impl<S, A, B> Future for ResponseFuture<S>
+where
+    S: Service<Request = http::Request<A>, Response = http::Response<B>>,
+    S::Error: Classify,
+    A: Default,
+{
+    type Item = S::Response;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            let next = match self.state {
+                State::Dispatched(ref mut fut) => {
+                    let result = match fut.poll() {
+                        Ok(Async::NotReady) => return Ok(Async::NotReady),
+                        Ok(Async::Ready(rsp)) => Ok(rsp),
+                        Err(e) => Err(e),
+                    };
+
+                    let retry = match self.retry {
+                        Some(ref mut retry) => retry.can_retry(&result),
+                        None => false,
+                    };
+                    if !retry {
+                        return result.map(Async::Ready);
+                    }
+
+                    State::Redispatching
+                },
+                State::Redispatching => {
+                    let retry = self.retry.as_mut().expect("retrying request");
+                    try_ready!(retry.service.poll_ready());
+                    let req = retry.request.to_request();
+                    State::Dispatched(retry.service.call(req))
+                },
+            };
+            self.state = next;
+        }
+    }
+}
+
+// ===== impl Retrying =====
+
+impl<S> Retrying<S> {
+    /// Returns true if the request should be dispatched again after `result`.
+    fn can_retry<B, E: Classify>(&mut self, result: &Result<http::Response<B>, E>) -> bool {
+        if self.retries == MAX_RETRIES_PER_REQUEST {
+            return false;
+        }
+
+        // Only an endpoint that couldn't be reached is retried. Other errors,
+        // like timeouts, may mean that the request was already processed.
+        let failed = match *result {
+            Ok(ref rsp) => self.policy.is_retryable_response(rsp, self.request.route()),
+            Err(ref e) => e.reason() == proxy_error::Reason::Connect,
+        };
+        if !failed {
+            return false;
+        }
+
+        let policy = &self.policy;
+        if !policy.budget.withdraw() {
+            debug!("retry budget for {} is exhausted", policy.authority);
+            policy.sensors.retry(&policy.proxy, &policy.authority, true);
+            return false;
+        }
+
+        self.retries += 1;
+        debug!("retrying {} {} (retry {})", self.request.method, self.request.uri, self.retries);
+        policy.sensors.retry(&policy.proxy, &policy.authority, false);
+        true
+    }
+}
+
+// ===== impl Template =====
+
+impl Template {
+    fn new<A>(req: &http::Request<A>) -> Self {
+        let mut extensions = http::Extensions::new();
+        copy_extensions(req.extensions(), &mut extensions);
+        Self {
+            method: req.method().clone(),
+            uri: req.uri().clone(),
+            version: req.version(),
+            headers: req.headers().clone(),
+            extensions,
+        }
+    }
+
+    fn route(&self) -> Option<&Route> {
+        self.extensions.get::<Arc<Route>>().map(|r| r.as_ref())
+    }
+
+    fn to_request<A: Default>(&self) -> http::Request<A> {
+        let mut req = http::Request::new(A::default());
+        *req.method_mut() = self.method.clone();
+        *req.uri_mut() = self.uri.clone();
+        *req.version_mut() = self.version;
+        *req.headers_mut() = self.headers.clone();
+        copy_extensions(&self.extensions, req.extensions_mut());
+        req
+    }
+}
+
+/// Copies the extensions that the layers beneath `Retry` read from a request
+/// to its retries.
+///
+/// `http::Extensions` can't be cloned, so this is the one list of the
+/// extensions that a retry keeps. An extension that's added to requests
+/// above `Retry` and read beneath it must be added here, or it's lost on
+/// retries.
+fn copy_extensions(from: &http::Extensions, to: &mut http::Extensions) {
+    // Telemetry describes the retry as a request from the same client.
+    copy_extension::<Arc<ctx::transport::Server>>(from, to);
+    copy_extension::<RequestOpen>(from, to);
+
+    copy_extension::<Arc<Route>>(from, to);
+    copy_extension::<RouteName>(from, to);
+    copy_extension::<ConcreteAuthority>(from, to);
+    copy_extension::<ShadowAuthority>(from, to);
+}
+
+fn copy_extension<T>(from: &http::Extensions, to: &mut http::Extensions)
+where
+    T: Clone + Send + Sync + 'static,
+{
+    if let Some(ext) = from.get::<T>() {
+        to.insert(ext.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use indexmap::IndexSet;
+
+    use proxy_error::Reason;
+    use super::*;
+
+    #[test]
+    fn budget_allows_ratio_of_requests() {
+        let budget = Budget::new(0.5, 0);
+        let now = Instant::now();
+        assert!(!budget.withdraw_at(now));
+
+        budget.deposit();
+        budget.deposit();
+        assert!(budget.withdraw_at(now));
+        assert!(!budget.withdraw_at(now));
+    }
+
+    #[test]
+    fn budget_allows_min_per_second() {
+        let budget = Budget::new(0.0, 2);
+        let now = Instant::now();
+        assert!(budget.withdraw_at(now));
+        assert!(budget.withdraw_at(now));
+        assert!(!budget.withdraw_at(now));
+
+        let later = now + Duration::from_millis(500);
+        assert!(budget.withdraw_at(later));
+        assert!(!budget.withdraw_at(later));
+    }
+
+    #[test]
+    fn budget_balance_is_bounded() {
+        let budget = Budget::new(1.0, 0);
+        for _ in 0..1000 {
+            budget.deposit();
+        }
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert!(budget.withdraw_at(now));
+        }
+        assert!(!budget.withdraw_at(now));
+    }
+
+    #[test]
+    fn only_connect_errors_are_retried() {
+        struct Failed(Reason);
+        impl Classify for Failed {
+            fn reason(&self) -> Reason {
+                self.0
+            }
+        }
+
+        let config = Arc::new(config::Retries {
+            budget_ratio: 0.0,
+            budget_min_per_second: 10,
+            http_statuses: IndexSet::new(),
+            grpc_statuses: IndexSet::new(),
+        });
+        let proxy = ctx::Proxy::outbound(&ctx::Process::test("test"));
+        let authority = http::uri::Authority::from_static("books:80");
+        let policy = Policy::new(&config, &telemetry::Sensors::null(), &proxy, authority);
+        let mut retrying = Retrying {
+            service: (),
+            policy,
+            request: Template::new(&http::Request::new(())),
+            retries: 0,
+        };
+
+        assert!(!retrying.can_retry::<(), _>(&Err(Failed(Reason::Timeout))));
+        assert!(!retrying.can_retry::<(), _>(&Err(Failed(Reason::Reset))));
+        assert!(retrying.can_retry::<(), _>(&Err(Failed(Reason::Connect))));
+    }
+
+    #[test]
+    fn retries_keep_extensions() {
+        let mut req = http::Request::new(());
+        req.extensions_mut().insert(RouteName::new("books"));
+        req.extensions_mut().insert(RequestOpen(Instant::now()));
+
+        let retry = Template::new(&req).to_request::<()>();
+        assert_eq!(retry.extensions().get::<RouteName>(), Some(&RouteName::new("books")));
+        assert!(retry.extensions().get::<RequestOpen>().is_some());
+    }
+
+    #[test]
+    fn idempotent_methods() {
+        assert!(is_idempotent(&http::Method::GET));
+        assert!(is_idempotent(&http::Method::PUT));
+        assert!(is_idempotent(&http::Method::DELETE));
+        assert!(!is_idempotent(&http::Method::POST));
+        assert!(!is_idempotent(&http::Method::PATCH));
+    }
+}
@@ -0,0 +1,299 @@
+use std::fmt;
+use std::mem;
+use std::str::FromStr;
+
+use futures::Poll;
+use http;
+use rand::{self, Rng};
+use tower_service::Service;
+
+use profiles::{self, WeightedDst};
+use transport::{DnsNameAndPort, Host, HostAndPort};
+
+/// Binds a service for each of a split's concrete destinations.
+pub trait Bind {
+    type Service: Service;
+    type Error;
+
+    fn bind(&self, dst: &DnsNameAndPort) -> Result<Self::Service, Self::Error>;
+}
+
+/// The authority of the concrete destination to which a request was split.
+///
+/// It's inserted into the extensions of requests that are split, so that
+/// their metrics are labeled by it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ConcreteAuthority(http::uri::Authority);
+
+/// Splits a logical destination's requests between concrete destinations.
+///
+/// If the logical destination's profile overrides it with other
+/// destinations, each request is sent to one of them, chosen at random by
+/// weight. Otherwise, requests are sent to the logical destination's own
+/// service.
+pub struct Split<T: Bind> {
+    bind: T,
+    logical: T::Service,
+    /// The logical destination's port, which is used for concrete
+    /// destinations that don't name one.
+    default_port: u16,
+    concrete: Vec<Concrete<T::Service>>,
+    profile_rx: Option<profiles::Receiver>,
+    /// The service chosen by `poll_ready` for the next request.
+    chosen: Option<Choice>,
+}
+
+struct Concrete<S> {
+    dst: DnsNameAndPort,
+    authority: ConcreteAuthority,
+    weight: u32,
+    service: S,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Choice {
+    Logical,
+    Concrete(usize),
+}
+
+/// Returns the destination that `authority` names, if it's a DNS name, and
+/// the authority by which it's labeled, which always names its port.
+///
+/// `default_port` is used if `authority` doesn't name a port.
+pub fn dns_dst(
+    authority: &http::uri::Authority,
+    default_port: u16,
+) -> Option<(DnsNameAndPort, http::uri::Authority)> {
+    let dst = match HostAndPort::normalize(authority, Some(default_port)) {
+        Ok(HostAndPort { host: Host::DnsName(host), port }) => DnsNameAndPort { host, port },
+        _ => return None,
+    };
+    let authority = http::uri::Authority::from_str(&format!("{}:{}", dst.host, dst.port))
+        .expect("destination is a valid authority");
+    Some((dst, authority))
+}
+
+// ===== impl ConcreteAuthority =====
+
+impl ConcreteAuthority {
+    pub fn new(authority: http::uri::Authority) -> Self {
+        ConcreteAuthority(authority)
+    }
+}
+
+impl fmt::Display for ConcreteAuthority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+// ===== impl Split =====
+
+impl<T: Bind> Split<T> {
+    pub fn new(
+        bind: T,
+        logical: T::Service,
+        default_port: u16,
+        profile_rx: Option<profiles::Receiver>,
+    ) -> Self {
+        Self {
+            bind,
+            logical,
+            default_port,
+            concrete: Vec::new(),
+            profile_rx,
+            chosen: None,
+        }
+    }
+
+    fn poll_profile(&mut self) {
+        if let Some(profile) = profiles::poll_latest(&mut self.profile_rx) {
+            self.update(profile.dst_overrides);
+        }
+    }
+
+    /// Replaces the concrete destinations, reusing the services of those
+    /// that remain.
+    fn update(&mut self, dst_overrides: Vec<WeightedDst>) {
+        let mut prior = mem::replace(&mut self.concrete, Vec::new());
+
+        for WeightedDst { authority, weight } in dst_overrides {
+            let (dst, authority) = match dns_dst(&authority, self.default_port) {
+                Some(dst) => dst,
+                None => {
+                    warn!("ignoring destination override {}: not a DNS name", authority);
+                    continue;
+                },
+            };
+
+            let service = match prior.iter().position(|c| c.dst == dst) {
+                Some(idx) => prior.swap_remove(idx).service,
+                None => match self.bind.bind(&dst) {
+                    Ok(service) => service,
+                    Err(_) => {
+                        warn!("failed to bind destination override {:?}", dst);
+                        continue;
+                    },
+                },
+            };
+
+            debug!("splitting {} of requests to {}", weight, authority);
+            self.concrete.push(Concrete {
+                dst,
+                authority: ConcreteAuthority::new(authority),
+                weight,
+                service,
+            });
+        }
+
+        self.chosen = None;
+    }
+
+    fn choose(&self) -> Choice {
+        let total = self.concrete.iter().map(|c| u64::from(c.weight)).sum::<u64>();
+        if total == 0 {
+            return Choice::Logical;
+        }
+
+        self.choose_nth(rand::thread_rng().gen_range(0, total))
+    }
+
+    /// Returns the concrete destination whose share of the sum of their
+    /// weights includes `n`.
+    fn choose_nth(&self, mut n: u64) -> Choice {
+        for (idx, concrete) in self.concrete.iter().enumerate() {
+            let weight = u64::from(concrete.weight);
+            if n < weight {
+                return Choice::Concrete(idx);
+            }
+            n -= weight;
+        }
+
+        unreachable!("n is less than the sum of the weights");
+    }
+}
+
+impl<T, B> Service for Split<T>
+where
+    T: Bind,
+    T::Service: Service<Request = http::Request<B>>,
+{
+    type Request = http::Request<B>;
+    type Response = <T::Service as Service>::Response;
+    type Error = <T::Service as Service>::Error;
+    type Future = <T::Service as Service>::Future;
+
+    fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+        self.poll_profile();
+
+        // The choice is kept until a request is dispatched, so that the
+        // request is sent to the service that was found to be ready.
+        let choice = match self.chosen {
+            Some(choice) => choice,
+            None => self.choose(),
+        };
+        self.chosen = Some(choice);
+
+        match choice {
+            Choice::Logical => self.logical.poll_ready(),
+            Choice::Concrete(idx) => self.concrete[idx].service.poll_ready(),
+        }
+    }
+
+    fn call(&mut self, mut req: Self::Request) -> Self::Future {
+        let choice = match self.chosen.take() {
+            Some(choice) => choice,
+            None => self.choose(),
+        };
+
+        match choice {
+            Choice::Logical => self.logical.call(req),
+            Choice::Concrete(idx) => {
+                let concrete = &mut self.concrete[idx];
+                req.extensions_mut().insert(concrete.authority.clone());
+                concrete.service.call(req)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{Async, Future};
+    use futures::future::{self, FutureResult};
+
+    use super::*;
+
+    /// Answers each request with the name of the destination it was bound to.
+    struct Named(String);
+
+    impl Service for Named {
+        type Request = http::Request<()>;
+        type Response = String;
+        type Error = ();
+        type Future = FutureResult<String, ()>;
+
+        fn poll_ready(&mut self) -> Poll<(), ()> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: Self::Request) -> Self::Future {
+            future::ok(self.0.clone())
+        }
+    }
+
+    struct BindNamed;
+
+    impl Bind for BindNamed {
+        type Service = Named;
+        type Error = ();
+
+        fn bind(&self, dst: &DnsNameAndPort) -> Result<Named, ()> {
+            Ok(Named(format!("{}:{}", dst.host, dst.port)))
+        }
+    }
+
+    fn dst(authority: &'static str, weight: u32) -> WeightedDst {
+        WeightedDst {
+            authority: http::uri::Authority::from_static(authority),
+            weight,
+        }
+    }
+
+    fn dispatch(split: &mut Split<BindNamed>) -> String {
+        assert!(split.poll_ready().unwrap().is_ready());
+        let req = http::Request::new(());
+        split.call(req).wait().unwrap()
+    }
+
+    #[test]
+    fn requests_go_to_the_logical_destination_without_overrides() {
+        let mut split = Split::new(BindNamed, Named("web".into()), 80, None);
+        assert_eq!(dispatch(&mut split), "web");
+    }
+
+    #[test]
+    fn requests_are_split_by_weight() {
+        let mut split = Split::new(BindNamed, Named("web".into()), 80, None);
+        split.update(vec![
+            dst("web-v1.prod.svc.cluster.local", 0),
+            dst("web-v2.prod.svc.cluster.local:8080", 1),
+        ]);
+        for _ in 0..10 {
+            assert_eq!(dispatch(&mut split), "web-v2.prod.svc.cluster.local:8080");
+        }
+
+        split.update(vec![
+            dst("web-v1.prod.svc.cluster.local", 1),
+            dst("web-v2.prod.svc.cluster.local:8080", 0),
+            dst("web-v3.prod.svc.cluster.local", 3),
+        ]);
+        let chosen = (0..4).map(|n| split.choose_nth(n)).collect::<Vec<_>>();
+        assert_eq!(chosen, vec![
+            Choice::Concrete(0),
+            Choice::Concrete(2),
+            Choice::Concrete(2),
+            Choice::Concrete(2),
+        ]);
+    }
+}
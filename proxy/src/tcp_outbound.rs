@@ -0,0 +1,190 @@
+use std::{error, fmt};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::{Async, Poll};
+use indexmap::IndexSet;
+use tower_service as tower;
+use tower_balance::Balance;
+use tower_buffer::Buffer;
+use tower_discover::{Change, Discover};
+use conduit_proxy_router::Recognize;
+
+use balance::{self, Weighted, WeightedPowerOfTwoChoices, WithLoad};
+use bind::{self, BindTcp};
+use config::LoadMetric;
+use control;
+use control::destination::{Bind as BindTrait, Resolution};
+use timeout::Timeout;
+use rng::LazyThreadRng;
+
+/// Routes opaque TCP connections by their original destination.
+///
+/// If the Destination service knows the original destination as the address
+/// of a service, connections are balanced across that service's endpoints.
+/// Otherwise, they are forwarded to the original destination.
+#[derive(Clone)]
+pub struct TcpOutbound {
+    bind: BindTcp,
+    discovery: control::Control,
+    /// Ports on which the peer's proxy doesn't detect protocols, and so
+    /// can't terminate TLS.
+    disable_tls_ports: Arc<IndexSet<u16>>,
+    bind_timeout: Duration,
+}
+
+/// Discovers the endpoints to which connections for an original destination
+/// are balanced.
+pub enum Discovery {
+    /// The original destination's endpoints are being resolved by the
+    /// Destination service.
+    NamedSvc(Resolution<BindTcp>),
+    /// The original destination can't be a service's, so it's the only
+    /// endpoint.
+    ImplicitOriginalDst(Option<(SocketAddr, BindTcp)>),
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum BindError {
+    External { addr: SocketAddr },
+    Internal,
+}
+
+// ===== impl TcpOutbound =====
+
+impl TcpOutbound {
+    pub fn new(
+        bind: BindTcp,
+        discovery: control::Control,
+        disable_tls_ports: IndexSet<u16>,
+        bind_timeout: Duration,
+    ) -> Self {
+        Self {
+            bind,
+            discovery,
+            disable_tls_ports: Arc::new(disable_tls_ports),
+            bind_timeout,
+        }
+    }
+}
+
+impl Recognize for TcpOutbound {
+    type Request = SocketAddr;
+    type Response = <bind::TcpService as tower::Service>::Response;
+    type Error = <Self::Service as tower::Service>::Error;
+    type Key = SocketAddr;
+    type RouteError = bind::BufferSpawnError;
+    type Service = Timeout<Buffer<Balance<
+        WithLoad<Discovery>,
+        WeightedPowerOfTwoChoices<LazyThreadRng>
+    >>>;
+
+    fn recognize(&self, orig_dst: &Self::Request) -> Option<Self::Key> {
+        Some(*orig_dst)
+    }
+
+    /// Builds a dynamic, load balancing service.
+    ///
+    /// Resolves the service that owns the original destination and
+    /// initializes a service that buffers and load balances connections
+    /// across its endpoints.
+    fn bind_service(
+        &self,
+        orig_dst: &Self::Key,
+    ) -> Result<Self::Service, Self::RouteError> {
+        let orig_dst = *orig_dst;
+        debug!("building outbound tcp client to {}", orig_dst);
+
+        // The peer's proxy forwards connections on these ports without
+        // looking for a TLS handshake, so TLS must not be originated.
+        let bind = if self.disable_tls_ports.contains(&orig_dst.port()) {
+            self.bind.without_tls()
+        } else {
+            self.bind.clone()
+        };
+
+        let resolve = match orig_dst {
+            SocketAddr::V4(addr) if !is_local(orig_dst.ip()) => {
+                Discovery::NamedSvc(self.discovery.resolve_addr(addr, bind))
+            },
+            // A loopback address can't be a service's, and the Destination
+            // service can't resolve IPv6 addresses.
+            _ => Discovery::ImplicitOriginalDst(Some((orig_dst, bind))),
+        };
+
+        // Each endpoint's load is its number of pending connects, relative
+        // to its weight.
+        let loaded = WithLoad::new(resolve, LoadMetric::PendingRequests);
+
+        let balance = balance::weighted_power_of_two_choices(loaded, LazyThreadRng);
+
+        // use the same executor as the underlying `BindTcp` for the `Buffer`
+        // and `Timeout`.
+        let handle = self.bind.executor();
+
+        let buffer = Buffer::new(balance, handle)
+            .map_err(|_| bind::BufferSpawnError::Outbound)?;
+
+        Ok(Timeout::new(buffer, self.bind_timeout, handle))
+    }
+}
+
+fn is_local(ip: IpAddr) -> bool {
+    ip.is_loopback() || ip.is_unspecified()
+}
+
+// ===== impl Discovery =====
+
+impl Discover for Discovery {
+    type Key = SocketAddr;
+    type Request = SocketAddr;
+    type Response = <bind::TcpService as tower::Service>::Response;
+    type Error = <bind::TcpService as tower::Service>::Error;
+    type Service = Weighted<bind::TcpService>;
+    type DiscoverError = BindError;
+
+    fn poll(&mut self) -> Poll<Change<Self::Key, Self::Service>, Self::DiscoverError> {
+        match *self {
+            Discovery::NamedSvc(ref mut w) => {
+                w.poll().map_err(|_| BindError::Internal)
+            },
+            Discovery::ImplicitOriginalDst(ref mut opt) => {
+                // As with HTTP, this "discovers" a single address that
+                // never has another change.
+                if let Some((addr, bind)) = opt.take() {
+                    let svc = bind.bind(&addr.into())
+                        .map_err(|_| BindError::External { addr })?;
+                    let svc = Weighted::with_default_weight(svc);
+                    Ok(Async::Ready(Change::Insert(addr, svc)))
+                } else {
+                    Ok(Async::NotReady)
+                }
+            },
+        }
+    }
+}
+
+// ===== impl BindError =====
+
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BindError::External { addr } =>
+                write!(f, "binding external tcp service for {:?} failed", addr),
+            BindError::Internal =>
+                write!(f, "binding internal tcp service failed"),
+        }
+    }
+}
+
+impl error::Error for BindError {
+    fn description(&self) -> &str {
+        match *self {
+            BindError::External { .. } => "binding external tcp service failed",
+            BindError::Internal => "binding internal tcp service failed",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> { None }
+}
@@ -2,14 +2,42 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use h2;
+use http;
 
 use ctx;
+use proxy_error;
 
 #[derive(Clone, Debug)]
 pub enum Event {
     TransportOpen(Arc<ctx::transport::Ctx>),
     TransportClose(Arc<ctx::transport::Ctx>, TransportClose),
 
+    /// No bytes were received from the client before protocol detection timed
+    /// out, so the connection is being forwarded as TCP.
+    ProtocolDetectionTimeout(Arc<ctx::transport::Server>),
+
+    /// A failed outbound request was dispatched again, or would have been if
+    /// the retry budget allowed it.
+    Retry(Arc<ctx::Proxy>, Retry),
+
+    /// The number of a destination's endpoints that are ejected from its load
+    /// balancer changed.
+    EndpointsEjected(Arc<ctx::Proxy>, EndpointsEjected),
+
+    /// A request entered or left the queue of a route's service.
+    Queue(Arc<ctx::Proxy>, Queue),
+
+    /// A connection accepted by one of the proxy's listeners opened or
+    /// closed.
+    Listener(Arc<ctx::Proxy>, Listener),
+
+    /// A connection to an endpoint failed, so it will be reconnected after a
+    /// backoff.
+    Reconnect(Arc<ctx::transport::Client>, Reconnect),
+
+    /// The proxy failed a request, and answered it with an error response.
+    ProxyError(Arc<ctx::Proxy>, ProxyError),
+
     StreamRequestOpen(Arc<ctx::http::Request>),
     StreamRequestFail(Arc<ctx::http::Request>, StreamRequestFail),
     StreamRequestEnd(Arc<ctx::http::Request>, StreamRequestEnd),
@@ -31,6 +59,58 @@ pub struct TransportClose {
     pub tx_bytes: u64,
 }
 
+#[derive(Clone, Debug)]
+pub struct Retry {
+    /// The authority of the destination to which the request was routed.
+    pub authority: http::uri::Authority,
+
+    /// Indicates that the request was not retried because the destination's
+    /// retry budget was exhausted.
+    pub budget_exhausted: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct EndpointsEjected {
+    /// The authority of the destination whose endpoints are ejected.
+    pub authority: http::uri::Authority,
+
+    /// The number of the destination's endpoints that are currently ejected.
+    pub ejected: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct Queue {
+    /// The authority of the route whose queue changed.
+    pub authority: http::uri::Authority,
+
+    /// The number of requests that are waiting in the queue.
+    pub depth: usize,
+
+    /// How long the request waited, if it left the queue.
+    pub waited: Option<Duration>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Listener {
+    /// The number of the listener's connections that are open.
+    pub open: usize,
+
+    /// The most connections that the listener may have open at once.
+    pub max: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct ProxyError {
+    /// Why the proxy failed the request.
+    pub reason: proxy_error::Reason,
+}
+
+#[derive(Clone, Debug)]
+pub struct Reconnect {
+    /// How long the endpoint is backed off before it's reconnected.
+    pub backoff: Duration,
+}
+
 #[derive(Clone, Debug)]
 pub struct StreamRequestFail {
     pub since_request_open: Duration,
@@ -90,6 +170,13 @@ impl Event {
     pub fn proxy(&self) -> &Arc<ctx::Proxy> {
         match *self {
             Event::TransportOpen(ref ctx) | Event::TransportClose(ref ctx, _) => ctx.proxy(),
+            Event::ProtocolDetectionTimeout(ref ctx) => &ctx.proxy,
+            Event::Retry(ref proxy, _) |
+            Event::EndpointsEjected(ref proxy, _) |
+            Event::Queue(ref proxy, _) |
+            Event::Listener(ref proxy, _) |
+            Event::ProxyError(ref proxy, _) => proxy,
+            Event::Reconnect(ref ctx, _) => &ctx.proxy,
             Event::StreamRequestOpen(ref req) |
             Event::StreamRequestFail(ref req, _) |
             Event::StreamRequestEnd(ref req, _) => &req.server.proxy,
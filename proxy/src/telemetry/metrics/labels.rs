@@ -5,8 +5,15 @@ use std::sync::Arc;
 
 use http;
 
+use config;
 use ctx;
+use fault::Injected;
+use mirror::ShadowAuthority;
+use profiles::RouteName;
+use proxy_error;
+use split::ConcreteAuthority;
 use telemetry::event;
+use transport::tls;
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct RequestLabels {
@@ -21,6 +28,28 @@ pub struct RequestLabels {
     /// The value of the `:authority` (HTTP/2) or `Host` (HTTP/1.1) header of
     /// the request.
     authority: Option<http::uri::Authority>,
+
+    /// The route of the destination's profile that an outbound request
+    /// matched, if the destination's routes are named.
+    ///
+    /// Inbound requests aren't labeled by route, since profiles are only
+    /// requested for the destinations of outbound requests.
+    route: Option<RouteName>,
+
+    /// The destination to which an outbound request was sent, if it was
+    /// split from the destination named by `authority`.
+    concrete_authority: Option<ConcreteAuthority>,
+
+    /// The shadow destination to which an outbound request was mirrored, if
+    /// it's a copy of another request.
+    shadow_authority: Option<ShadowAuthority>,
+
+    /// The kind of fault injected into the request, if any.
+    fault: Option<&'static str>,
+
+    /// The TLS identity of the peer: the client of an inbound request, or
+    /// the server of an outbound request.
+    tls_identity: Option<tls::Identity>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -40,19 +69,26 @@ pub struct ResponseLabels {
 }
 
 /// Labels describing a TCP connection
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct TransportLabels {
     /// Was the transport opened in the inbound or outbound direction?
     direction: Direction,
 
     peer: Peer,
+
+    /// The TLS identity of the peer, if the connection is authenticated.
+    tls_identity: Option<tls::Identity>,
+
+    // Additional labels identifying the destination service of a client
+    // connection, provided by the Conduit control plane's service discovery.
+    dst_labels: Option<DstLabels>,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 pub enum Peer { Src, Dst }
 
 /// Labels describing the end of a TCP connection
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct TransportCloseLabels {
     /// Labels describing the TCP connection that closed.
     pub(super) transport: TransportLabels,
@@ -61,6 +97,47 @@ pub struct TransportCloseLabels {
     classification: Classification,
 }
 
+/// Labels describing a connection on which protocol detection timed out.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ProtocolDetectionTimeoutLabels {
+    direction: Direction,
+
+    /// The port that the client connected to.
+    target_port: u16,
+}
+
+/// Labels describing the destination to which outbound requests are routed.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct DestinationLabels {
+    direction: Direction,
+
+    /// The authority of the destination to which the request was routed.
+    authority: http::uri::Authority,
+}
+
+/// Labels describing a single endpoint to which connections are opened.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct EndpointLabels {
+    transport: TransportLabels,
+
+    /// The address of the endpoint.
+    endpoint: config::Addr,
+}
+
+/// Labels describing why the proxy failed requests.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ProxyErrorLabels {
+    direction: Direction,
+
+    reason: proxy_error::Reason,
+}
+
+/// Labels describing one of the proxy's listeners.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ListenerLabels {
+    direction: Direction,
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
 enum Classification {
     Success,
@@ -79,6 +156,10 @@ pub struct DstLabels {
     original: Arc<HashMap<String, String>>,
 }
 
+/// Formats a label value that may contain characters which must be escaped
+/// in the Prometheus text format.
+struct Escaped<'a>(&'a str);
+
 // ===== impl RequestLabels =====
 
 impl RequestLabels {
@@ -92,10 +173,20 @@ impl RequestLabels {
             .authority_part()
             .cloned();
 
+        let tls_identity = match direction {
+            Direction::Inbound => req.server.tls_identity.clone(),
+            Direction::Outbound => req.client.tls_identity.clone(),
+        };
+
         RequestLabels {
             direction,
             outbound_labels,
             authority,
+            route: req.route.clone(),
+            concrete_authority: req.concrete_authority.clone(),
+            shadow_authority: req.shadow_authority.clone(),
+            fault: req.fault.as_ref().map(Injected::as_str),
+            tls_identity,
         }
     }
 }
@@ -109,6 +200,26 @@ impl fmt::Display for RequestLabels {
                 write!(f, "authority=\"\",{}", self.direction),
         }?;
 
+        if let Some(ref route) = self.route {
+            write!(f, ",route=\"{}\"", Escaped(route.as_str()))?;
+        }
+
+        if let Some(ref concrete) = self.concrete_authority {
+            write!(f, ",concrete_authority=\"{}\"", concrete)?;
+        }
+
+        if let Some(ref shadow) = self.shadow_authority {
+            write!(f, ",shadow_authority=\"{}\"", shadow)?;
+        }
+
+        if let Some(fault) = self.fault {
+            write!(f, ",fault=\"{}\"", fault)?;
+        }
+
+        if let Some(ref identity) = self.tls_identity {
+            write!(f, ",tls_identity=\"{}\"", identity)?;
+        }
+
         if let Some(ref outbound) = self.outbound_labels {
             // leading comma added between the direction label and the
             // destination labels, if there are destination labels.
@@ -302,6 +413,13 @@ impl TransportLabels {
                 ctx::transport::Ctx::Server(_) => Peer::Src,
                 ctx::transport::Ctx::Client(_) => Peer::Dst,
             },
+            tls_identity: ctx.tls_identity().cloned(),
+            dst_labels: match *ctx {
+                ctx::transport::Ctx::Server(_) => None,
+                ctx::transport::Ctx::Client(ref ctx) => ctx.dst_labels
+                    .as_ref()
+                    .and_then(|labels| labels.borrow().clone()),
+            },
         }
     }
 }
@@ -312,7 +430,17 @@ impl fmt::Display for TransportLabels {
         f.pad(match self.peer {
             Peer::Src => ",peer=\"src\"",
             Peer::Dst => ",peer=\"dst\"",
-        })
+        })?;
+
+        if let Some(ref identity) = self.tls_identity {
+            write!(f, ",tls_identity=\"{}\"", identity)?;
+        }
+
+        if let Some(ref labels) = self.dst_labels {
+            write!(f, ",{}", labels)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -335,3 +463,308 @@ impl fmt::Display for TransportCloseLabels {
     }
 }
 
+// ===== impl ProtocolDetectionTimeoutLabels =====
+
+impl ProtocolDetectionTimeoutLabels {
+    pub fn new(ctx: &ctx::transport::Server) -> Self {
+        // Connections that weren't redirected were made to the listener itself.
+        let target_port = ctx.orig_dst.unwrap_or(ctx.local).port();
+        ProtocolDetectionTimeoutLabels {
+            direction: Direction::from_context(&ctx.proxy),
+            target_port,
+        }
+    }
+}
+
+impl fmt::Display for ProtocolDetectionTimeoutLabels {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},target_port=\"{}\"", self.direction, self.target_port)
+    }
+}
+
+// ===== impl DestinationLabels =====
+
+impl DestinationLabels {
+    pub fn new(proxy: &ctx::Proxy, authority: &http::uri::Authority) -> Self {
+        DestinationLabels {
+            direction: Direction::from_context(proxy),
+            authority: authority.clone(),
+        }
+    }
+}
+
+impl fmt::Display for DestinationLabels {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "authority=\"{}\",{}", self.authority, self.direction)
+    }
+}
+
+// ===== impl EndpointLabels =====
+
+impl EndpointLabels {
+    pub fn new(ctx: &Arc<ctx::transport::Client>) -> Self {
+        let transport = ctx::transport::Ctx::Client(Arc::clone(ctx));
+        EndpointLabels {
+            transport: TransportLabels::new(&transport),
+            endpoint: ctx.remote.clone(),
+        }
+    }
+}
+
+impl fmt::Display for EndpointLabels {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},endpoint=\"{}\"", self.transport, self.endpoint)
+    }
+}
+
+// ===== impl ProxyErrorLabels =====
+
+impl ProxyErrorLabels {
+    pub fn new(proxy: &ctx::Proxy, reason: proxy_error::Reason) -> Self {
+        ProxyErrorLabels {
+            direction: Direction::from_context(proxy),
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for ProxyErrorLabels {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},reason=\"{}\"", self.direction, self.reason)
+    }
+}
+
+// ===== impl ListenerLabels =====
+
+impl ListenerLabels {
+    pub fn new(proxy: &ctx::Proxy) -> Self {
+        ListenerLabels {
+            direction: Direction::from_context(proxy),
+        }
+    }
+}
+
+impl fmt::Display for ListenerLabels {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.direction, f)
+    }
+}
+
+// ===== impl Escaped =====
+
+impl<'a> fmt::Display for Escaped<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '\\' => f.write_str("\\\\")?,
+                '"' => f.write_str("\\\"")?,
+                '\n' => f.write_str("\\n")?,
+                c => f.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ctx::{self, test_util::*};
+    use super::*;
+
+    #[test]
+    fn tls_identity_labels() {
+        let process = process();
+        let proxy = ctx::Proxy::inbound(&process);
+        let server = tls_server(&proxy, "foo.ns.conduit-managed.svc.cluster.local");
+        let client = client(&proxy, vec![("team", "warriors")]);
+        let (req, _) = request("http://nba.com", &server, &client, 1);
+
+        assert_eq!(
+            RequestLabels::new(&req).to_string(),
+            "authority=\"nba.com\",direction=\"inbound\",\
+             tls_identity=\"foo.ns.conduit-managed.svc.cluster.local\",\
+             dst_team=\"warriors\""
+        );
+
+        let transport = ctx::transport::Ctx::Server(Arc::clone(&server));
+        assert_eq!(
+            TransportLabels::new(&transport).to_string(),
+            "direction=\"inbound\",peer=\"src\",\
+             tls_identity=\"foo.ns.conduit-managed.svc.cluster.local\""
+        );
+    }
+
+    #[test]
+    fn route_labels() {
+        let process = process();
+        let proxy = ctx::Proxy::outbound(&process);
+        let server = server(&proxy);
+        let client = client(&proxy, vec![("team", "warriors")]);
+
+        let mut req = http::Request::get("http://nba.com/teams").body(()).unwrap();
+        req.extensions_mut().insert(RouteName::new("teams"));
+        let req = ctx::http::Request::new(&req, &server, &client, 1);
+
+        assert_eq!(
+            RequestLabels::new(&req).to_string(),
+            "authority=\"nba.com\",direction=\"outbound\",route=\"teams\",dst_team=\"warriors\""
+        );
+    }
+
+    #[test]
+    fn route_labels_are_escaped() {
+        let process = process();
+        let proxy = ctx::Proxy::outbound(&process);
+        let server = server(&proxy);
+        let client = client(&proxy, vec![("team", "warriors")]);
+
+        let mut req = http::Request::get("http://nba.com/teams").body(()).unwrap();
+        req.extensions_mut().insert(RouteName::new("teams \"west\"\\east"));
+        let req = ctx::http::Request::new(&req, &server, &client, 1);
+
+        assert_eq!(
+            RequestLabels::new(&req).to_string(),
+            "authority=\"nba.com\",direction=\"outbound\",route=\"teams \\\"west\\\"\\\\east\",dst_team=\"warriors\""
+        );
+    }
+
+    #[test]
+    fn concrete_authority_labels() {
+        let process = process();
+        let proxy = ctx::Proxy::outbound(&process);
+        let server = server(&proxy);
+        let client = client(&proxy, vec![("service", "web-v2")]);
+
+        let mut req = http::Request::get("http://web.prod.svc.cluster.local/").body(()).unwrap();
+        req.extensions_mut().insert(ConcreteAuthority::new(
+            http::uri::Authority::from_static("web-v2.prod.svc.cluster.local:80"),
+        ));
+        let req = ctx::http::Request::new(&req, &server, &client, 1);
+
+        assert_eq!(
+            RequestLabels::new(&req).to_string(),
+            "authority=\"web.prod.svc.cluster.local\",direction=\"outbound\",\
+             concrete_authority=\"web-v2.prod.svc.cluster.local:80\",\
+             dst_service=\"web-v2\""
+        );
+    }
+
+    #[test]
+    fn shadow_authority_labels() {
+        let process = process();
+        let proxy = ctx::Proxy::outbound(&process);
+        let server = server(&proxy);
+        let client = client(&proxy, vec![("service", "web-v2")]);
+
+        let mut req = http::Request::get("http://web.prod.svc.cluster.local/").body(()).unwrap();
+        req.extensions_mut().insert(ShadowAuthority::new(
+            http::uri::Authority::from_static("web-v2.prod.svc.cluster.local:80"),
+        ));
+        let req = ctx::http::Request::new(&req, &server, &client, 1);
+
+        assert_eq!(
+            RequestLabels::new(&req).to_string(),
+            "authority=\"web.prod.svc.cluster.local\",direction=\"outbound\",\
+             shadow_authority=\"web-v2.prod.svc.cluster.local:80\",\
+             dst_service=\"web-v2\""
+        );
+    }
+
+    #[test]
+    fn fault_labels() {
+        let process = process();
+        let proxy = ctx::Proxy::inbound(&process);
+        let server = server(&proxy);
+        let client = client(&proxy, vec![("team", "warriors")]);
+
+        let mut req = http::Request::get("http://books:8080/").body(()).unwrap();
+        req.extensions_mut().insert(Injected::GrpcAbort(14));
+        let req = ctx::http::Request::new(&req, &server, &client, 1);
+
+        assert_eq!(
+            RequestLabels::new(&req).to_string(),
+            "authority=\"books:8080\",direction=\"inbound\",fault=\"abort\",dst_team=\"warriors\""
+        );
+    }
+
+    #[test]
+    fn no_tls_identity_labels() {
+        let process = process();
+        let proxy = ctx::Proxy::outbound(&process);
+        let transport = ctx::transport::Ctx::Server(server(&proxy));
+        assert_eq!(
+            TransportLabels::new(&transport).to_string(),
+            "direction=\"outbound\",peer=\"src\""
+        );
+    }
+
+    #[test]
+    fn client_transport_dst_labels() {
+        let process = process();
+        let proxy = ctx::Proxy::outbound(&process);
+        let transport = ctx::transport::Ctx::Client(client(&proxy, vec![("team", "warriors")]));
+        assert_eq!(
+            TransportLabels::new(&transport).to_string(),
+            "direction=\"outbound\",peer=\"dst\",dst_team=\"warriors\""
+        );
+    }
+
+    #[test]
+    fn endpoint_labels() {
+        let process = process();
+        let proxy = ctx::Proxy::outbound(&process);
+        let client = client(&proxy, vec![("team", "warriors")]);
+        assert_eq!(
+            EndpointLabels::new(&client).to_string(),
+            "direction=\"outbound\",peer=\"dst\",dst_team=\"warriors\",\
+             endpoint=\"tcp://1.2.3.4:5678\""
+        );
+    }
+
+    #[test]
+    fn protocol_detection_timeout_labels() {
+        let process = process();
+        let proxy = ctx::Proxy::inbound(&process);
+        assert_eq!(
+            ProtocolDetectionTimeoutLabels::new(&server(&proxy)).to_string(),
+            "direction=\"inbound\",target_port=\"5678\""
+        );
+
+        let local = ([127, 0, 0, 1], 4143).into();
+        let remote = ([10, 1, 2, 3], 56324).into();
+        let no_orig_dst = ctx::transport::Server::new(&proxy, &local, &remote, &None, None);
+        assert_eq!(
+            ProtocolDetectionTimeoutLabels::new(&no_orig_dst).to_string(),
+            "direction=\"inbound\",target_port=\"4143\""
+        );
+    }
+
+    #[test]
+    fn grpc_errors_are_failures() {
+        let process = process();
+        let proxy = ctx::Proxy::outbound(&process);
+        let server = server(&proxy);
+        let client = client(&proxy, vec![("team", "warriors")]);
+        let (_, rsp) = request("http://buoyant.io", &server, &client, 1);
+
+        // A gRPC error is answered with a 200, so only its `grpc-status`
+        // tells that it failed.
+        let labels = ResponseLabels::new(&rsp, Some(14));
+        assert_eq!(labels.classification, Classification::Failure);
+
+        let labels = ResponseLabels::new(&rsp, Some(0));
+        assert_eq!(labels.classification, Classification::Success);
+    }
+
+    #[test]
+    fn proxy_error_labels() {
+        let process = process();
+        let proxy = ctx::Proxy::outbound(&process);
+        assert_eq!(
+            ProxyErrorLabels::new(&proxy, proxy_error::Reason::Connect).to_string(),
+            "direction=\"outbound\",reason=\"connect\""
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+
+use super::{
+    Gauge,
+    ListenerLabels,
+    Metric,
+    Scopes,
+    Stamped,
+};
+
+pub(super) type ListenerScopes = Scopes<ListenerLabels, Stamped<ListenerMetrics>>;
+
+#[derive(Debug, Default)]
+pub(super) struct ListenerMetrics {
+    open_connections: Gauge,
+    max_connections: Gauge,
+}
+
+// ===== impl ListenerScopes =====
+
+impl ListenerScopes {
+    metrics! {
+        listener_open_connections: Gauge {
+            "Number of connections accepted by a listener that are open"
+        },
+        listener_max_connections: Gauge {
+            "Most connections that a listener may have open at once"
+        }
+    }
+}
+
+impl fmt::Display for ListenerScopes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.scopes.is_empty() {
+            return Ok(());
+        }
+
+        Self::listener_open_connections.fmt_help(f)?;
+        Self::listener_open_connections.fmt_scopes(f, &self, |s| &s.open_connections)?;
+
+        Self::listener_max_connections.fmt_help(f)?;
+        Self::listener_max_connections.fmt_scopes(f, &self, |s| &s.max_connections)?;
+
+        Ok(())
+    }
+}
+
+// ===== impl ListenerMetrics =====
+
+impl ListenerMetrics {
+    pub(super) fn set_connections(&mut self, open: usize, max: usize) {
+        self.open_connections = (open as u64).into();
+        self.max_connections = (max as u64).into();
+    }
+
+    #[cfg(test)]
+    pub(super) fn open_connections(&self) -> u64 {
+        self.open_connections.into()
+    }
+
+    #[cfg(test)]
+    pub(super) fn max_connections(&self) -> u64 {
+        self.max_connections.into()
+    }
+}
@@ -56,7 +56,13 @@ mod histogram;
 mod http;
 mod labels;
 mod latency;
+mod listener;
+mod outlier;
+mod proxy_error;
+mod queue;
+mod reconnect;
 mod record;
+mod retry;
 mod serve;
 mod transport;
 
@@ -64,6 +70,11 @@ use self::counter::Counter;
 use self::gauge::Gauge;
 use self::histogram::Histogram;
 use self::labels::{
+    DestinationLabels,
+    EndpointLabels,
+    ListenerLabels,
+    ProtocolDetectionTimeoutLabels,
+    ProxyErrorLabels,
     RequestLabels,
     ResponseLabels,
     TransportLabels,
@@ -108,6 +119,13 @@ struct Root {
     responses: http::ResponseScopes,
     transports: transport::OpenScopes,
     transport_closes: transport::CloseScopes,
+    protocol_detection_timeouts: transport::DetectionTimeoutScopes,
+    retries: retry::RetryScopes,
+    ejections: outlier::EjectionScopes,
+    queues: queue::QueueScopes,
+    listeners: listener::ListenerScopes,
+    reconnects: reconnect::ReconnectScopes,
+    proxy_errors: proxy_error::ProxyErrorScopes,
 
     start_time: Gauge,
 }
@@ -212,11 +230,63 @@ impl Root {
             .stamped()
     }
 
+    fn protocol_detection_timeout(
+        &mut self,
+        labels: ProtocolDetectionTimeoutLabels,
+    ) -> &mut transport::DetectionTimeoutMetrics {
+        self.protocol_detection_timeouts.scopes.entry(labels)
+            .or_insert_with(|| transport::DetectionTimeoutMetrics::default().into())
+            .stamped()
+    }
+
+    fn retry(&mut self, labels: DestinationLabels) -> &mut retry::RetryMetrics {
+        self.retries.scopes.entry(labels)
+            .or_insert_with(|| retry::RetryMetrics::default().into())
+            .stamped()
+    }
+
+    fn ejection(&mut self, labels: DestinationLabels) -> &mut outlier::EjectionMetrics {
+        self.ejections.scopes.entry(labels)
+            .or_insert_with(|| outlier::EjectionMetrics::default().into())
+            .stamped()
+    }
+
+    fn queue(&mut self, labels: DestinationLabels) -> &mut queue::QueueMetrics {
+        self.queues.scopes.entry(labels)
+            .or_insert_with(|| queue::QueueMetrics::default().into())
+            .stamped()
+    }
+
+    fn listener(&mut self, labels: ListenerLabels) -> &mut listener::ListenerMetrics {
+        self.listeners.scopes.entry(labels)
+            .or_insert_with(|| listener::ListenerMetrics::default().into())
+            .stamped()
+    }
+
+    fn reconnect(&mut self, labels: EndpointLabels) -> &mut reconnect::ReconnectMetrics {
+        self.reconnects.scopes.entry(labels)
+            .or_insert_with(|| reconnect::ReconnectMetrics::default().into())
+            .stamped()
+    }
+
+    fn proxy_error(&mut self, labels: ProxyErrorLabels) -> &mut proxy_error::ProxyErrorMetrics {
+        self.proxy_errors.scopes.entry(labels)
+            .or_insert_with(|| proxy_error::ProxyErrorMetrics::default().into())
+            .stamped()
+    }
+
     fn retain_since(&mut self, epoch: Instant) {
         self.requests.retain_since(epoch);
         self.responses.retain_since(epoch);
         self.transports.retain_since(epoch);
         self.transport_closes.retain_since(epoch);
+        self.protocol_detection_timeouts.retain_since(epoch);
+        self.retries.retain_since(epoch);
+        self.ejections.retain_since(epoch);
+        self.queues.retain_since(epoch);
+        self.listeners.retain_since(epoch);
+        self.reconnects.retain_since(epoch);
+        self.proxy_errors.retain_since(epoch);
     }
 }
 
@@ -226,6 +296,13 @@ impl fmt::Display for Root {
         self.responses.fmt(f)?;
         self.transports.fmt(f)?;
         self.transport_closes.fmt(f)?;
+        self.protocol_detection_timeouts.fmt(f)?;
+        self.retries.fmt(f)?;
+        self.ejections.fmt(f)?;
+        self.queues.fmt(f)?;
+        self.listeners.fmt(f)?;
+        self.reconnects.fmt(f)?;
+        self.proxy_errors.fmt(f)?;
 
         Self::process_start_time_seconds.fmt_help(f)?;
         Self::process_start_time_seconds.fmt_metric(f, self.start_time)?;
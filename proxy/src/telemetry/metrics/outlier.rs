@@ -0,0 +1,52 @@
+use std::fmt;
+
+use super::{
+    DestinationLabels,
+    Gauge,
+    Metric,
+    Scopes,
+    Stamped,
+};
+
+pub(super) type EjectionScopes = Scopes<DestinationLabels, Stamped<EjectionMetrics>>;
+
+#[derive(Debug, Default)]
+pub(super) struct EjectionMetrics {
+    ejected_endpoints: Gauge,
+}
+
+// ===== impl EjectionScopes =====
+
+impl EjectionScopes {
+    metrics! {
+        ejected_endpoints: Gauge {
+            "Number of a destination's endpoints that are ejected from its load balancer"
+        }
+    }
+}
+
+impl fmt::Display for EjectionScopes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.scopes.is_empty() {
+            return Ok(());
+        }
+
+        Self::ejected_endpoints.fmt_help(f)?;
+        Self::ejected_endpoints.fmt_scopes(f, &self, |s| &s.ejected_endpoints)?;
+
+        Ok(())
+    }
+}
+
+// ===== impl EjectionMetrics =====
+
+impl EjectionMetrics {
+    pub(super) fn set_ejected(&mut self, ejected: usize) {
+        self.ejected_endpoints = (ejected as u64).into();
+    }
+
+    #[cfg(test)]
+    pub(super) fn ejected_endpoints(&self) -> u64 {
+        self.ejected_endpoints.into()
+    }
+}
@@ -0,0 +1,52 @@
+use std::fmt;
+
+use super::{
+    Counter,
+    Metric,
+    ProxyErrorLabels,
+    Scopes,
+    Stamped,
+};
+
+pub(super) type ProxyErrorScopes = Scopes<ProxyErrorLabels, Stamped<ProxyErrorMetrics>>;
+
+#[derive(Debug, Default)]
+pub(super) struct ProxyErrorMetrics {
+    errors_total: Counter,
+}
+
+// ===== impl ProxyErrorScopes =====
+
+impl ProxyErrorScopes {
+    metrics! {
+        proxy_errors_total: Counter {
+            "Total count of requests that the proxy failed rather than forwarding a response"
+        }
+    }
+}
+
+impl fmt::Display for ProxyErrorScopes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.scopes.is_empty() {
+            return Ok(());
+        }
+
+        Self::proxy_errors_total.fmt_help(f)?;
+        Self::proxy_errors_total.fmt_scopes(f, &self, |s| &s.errors_total)?;
+
+        Ok(())
+    }
+}
+
+// ===== impl ProxyErrorMetrics =====
+
+impl ProxyErrorMetrics {
+    pub(super) fn error(&mut self) {
+        self.errors_total.incr();
+    }
+
+    #[cfg(test)]
+    pub(super) fn errors_total(&self) -> u64 {
+        self.errors_total.into()
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+use std::time::Duration;
+
+use super::{
+    latency,
+    DestinationLabels,
+    Gauge,
+    Histogram,
+    Metric,
+    Scopes,
+    Stamped,
+};
+
+pub(super) type QueueScopes = Scopes<DestinationLabels, Stamped<QueueMetrics>>;
+
+#[derive(Debug, Default)]
+pub(super) struct QueueMetrics {
+    queue_depth: Gauge,
+    queue_latency: Histogram<latency::Ms>,
+}
+
+// ===== impl QueueScopes =====
+
+impl QueueScopes {
+    metrics! {
+        queue_depth: Gauge {
+            "Number of requests that are waiting for a route's service"
+        },
+        queue_latency_ms: Histogram<latency::Ms> {
+            "Time that requests waited for a route's service"
+        }
+    }
+}
+
+impl fmt::Display for QueueScopes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.scopes.is_empty() {
+            return Ok(());
+        }
+
+        Self::queue_depth.fmt_help(f)?;
+        Self::queue_depth.fmt_scopes(f, &self, |s| &s.queue_depth)?;
+
+        Self::queue_latency_ms.fmt_help(f)?;
+        Self::queue_latency_ms.fmt_scopes(f, &self, |s| &s.queue_latency)?;
+
+        Ok(())
+    }
+}
+
+// ===== impl QueueMetrics =====
+
+impl QueueMetrics {
+    pub(super) fn set_depth(&mut self, depth: usize) {
+        self.queue_depth = (depth as u64).into();
+    }
+
+    pub(super) fn waited(&mut self, waited: Duration) {
+        self.queue_latency.add(waited);
+    }
+
+    #[cfg(test)]
+    pub(super) fn queue_depth(&self) -> u64 {
+        self.queue_depth.into()
+    }
+
+    #[cfg(test)]
+    pub(super) fn queue_latency(&self) -> &Histogram<latency::Ms> {
+        &self.queue_latency
+    }
+}
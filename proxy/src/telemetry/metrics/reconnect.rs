@@ -0,0 +1,52 @@
+use std::fmt;
+
+use super::{
+    Counter,
+    EndpointLabels,
+    Metric,
+    Scopes,
+    Stamped,
+};
+
+pub(super) type ReconnectScopes = Scopes<EndpointLabels, Stamped<ReconnectMetrics>>;
+
+#[derive(Debug, Default)]
+pub(super) struct ReconnectMetrics {
+    reconnects_total: Counter,
+}
+
+// ===== impl ReconnectScopes =====
+
+impl ReconnectScopes {
+    metrics! {
+        endpoint_reconnects_total: Counter {
+            "Total count of failed connections to an endpoint after which it was reconnected"
+        }
+    }
+}
+
+impl fmt::Display for ReconnectScopes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.scopes.is_empty() {
+            return Ok(());
+        }
+
+        Self::endpoint_reconnects_total.fmt_help(f)?;
+        Self::endpoint_reconnects_total.fmt_scopes(f, &self, |s| &s.reconnects_total)?;
+
+        Ok(())
+    }
+}
+
+// ===== impl ReconnectMetrics =====
+
+impl ReconnectMetrics {
+    pub(super) fn reconnect(&mut self) {
+        self.reconnects_total.incr();
+    }
+
+    #[cfg(test)]
+    pub(super) fn reconnects_total(&self) -> u64 {
+        self.reconnects_total.into()
+    }
+}
@@ -3,8 +3,13 @@ use std::sync::{Arc, Mutex};
 use telemetry::event::Event;
 use super::Root;
 use super::labels::{
+    DestinationLabels,
+    EndpointLabels,
+    ListenerLabels,
     RequestLabels,
     ResponseLabels,
+    ProtocolDetectionTimeoutLabels,
+    ProxyErrorLabels,
     TransportLabels,
     TransportCloseLabels
 };
@@ -79,6 +84,60 @@ impl Record {
                         .close(close.duration);
                 })
             },
+
+            Event::ProtocolDetectionTimeout(ref ctx) => {
+                self.update(|metrics| {
+                    metrics.protocol_detection_timeout(ProtocolDetectionTimeoutLabels::new(ctx))
+                        .timeout();
+                })
+            },
+
+            Event::Retry(ref proxy, ref retry) => {
+                self.update(|metrics| {
+                    let metrics = metrics.retry(DestinationLabels::new(proxy, &retry.authority));
+                    if retry.budget_exhausted {
+                        metrics.budget_exhausted();
+                    } else {
+                        metrics.retry();
+                    }
+                })
+            },
+
+            Event::EndpointsEjected(ref proxy, ref ejected) => {
+                self.update(|metrics| {
+                    metrics.ejection(DestinationLabels::new(proxy, &ejected.authority))
+                        .set_ejected(ejected.ejected);
+                })
+            },
+
+            Event::Queue(ref proxy, ref queue) => {
+                self.update(|metrics| {
+                    let metrics = metrics.queue(DestinationLabels::new(proxy, &queue.authority));
+                    metrics.set_depth(queue.depth);
+                    if let Some(waited) = queue.waited {
+                        metrics.waited(waited);
+                    }
+                })
+            },
+
+            Event::Listener(ref proxy, ref listener) => {
+                self.update(|metrics| {
+                    metrics.listener(ListenerLabels::new(proxy))
+                        .set_connections(listener.open, listener.max);
+                })
+            },
+
+            Event::Reconnect(ref ctx, _) => {
+                self.update(|metrics| {
+                    metrics.reconnect(EndpointLabels::new(ctx)).reconnect();
+                })
+            },
+
+            Event::ProxyError(ref proxy, ref error) => {
+                self.update(|metrics| {
+                    metrics.proxy_error(ProxyErrorLabels::new(proxy, error.reason)).error();
+                })
+            },
         };
     }
 }
@@ -91,6 +150,7 @@ mod test {
         Event,
     };
     use ctx::{self, test_util::* };
+    use proxy_error::Reason;
     use std::time::Duration;
 
     #[test]
@@ -297,4 +357,183 @@ mod test {
         }
     }
 
+    #[test]
+    fn record_protocol_detection_timeout() {
+        let process = process();
+        let proxy = ctx::Proxy::inbound(&process);
+        let server = server(&proxy);
+
+        let (mut r, _) = metrics::new(&process, Duration::from_secs(100));
+        let labels = labels::ProtocolDetectionTimeoutLabels::new(&server);
+
+        r.record_event(&Event::ProtocolDetectionTimeout(server.clone()));
+        r.record_event(&Event::ProtocolDetectionTimeout(server.clone()));
+
+        let lock = r.metrics.lock()
+            .expect("lock");
+        let scope = lock.protocol_detection_timeouts.scopes
+            .get(&labels)
+            .expect("scope should be some after event");
+        assert_eq!(scope.timeout_total(), 2);
+    }
+
+    #[test]
+    fn record_retries() {
+        let process = process();
+        let proxy = ctx::Proxy::outbound(&process);
+
+        let (mut r, _) = metrics::new(&process, Duration::from_secs(100));
+        let retry = event::Retry {
+            authority: "foo.ns.svc.cluster.local:8080".parse().unwrap(),
+            budget_exhausted: false,
+        };
+        let exhausted = event::Retry {
+            budget_exhausted: true,
+            .. retry.clone()
+        };
+        let labels = labels::DestinationLabels::new(&proxy, &retry.authority);
+
+        r.record_event(&Event::Retry(proxy.clone(), retry.clone()));
+        r.record_event(&Event::Retry(proxy.clone(), retry));
+        r.record_event(&Event::Retry(proxy.clone(), exhausted));
+
+        let lock = r.metrics.lock()
+            .expect("lock");
+        let scope = lock.retries.scopes
+            .get(&labels)
+            .expect("scope should be some after event");
+        assert_eq!(scope.retries_total(), 2);
+        assert_eq!(scope.budget_exhausted_total(), 1);
+    }
+
+    #[test]
+    fn record_endpoints_ejected() {
+        let process = process();
+        let proxy = ctx::Proxy::outbound(&process);
+
+        let (mut r, _) = metrics::new(&process, Duration::from_secs(100));
+        let authority = "foo.ns.svc.cluster.local:8080".parse().unwrap();
+        let labels = labels::DestinationLabels::new(&proxy, &authority);
+
+        r.record_event(&Event::EndpointsEjected(proxy.clone(), event::EndpointsEjected {
+            authority: authority.clone(),
+            ejected: 2,
+        }));
+        r.record_event(&Event::EndpointsEjected(proxy.clone(), event::EndpointsEjected {
+            authority,
+            ejected: 1,
+        }));
+
+        let lock = r.metrics.lock()
+            .expect("lock");
+        let scope = lock.ejections.scopes
+            .get(&labels)
+            .expect("scope should be some after event");
+        assert_eq!(scope.ejected_endpoints(), 1);
+    }
+
+    #[test]
+    fn record_queue() {
+        let process = process();
+        let proxy = ctx::Proxy::inbound(&process);
+
+        let (mut r, _) = metrics::new(&process, Duration::from_secs(100));
+        let authority = "127.0.0.1:8080".parse().unwrap();
+        let labels = labels::DestinationLabels::new(&proxy, &authority);
+
+        r.record_event(&Event::Queue(proxy.clone(), event::Queue {
+            authority: authority.clone(),
+            depth: 2,
+            waited: None,
+        }));
+        r.record_event(&Event::Queue(proxy.clone(), event::Queue {
+            authority,
+            depth: 1,
+            waited: Some(Duration::from_millis(20)),
+        }));
+
+        let lock = r.metrics.lock()
+            .expect("lock");
+        let scope = lock.queues.scopes
+            .get(&labels)
+            .expect("scope should be some after event");
+        assert_eq!(scope.queue_depth(), 1);
+        scope.queue_latency().assert_bucket_exactly(20, 1);
+    }
+
+    #[test]
+    fn record_reconnects() {
+        let process = process();
+        let proxy = ctx::Proxy::outbound(&process);
+        let client = client(&proxy, vec![("team", "warriors")]);
+
+        let (mut r, _) = metrics::new(&process, Duration::from_secs(100));
+        let labels = labels::EndpointLabels::new(&client);
+
+        r.record_event(&Event::Reconnect(client.clone(), event::Reconnect {
+            backoff: Duration::from_millis(100),
+        }));
+        r.record_event(&Event::Reconnect(client.clone(), event::Reconnect {
+            backoff: Duration::from_millis(200),
+        }));
+
+        let lock = r.metrics.lock()
+            .expect("lock");
+        let scope = lock.reconnects.scopes
+            .get(&labels)
+            .expect("scope should be some after event");
+        assert_eq!(scope.reconnects_total(), 2);
+    }
+
+    #[test]
+    fn record_proxy_errors() {
+        let process = process();
+        let proxy = ctx::Proxy::outbound(&process);
+
+        let (mut r, _) = metrics::new(&process, Duration::from_secs(100));
+        let timeouts = labels::ProxyErrorLabels::new(&proxy, Reason::Timeout);
+        let connects = labels::ProxyErrorLabels::new(&proxy, Reason::Connect);
+
+        r.record_event(&Event::ProxyError(proxy.clone(), event::ProxyError {
+            reason: Reason::Timeout,
+        }));
+        r.record_event(&Event::ProxyError(proxy.clone(), event::ProxyError {
+            reason: Reason::Timeout,
+        }));
+        r.record_event(&Event::ProxyError(proxy.clone(), event::ProxyError {
+            reason: Reason::Connect,
+        }));
+
+        let lock = r.metrics.lock()
+            .expect("lock");
+        let timeouts = lock.proxy_errors.scopes
+            .get(&timeouts)
+            .expect("scope should be some after event");
+        assert_eq!(timeouts.errors_total(), 2);
+        let connects = lock.proxy_errors.scopes
+            .get(&connects)
+            .expect("scope should be some after event");
+        assert_eq!(connects.errors_total(), 1);
+    }
+
+    #[test]
+    fn record_listener_connections() {
+        let process = process();
+        let proxy = ctx::Proxy::inbound(&process);
+
+        let (mut r, _) = metrics::new(&process, Duration::from_secs(100));
+        let labels = labels::ListenerLabels::new(&proxy);
+
+        r.record_event(&Event::Listener(proxy.clone(), event::Listener { open: 2, max: 10 }));
+        r.record_event(&Event::Listener(proxy.clone(), event::Listener { open: 1, max: 10 }));
+
+        let lock = r.metrics.lock()
+            .expect("lock");
+        let scope = lock.listeners.scopes
+            .get(&labels)
+            .expect("scope should be some after event");
+        assert_eq!(scope.open_connections(), 1);
+        assert_eq!(scope.max_connections(), 10);
+    }
+
 }
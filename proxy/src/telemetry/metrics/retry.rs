@@ -0,0 +1,68 @@
+use std::fmt;
+
+use super::{
+    Counter,
+    DestinationLabels,
+    Metric,
+    Scopes,
+    Stamped,
+};
+
+pub(super) type RetryScopes = Scopes<DestinationLabels, Stamped<RetryMetrics>>;
+
+#[derive(Debug, Default)]
+pub(super) struct RetryMetrics {
+    retries_total: Counter,
+    budget_exhausted_total: Counter,
+}
+
+// ===== impl RetryScopes =====
+
+impl RetryScopes {
+    metrics! {
+        retries_total: Counter {
+            "Total count of failed requests that were dispatched again"
+        },
+        retry_budget_exhausted_total: Counter {
+            "Total count of failed requests that were not retried because the retry budget was exhausted"
+        }
+    }
+}
+
+impl fmt::Display for RetryScopes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.scopes.is_empty() {
+            return Ok(());
+        }
+
+        Self::retries_total.fmt_help(f)?;
+        Self::retries_total.fmt_scopes(f, &self, |s| &s.retries_total)?;
+
+        Self::retry_budget_exhausted_total.fmt_help(f)?;
+        Self::retry_budget_exhausted_total.fmt_scopes(f, &self, |s| &s.budget_exhausted_total)?;
+
+        Ok(())
+    }
+}
+
+// ===== impl RetryMetrics =====
+
+impl RetryMetrics {
+    pub(super) fn retry(&mut self) {
+        self.retries_total.incr();
+    }
+
+    pub(super) fn budget_exhausted(&mut self) {
+        self.budget_exhausted_total.incr();
+    }
+
+    #[cfg(test)]
+    pub(super) fn retries_total(&self) -> u64 {
+        self.retries_total.into()
+    }
+
+    #[cfg(test)]
+    pub(super) fn budget_exhausted_total(&self) -> u64 {
+        self.budget_exhausted_total.into()
+    }
+}
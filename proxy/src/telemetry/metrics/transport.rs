@@ -7,6 +7,7 @@ use super::{
     Gauge,
     Histogram,
     Metric,
+    ProtocolDetectionTimeoutLabels,
     TransportLabels,
     TransportCloseLabels,
     Scopes,
@@ -31,6 +32,14 @@ pub(super) struct CloseMetrics {
     connection_duration: Histogram<latency::Ms>,
 }
 
+pub(super) type DetectionTimeoutScopes =
+    Scopes<ProtocolDetectionTimeoutLabels, Stamped<DetectionTimeoutMetrics>>;
+
+#[derive(Debug, Default)]
+pub(super) struct DetectionTimeoutMetrics {
+    timeout_total: Counter,
+}
+
 // ===== impl OpenScopes =====
 
 impl OpenScopes {
@@ -137,3 +146,39 @@ impl CloseMetrics {
         &self.connection_duration
     }
 }
+
+// ===== impl DetectionTimeoutScopes =====
+
+impl DetectionTimeoutScopes {
+    metrics! {
+        protocol_detection_timeout_total: Counter {
+            "Total count of connections that were forwarded as TCP because protocol detection timed out"
+        }
+    }
+}
+
+impl fmt::Display for DetectionTimeoutScopes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.scopes.is_empty() {
+            return Ok(());
+        }
+
+        Self::protocol_detection_timeout_total.fmt_help(f)?;
+        Self::protocol_detection_timeout_total.fmt_scopes(f, &self, |s| &s.timeout_total)?;
+
+        Ok(())
+    }
+}
+
+// ===== impl DetectionTimeoutMetrics =====
+
+impl DetectionTimeoutMetrics {
+    pub(super) fn timeout(&mut self) {
+        self.timeout_total.incr();
+    }
+
+    #[cfg(test)]
+    pub(super) fn timeout_total(&self) -> u64 {
+        self.timeout_total.into()
+    }
+}
@@ -101,6 +101,9 @@ pub struct ResponseBodyInner {
     frames_sent: u32,
     request_open: Instant,
     response_open: Instant,
+    /// The `grpc-status` in the response's headers, if any, which is used
+    /// when the trailers don't have one.
+    grpc_status: Option<u32>,
 }
 
 
@@ -318,13 +321,15 @@ where
                         )
                     });
 
+                    // A trailers-only response, such as a gRPC error, has its
+                    // `grpc-status` in its headers.
+                    let grpc_status = rsp.headers()
+                        .get(GRPC_STATUS)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|s| s.parse::<u32>().ok());
+
                     if rsp.body().is_end_stream() {
                         handle.send(|| {
-                            let grpc_status = rsp.headers()
-                                .get(GRPC_STATUS)
-                                .and_then(|v| v.to_str().ok())
-                                .and_then(|s| s.parse::<u32>().ok());
-
                             event::Event::StreamResponseEnd(
                                 Arc::clone(&ctx),
                                 event::StreamResponseEnd {
@@ -346,6 +351,7 @@ where
                             frames_sent: 0,
                             request_open,
                             response_open: Instant::now(),
+                            grpc_status,
                         })
                     }
                 });
@@ -529,13 +535,14 @@ impl BodySensor for ResponseBodyInner {
             response_open,
             bytes_sent,
             frames_sent,
+            grpc_status: headers_grpc_status,
         } = self;
 
         handle.send(||
             event::Event::StreamResponseEnd(
                 Arc::clone(&ctx),
                 event::StreamResponseEnd {
-                    grpc_status,
+                    grpc_status: grpc_status.or(headers_grpc_status),
                     since_request_open: request_open.elapsed(),
                     since_response_open: response_open.elapsed(),
                     bytes_sent,
@@ -646,3 +653,90 @@ where
         self.inner.new_service().map(TimestampRequestOpen::new)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use futures_mpsc_lossy;
+
+    use ctx::{self, test_util::*};
+    use super::*;
+
+    #[derive(Debug)]
+    struct EmptyBody;
+
+    impl Body for EmptyBody {
+        type Data = Bytes;
+
+        fn is_end_stream(&self) -> bool {
+            true
+        }
+
+        fn poll_data(&mut self) -> Poll<Option<Bytes>, h2::Error> {
+            Ok(Async::Ready(None))
+        }
+
+        fn poll_trailers(&mut self) -> Poll<Option<http::HeaderMap>, h2::Error> {
+            Ok(Async::Ready(None))
+        }
+    }
+
+    /// Answers every request with a trailers-only gRPC response.
+    struct TrailersOnly;
+
+    impl Service for TrailersOnly {
+        type Request = http::Request<RequestBody<EmptyBody>>;
+        type Response = http::Response<EmptyBody>;
+        type Error = client::Error;
+        type Future = future::FutureResult<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self) -> Poll<(), Self::Error> {
+            Ok(Async::Ready(()))
+        }
+
+        fn call(&mut self, _: Self::Request) -> Self::Future {
+            let rsp = http::Response::builder()
+                .header("content-type", "application/grpc")
+                .header(GRPC_STATUS, "14")
+                .body(EmptyBody)
+                .unwrap();
+            future::ok(rsp)
+        }
+    }
+
+    #[test]
+    fn trailers_only_responses_end_with_grpc_status_from_headers() {
+        let (tx, rx) = futures_mpsc_lossy::channel(8);
+
+        let process = process();
+        let proxy = ctx::Proxy::outbound(&process);
+        let server = server(&proxy);
+        let client = client(&proxy, vec![("service", "svc")]);
+
+        let mut sensor = Http {
+            service: TrailersOnly,
+            handle: super::super::Handle(Some(tx)),
+            next_id: Arc::new(AtomicUsize::new(0)),
+            client_ctx: client,
+            _p: PhantomData,
+        };
+
+        let mut req = http::Request::get("http://svc.test/").body(EmptyBody).unwrap();
+        req.extensions_mut().insert(server);
+        req.extensions_mut().insert(RequestOpen(Instant::now()));
+
+        let rsp = sensor.call(req).wait().expect("response");
+        assert!(rsp.body().is_end_stream());
+        drop((sensor, rsp));
+
+        let end = rx.collect().wait().expect("events")
+            .into_iter()
+            .filter_map(|ev| match ev {
+                Event::StreamResponseEnd(_, end) => Some(end),
+                _ => None,
+            })
+            .next()
+            .expect("response should end");
+        assert_eq!(end.grpc_status, Some(14));
+    }
+}
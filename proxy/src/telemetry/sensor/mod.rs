@@ -1,6 +1,6 @@
 use std::sync::Arc;
 use std::sync::atomic::AtomicUsize;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use futures_mpsc_lossy::Sender;
 use http::{Request, Response};
@@ -10,6 +10,7 @@ use tower_service::NewService;
 use tower_h2::{client, Body};
 
 use ctx;
+use proxy_error;
 use telemetry::event;
 
 pub mod http;
@@ -68,6 +69,79 @@ impl Sensors {
         Transport::open(io, opened_at, &self.0, ctx)
     }
 
+    /// Records that protocol detection timed out on a server connection.
+    pub fn protocol_detection_timeout(&self, ctx: &Arc<ctx::transport::Server>) {
+        let mut handle = self.0.clone();
+        handle.send(|| event::Event::ProtocolDetectionTimeout(Arc::clone(ctx)));
+    }
+
+    /// Records that a failed request was retried, or that it wasn't because
+    /// the retry budget was exhausted.
+    pub fn retry(
+        &self,
+        proxy: &Arc<ctx::Proxy>,
+        authority: &::http::uri::Authority,
+        budget_exhausted: bool,
+    ) {
+        let mut handle = self.0.clone();
+        handle.send(|| event::Event::Retry(Arc::clone(proxy), event::Retry {
+            authority: authority.clone(),
+            budget_exhausted,
+        }));
+    }
+
+    /// Records the number of a destination's endpoints that are ejected from
+    /// its load balancer.
+    pub fn endpoints_ejected(
+        &self,
+        proxy: &Arc<ctx::Proxy>,
+        authority: &::http::uri::Authority,
+        ejected: usize,
+    ) {
+        let mut handle = self.0.clone();
+        handle.send(|| event::Event::EndpointsEjected(Arc::clone(proxy), event::EndpointsEjected {
+            authority: authority.clone(),
+            ejected,
+        }));
+    }
+
+    /// Records that a request entered a route's queue, or left it after
+    /// `waited`.
+    pub fn queue(
+        &self,
+        proxy: &Arc<ctx::Proxy>,
+        authority: &::http::uri::Authority,
+        depth: usize,
+        waited: Option<Duration>,
+    ) {
+        let mut handle = self.0.clone();
+        handle.send(|| event::Event::Queue(Arc::clone(proxy), event::Queue {
+            authority: authority.clone(),
+            depth,
+            waited,
+        }));
+    }
+
+    /// Records the number of connections that are open on one of the proxy's
+    /// listeners, which may have at most `max` open.
+    pub fn listener(&self, proxy: &Arc<ctx::Proxy>, open: usize, max: usize) {
+        let mut handle = self.0.clone();
+        handle.send(|| event::Event::Listener(Arc::clone(proxy), event::Listener { open, max }));
+    }
+
+    /// Records that a connection to an endpoint failed, and that it will be
+    /// reconnected after `backoff`.
+    pub fn reconnect(&self, ctx: &Arc<ctx::transport::Client>, backoff: Duration) {
+        let mut handle = self.0.clone();
+        handle.send(|| event::Event::Reconnect(Arc::clone(ctx), event::Reconnect { backoff }));
+    }
+
+    /// Records that the proxy failed a request for `reason`.
+    pub fn proxy_error(&self, proxy: &Arc<ctx::Proxy>, reason: proxy_error::Reason) {
+        let mut handle = self.0.clone();
+        handle.send(|| event::Event::ProxyError(Arc::clone(proxy), event::ProxyError { reason }));
+    }
+
     pub fn connect<C>(&self, connect: C, ctx: &Arc<ctx::transport::Client>) -> Connect<C>
     where
         C: tokio_connect::Connect,
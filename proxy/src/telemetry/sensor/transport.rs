@@ -187,6 +187,10 @@ impl<T: AsyncRead + AsyncWrite + Peek> Peek for Transport<T> {
     fn peeked(&self) -> &[u8] {
         self.0.peeked()
     }
+
+    fn poll_peek_more(&mut self) -> Poll<usize, io::Error> {
+        self.sense_err(|io| io.poll_peek_more())
+    }
 }
 
 // === impl Connect ===
@@ -7,6 +7,7 @@ use http;
 use ipnet::{Contains, Ipv4Net, Ipv6Net};
 
 use super::Event;
+use config::Addr;
 use conduit_proxy_controller_grpc::common::ip_address;
 use conduit_proxy_controller_grpc::tap::observe_request;
 use convert::*;
@@ -97,11 +98,11 @@ impl Match {
 
             Match::Destination(ref dst) => match *ev {
                 Event::StreamRequestOpen(ref req) | Event::StreamRequestFail(ref req, _) => {
-                    dst.matches(&req.client.remote)
+                    dst.matches_addr(&req.client.remote)
                 }
                 Event::StreamResponseOpen(ref rsp, _) |
                 Event::StreamResponseFail(ref rsp, _) |
-                Event::StreamResponseEnd(ref rsp, _) => dst.matches(&rsp.request.client.remote),
+                Event::StreamResponseEnd(ref rsp, _) => dst.matches_addr(&rsp.request.client.remote),
                 _ => false,
             },
 
@@ -237,6 +238,13 @@ impl TcpMatch {
             TcpMatch::Net(ref net) => net.matches(&addr.ip()),
         }
     }
+
+    /// Unix sockets never match, since they have neither an IP nor a port.
+    fn matches_addr(&self, addr: &Addr) -> bool {
+        addr.socket_addr()
+            .map(|addr| self.matches(&addr))
+            .unwrap_or(false)
+    }
 }
 
 impl<'a> TryFrom<&'a observe_request::match_::Tcp> for TcpMatch {
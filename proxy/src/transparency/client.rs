@@ -1,3 +1,5 @@
+use std::io;
+
 use futures::{Async, Future, Poll};
 use h2;
 use http;
@@ -10,7 +12,8 @@ use tower_h2::{self, Body};
 use bind;
 use telemetry::sensor::http::RequestBody;
 use super::glue::{BodyStream, HttpBody, HyperConnect};
-use super::h1::UriIsAbsoluteForm;
+use super::h1::{self, UriIsAbsoluteForm};
+use super::upgrade;
 
 type HyperClient<C, B> =
     hyper::Client<HyperConnect<C>, BodyStream<RequestBody<B>>>;
@@ -27,7 +30,7 @@ enum ClientInner<C, B>
 where
     B: tower_h2::Body + 'static,
 {
-    Http1(HyperClient<C, B>),
+    Http1(HyperClient<C, B>, C),
     Http2(tower_h2::client::Connect<C, Handle, RequestBody<B>>),
 }
 
@@ -45,7 +48,7 @@ where
     B: tower_h2::Body + 'static,
     C: Connect + 'static,
 {
-    Http1(Option<HyperClient<C, B>>),
+    Http1(Option<(HyperClient<C, B>, C)>),
     Http2(tower_h2::client::ConnectFuture<C, Handle, RequestBody<B>>),
 }
 
@@ -63,7 +66,7 @@ where
     B: tower_h2::Body + 'static,
     C: Connect
 {
-    Http1(HyperClient<C, B>),
+    Http1(HyperClient<C, B>, C),
     Http2(tower_h2::client::Connection<
         <C as Connect>::Connected,
         Handle,
@@ -90,14 +93,14 @@ where
                     // to configure the connector with whether or not the
                     // request URI is in absolute form, since this will be
                     // handled at the connection level soon.
-                    .connector(HyperConnect::new(connect))
+                    .connector(HyperConnect::new(connect.clone()))
                     .body()
                     // hyper should never try to automatically set the Host
                     // header, instead always just passing whatever we received.
                     .set_host(false)
                     .build(&executor);
                 Client {
-                    inner: ClientInner::Http1(h1),
+                    inner: ClientInner::Http1(h1, connect),
                 }
             },
             bind::Protocol::Http2 => {
@@ -119,6 +122,7 @@ impl<C, B> NewService for Client<C, B>
 where
     C: Connect + Clone + 'static,
     C::Future: 'static,
+    C::Connected: Send + 'static,
     B: tower_h2::Body + 'static,
 {
     type Request = bind::HttpRequest<B>;
@@ -130,8 +134,8 @@ where
 
     fn new_service(&self) -> Self::Future {
         let inner = match self.inner {
-            ClientInner::Http1(ref h1) => {
-                ClientNewServiceFutureInner::Http1(Some(h1.clone()))
+            ClientInner::Http1(ref h1, ref connect) => {
+                ClientNewServiceFutureInner::Http1(Some((h1.clone(), connect.clone())))
             },
             ClientInner::Http2(ref h2) => {
                 ClientNewServiceFutureInner::Http2(h2.new_service())                        },
@@ -153,7 +157,8 @@ where
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
         let inner = match self.inner {
             ClientNewServiceFutureInner::Http1(ref mut h1) => {
-                ClientServiceInner::Http1(h1.take().expect("poll more than once"))
+                let (h1, connect) = h1.take().expect("poll more than once");
+                ClientServiceInner::Http1(h1, connect)
             },
             ClientNewServiceFutureInner::Http2(ref mut h2) => {
                 let s = try_ready!(h2.poll());
@@ -170,6 +175,7 @@ impl<C, B> Service for ClientService<C, B>
 where
     C: Connect + 'static,
     C::Future: 'static,
+    C::Connected: Send + 'static,
     B: tower_h2::Body + 'static,
 {
     type Request = bind::HttpRequest<B>;
@@ -179,7 +185,7 @@ where
 
     fn poll_ready(&mut self) -> Poll<(), Self::Error> {
         match self.inner {
-            ClientServiceInner::Http1(_) => Ok(Async::Ready(())),
+            ClientServiceInner::Http1(..) => Ok(Async::Ready(())),
             ClientServiceInner::Http2(ref mut h2) => h2.poll_ready(),
         }
     }
@@ -188,7 +194,15 @@ where
         use http::header::CONTENT_LENGTH;
 
         match self.inner {
-            ClientServiceInner::Http1(ref h1) => {
+            ClientServiceInner::Http1(ref h1, ref connect) => {
+                // hyper can't hand over an upgraded connection, so upgrades
+                // are sent on a connection of their own.
+                if h1::is_upgrade(&req) {
+                    trace!("upgrading http/1 connection");
+                    let f = upgrade::handshake(connect.connect(), &req);
+                    return ClientServiceFuture::Upgrade(f);
+                }
+
                 // This sentinel may be set in h1::normalize_our_view_of_uri
                 // when the original request-target was in absolute-form. In
                 // that case, for hyper 0.11.x, we need to call `req.set_proxy`.
@@ -237,6 +251,7 @@ where
 pub enum ClientServiceFuture {
     Http1(hyper::client::FutureResponse),
     Http2(tower_h2::client::ResponseFuture),
+    Upgrade(Box<Future<Item=http::Response<HttpBody>, Error=io::Error>>),
 }
 
 impl Future for ClientServiceFuture {
@@ -255,7 +270,7 @@ impl Future for ClientServiceFuture {
                     Ok(Async::NotReady) => Ok(Async::NotReady),
                     Err(e) => {
                         debug!("http/1 client error: {}", e);
-                        Err(h2::Reason::INTERNAL_ERROR.into())
+                        Err(http1_error_reason(&e).into())
                     }
                 }
             },
@@ -263,8 +278,25 @@ impl Future for ClientServiceFuture {
                 let res = try_ready!(f.poll());
                 let res = res.map(HttpBody::Http2);
                 Ok(Async::Ready(res))
+            },
+            ClientServiceFuture::Upgrade(ref mut f) => {
+                f.poll().map_err(|e| {
+                    debug!("http/1 upgrade error: {}", e);
+                    h2::Reason::CONNECT_ERROR.into()
+                })
             }
         }
     }
 }
 
+/// Describes an HTTP/1 client error with an HTTP/2 reason.
+///
+/// I/O errors, including failures to connect, are described as
+/// `CONNECT_ERROR`, so that they can be told apart from other failures.
+fn http1_error_reason(e: &hyper::Error) -> h2::Reason {
+    match *e {
+        hyper::Error::Io(_) => h2::Reason::CONNECT_ERROR,
+        _ => h2::Reason::INTERNAL_ERROR,
+    }
+}
+
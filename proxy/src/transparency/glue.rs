@@ -206,8 +206,10 @@ where
     >;
 
     fn call(&self, req: Self::Request) -> Self::Future {
+        // `CONNECT` is handled by `upgrade` when it's the first request on
+        // a connection, but hyper can't hand over the connection afterwards.
         if let &hyper::Method::Connect = req.method() {
-            debug!("HTTP/1.1 CONNECT not supported");
+            debug!("HTTP/1.1 CONNECT not supported after the first request");
             let res = hyper::Response::new()
                 .with_status(hyper::StatusCode::BadGateway);
             return Either::B(future::ok(res));
@@ -73,6 +73,22 @@ fn set_authority(uri: &mut http::Uri, auth: Authority) {
     *uri = new;
 }
 
+/// Returns true if the request asks to take over its connection, either to
+/// switch protocols or, with `CONNECT`, to open a tunnel.
+pub fn is_upgrade<B>(req: &http::Request<B>) -> bool {
+    if req.method() == &http::Method::CONNECT {
+        return true;
+    }
+
+    // The `Upgrade` header only applies if `Connection` lists it.
+    let connection_upgrade = req.headers().get_all(http::header::CONNECTION)
+        .iter()
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(','))
+        .any(|name| name.trim().eq_ignore_ascii_case("upgrade"));
+    connection_upgrade && req.headers().contains_key(http::header::UPGRADE)
+}
+
 pub fn strip_connection_headers(headers: &mut http::HeaderMap) {
     let conn_val = if let Some(val) = headers.remove(http::header::CONNECTION) {
         val
@@ -4,6 +4,7 @@ pub mod h1;
 mod protocol;
 mod server;
 mod tcp;
+mod upgrade;
 
 pub use self::client::Client;
 pub use self::glue::HttpBody;
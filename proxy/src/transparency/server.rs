@@ -1,26 +1,29 @@
-use std::fmt;
+use std::{fmt, io};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use futures::Future;
+use futures::{future::{self, Either}, Async, Future, Poll};
 use http;
 use hyper;
 use indexmap::IndexSet;
-use tokio_core::reactor::Handle;
+use tokio_core::reactor::{Handle, Timeout};
 use tokio_io::{AsyncRead, AsyncWrite};
 use tower_service::NewService;
 use tower_h2;
+use conduit_proxy_router::Router;
 
 use connection::{Connection, Peek};
 use ctx::Proxy as ProxyCtx;
 use ctx::transport::{Server as ServerCtx};
 use drain;
+use tcp_outbound::TcpOutbound;
 use telemetry::Sensors;
-use transport::GetOriginalDst;
+use transport::{proxy_protocol, tls, GetOriginalDst};
 use super::glue::{HttpBody, HttpBodyNewSvc, HyperServerSvc};
 use super::protocol::Protocol;
 use super::tcp;
+use super::upgrade;
 
 /// A protocol-transparent Server!
 ///
@@ -33,7 +36,9 @@ where
     S::Future: 'static,
     B: tower_h2::Body,
 {
-    disable_protocol_detection_ports: IndexSet<u16>,
+    accept_proxy_protocol: bool,
+    detection_timeout: Option<Duration>,
+    disable_protocol_detection_ports: Arc<IndexSet<u16>>,
     drain_signal: drain::Watch,
     executor: Handle,
     get_orig_dst: G,
@@ -44,6 +49,7 @@ where
     proxy_ctx: Arc<ProxyCtx>,
     sensors: Sensors,
     tcp: tcp::Proxy,
+    tls_config: Option<tls::ServerConfig>,
 }
 
 impl<S, B, G> Server<S, B, G>
@@ -53,6 +59,7 @@ where
         Response = http::Response<B>
     > + Clone + 'static,
     S::Future: 'static,
+    S::Service: 'static,
     S::Error: fmt::Debug,
     S::InitError: fmt::Debug,
     B: tower_h2::Body + 'static,
@@ -66,14 +73,27 @@ where
         get_orig_dst: G,
         stack: S,
         tcp_connect_timeout: Duration,
+        tcp_router: Option<Router<TcpOutbound>>,
+        detection_timeout: Option<Duration>,
         disable_protocol_detection_ports: IndexSet<u16>,
+        accept_proxy_protocol: bool,
+        send_proxy_protocol: bool,
+        tls_config: Option<tls::ServerConfig>,
         drain_signal: drain::Watch,
         executor: Handle,
     ) -> Self {
         let recv_body_svc = HttpBodyNewSvc::new(stack.clone());
-        let tcp = tcp::Proxy::new(tcp_connect_timeout, sensors.clone(), &executor);
+        let tcp = tcp::Proxy::new(
+            tcp_connect_timeout,
+            send_proxy_protocol,
+            tcp_router,
+            sensors.clone(),
+            &executor,
+        );
         Server {
-            disable_protocol_detection_ports,
+            accept_proxy_protocol,
+            detection_timeout,
+            disable_protocol_detection_ports: Arc::new(disable_protocol_detection_ports),
             drain_signal,
             executor: executor.clone(),
             get_orig_dst,
@@ -84,6 +104,7 @@ where
             proxy_ctx,
             sensors,
             tcp,
+            tls_config,
         }
     }
 
@@ -93,100 +114,363 @@ where
     /// what protocol the connection is speaking. From there, the connection
     /// will be mapped into respective services, and spawned into an
     /// executor.
+    ///
+    /// If the PROXY protocol is accepted, the connection's header is read
+    /// first, and its addresses replace the connection's own. A header that
+    /// hasn't been read by the detection timeout closes the connection. If TLS is
+    /// configured and the peer begins a TLS handshake, TLS is terminated
+    /// before the protocol is detected. A handshake that hasn't finished by
+    /// the detection timeout closes the connection.
+    ///
+    /// If the client sends nothing before the detection timeout, as with
+    /// server-first protocols, the connection is forwarded as TCP.
     pub fn serve(&self, connection: Connection, remote_addr: SocketAddr) {
         let opened_at = Instant::now();
 
-        // create Server context
         let orig_dst = connection.original_dst_addr(&self.get_orig_dst);
         let local_addr = connection.local_addr().unwrap_or(self.listen_addr);
-        let srv_ctx = ServerCtx::new(
-            &self.proxy_ctx,
-            &local_addr,
-            &remote_addr,
-            &orig_dst,
-        );
 
-        // record telemetry
-        let io = self.sensors.accept(connection, opened_at, &srv_ctx);
-
-        // We are using the port from the connection's SO_ORIGINAL_DST to
-        // determine whether to skip protocol detection, not any port that
-        // would be found after doing discovery.
-        let disable_protocol_detection = orig_dst
-            .map(|addr| {
-                self.disable_protocol_detection_ports.contains(&addr.port())
-            })
-            .unwrap_or(false);
-
-        if disable_protocol_detection {
-            trace!("protocol detection disabled for {:?}", orig_dst);
-            let fut = tcp_serve(
-                &self.tcp,
-                io,
-                srv_ctx,
-                self.drain_signal.clone(),
-            );
-            self.executor.spawn(fut);
-            return;
-        }
+        // Reading the PROXY header, the TLS peek, the TLS handshake, and the
+        // protocol detection peek share a deadline, so that a slow client is
+        // only waited on once.
+        let detection_deadline = self.detection_timeout.map(|t| opened_at + t);
+
+        let addressed: Box<Future<Item=(Connection, SocketAddr, Option<SocketAddr>), Error=()>> = if self.accept_proxy_protocol {
+            // A client that never finishes its header is closed once the
+            // deadline passes.
+            let fut = UntilDeadline::new(connection.read_proxy_header(), detection_deadline, &self.executor)
+                .map_err(|e| debug!("proxy protocol error: {}", e))
+                .map(move |(connection, header)| match header {
+                    proxy_protocol::Header::Proxy { source, destination } => {
+                        trace!("proxy protocol source={} destination={}", source, destination);
+                        (connection, source, Some(destination))
+                    },
+                    proxy_protocol::Header::Local => (connection, remote_addr, orig_dst),
+                });
+            Box::new(fut)
+        } else {
+            Box::new(future::ok((connection, remote_addr, orig_dst)))
+        };
 
-        // try to sniff protocol
+        let disable_protocol_detection_ports = self.disable_protocol_detection_ports.clone();
+        let executor = self.executor.clone();
+        let tls_config = self.tls_config.clone();
         let h1 = self.h1.clone();
         let h2 = self.h2.clone();
         let tcp = self.tcp.clone();
         let new_service = self.new_service.clone();
         let drain_signal = self.drain_signal.clone();
-        let fut = io.peek()
-            .map_err(|e| debug!("peek error: {}", e))
-            .and_then(move |io| -> Box<Future<Item=(), Error=()>> {
-                if let Some(proto) = Protocol::detect(io.peeked()) {
-                    match proto {
-                        Protocol::Http1 => {
-                            trace!("transparency detected HTTP/1");
-
-                            Box::new(new_service.new_service()
-                                .map_err(|_| ())
-                                .and_then(move |s| {
-                                    let svc = HyperServerSvc::new(s, srv_ctx);
-                                    drain_signal
-                                        .watch(h1.serve_connection(io, svc), |conn| {
-                                            conn.disable_keep_alive();
-                                        })
-                                        .map(|_| ())
-                                        .map_err(|e| trace!("http1 server error: {:?}", e))
-                                }))
+        let proxy_ctx = self.proxy_ctx.clone();
+        let sensors = self.sensors.clone();
+        let fut = addressed.and_then(move |(connection, remote_addr, orig_dst)| -> Box<Future<Item=(), Error=()>> {
+            // We are using the port from the connection's SO_ORIGINAL_DST to
+            // determine whether to skip protocol detection, not any port that
+            // would be found after doing discovery.
+            let disable_protocol_detection = orig_dst
+                .map(|addr| {
+                    disable_protocol_detection_ports.contains(&addr.port())
+                })
+                .unwrap_or(false);
+
+            if disable_protocol_detection {
+                trace!("protocol detection disabled for {:?}", orig_dst);
+                let srv_ctx = ServerCtx::new(
+                    &proxy_ctx,
+                    &local_addr,
+                    &remote_addr,
+                    &orig_dst,
+                    None,
+                );
+                let io = sensors.accept(connection, opened_at, &srv_ctx);
+                return tcp_serve(
+                    &tcp,
+                    io,
+                    srv_ctx,
+                    drain_signal,
+                );
+            }
+
+            let connection: Box<Future<Item=Connection, Error=()>> = match tls_config {
+                None => Box::new(future::ok(connection)),
+                Some(tls_config) => {
+                    let tls_executor = executor.clone();
+                    let fut = PeekComplete::new(
+                        connection,
+                        tls::can_detect_client_hello,
+                        detection_deadline,
+                        &executor,
+                    )
+                        .map_err(|e| debug!("peek error: {}", e))
+                        .and_then(move |connection| {
+                            // If too little was peeked, TLS is not attempted
+                            // and detection continues, or times out, below.
+                            if tls::is_client_hello(connection.peeked()) {
+                                trace!("transparency detected TLS");
+                                // The handshake must also finish before the
+                                // deadline, or a peer could hold the
+                                // connection by never finishing it.
+                                let accept = connection.accept_tls(&tls_config);
+                                let fut = UntilDeadline::new(accept, detection_deadline, &tls_executor)
+                                    .map_err(|e| debug!("tls handshake error: {}", e));
+                                Either::A(fut)
+                            } else {
+                                Either::B(future::ok(connection))
+                            }
+                        });
+                    Box::new(fut)
+                },
+            };
+
+            // try to sniff protocol
+            let detection_sensors = sensors.clone();
+            let head_executor = executor.clone();
+            let fut = connection
+                .and_then(move |connection| {
+                    // create Server context
+                    let srv_ctx = ServerCtx::new(
+                        &proxy_ctx,
+                        &local_addr,
+                        &remote_addr,
+                        &orig_dst,
+                        connection.tls_peer_identity(),
+                    );
+
+                    // record telemetry
+                    let io = sensors.accept(connection, opened_at, &srv_ctx);
+
+                    PeekUntil::new(io, detection_deadline, &executor)
+                        .map_err(|e| debug!("peek error: {}", e))
+                        .map(move |peeked| (peeked, srv_ctx))
+                })
+                .and_then(move |(peeked, srv_ctx)| -> Box<Future<Item=(), Error=()>> {
+                    let io = match peeked {
+                        Peeked::Bytes(io) => io,
+                        Peeked::TimedOut(io) => {
+                            debug!("protocol detection timed out, treating as TCP");
+                            detection_sensors.protocol_detection_timeout(&srv_ctx);
+                            return tcp_serve(
+                                &tcp,
+                                io,
+                                srv_ctx,
+                                drain_signal,
+                            );
                         },
-                        Protocol::Http2 => {
-                            trace!("transparency detected HTTP/2");
+                    };
+
+                    if let Some(proto) = Protocol::detect(io.peeked()) {
+                        match proto {
+                            Protocol::Http1 => {
+                                trace!("transparency detected HTTP/1");
+
+                                // An upgrade is only detected once its whole
+                                // head has been received, which may take
+                                // several reads.
+                                let fut = PeekComplete::new(
+                                    io,
+                                    upgrade::is_request_head_complete,
+                                    detection_deadline,
+                                    &head_executor,
+                                )
+                                    .map_err(|e| debug!("peek error: {}", e))
+                                    .and_then(move |io| -> Box<Future<Item=(), Error=()>> {
+                                        // Upgrades are only recognized as the
+                                        // first request on a connection, as
+                                        // hyper can't hand over the
+                                        // connection afterwards.
+                                        let upgrade = upgrade::parse_request(io.peeked());
+                                        if let Some((req, head_len)) = upgrade {
+                                            trace!("transparency detected HTTP/1 upgrade");
+                                            let fut = new_service.new_service()
+                                                .map_err(|e| debug!("upgrade service error: {:?}", e))
+                                                .and_then(move |s| {
+                                                    upgrade::serve(io, req, head_len, s, srv_ctx)
+                                                });
+
+                                            // As with TCP, the upgraded
+                                            // connection is left to finish on
+                                            // its own when draining.
+                                            return Box::new(drain_signal.watch(fut, |_| ()));
+                                        }
+
+                                        Box::new(new_service.new_service()
+                                            .map_err(|_| ())
+                                            .and_then(move |s| {
+                                                let svc = HyperServerSvc::new(s, srv_ctx);
+                                                drain_signal
+                                                    .watch(h1.serve_connection(io, svc), |conn| {
+                                                        conn.disable_keep_alive();
+                                                    })
+                                                    .map(|_| ())
+                                                    .map_err(|e| trace!("http1 server error: {:?}", e))
+                                            }))
+                                    });
+                                Box::new(fut)
+                            },
+                            Protocol::Http2 => {
+                                trace!("transparency detected HTTP/2");
 
-                            let set_ctx = move |request: &mut http::Request<()>| {
-                                request.extensions_mut().insert(srv_ctx.clone());
-                            };
+                                let set_ctx = move |request: &mut http::Request<()>| {
+                                    request.extensions_mut().insert(srv_ctx.clone());
+                                };
 
-                            let fut = drain_signal
-                                .watch(h2.serve_modified(io, set_ctx), |conn| {
-                                    conn.graceful_shutdown();
-                                })
-                                .map_err(|e| trace!("h2 server error: {:?}", e));
+                                let fut = drain_signal
+                                    .watch(h2.serve_modified(io, set_ctx), |conn| {
+                                        conn.graceful_shutdown();
+                                    })
+                                    .map_err(|e| trace!("h2 server error: {:?}", e));
 
-                            Box::new(fut)
+                                Box::new(fut)
+                            }
                         }
+                    } else {
+                        trace!("transparency did not detect protocol, treating as TCP");
+                        tcp_serve(
+                            &tcp,
+                            io,
+                            srv_ctx,
+                            drain_signal,
+                        )
                     }
-                } else {
-                    trace!("transparency did not detect protocol, treating as TCP");
-                    tcp_serve(
-                        &tcp,
-                        io,
-                        srv_ctx,
-                        drain_signal,
-                    )
-                }
-            });
+                });
+            Box::new(fut)
+        });
 
         self.executor.spawn(fut);
     }
 }
 
+/// The result of peeking on a connection before a deadline.
+enum Peeked<T> {
+    /// Some bytes were peeked, or the connection was closed.
+    Bytes(T),
+
+    /// The deadline passed before anything was received.
+    TimedOut(T),
+}
+
+/// A future that peeks on `T` until it receives bytes or the deadline, if
+/// there is one, passes.
+struct PeekUntil<T> {
+    io: Option<T>,
+    timeout: Option<Timeout>,
+}
+
+/// A future that peeks on `T` until the peeked bytes are `complete`, the
+/// connection is closed, or the deadline, if there is one, passes.
+struct PeekComplete<T> {
+    io: Option<T>,
+    complete: fn(&[u8]) -> bool,
+    timeout: Option<Timeout>,
+}
+
+/// A future that fails if `F` hasn't completed by the deadline, if there is
+/// one.
+struct UntilDeadline<F> {
+    inner: F,
+    timeout: Option<Timeout>,
+}
+
+impl<T: Peek> PeekUntil<T> {
+    fn new(io: T, deadline: Option<Instant>, executor: &Handle) -> Self {
+        PeekUntil {
+            io: Some(io),
+            timeout: deadline.map(|d| Timeout::new_at(d, executor).expect("reactor gone")),
+        }
+    }
+}
+
+impl<T: Peek> Future for PeekUntil<T> {
+    type Item = Peeked<T>;
+    type Error = ::std::io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        let ready = self.io.as_mut().expect("polled after completed").poll_peek()?.is_ready();
+        if ready {
+            let io = self.io.take().expect("polled after completed");
+            return Ok(Async::Ready(Peeked::Bytes(io)));
+        }
+
+        match self.timeout {
+            Some(ref mut timeout) => try_ready!(timeout.poll()),
+            None => return Ok(Async::NotReady),
+        }
+        let io = self.io.take().expect("polled after completed");
+        Ok(Async::Ready(Peeked::TimedOut(io)))
+    }
+}
+
+impl<T: Peek> PeekComplete<T> {
+    fn new(
+        io: T,
+        complete: fn(&[u8]) -> bool,
+        deadline: Option<Instant>,
+        executor: &Handle,
+    ) -> Self {
+        PeekComplete {
+            io: Some(io),
+            complete,
+            timeout: deadline.map(|d| Timeout::new_at(d, executor).expect("reactor gone")),
+        }
+    }
+}
+
+impl<T: Peek> Future for PeekComplete<T> {
+    type Item = T;
+    type Error = ::std::io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            let io = self.io.as_mut().expect("polled after completed");
+            if (self.complete)(io.peeked()) {
+                break;
+            }
+
+            match io.poll_peek_more()? {
+                // The connection was closed before the peeked bytes were
+                // complete.
+                Async::Ready(0) => break,
+                Async::Ready(_) => continue,
+                Async::NotReady => {}
+            }
+
+            match self.timeout {
+                Some(ref mut timeout) => try_ready!(timeout.poll()),
+                None => return Ok(Async::NotReady),
+            }
+            trace!("detection deadline passed before the peeked bytes were complete");
+            break;
+        }
+
+        Ok(Async::Ready(self.io.take().expect("polled after completed")))
+    }
+}
+
+impl<F: Future<Error = io::Error>> UntilDeadline<F> {
+    fn new(inner: F, deadline: Option<Instant>, executor: &Handle) -> Self {
+        UntilDeadline {
+            inner,
+            timeout: deadline.map(|d| Timeout::new_at(d, executor).expect("reactor gone")),
+        }
+    }
+}
+
+impl<F: Future<Error = io::Error>> Future for UntilDeadline<F> {
+    type Item = F::Item;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Async::Ready(item) = self.inner.poll()? {
+            return Ok(Async::Ready(item));
+        }
+
+        match self.timeout {
+            Some(ref mut timeout) => try_ready!(timeout.poll()),
+            None => return Ok(Async::NotReady),
+        }
+        Err(io::Error::new(io::ErrorKind::TimedOut, "detection deadline passed"))
+    }
+}
+
 fn tcp_serve<T: AsyncRead + AsyncWrite + 'static>(
     tcp: &tcp::Proxy,
     io: T,
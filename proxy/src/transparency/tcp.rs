@@ -3,30 +3,50 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use bytes::{Buf, BufMut};
-use futures::{future, Async, Future, Poll};
+use futures::{future::{self, Either}, Async, Future, Poll};
 use tokio_connect::Connect;
 use tokio_core::reactor::Handle;
-use tokio_io::{AsyncRead, AsyncWrite};
+use tokio_io::{io::write_all, AsyncRead, AsyncWrite};
+use tower_service::Service;
+use conduit_proxy_router::Router;
 
 use ctx::transport::{Client as ClientCtx, Server as ServerCtx};
+use tcp_outbound::TcpOutbound;
 use telemetry::Sensors;
 use timeout::Timeout;
-use transport;
+use transport::{self, proxy_protocol};
 
 /// TCP Server Proxy
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Proxy {
     connect_timeout: Duration,
     executor: Handle,
+    router: Option<Router<TcpOutbound>>,
+    send_proxy_protocol: bool,
     sensors: Sensors,
 }
 
 impl Proxy {
     /// Create a new TCP `Proxy`.
-    pub fn new(connect_timeout: Duration, sensors: Sensors, executor: &Handle) -> Self {
+    ///
+    /// If `send_proxy_protocol` is set, each forwarded connection begins with
+    /// a PROXY protocol header describing the client and original destination.
+    ///
+    /// If a `router` is given, connections are routed through it to the
+    /// endpoints of their original destination's service, rather than
+    /// directly to the original destination.
+    pub fn new(
+        connect_timeout: Duration,
+        send_proxy_protocol: bool,
+        router: Option<Router<TcpOutbound>>,
+        sensors: Sensors,
+        executor: &Handle,
+    ) -> Self {
         Self {
             connect_timeout,
             executor: executor.clone(),
+            router,
+            send_proxy_protocol,
             sensors,
         }
     }
@@ -56,20 +76,45 @@ impl Proxy {
             return Box::new(future::ok(()));
         };
 
-        let client_ctx = ClientCtx::new(
-            &srv_ctx.proxy,
-            &orig_dst,
-            None,
-        );
-        let c = Timeout::new(
-            transport::Connect::new(orig_dst, &self.executor),
-            self.connect_timeout,
-            &self.executor,
-        );
-        let connect = self.sensors.connect(c, &client_ctx);
-
-        let fut = connect.connect()
-            .map_err(move |e| error!("tcp connect error to {}: {:?}", orig_dst, e))
+        let connect = match self.router {
+            Some(ref router) => {
+                let mut router = router.clone();
+                let fut = router.call(orig_dst)
+                    .map_err(move |e| error!("tcp connect error to {}: {:?}", orig_dst, e));
+                Either::A(fut)
+            },
+            None => {
+                let client_ctx = ClientCtx::new(
+                    &srv_ctx.proxy,
+                    &orig_dst.into(),
+                    None,
+                    None,
+                );
+                let c = Timeout::new(
+                    transport::Connect::new(orig_dst.into(), None, &self.executor),
+                    self.connect_timeout,
+                    &self.executor,
+                );
+                let fut = self.sensors.connect(c, &client_ctx)
+                    .connect()
+                    .map_err(move |e| error!("tcp connect error to {}: {:?}", orig_dst, e));
+                Either::B(fut)
+            },
+        };
+
+        let proxy_header = if self.send_proxy_protocol {
+            Some(proxy_protocol::encode_v1(&srv_ctx.remote, &orig_dst))
+        } else {
+            None
+        };
+
+        let fut = connect
+            .and_then(move |tcp_out| match proxy_header {
+                None => Either::A(future::ok(tcp_out)),
+                Some(header) => Either::B(write_all(tcp_out, header)
+                    .map(|(tcp_out, _)| tcp_out)
+                    .map_err(|e| error!("tcp proxy protocol error: {}", e))),
+            })
             .and_then(move |tcp_out| {
                 Duplex::new(tcp_in, tcp_out)
                     .map_err(|e| error!("tcp duplex error: {}", e))
@@ -79,7 +124,7 @@ impl Proxy {
 }
 
 /// A future piping data bi-directionally to In and Out.
-struct Duplex<In, Out> {
+pub(super) struct Duplex<In, Out> {
     half_in: HalfDuplex<In>,
     half_out: HalfDuplex<Out>,
 }
@@ -109,7 +154,7 @@ where
     In: AsyncRead + AsyncWrite,
     Out: AsyncRead + AsyncWrite,
 {
-    fn new(in_io: In, out_io: Out) -> Self {
+    pub(super) fn new(in_io: In, out_io: Out) -> Self {
         Duplex {
             half_in: HalfDuplex::new(in_io),
             half_out: HalfDuplex::new(out_io),
@@ -0,0 +1,427 @@
+//! HTTP/1 upgrades and `CONNECT` tunnels.
+//!
+//! hyper 0.11 cannot hand over a connection once a request has upgraded it,
+//! so upgrade handshakes bypass hyper. The server reads the request head
+//! itself and dispatches it through the usual service stack. The client
+//! writes the head to a dedicated connection, reads the response head back,
+//! and returns that connection along with the response. Once the response has
+//! been written, the two connections are spliced together.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::{future::{self, Either}, Async, Future, Poll};
+use http::{self, header};
+use httparse;
+use hyper;
+use tokio_io::{io::{read_exact, write_all}, AsyncRead, AsyncWrite};
+use tower_service::Service;
+use tower_h2::{self, Body};
+
+use ctx::transport::{Server as ServerCtx};
+use super::glue::HttpBody;
+use super::h1::{self, UriIsAbsoluteForm};
+use super::tcp::Duplex;
+
+/// The most headers that an upgrade request or response may have.
+const MAX_HEADERS: usize = 100;
+
+/// The largest response head that is read from an upgraded connection.
+const MAX_RESPONSE_HEAD_LEN: usize = 16 * 1024;
+
+/// The largest request head that is peeked to detect an upgrade. Requests
+/// with larger heads are served as ordinary HTTP/1 requests.
+pub(super) const MAX_REQUEST_HEAD_LEN: usize = 16 * 1024;
+
+/// A connection that can be carried in a response's extensions.
+pub(super) trait Io: AsyncRead + AsyncWrite + Send {}
+
+/// The client connection on which an upgrade handshake was sent.
+///
+/// This is added to the extensions of the handshake's response, so that the
+/// server can splice it to the connection that the request was received on.
+/// It is added whether or not the upgrade was accepted, since the connection
+/// is dedicated to the request and the rest of the response must be relayed
+/// in any case.
+pub(super) struct Upgraded {
+    // The `Mutex` is never contended; it only makes `Upgraded` `Sync`, as
+    // extensions must be.
+    inner: Mutex<(Box<Io>, Bytes)>,
+}
+
+/// Reads a response head from an upgraded connection.
+struct ReadResponseHead<T> {
+    io: Option<T>,
+    buf: BytesMut,
+}
+
+/// Polls a response body to its end, discarding its data.
+struct DrainBody<B>(B);
+
+impl<T: AsyncRead + AsyncWrite + Send> Io for T {}
+
+// ===== server =====
+
+/// Returns true unless `bytes` hold the beginning of an HTTP/1 request head
+/// that hasn't been received in full.
+pub(super) fn is_request_head_complete(bytes: &[u8]) -> bool {
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    match httparse::Request::new(&mut headers).parse(bytes) {
+        Ok(httparse::Status::Partial) => bytes.len() >= MAX_REQUEST_HEAD_LEN,
+        _ => true,
+    }
+}
+
+/// Parses the head of an upgrade request from the bytes peeked on a new
+/// connection.
+///
+/// Returns the request and the length of its head, or `None` if the bytes
+/// don't hold a complete upgrade request. Requests with bodies are not
+/// treated as upgrades.
+pub(super) fn parse_request(bytes: &[u8]) -> Option<(http::Request<()>, usize)> {
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut parsed = httparse::Request::new(&mut headers);
+    let head_len = match parsed.parse(bytes) {
+        Ok(httparse::Status::Complete(len)) => len,
+        _ => return None,
+    };
+
+    let mut req = http::Request::new(());
+    *req.method_mut() = http::Method::from_bytes(parsed.method?.as_bytes()).ok()?;
+    *req.uri_mut() = parsed.path?.parse().ok()?;
+    *req.version_mut() = match parsed.version? {
+        0 => http::Version::HTTP_10,
+        _ => http::Version::HTTP_11,
+    };
+    for h in parsed.headers.iter() {
+        let name = header::HeaderName::from_bytes(h.name.as_bytes()).ok()?;
+        let value = header::HeaderValue::from_bytes(h.value).ok()?;
+        req.headers_mut().append(name, value);
+    }
+
+    let has_body = req.headers().contains_key(header::TRANSFER_ENCODING) ||
+        req.headers().get(header::CONTENT_LENGTH).map_or(false, |len| len.as_bytes() != b"0");
+    if !h1::is_upgrade(&req) || has_body {
+        return None;
+    }
+
+    Some((req, head_len))
+}
+
+/// Serves an upgrade request whose head, of `head_len` bytes, is at the
+/// front of `io`.
+///
+/// The request is dispatched to `service`, and its response head is written
+/// back before `io` is spliced to the upgraded client connection.
+pub(super) fn serve<T, S, B>(
+    io: T,
+    req: http::Request<()>,
+    head_len: usize,
+    mut service: S,
+    srv_ctx: Arc<ServerCtx>,
+) -> Box<Future<Item=(), Error=()>>
+where
+    T: AsyncRead + AsyncWrite + 'static,
+    S: Service<
+        Request=http::Request<HttpBody>,
+        Response=http::Response<B>,
+    > + 'static,
+    S::Error: fmt::Debug,
+    B: tower_h2::Body + 'static,
+{
+    let is_connect = req.method() == &http::Method::CONNECT;
+    let mut req = req.map(|()| HttpBody::Http1(hyper::Body::empty()));
+    req.extensions_mut().insert(srv_ctx);
+
+    let fut = read_exact(io, vec![0; head_len])
+        .map_err(|e| debug!("upgrade request error: {}", e))
+        .and_then(move |(io, _)| {
+            service.call(req)
+                .map_err(|e| debug!("upgrade error: {:?}", e))
+                .map(move |res| (io, res))
+        })
+        .and_then(move |(io, mut res)| {
+            let upgraded = res.extensions_mut().remove::<Upgraded>();
+            let upstream = match upgraded {
+                Some(upgraded) => Some(upgraded.into_inner()),
+                // The proxy responded itself, such as when the request
+                // couldn't be routed. Such responses don't have bodies.
+                None if res.body().is_end_stream() => {
+                    res.headers_mut().insert(
+                        header::CONTENT_LENGTH,
+                        header::HeaderValue::from_static("0"),
+                    );
+                    None
+                },
+                None => {
+                    debug!("upgrade response was not received on an upgraded connection");
+                    return Either::A(future::err(()));
+                },
+            };
+
+            let switched = upstream.is_some() && (
+                res.status() == http::StatusCode::SWITCHING_PROTOCOLS ||
+                (is_connect && res.status().is_success())
+            );
+            if !switched {
+                // The rest of the response is relayed as-is, but the
+                // connection can't be used for any other requests.
+                trace!("upgrade refused with {}", res.status());
+                res.headers_mut().insert(
+                    header::CONNECTION,
+                    header::HeaderValue::from_static("close"),
+                );
+            }
+
+            let mut head = encode_response(&res);
+            let upstream = upstream.map(|(upstream, buffered)| {
+                head.extend_from_slice(&buffered);
+                upstream
+            });
+            let (_, body) = res.into_parts();
+
+            let fut = write_all(io, head)
+                .map_err(|e| debug!("upgrade response error: {}", e))
+                .and_then(move |(io, _)| DrainBody(body).map(move |_| io))
+                .and_then(move |io| match upstream {
+                    Some(upstream) => {
+                        let fut = Duplex::new(io, upstream)
+                            .map_err(|e| debug!("upgrade duplex error: {}", e));
+                        Either::A(fut)
+                    },
+                    None => Either::B(future::ok(())),
+                });
+            Either::B(fut)
+        });
+    Box::new(fut)
+}
+
+fn encode_response<B>(res: &http::Response<B>) -> Vec<u8> {
+    let mut head = Vec::new();
+    let status = res.status();
+    let _ = write!(
+        head,
+        "HTTP/1.1 {} {}\r\n",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or(""),
+    );
+    encode_headers(res.headers(), &mut head);
+    head
+}
+
+// ===== client =====
+
+/// Sends an upgrade request's head on a new connection and reads the
+/// response head back.
+///
+/// The connection is returned in the response's `Upgraded` extension. The
+/// request's body is not sent.
+pub(super) fn handshake<F, B>(
+    connect: F,
+    req: &http::Request<B>,
+) -> Box<Future<Item=http::Response<HttpBody>, Error=io::Error>>
+where
+    F: Future + 'static,
+    F::Item: Io + 'static,
+{
+    let head = encode_request(req);
+    let fut = connect
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "upgrade connect failed"))
+        .and_then(move |io| write_all(io, head))
+        .and_then(|(io, _)| ReadResponseHead::new(io));
+    Box::new(fut)
+}
+
+fn encode_request<B>(req: &http::Request<B>) -> Vec<u8> {
+    let mut head = Vec::new();
+    let _ = write!(head, "{} ", req.method());
+
+    // `CONNECT` requests always use the authority-form. Other requests keep
+    // the absolute-form only if they were received in it.
+    let uri = req.uri();
+    let _ = if req.method() == &http::Method::CONNECT {
+        write!(head, "{}", uri.authority_part().map_or("", |a| a.as_str()))
+    } else if req.extensions().get::<UriIsAbsoluteForm>().is_some() {
+        write!(head, "{}", uri)
+    } else {
+        write!(head, "{}", uri.path_and_query().map_or("/", |p| p.as_str()))
+    };
+
+    let version = if req.version() == http::Version::HTTP_10 {
+        "HTTP/1.0"
+    } else {
+        "HTTP/1.1"
+    };
+    let _ = write!(head, " {}\r\n", version);
+    encode_headers(req.headers(), &mut head);
+    head
+}
+
+fn encode_headers(headers: &http::HeaderMap, head: &mut Vec<u8>) {
+    for (name, value) in headers {
+        head.extend_from_slice(name.as_str().as_bytes());
+        head.extend_from_slice(b": ");
+        head.extend_from_slice(value.as_bytes());
+        head.extend_from_slice(b"\r\n");
+    }
+    head.extend_from_slice(b"\r\n");
+}
+
+/// Parses a response head, returning it and its length if it's complete.
+fn parse_response(bytes: &[u8]) -> io::Result<Option<(http::Response<()>, usize)>> {
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut parsed = httparse::Response::new(&mut headers);
+    let head_len = match parsed.parse(bytes).map_err(invalid_response)? {
+        httparse::Status::Complete(len) => len,
+        httparse::Status::Partial => return Ok(None),
+    };
+
+    let mut res = http::Response::new(());
+    let code = parsed.code.expect("complete response has a status code");
+    *res.status_mut() = http::StatusCode::from_u16(code).map_err(invalid_response)?;
+    for h in parsed.headers.iter() {
+        let name = header::HeaderName::from_bytes(h.name.as_bytes())
+            .map_err(invalid_response)?;
+        let value = header::HeaderValue::from_bytes(h.value)
+            .map_err(invalid_response)?;
+        res.headers_mut().append(name, value);
+    }
+
+    Ok(Some((res, head_len)))
+}
+
+fn invalid_response<E: fmt::Display>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid upgrade response: {}", e))
+}
+
+// ===== impl Upgraded =====
+
+impl Upgraded {
+    fn new<T: Io + 'static>(io: T, buffered: Bytes) -> Self {
+        Upgraded {
+            inner: Mutex::new((Box::new(io), buffered)),
+        }
+    }
+
+    /// Returns the connection and any bytes that were read from it after the
+    /// response head.
+    fn into_inner(self) -> (Box<Io>, Bytes) {
+        self.inner.into_inner().expect("upgraded connection lock poisoned")
+    }
+}
+
+// ===== impl ReadResponseHead =====
+
+impl<T: Io + 'static> ReadResponseHead<T> {
+    fn new(io: T) -> Self {
+        ReadResponseHead {
+            io: Some(io),
+            buf: BytesMut::with_capacity(1024),
+        }
+    }
+}
+
+impl<T: Io + 'static> Future for ReadResponseHead<T> {
+    type Item = http::Response<HttpBody>;
+    type Error = io::Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        loop {
+            if self.buf.remaining_mut() == 0 {
+                if self.buf.len() >= MAX_RESPONSE_HEAD_LEN {
+                    return Err(invalid_response("head too large"));
+                }
+                self.buf.reserve(1024);
+            }
+
+            let n = {
+                let io = self.io.as_mut().expect("polled after complete");
+                try_ready!(io.read_buf(&mut self.buf))
+            };
+            if n == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            if let Some((res, head_len)) = parse_response(&self.buf)? {
+                let buffered = self.buf.split_off(head_len).freeze();
+                let io = self.io.take().expect("polled after complete");
+                let mut res = res.map(|()| HttpBody::Http1(hyper::Body::empty()));
+                res.extensions_mut().insert(Upgraded::new(io, buffered));
+                return Ok(Async::Ready(res));
+            }
+        }
+    }
+}
+
+// ===== impl DrainBody =====
+
+impl<B: tower_h2::Body> Future for DrainBody<B> {
+    type Item = ();
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        while let Some(_) = try_ready!(self.0.poll_data().map_err(|_| ())) {}
+        try_ready!(self.0.poll_trailers().map_err(|_| ()));
+        Ok(Async::Ready(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_websocket_handshake() {
+        let head = b"GET /chat HTTP/1.1\r\n\
+                     host: example.com\r\n\
+                     connection: keep-alive, Upgrade\r\n\
+                     upgrade: websocket\r\n\
+                     \r\n\
+                     frame";
+        let (req, head_len) = parse_request(head).expect("upgrade");
+        assert_eq!(head_len, head.len() - "frame".len());
+        assert_eq!(req.uri().path(), "/chat");
+        assert_eq!(req.headers()["upgrade"].as_bytes(), b"websocket");
+        assert_eq!(encode_request(&req), &head[..head_len]);
+    }
+
+    #[test]
+    fn parses_connect() {
+        let head = b"CONNECT example.com:443 HTTP/1.1\r\nhost: example.com:443\r\n\r\n";
+        let (req, head_len) = parse_request(head).expect("upgrade");
+        assert_eq!(head_len, head.len());
+        assert_eq!(req.method(), &http::Method::CONNECT);
+        assert_eq!(encode_request(&req), &head[..]);
+    }
+
+    #[test]
+    fn ignores_other_requests() {
+        // Not an upgrade.
+        assert!(parse_request(b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n").is_none());
+        // An upgrade without the `Connection` header's token.
+        assert!(parse_request(b"GET / HTTP/1.1\r\nUpgrade: websocket\r\n\r\n").is_none());
+        // An upgrade with a body.
+        let with_body = b"POST / HTTP/1.1\r\n\
+                          Connection: upgrade\r\n\
+                          Upgrade: h2c\r\n\
+                          Content-Length: 3\r\n\
+                          \r\n\
+                          foo";
+        assert!(parse_request(with_body).is_none());
+        // An incomplete head.
+        assert!(parse_request(b"GET / HTTP/1.1\r\nConnection: upgrade\r\n").is_none());
+    }
+
+    #[test]
+    fn parses_response_head() {
+        let head = b"HTTP/1.1 101 Switching Protocols\r\nupgrade: websocket\r\n\r\n";
+        assert!(parse_response(&head[..10]).unwrap().is_none());
+
+        let (res, head_len) = parse_response(head).unwrap().expect("complete");
+        assert_eq!(head_len, head.len());
+        assert_eq!(res.status(), http::StatusCode::SWITCHING_PROTOCOLS);
+        assert_eq!(encode_response(&res), &head[..]);
+    }
+}
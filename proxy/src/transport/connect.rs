@@ -8,12 +8,15 @@ use std::str::FromStr;
 
 use http;
 
+use config::Addr;
 use connection;
 use dns;
+use super::tls;
 
 #[derive(Debug, Clone)]
 pub struct Connect {
-    addr: SocketAddr,
+    addr: Addr,
+    tls: Option<tls::ConnectionConfig>,
     handle: Handle,
 }
 
@@ -85,9 +88,12 @@ impl<'a> From<&'a HostAndPort> for http::uri::Authority {
 
 impl Connect {
     /// Returns a `Connect` to `addr` and `handle`.
-    pub fn new(addr: SocketAddr, handle: &Handle) -> Self {
+    ///
+    /// If `tls` is set, TLS is originated on each connection.
+    pub fn new(addr: Addr, tls: Option<tls::ConnectionConfig>, handle: &Handle) -> Self {
         Self {
             addr,
+            tls,
             handle: handle.clone(),
         }
     }
@@ -99,7 +105,7 @@ impl tokio_connect::Connect for Connect {
     type Future = connection::Connecting;
 
     fn connect(&self) -> Self::Future {
-        connection::connect(&self.addr, &self.handle)
+        connection::connect(&self.addr, self.tls.clone(), &self.handle)
     }
 }
 
@@ -137,7 +143,7 @@ impl tokio_connect::Connect for LookupAddressAndConnect {
                 info!("DNS resolved {:?} to {}", host, ip_addr);
                 let addr = SocketAddr::from((ip_addr, port));
                 trace!("connect {}", addr);
-                connection::connect(&addr, &handle)
+                connection::connect(&addr.into(), None, &handle)
             });
         Box::new(c)
     }
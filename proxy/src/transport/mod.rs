@@ -1,5 +1,7 @@
 mod connect;
+pub mod proxy_protocol;
 mod so_original_dst;
+pub mod tls;
 
 pub use self::connect::{
     Connect,
@@ -0,0 +1,385 @@
+//! HAProxy's PROXY protocol, which L4 load balancers use to describe the
+//! client and destination addresses of the connections they forward.
+//!
+//! Both the text (v1) and binary (v2) header formats are parsed. Only the
+//! text format is ever sent, since it is the most widely supported.
+//!
+//! See https://www.haproxy.org/download/1.8/doc/proxy-protocol.txt.
+
+use std::error;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::{self, FromStr};
+
+/// The addresses described by a PROXY protocol header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Header {
+    /// The connection was forwarded from `source` to `destination`.
+    Proxy {
+        source: SocketAddr,
+        destination: SocketAddr,
+    },
+
+    /// The connection's own addresses should be used.
+    ///
+    /// This is sent by load balancers for their own health checks, and when
+    /// the forwarded connection isn't TCP over IP.
+    Local,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The connection doesn't start with a PROXY protocol header.
+    NotProxyProtocol,
+
+    /// The header is invalid.
+    Malformed,
+}
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+
+/// The length of the longest valid v1 header, including the final CRLF.
+const V1_MAX_LEN: usize = 107;
+
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The length of a v2 header before its addresses.
+const V2_HEADER_LEN: usize = 16;
+
+const V2_VERSION: u8 = 0x20;
+const V2_CMD_LOCAL: u8 = 0x00;
+const V2_CMD_PROXY: u8 = 0x01;
+const V2_TCP_OVER_IPV4: u8 = 0x11;
+const V2_TCP_OVER_IPV6: u8 = 0x21;
+
+/// Parses a PROXY protocol header from the start of `buf`.
+///
+/// Returns the header and its length, or `None` if `buf` is a valid prefix of
+/// a header but more bytes are needed to parse it.
+pub fn parse(buf: &[u8]) -> Result<Option<(Header, usize)>, Error> {
+    if starts_like(buf, V2_SIGNATURE) {
+        return parse_v2(buf);
+    }
+    if starts_like(buf, V1_PREFIX) {
+        return parse_v1(buf);
+    }
+    Err(Error::NotProxyProtocol)
+}
+
+/// Encodes a v1 header describing a TCP connection from `source` to
+/// `destination`.
+pub fn encode_v1(source: &SocketAddr, destination: &SocketAddr) -> Vec<u8> {
+    let (family, src_ip, dst_ip) = match (source.ip(), destination.ip()) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => ("TCP4", IpAddr::V4(src), IpAddr::V4(dst)),
+        // Both addresses must be of the same family.
+        (src, dst) => ("TCP6", IpAddr::V6(to_ipv6(src)), IpAddr::V6(to_ipv6(dst))),
+    };
+    format!(
+        "PROXY {} {} {} {} {}\r\n",
+        family,
+        src_ip,
+        dst_ip,
+        source.port(),
+        destination.port(),
+    ).into_bytes()
+}
+
+/// Returns true if `buf` matches `prefix` for as many bytes as it has.
+fn starts_like(buf: &[u8], prefix: &[u8]) -> bool {
+    if buf.len() < prefix.len() {
+        prefix.starts_with(buf)
+    } else {
+        buf.starts_with(prefix)
+    }
+}
+
+fn parse_v1(buf: &[u8]) -> Result<Option<(Header, usize)>, Error> {
+    let newline = match buf.iter().take(V1_MAX_LEN).position(|&b| b == b'\n') {
+        Some(pos) => pos,
+        None if buf.len() < V1_MAX_LEN => return Ok(None),
+        None => return Err(Error::Malformed),
+    };
+    if buf[newline - 1] != b'\r' {
+        return Err(Error::Malformed);
+    }
+
+    let line = str::from_utf8(&buf[V1_PREFIX.len()..newline - 1])
+        .map_err(|_| Error::Malformed)?;
+    let mut fields = line.split(' ');
+    let header = match fields.next() {
+        // The rest of the line is ignored, whatever it contains.
+        Some("UNKNOWN") => Header::Local,
+        Some(family @ "TCP4") | Some(family @ "TCP6") => {
+            let src_ip: IpAddr = next_field(&mut fields)?;
+            let dst_ip: IpAddr = next_field(&mut fields)?;
+            let src_port: u16 = next_field(&mut fields)?;
+            let dst_port: u16 = next_field(&mut fields)?;
+            if fields.next().is_some() {
+                return Err(Error::Malformed);
+            }
+            if src_ip.is_ipv4() != (family == "TCP4") || dst_ip.is_ipv4() != (family == "TCP4") {
+                return Err(Error::Malformed);
+            }
+            Header::Proxy {
+                source: SocketAddr::new(src_ip, src_port),
+                destination: SocketAddr::new(dst_ip, dst_port),
+            }
+        },
+        _ => return Err(Error::Malformed),
+    };
+
+    Ok(Some((header, newline + 1)))
+}
+
+fn next_field<'a, T, I>(fields: &mut I) -> Result<T, Error>
+where
+    T: FromStr,
+    I: Iterator<Item = &'a str>,
+{
+    fields.next()
+        .and_then(|field| field.parse().ok())
+        .ok_or(Error::Malformed)
+}
+
+fn parse_v2(buf: &[u8]) -> Result<Option<(Header, usize)>, Error> {
+    if buf.len() < V2_HEADER_LEN {
+        return Ok(None);
+    }
+
+    let version_and_command = buf[12];
+    let family = buf[13];
+    let len = V2_HEADER_LEN + ((buf[14] as usize) << 8 | buf[15] as usize);
+    if version_and_command & 0xf0 != V2_VERSION {
+        return Err(Error::Malformed);
+    }
+    if buf.len() < len {
+        return Ok(None);
+    }
+
+    // Any TLVs following the addresses are ignored.
+    let addrs = &buf[V2_HEADER_LEN..len];
+    let header = match version_and_command & 0x0f {
+        V2_CMD_LOCAL => Header::Local,
+        V2_CMD_PROXY => match family {
+            V2_TCP_OVER_IPV4 => {
+                if addrs.len() < 12 {
+                    return Err(Error::Malformed);
+                }
+                let src_ip = Ipv4Addr::new(addrs[0], addrs[1], addrs[2], addrs[3]);
+                let dst_ip = Ipv4Addr::new(addrs[4], addrs[5], addrs[6], addrs[7]);
+                Header::Proxy {
+                    source: SocketAddr::new(src_ip.into(), read_port(&addrs[8..])),
+                    destination: SocketAddr::new(dst_ip.into(), read_port(&addrs[10..])),
+                }
+            },
+            V2_TCP_OVER_IPV6 => {
+                if addrs.len() < 36 {
+                    return Err(Error::Malformed);
+                }
+                Header::Proxy {
+                    source: SocketAddr::new(read_ipv6(&addrs[0..]).into(), read_port(&addrs[32..])),
+                    destination: SocketAddr::new(read_ipv6(&addrs[16..]).into(), read_port(&addrs[34..])),
+                }
+            },
+            // UDP, Unix sockets, and unspecified protocols can't be
+            // described by a `Header::Proxy`.
+            _ => Header::Local,
+        },
+        _ => return Err(Error::Malformed),
+    };
+
+    Ok(Some((header, len)))
+}
+
+fn read_port(buf: &[u8]) -> u16 {
+    (buf[0] as u16) << 8 | buf[1] as u16
+}
+
+fn read_ipv6(buf: &[u8]) -> Ipv6Addr {
+    let mut octets = [0; 16];
+    octets.copy_from_slice(&buf[..16]);
+    Ipv6Addr::from(octets)
+}
+
+fn to_ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+// ===== impl Error =====
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::NotProxyProtocol => write!(f, "connection did not start with a PROXY protocol header"),
+            Error::Malformed => write!(f, "malformed PROXY protocol header"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::NotProxyProtocol => "not PROXY protocol",
+            Error::Malformed => "malformed PROXY protocol header",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v2(command: u8, family: u8, addrs: &[u8]) -> Vec<u8> {
+        let mut buf = V2_SIGNATURE.to_vec();
+        buf.push(V2_VERSION | command);
+        buf.push(family);
+        buf.push((addrs.len() >> 8) as u8);
+        buf.push(addrs.len() as u8);
+        buf.extend_from_slice(addrs);
+        buf
+    }
+
+    #[test]
+    fn parse_v1_tcp4() {
+        let buf = b"PROXY TCP4 10.1.2.3 10.4.5.6 56324 8080\r\nGET / HTTP/1.1\r\n";
+        let header = Header::Proxy {
+            source: "10.1.2.3:56324".parse().unwrap(),
+            destination: "10.4.5.6:8080".parse().unwrap(),
+        };
+        assert_eq!(parse(buf), Ok(Some((header, 41))));
+    }
+
+    #[test]
+    fn parse_v1_tcp6() {
+        let buf = b"PROXY TCP6 fd00::1 fd00::2 56324 8080\r\n";
+        let header = Header::Proxy {
+            source: "[fd00::1]:56324".parse().unwrap(),
+            destination: "[fd00::2]:8080".parse().unwrap(),
+        };
+        assert_eq!(parse(buf), Ok(Some((header, buf.len()))));
+    }
+
+    #[test]
+    fn parse_v1_unknown() {
+        let buf = b"PROXY UNKNOWN whatever\r\n";
+        assert_eq!(parse(buf), Ok(Some((Header::Local, buf.len()))));
+    }
+
+    #[test]
+    fn parse_v1_malformed() {
+        let bufs: &[&[u8]] = &[
+            b"PROXY TCP4 10.1.2.3 10.4.5.6 56324\r\n",
+            b"PROXY TCP4 10.1.2.3 10.4.5.6 56324 8080 80\r\n",
+            b"PROXY TCP4 fd00::1 fd00::2 56324 8080\r\n",
+            b"PROXY TCP4 10.1.2.3 10.4.5.6 56324 65536\r\n",
+            b"PROXY UDP4 10.1.2.3 10.4.5.6 56324 8080\r\n",
+            b"PROXY TCP4 10.1.2.3 10.4.5.6 56324 8080\n",
+        ];
+        for buf in bufs {
+            assert_eq!(parse(buf), Err(Error::Malformed));
+        }
+
+        let too_long = [b'P'; V1_MAX_LEN];
+        let mut buf = V1_PREFIX.to_vec();
+        buf.extend_from_slice(&too_long);
+        assert_eq!(parse(&buf), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn parse_v2_tcp4() {
+        let buf = v2(V2_CMD_PROXY, V2_TCP_OVER_IPV4, &[
+            10, 1, 2, 3,
+            10, 4, 5, 6,
+            0xdc, 0x04,
+            0x1f, 0x90,
+            // A TLV, which is ignored.
+            0x04, 0x00, 0x01, 0x00,
+        ]);
+        let header = Header::Proxy {
+            source: "10.1.2.3:56324".parse().unwrap(),
+            destination: "10.4.5.6:8080".parse().unwrap(),
+        };
+        assert_eq!(parse(&buf), Ok(Some((header, buf.len()))));
+    }
+
+    #[test]
+    fn parse_v2_tcp6() {
+        let mut addrs = Vec::new();
+        addrs.extend_from_slice(&"fd00::1".parse::<Ipv6Addr>().unwrap().octets());
+        addrs.extend_from_slice(&"fd00::2".parse::<Ipv6Addr>().unwrap().octets());
+        addrs.extend_from_slice(&[0xdc, 0x04, 0x1f, 0x90]);
+        let buf = v2(V2_CMD_PROXY, V2_TCP_OVER_IPV6, &addrs);
+        let header = Header::Proxy {
+            source: "[fd00::1]:56324".parse().unwrap(),
+            destination: "[fd00::2]:8080".parse().unwrap(),
+        };
+        assert_eq!(parse(&buf), Ok(Some((header, buf.len()))));
+    }
+
+    #[test]
+    fn parse_v2_local() {
+        let buf = v2(V2_CMD_LOCAL, 0x00, &[]);
+        assert_eq!(parse(&buf), Ok(Some((Header::Local, buf.len()))));
+
+        // Unix socket addresses can't be represented.
+        let buf = v2(V2_CMD_PROXY, 0x31, &[0; 216]);
+        assert_eq!(parse(&buf), Ok(Some((Header::Local, buf.len()))));
+    }
+
+    #[test]
+    fn parse_v2_malformed() {
+        let buf = v2(V2_CMD_PROXY, V2_TCP_OVER_IPV4, &[10, 1, 2, 3]);
+        assert_eq!(parse(&buf), Err(Error::Malformed));
+
+        let mut buf = v2(V2_CMD_LOCAL, 0x00, &[]);
+        buf[12] = 0x10;
+        assert_eq!(parse(&buf), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn parse_incomplete() {
+        let v1 = b"PROXY TCP4 10.1.2.3 10.4.5.6 56324 8080\r\n".to_vec();
+        let v2 = v2(V2_CMD_PROXY, V2_TCP_OVER_IPV4, &[10, 1, 2, 3, 10, 4, 5, 6, 0, 1, 0, 2]);
+        for buf in &[v1, v2] {
+            for len in 0..buf.len() {
+                assert_eq!(parse(&buf[..len]), Ok(None));
+            }
+        }
+    }
+
+    #[test]
+    fn parse_not_proxy_protocol() {
+        assert_eq!(parse(b"GET / HTTP/1.1\r\n"), Err(Error::NotProxyProtocol));
+        assert_eq!(parse(b"PRI * HTTP/2.0\r\n"), Err(Error::NotProxyProtocol));
+        assert_eq!(parse(&[0x16, 0x03, 0x01]), Err(Error::NotProxyProtocol));
+    }
+
+    #[test]
+    fn encode_v1_round_trips() {
+        let addrs = [
+            ("10.1.2.3:56324", "10.4.5.6:8080", "TCP4"),
+            ("[fd00::1]:56324", "[fd00::2]:8080", "TCP6"),
+            ("10.1.2.3:56324", "[fd00::2]:8080", "TCP6"),
+        ];
+        for &(src, dst, family) in &addrs {
+            let source: SocketAddr = src.parse().unwrap();
+            let destination: SocketAddr = dst.parse().unwrap();
+            let buf = encode_v1(&source, &destination);
+            assert!(buf.starts_with(format!("PROXY {} ", family).as_bytes()));
+
+            let (header, len) = parse(&buf).unwrap().unwrap();
+            assert_eq!(len, buf.len());
+            match header {
+                Header::Proxy { source: s, destination: d } => {
+                    assert_eq!(s.port(), source.port());
+                    assert_eq!(d, destination);
+                    assert_eq!(to_ipv6(s.ip()), to_ipv6(source.ip()));
+                },
+                Header::Local => panic!("expected addresses"),
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Reads the DNS names out of a certificate's subjectAltName extension.
+//!
+//! The certificate is walked with ring's DER reader, the same one that webpki
+//! parses certificates with. The names found here are only candidates: a name
+//! is used as a peer's identity only once webpki has confirmed that the
+//! certificate is valid for it. Anything unexpected results in no names being
+//! found.
+
+use ring::der::{self, Tag};
+use untrusted::{Input, Reader};
+
+/// The `[2] IMPLICIT` tag of a GeneralName's `dNSName` choice.
+const DNS_NAME: u8 = der::CONTEXT_SPECIFIC | 2;
+
+/// The encoded OID of the subjectAltName extension, 2.5.29.17.
+const SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// Returns the `dNSName` entries of a DER-encoded certificate's
+/// subjectAltName extension, in the order in which they appear.
+pub fn dns_names(cert: &[u8]) -> Vec<&[u8]> {
+    let names = match subject_alt_names(Input::from(cert)) {
+        Some(names) => names,
+        None => return Vec::new(),
+    };
+
+    let mut names = Reader::new(names);
+    let mut dns_names = Vec::new();
+    while let Ok((tag, name)) = der::read_tag_and_get_value(&mut names) {
+        if tag == DNS_NAME {
+            dns_names.push(name.as_slice_less_safe());
+        }
+    }
+    dns_names
+}
+
+fn subject_alt_names(cert: Input) -> Option<Input> {
+    let cert = expect(&mut Reader::new(cert), Tag::Sequence)?;
+    let tbs = expect(&mut Reader::new(cert), Tag::Sequence)?;
+
+    // No other field of the TBSCertificate is tagged [3].
+    let mut tbs = Reader::new(tbs);
+    let extensions = loop {
+        let (tag, value) = der::read_tag_and_get_value(&mut tbs).ok()?;
+        if tag == Tag::ContextSpecificConstructed3 as u8 {
+            break value;
+        }
+    };
+    let mut extensions = Reader::new(expect(&mut Reader::new(extensions), Tag::Sequence)?);
+
+    while !extensions.at_end() {
+        let mut extension = Reader::new(expect(&mut extensions, Tag::Sequence)?);
+        let id = expect(&mut extension, Tag::OID)?;
+        if id.as_slice_less_safe() != SUBJECT_ALT_NAME {
+            continue;
+        }
+        // Skip the `critical` flag, which is omitted when false.
+        if extension.peek(Tag::Boolean as u8) {
+            expect(&mut extension, Tag::Boolean)?;
+        }
+        let value = expect(&mut extension, Tag::OctetString)?;
+        return expect(&mut Reader::new(value), Tag::Sequence);
+    }
+
+    None
+}
+
+fn expect<'a>(input: &mut Reader<'a>, tag: Tag) -> Option<Input<'a>> {
+    der::expect_tag_and_get_value(input, tag).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEQUENCE: u8 = Tag::Sequence as u8;
+    const OID: u8 = Tag::OID as u8;
+    const BOOLEAN: u8 = Tag::Boolean as u8;
+    const OCTET_STRING: u8 = Tag::OctetString as u8;
+    const EXTENSIONS: u8 = Tag::ContextSpecificConstructed3 as u8;
+
+    fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        if value.len() < 0x80 {
+            out.push(value.len() as u8);
+        } else if value.len() < 0x100 {
+            out.push(0x81);
+            out.push(value.len() as u8);
+        } else {
+            out.push(0x82);
+            out.push((value.len() >> 8) as u8);
+            out.push(value.len() as u8);
+        }
+        out.extend_from_slice(value);
+        out
+    }
+
+    fn cert(extensions: &[Vec<u8>]) -> Vec<u8> {
+        let mut tbs = Vec::new();
+        tbs.extend(tlv(0xa0, &tlv(0x02, &[2]))); // version
+        tbs.extend(tlv(0x02, &[1])); // serialNumber
+        for _ in 0..5 {
+            // signature, issuer, validity, subject, subjectPublicKeyInfo
+            tbs.extend(tlv(SEQUENCE, &[]));
+        }
+        if !extensions.is_empty() {
+            tbs.extend(tlv(EXTENSIONS, &tlv(SEQUENCE, &extensions.concat())));
+        }
+
+        let mut cert = tlv(SEQUENCE, &tbs);
+        cert.extend(tlv(SEQUENCE, &[])); // signatureAlgorithm
+        cert.extend(tlv(0x03, &[0])); // signatureValue
+        tlv(SEQUENCE, &cert)
+    }
+
+    fn san(names: &[Vec<u8>], critical: bool) -> Vec<u8> {
+        let mut ext = tlv(OID, SUBJECT_ALT_NAME);
+        if critical {
+            ext.extend(tlv(BOOLEAN, &[0xff]));
+        }
+        ext.extend(tlv(OCTET_STRING, &tlv(SEQUENCE, &names.concat())));
+        tlv(SEQUENCE, &ext)
+    }
+
+    fn basic_constraints() -> Vec<u8> {
+        let mut ext = tlv(OID, &[0x55, 0x1d, 0x13]);
+        ext.extend(tlv(BOOLEAN, &[0xff]));
+        ext.extend(tlv(OCTET_STRING, &tlv(SEQUENCE, &[])));
+        tlv(SEQUENCE, &ext)
+    }
+
+    #[test]
+    fn finds_dns_names() {
+        let names = [
+            tlv(DNS_NAME, b"foo.ns.svc.cluster.local"),
+            tlv(0x87, &[10, 1, 2, 3]), // iPAddress
+            tlv(DNS_NAME, b"bar.ns.svc.cluster.local"),
+        ];
+        let cert = cert(&[basic_constraints(), san(&names, false)]);
+        assert_eq!(
+            dns_names(&cert),
+            vec![&b"foo.ns.svc.cluster.local"[..], &b"bar.ns.svc.cluster.local"[..]]
+        );
+    }
+
+    #[test]
+    fn skips_critical_flag() {
+        let cert = cert(&[san(&[tlv(DNS_NAME, b"foo.example.com")], true)]);
+        assert_eq!(dns_names(&cert), vec![&b"foo.example.com"[..]]);
+    }
+
+    #[test]
+    fn reads_long_form_lengths() {
+        let long_name = vec![b'a'; 200];
+        let cert = cert(&[san(&[tlv(DNS_NAME, &long_name)], false)]);
+        assert_eq!(dns_names(&cert), vec![&long_name[..]]);
+    }
+
+    #[test]
+    fn no_extensions() {
+        assert!(dns_names(&cert(&[])).is_empty());
+        assert!(dns_names(&cert(&[basic_constraints()])).is_empty());
+    }
+
+    #[test]
+    fn malformed() {
+        let cert = cert(&[san(&[tlv(DNS_NAME, b"foo.example.com")], false)]);
+        for len in 0..cert.len() {
+            assert!(dns_names(&cert[..len]).is_empty());
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use rustls::{self, Session};
+use rustls::internal::pemfile;
+use tokio_rustls::{ClientConfigExt, ServerConfigExt};
+use untrusted;
+use webpki;
+
+pub use rustls::{ClientSession, ServerSession};
+pub use tokio_rustls::{AcceptAsync, ConnectAsync, TlsStream};
+
+mod cert;
+
+/// The TLS record content type of a handshake message.
+const CONTENT_TYPE_HANDSHAKE: u8 = 0x16;
+
+/// The major version of every TLS record version from SSL 3.0 through TLS 1.3.
+const VERSION_MAJOR: u8 = 0x03;
+
+/// Paths to the PEM-encoded files from which the TLS configuration is loaded.
+#[derive(Clone, Debug)]
+pub struct CommonSettings {
+    /// The trust anchors that peer certificates must chain to.
+    pub trust_anchors: PathBuf,
+
+    /// The proxy's certificate, followed by any intermediate certificates.
+    pub end_entity_cert: PathBuf,
+
+    /// The PKCS#8 private key for `end_entity_cert`.
+    pub private_key: PathBuf,
+}
+
+/// The TLS material shared by the server and client configurations.
+pub struct CommonConfig {
+    root_cert_store: rustls::RootCertStore,
+    cert_chain: Vec<rustls::Certificate>,
+    private_key: rustls::PrivateKey,
+}
+
+/// Configures how TLS is terminated on accepted connections.
+///
+/// Clients must present a certificate that chains to the trust anchors.
+#[derive(Clone)]
+pub struct ServerConfig(Arc<rustls::ServerConfig>);
+
+/// Configures how TLS is originated on connections to other proxies.
+#[derive(Clone)]
+pub struct ClientConfig(Arc<rustls::ClientConfig>);
+
+/// Everything needed to originate TLS on a single connection.
+#[derive(Clone, Debug)]
+pub struct ConnectionConfig {
+    /// The identity that the server's certificate must be valid for.
+    pub server_identity: Identity,
+
+    pub config: ClientConfig,
+}
+
+/// The DNS name that a peer's certificate is valid for.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Identity(webpki::DNSName);
+
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, io::Error),
+    FailedToParseTrustAnchors,
+    EmptyEndEntityCert,
+    FailedToParseEndEntityCert,
+    FailedToParsePrivateKey,
+    InvalidPrivateKey(rustls::TLSError),
+}
+
+/// Returns true if `bytes` begin with a TLS handshake record, i.e. the first
+/// bytes a TLS client sends.
+///
+/// Neither HTTP/1 nor HTTP/2 requests can begin this way, so this can be used
+/// to determine whether an accepted connection should be upgraded to TLS
+/// before attempting any other protocol detection.
+pub fn is_client_hello(bytes: &[u8]) -> bool {
+    can_detect_client_hello(bytes)
+        && bytes[0] == CONTENT_TYPE_HANDSHAKE
+        && bytes[1] == VERSION_MAJOR
+        && bytes[2] <= 0x04
+}
+
+/// Returns true once `bytes` are long enough for `is_client_hello` to tell
+/// whether they begin a TLS handshake.
+pub fn can_detect_client_hello(bytes: &[u8]) -> bool {
+    bytes.len() >= 3
+}
+
+/// Returns the identity of the peer of an established TLS session.
+///
+/// The identity is the first DNS name in the subjectAltName extension of the
+/// peer's end-entity certificate that webpki considers the certificate valid
+/// for. The certificate has already been verified against the trust anchors
+/// by the time the handshake completes.
+pub fn peer_identity<S: Session>(session: &S) -> Option<Identity> {
+    let certs = session.get_peer_certificates()?;
+    let end_entity = certs.first()?;
+    let cert = webpki::EndEntityCert::from(untrusted::Input::from(&end_entity.0)).ok()?;
+    cert::dns_names(&end_entity.0)
+        .into_iter()
+        .filter_map(|name| webpki::DNSNameRef::try_from_ascii(untrusted::Input::from(name)).ok())
+        .find(|&name| cert.verify_is_valid_for_dns_name(name).is_ok())
+        .map(|name| Identity(name.to_owned()))
+}
+
+// ===== impl CommonConfig =====
+
+impl CommonConfig {
+    /// Loads the trust anchors, certificate chain, and private key named by
+    /// `settings`.
+    ///
+    /// The certificate is not validated against the trust anchors here; a
+    /// misconfigured certificate will cause handshakes to fail.
+    pub fn load_from_disk(settings: &CommonSettings) -> Result<Self, Error> {
+        let mut root_cert_store = rustls::RootCertStore::empty();
+        let (added, _skipped) = load_file(&settings.trust_anchors, |r| {
+            root_cert_store.add_pem_file(r)
+        })?.map_err(|()| Error::FailedToParseTrustAnchors)?;
+        if added == 0 {
+            return Err(Error::FailedToParseTrustAnchors);
+        }
+
+        let cert_chain = load_file(&settings.end_entity_cert, pemfile::certs)?
+            .map_err(|()| Error::FailedToParseEndEntityCert)?;
+        if cert_chain.is_empty() {
+            return Err(Error::EmptyEndEntityCert);
+        }
+
+        let private_key = load_file(&settings.private_key, pemfile::pkcs8_private_keys)?
+            .map_err(|()| Error::FailedToParsePrivateKey)?
+            .into_iter()
+            .next()
+            .ok_or(Error::FailedToParsePrivateKey)?;
+
+        Ok(CommonConfig {
+            root_cert_store,
+            cert_chain,
+            private_key,
+        })
+    }
+
+    pub fn server_config(&self) -> Result<ServerConfig, Error> {
+        let verifier = rustls::AllowAnyAuthenticatedClient::new(self.root_cert_store.clone());
+        let mut config = rustls::ServerConfig::new(verifier);
+        config.set_single_cert(self.cert_chain.clone(), self.private_key.clone())
+            .map_err(Error::InvalidPrivateKey)?;
+        Ok(ServerConfig(Arc::new(config)))
+    }
+
+    pub fn client_config(&self) -> ClientConfig {
+        let mut config = rustls::ClientConfig::new();
+        config.root_store = self.root_cert_store.clone();
+        config.set_single_client_cert(self.cert_chain.clone(), self.private_key.clone());
+        ClientConfig(Arc::new(config))
+    }
+}
+
+fn load_file<T, F>(path: &Path, load: F) -> Result<T, Error>
+where
+    F: FnOnce(&mut io::BufRead) -> T,
+{
+    let file = File::open(path).map_err(|e| Error::Io(path.to_path_buf(), e))?;
+    Ok(load(&mut BufReader::new(file)))
+}
+
+// ===== impl ServerConfig =====
+
+impl ServerConfig {
+    /// Begins a TLS handshake as the server on `io`.
+    pub fn accept<T>(&self, io: T) -> AcceptAsync<T>
+    where
+        T: io::Read + io::Write,
+    {
+        self.0.accept_async(io)
+    }
+}
+
+impl fmt::Debug for ServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ServerConfig").finish()
+    }
+}
+
+// ===== impl ClientConfig =====
+
+impl ClientConfig {
+    /// Begins a TLS handshake as the client on `io`, requiring the server to
+    /// present a certificate valid for `identity`.
+    pub fn connect<T>(&self, identity: &Identity, io: T) -> ConnectAsync<T>
+    where
+        T: io::Read + io::Write,
+    {
+        self.0.connect_async(identity.0.as_ref(), io)
+    }
+}
+
+impl fmt::Debug for ClientConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClientConfig").finish()
+    }
+}
+
+// ===== impl Identity =====
+
+impl Identity {
+    pub fn from_dns_name(name: &str) -> Result<Self, ()> {
+        webpki::DNSNameRef::try_from_ascii_str(name)
+            .map(|n| Identity(n.to_owned()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_ref().into()
+    }
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// ===== impl Error =====
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref path, ref e) => write!(f, "{}: {}", path.display(), e),
+            Error::InvalidPrivateKey(ref e) => write!(f, "invalid private key: {:?}", e),
+            _ => f.pad(self.description()),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Io(_, ref e) => e.description(),
+            Error::FailedToParseTrustAnchors => "failed to parse trust anchors",
+            Error::EmptyEndEntityCert => "end-entity certificate file is empty",
+            Error::FailedToParseEndEntityCert => "failed to parse end-entity certificate",
+            Error::FailedToParsePrivateKey => "failed to parse private key",
+            Error::InvalidPrivateKey(_) => "invalid private key",
+        }
+    }
+
+    fn cause(&self) -> Option<&StdError> {
+        match *self {
+            Error::Io(_, ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_client_hello() {
+        // The record header of a TLS 1.2 ClientHello.
+        assert!(is_client_hello(&[0x16, 0x03, 0x01, 0x02, 0x00, 0x01]));
+        assert!(is_client_hello(&[0x16, 0x03, 0x03]));
+    }
+
+    #[test]
+    fn http_is_not_client_hello() {
+        assert!(!is_client_hello(b"GET / HTTP/1.1\r\n"));
+        assert!(!is_client_hello(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n"));
+    }
+
+    #[test]
+    fn short_input_is_not_client_hello() {
+        assert!(!is_client_hello(&[]));
+        assert!(!is_client_hello(&[0x16, 0x03]));
+        assert!(!can_detect_client_hello(&[0x16, 0x03]));
+        assert!(can_detect_client_hello(&[0x16, 0x03, 0x01]));
+    }
+
+    #[test]
+    fn identity_must_be_a_dns_name() {
+        let id = Identity::from_dns_name("foo.ns.conduit-managed.svc.cluster.local")
+            .expect("valid name");
+        assert_eq!(id.as_str(), "foo.ns.conduit-managed.svc.cluster.local");
+        assert!(Identity::from_dns_name("not a name").is_err());
+    }
+}
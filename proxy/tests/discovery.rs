@@ -101,7 +101,8 @@ macro_rules! generate_tests {
             let mut req = initially_exists.request_builder("/");
             let rsp = initially_exists.request(req.method("GET"));
             // the request should time out
-            assert_eq!(rsp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+            assert_eq!(rsp.status(), http::StatusCode::GATEWAY_TIMEOUT);
+            assert_eq!(rsp.headers()["conduit-proxy-error"], "timeout");
         }
 
         #[test]
@@ -128,7 +129,202 @@ macro_rules! generate_tests {
             let mut req = client.request_builder("/");
             let rsp = client.request(req.method("GET"));
             // the request should time out
-            assert_eq!(rsp.status(), http::StatusCode::INTERNAL_SERVER_ERROR);
+            assert_eq!(rsp.status(), http::StatusCode::GATEWAY_TIMEOUT);
+            assert_eq!(rsp.headers()["conduit-proxy-error"], "timeout");
+        }
+
+        #[test]
+        fn outbound_balances_by_peak_ewma() {
+            use std::sync::Arc;
+            use std::sync::atomic::{AtomicUsize, Ordering};
+
+            let _ = env_logger::try_init();
+            let mut env = config::TestEnv::new();
+            env.put(config::ENV_OUTBOUND_LOAD_METRIC, "peak-ewma".to_owned());
+
+            let counting = |calls: &Arc<AtomicUsize>, latency: Duration| {
+                let calls = calls.clone();
+                $make_server()
+                    .route_fn("/", move |_| {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        ::std::thread::sleep(latency);
+                        Response::builder()
+                            .body("hello".into())
+                            .unwrap()
+                    })
+                    .run()
+            };
+            let fast_calls = Arc::new(AtomicUsize::new(0));
+            let slow_calls = Arc::new(AtomicUsize::new(0));
+            let fast = counting(&fast_calls, Duration::from_millis(0));
+            let slow = counting(&slow_calls, Duration::from_millis(200));
+
+            let ctrl = controller::new();
+            let dst_tx = ctrl.destination_tx("disco.test.svc.cluster.local");
+            dst_tx.send_addr(fast.addr);
+            dst_tx.send_addr(slow.addr);
+
+            let proxy = proxy::new()
+                .controller(ctrl.run())
+                .outbound(fast)
+                .run_with_test_env(env);
+            let client = $make_client(proxy.outbound, "disco.test.svc.cluster.local");
+
+            for _ in 0..20 {
+                assert_eq!(client.get("/"), "hello");
+            }
+
+            // Once the slow endpoint's latency has been observed, the fast
+            // endpoint is preferred.
+            let fast_calls = fast_calls.load(Ordering::SeqCst);
+            let slow_calls = slow_calls.load(Ordering::SeqCst);
+            assert_eq!(fast_calls + slow_calls, 20);
+            assert!(
+                slow_calls < fast_calls,
+                "slow endpoint got {} requests, fast endpoint got {}",
+                slow_calls,
+                fast_calls
+            );
+        }
+
+        #[test]
+        fn outbound_prefers_endpoints_in_the_same_locality() {
+            use std::collections::HashMap;
+            use std::sync::Arc;
+            use std::sync::atomic::{AtomicUsize, Ordering};
+
+            let _ = env_logger::try_init();
+            let mut env = config::TestEnv::new();
+            env.put(config::ENV_LOCALITY, "zone-a".to_owned());
+
+            let remote_calls = Arc::new(AtomicUsize::new(0));
+            let srv_calls = remote_calls.clone();
+            let remote = $make_server()
+                .route_fn("/", move |_| {
+                    srv_calls.fetch_add(1, Ordering::SeqCst);
+                    Response::builder()
+                        .body("remote".into())
+                        .unwrap()
+                })
+                .run();
+            let local = $make_server().route("/", "local").run();
+
+            let zone = |zone: &str| {
+                let mut labels = HashMap::new();
+                labels.insert("zone".to_owned(), zone.to_owned());
+                labels
+            };
+            let ctrl = controller::new();
+            let dst_tx = ctrl.destination_tx("disco.test.svc.cluster.local");
+            dst_tx.send_each_labeled(vec![
+                (remote.addr, zone("zone-b")),
+                (local.addr, zone("zone-a")),
+            ]);
+
+            let proxy = proxy::new()
+                .controller(ctrl.run())
+                .outbound(local)
+                .run_with_test_env(env);
+            let client = $make_client(proxy.outbound, "disco.test.svc.cluster.local");
+
+            for _ in 0..10 {
+                assert_eq!(client.get("/"), "local");
+            }
+            assert_eq!(remote_calls.load(Ordering::SeqCst), 0);
+        }
+
+        #[test]
+        fn outbound_retries_idempotent_requests() {
+            use std::sync::Arc;
+            use std::sync::atomic::{AtomicUsize, Ordering};
+
+            let _ = env_logger::try_init();
+            let mut env = config::TestEnv::new();
+            env.put(config::ENV_OUTBOUND_RETRIES_ENABLED, "true".to_owned());
+
+            // Fail the first request, as an endpoint that's shutting down might.
+            let calls = Arc::new(AtomicUsize::new(0));
+            let srv_calls = calls.clone();
+            let srv = $make_server()
+                .route_fn("/", move |_| {
+                    let status = match srv_calls.fetch_add(1, Ordering::SeqCst) {
+                        0 => http::StatusCode::SERVICE_UNAVAILABLE,
+                        _ => http::StatusCode::OK,
+                    };
+                    Response::builder()
+                        .status(status)
+                        .body("hello".into())
+                        .unwrap()
+                })
+                .run();
+            let ctrl = controller::new()
+                .destination_and_close("disco.test.svc.cluster.local", srv.addr);
+
+            let proxy = proxy::new()
+                .controller(ctrl.run())
+                .outbound(srv)
+                .run_with_test_env(env);
+            let client = $make_client(proxy.outbound, "disco.test.svc.cluster.local");
+
+            assert_eq!(client.get("/"), "hello");
+            assert_eq!(calls.load(Ordering::SeqCst), 2);
+        }
+
+        #[test]
+        fn outbound_does_not_retry_non_idempotent_requests() {
+            use std::sync::Arc;
+            use std::sync::atomic::{AtomicUsize, Ordering};
+
+            let _ = env_logger::try_init();
+            let mut env = config::TestEnv::new();
+            env.put(config::ENV_OUTBOUND_RETRIES_ENABLED, "true".to_owned());
+
+            let calls = Arc::new(AtomicUsize::new(0));
+            let srv_calls = calls.clone();
+            let srv = $make_server()
+                .route_fn("/", move |_| {
+                    srv_calls.fetch_add(1, Ordering::SeqCst);
+                    Response::builder()
+                        .status(http::StatusCode::SERVICE_UNAVAILABLE)
+                        .body("".into())
+                        .unwrap()
+                })
+                .run();
+            let ctrl = controller::new()
+                .destination_and_close("disco.test.svc.cluster.local", srv.addr);
+
+            let proxy = proxy::new()
+                .controller(ctrl.run())
+                .outbound(srv)
+                .run_with_test_env(env);
+            let client = $make_client(proxy.outbound, "disco.test.svc.cluster.local");
+
+            let mut req = client.request_builder("/");
+            let rsp = client.request(req.method("POST"));
+            assert_eq!(rsp.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+        }
+
+        #[test]
+        fn outbound_sheds_requests_when_queue_is_full() {
+            let _ = env_logger::try_init();
+            let mut env = config::TestEnv::new();
+            env.put(config::ENV_OUTBOUND_QUEUE_CAPACITY, "0".to_owned());
+
+            let srv = $make_server().route("/", "hello").run();
+            let ctrl = controller::new()
+                .destination_and_close("disco.test.svc.cluster.local", srv.addr);
+
+            let proxy = proxy::new()
+                .controller(ctrl.run())
+                .outbound(srv)
+                .run_with_test_env(env);
+            let client = $make_client(proxy.outbound, "disco.test.svc.cluster.local");
+
+            let mut req = client.request_builder("/");
+            let rsp = client.request(req.method("GET"));
+            assert_eq!(rsp.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(rsp.headers()["conduit-proxy-error"], "queue_full");
         }
 
         #[test]
@@ -212,3 +408,265 @@ fn outbound_updates_newer_services() {
     let client2 = client::http1(proxy.outbound, "disco.test.svc.cluster.local");
     assert_eq!(client2.get("/h1"), "hello h1");
 }
+
+#[test]
+fn outbound_fails_grpc_requests_with_grpc_status() {
+    let _ = env_logger::try_init();
+    let mut env = config::TestEnv::new();
+    env.put(config::ENV_OUTBOUND_QUEUE_CAPACITY, "0".to_owned());
+
+    let srv = server::http2().route("/", "hello").run();
+    let ctrl = controller::new()
+        .destination_and_close("disco.test.svc.cluster.local", srv.addr);
+
+    let proxy = proxy::new()
+        .controller(ctrl.run())
+        .outbound(srv)
+        .run_with_test_env(env);
+    let client = client::http2(proxy.outbound, "disco.test.svc.cluster.local");
+
+    let mut req = client.request_builder("/");
+    let rsp = client.request(req.method("POST").header("content-type", "application/grpc"));
+    assert_eq!(rsp.status(), http::StatusCode::OK);
+    assert_eq!(rsp.headers()["grpc-status"], "8");
+    assert_eq!(rsp.headers()["conduit-proxy-error"], "queue_full");
+}
+
+#[test]
+fn outbound_rejects_requests_over_rate_limit() {
+    let _ = env_logger::try_init();
+    let mut env = config::TestEnv::new();
+    env.put(
+        config::ENV_OUTBOUND_RATE_LIMITS,
+        "disco.test.svc.cluster.local=1".to_owned(),
+    );
+
+    let srv = server::http1().route("/", "hello").run();
+    let ctrl = controller::new()
+        .destination_and_close("disco.test.svc.cluster.local", srv.addr);
+
+    let proxy = proxy::new()
+        .controller(ctrl.run())
+        .outbound(srv)
+        .run_with_test_env(env);
+    let client = client::http1(proxy.outbound, "disco.test.svc.cluster.local");
+
+    assert_eq!(client.get("/"), "hello");
+
+    let rsp = client.request(&mut client.request_builder("/"));
+    assert_eq!(rsp.status(), http::StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(rsp.headers()["conduit-proxy-error"], "rate_limited");
+}
+
+#[test]
+fn outbound_http1_fails_requests_to_refused_endpoints_with_502() {
+    let _ = env_logger::try_init();
+
+    // Find a port on which nothing is listening.
+    let addr = ::std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|l| l.local_addr())
+        .expect("bind");
+    let ctrl = controller::new()
+        .destination_and_close("disco.test.svc.cluster.local", addr);
+
+    let proxy = proxy::new()
+        .controller(ctrl.run())
+        .run();
+    let client = client::http1(proxy.outbound, "disco.test.svc.cluster.local");
+
+    let rsp = client.request(&mut client.request_builder("/"));
+    assert_eq!(rsp.status(), http::StatusCode::BAD_GATEWAY);
+    assert_eq!(rsp.headers()["conduit-proxy-error"], "connect");
+}
+
+#[test]
+fn outbound_enforces_grpc_timeout() {
+    let _ = env_logger::try_init();
+
+    let srv = server::http2()
+        .route_with_latency("/", "hello", Duration::from_millis(500))
+        .run();
+    let ctrl = controller::new()
+        .destination_and_close("disco.test.svc.cluster.local", srv.addr);
+
+    let proxy = proxy::new()
+        .controller(ctrl.run())
+        .outbound(srv)
+        .run();
+    let client = client::http2(proxy.outbound, "disco.test.svc.cluster.local");
+
+    let mut req = client.request_builder("/");
+    let rsp = client.request(req
+        .method("POST")
+        .header("content-type", "application/grpc")
+        .header("grpc-timeout", "100m"));
+    assert_eq!(rsp.status(), http::StatusCode::OK);
+    assert_eq!(rsp.headers()["grpc-status"], "4");
+    assert_eq!(rsp.headers()["conduit-proxy-error"], "timeout");
+}
+
+#[test]
+#[cfg_attr(not(feature = "flaky_tests"), ignore)]
+fn outbound_applies_route_timeouts_from_profile() {
+    let _ = env_logger::try_init();
+    let mut env = config::TestEnv::new();
+    env.put(config::ENV_DESTINATION_PROFILES_ENABLED, "true".to_owned());
+
+    let srv = server::http2()
+        .route("/fast", "hello")
+        .route_with_latency("/slow", "hello", Duration::from_millis(500))
+        .run();
+    let ctrl = controller::new()
+        .destination_and_close("disco.test.svc.cluster.local", srv.addr);
+    let profile_tx = ctrl.profile_tx("disco.test.svc.cluster.local");
+    profile_tx.send(controller::profile_with_route_timeout("/slow", Duration::from_millis(100)));
+
+    let proxy = proxy::new()
+        .controller(ctrl.run())
+        .outbound(srv)
+        .run_with_test_env(env);
+    let client = client::http2(proxy.outbound, "disco.test.svc.cluster.local");
+
+    // Requests that don't match the route aren't affected by its timeout.
+    assert_eq!(client.get("/fast"), "hello");
+
+    // Wait for the profile to be received. TODO: Replace this flaky logic.
+    ::std::thread::sleep(Duration::from_millis(500));
+
+    let mut req = client.request_builder("/slow");
+    let rsp = client.request(req.method("GET"));
+    assert_eq!(rsp.status(), http::StatusCode::GATEWAY_TIMEOUT);
+    assert_eq!(rsp.headers()["conduit-proxy-error"], "timeout");
+}
+
+#[test]
+fn outbound_splits_requests_to_dst_overrides() {
+    let _ = env_logger::try_init();
+    let mut env = config::TestEnv::new();
+    env.put(config::ENV_DESTINATION_PROFILES_ENABLED, "true".to_owned());
+
+    let v1 = server::http2().route("/", "v1").run();
+    let v2 = server::http2().route("/", "v2").run();
+    let ctrl = controller::new()
+        .destination_and_close("web.prod.svc.cluster.local", v1.addr)
+        .destination_and_close("web-v2.prod.svc.cluster.local", v2.addr);
+    let profile_tx = ctrl.profile_tx("web.prod.svc.cluster.local");
+    profile_tx.send(controller::profile_with_dst_overrides(vec![
+        ("web.prod.svc.cluster.local", 0),
+        ("web-v2.prod.svc.cluster.local", 100),
+    ]));
+
+    let proxy = proxy::new()
+        .controller(ctrl.run())
+        .outbound(v1)
+        .run_with_test_env(env);
+    let client = client::http2(proxy.outbound, "web.prod.svc.cluster.local");
+
+    // The first request binds the route, which starts watching its profile.
+    // Requests are sent to the logical destination until it's received.
+    assert_eventually!(client.get("/") == "v2", retries: 100);
+
+    // The logical destination's weight is 0, so it's never chosen.
+    for _ in 0..20 {
+        assert_eq!(client.get("/"), "v2");
+    }
+}
+
+#[test]
+fn outbound_mirrors_requests_to_shadow() {
+    use std::sync::mpsc;
+
+    let _ = env_logger::try_init();
+    let mut env = config::TestEnv::new();
+    env.put(config::ENV_DESTINATION_PROFILES_ENABLED, "true".to_owned());
+
+    let (shadow_tx, shadow_rx) = mpsc::channel();
+    let shadow = server::http2()
+        .route_fn("/", move |_| {
+            shadow_tx.send(()).unwrap();
+            // The shadow's failures aren't seen by the client.
+            Response::builder()
+                .status(500)
+                .body("shadow".into())
+                .unwrap()
+        })
+        .run();
+    let web = server::http2().route("/", "web").run();
+    let ctrl = controller::new()
+        .destination_and_close("web.prod.svc.cluster.local", web.addr)
+        .destination_and_close("web-shadow.prod.svc.cluster.local", shadow.addr);
+    let profile_tx = ctrl.profile_tx("web.prod.svc.cluster.local");
+    profile_tx.send(controller::profile_with_mirror("web-shadow.prod.svc.cluster.local", 100));
+
+    let proxy = proxy::new()
+        .controller(ctrl.run())
+        .outbound(web)
+        .run_with_test_env(env);
+    let client = client::http2(proxy.outbound, "web.prod.svc.cluster.local");
+
+    // The first request binds the route, which starts watching its profile.
+    // Requests aren't mirrored until it's received, and the shadow's service
+    // is bound when it is, so it may not be ready for the first of them.
+    assert_eventually!({
+        assert_eq!(client.get("/"), "web");
+        shadow_rx.recv_timeout(Duration::from_millis(100)).is_ok()
+    }, retries: 50);
+
+    for _ in 0..5 {
+        assert_eq!(client.get("/"), "web");
+        shadow_rx.recv_timeout(Duration::from_secs(5))
+            .expect("request is mirrored to the shadow");
+    }
+}
+
+#[test]
+fn outbound_tcp_does_not_originate_tls_on_ports_without_protocol_detection() {
+    use std::sync::mpsc;
+
+    let _ = env_logger::try_init();
+
+    let msg1 = "custom tcp server starts";
+    let msg2 = "custom tcp client second";
+
+    let (tx, rx) = mpsc::channel();
+
+    // The server speaks first, as the peer's proxy would forward it without
+    // looking for a TLS handshake.
+    let srv = server::tcp()
+        .accept_fut(move |sock| {
+            tokio_io::io::write_all(sock, msg1.as_bytes())
+                .and_then(move |(sock, _)| {
+                    tokio_io::io::read(sock, vec![0; 512])
+                })
+                .map(move |(_sock, vec, n)| {
+                    assert_eq!(&vec[..n], msg2.as_bytes());
+                    tx.send(()).unwrap();
+                })
+                .map_err(|e| panic!("tcp server error: {}", e))
+        })
+        .run();
+
+    // The original destination is a service's cluster IP, which resolves to
+    // a meshed endpoint.
+    let orig_dst = SocketAddr::from(([10, 1, 2, 3], srv.addr.port()));
+    let ctrl = controller::new();
+    ctrl.destination_tx(&orig_dst.to_string())
+        .send_tls(srv.addr, "foo.ns1.conduit-managed.conduit.svc.cluster.local");
+
+    let port = srv.addr.port();
+    let proxy = proxy::new()
+        .controller(ctrl.run())
+        .outbound(srv)
+        .outbound_orig_dst(orig_dst)
+        .disable_outbound_ports_protocol_detection(vec![port])
+        .tls()
+        .run();
+
+    let client = client::tcp(proxy.outbound);
+
+    let tcp_client = client.connect();
+
+    assert_eq!(tcp_client.read(), msg1.as_bytes());
+    tcp_client.write(msg2);
+    rx.recv_timeout(Duration::from_secs(5)).unwrap();
+}
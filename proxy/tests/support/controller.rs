@@ -21,9 +21,16 @@ pub struct DstReceiver(sync::mpsc::UnboundedReceiver<pb::Update>);
 #[derive(Clone, Debug)]
 pub struct DstSender(sync::mpsc::UnboundedSender<pb::Update>);
 
+#[derive(Debug)]
+pub struct ProfileReceiver(sync::mpsc::UnboundedReceiver<pb::DestinationProfile>);
+
+#[derive(Clone, Debug)]
+pub struct ProfileSender(sync::mpsc::UnboundedSender<pb::DestinationProfile>);
+
 #[derive(Clone, Debug, Default)]
 pub struct Controller {
     expect_dst_calls: Arc<Mutex<VecDeque<(Destination, DstReceiver)>>>,
+    expect_profile_calls: Arc<Mutex<VecDeque<(Destination, ProfileReceiver)>>>,
 }
 
 pub struct Listening {
@@ -49,6 +56,19 @@ impl Controller {
         DstSender(tx)
     }
 
+    pub fn profile_tx(&self, dest: &str) -> ProfileSender {
+        let (tx, rx) = sync::mpsc::unbounded();
+        let dst = common::Destination {
+            scheme: "k8s".into(),
+            path: dest.into(),
+        };
+        self.expect_profile_calls
+            .lock()
+            .unwrap()
+            .push_back((dst, ProfileReceiver(rx)));
+        ProfileSender(tx)
+    }
+
     pub fn destination_and_close(self, dest: &str, addr: SocketAddr) -> Self {
         self.destination_tx(dest).send_addr(addr);
         self
@@ -72,6 +92,20 @@ impl Stream for DstReceiver {
     }
 }
 
+impl Stream for ProfileReceiver {
+    type Item = pb::DestinationProfile;
+    type Error = grpc::Error;
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        self.0.poll().map_err(|_| grpc::Error::Grpc(grpc::Status::INTERNAL, HeaderMap::new()))
+    }
+}
+
+impl ProfileSender {
+    pub fn send(&self, profile: pb::DestinationProfile) {
+        self.0.unbounded_send(profile).expect("send profile update")
+    }
+}
+
 impl DstSender {
     pub fn send(&self, up: pb::Update) {
         self.0.unbounded_send(up).expect("send dst update")
@@ -84,6 +118,23 @@ impl DstSender {
     pub fn send_labeled(&self, addr: SocketAddr, addr_labels: Labels, parent_labels: Labels) {
         self.send(destination_add_labeled(addr, addr_labels, parent_labels));
     }
+
+    /// Adds several endpoints, each with its own labels, in a single update.
+    pub fn send_each_labeled(&self, addrs: Vec<(SocketAddr, Labels)>) {
+        self.send(destination_add_each_labeled(addrs));
+    }
+
+    pub fn send_h2_hinted(&self, addr: SocketAddr) {
+        self.send(destination_add_h2_hinted(addr));
+    }
+
+    pub fn send_tls(&self, addr: SocketAddr, server_name: &str) {
+        self.send(destination_add_tls(addr, server_name));
+    }
+
+    pub fn send_h2_hinted_tls(&self, addr: SocketAddr, server_name: &str) {
+        self.send(destination_add_h2_hinted_tls(addr, server_name));
+    }
 }
 
 impl pb::server::Destination for Controller {
@@ -103,6 +154,23 @@ impl pb::server::Destination for Controller {
 
         future::err(grpc::Error::Grpc(grpc::Status::INTERNAL, HeaderMap::new()))
     }
+
+    type GetProfileStream = ProfileReceiver;
+    type GetProfileFuture = future::FutureResult<grpc::Response<Self::GetProfileStream>, grpc::Error>;
+
+    fn get_profile(&mut self, req: grpc::Request<Destination>) -> Self::GetProfileFuture {
+        if let Ok(mut calls) = self.expect_profile_calls.lock() {
+            if let Some((dst, profiles)) = calls.pop_front() {
+                if &dst == req.get_ref() {
+                    return future::ok(grpc::Response::new(profiles));
+                }
+
+                calls.push_front((dst, profiles));
+            }
+        }
+
+        future::err(grpc::Error::Grpc(grpc::Status::INTERNAL, HeaderMap::new()))
+    }
 }
 
 fn run(controller: Controller) -> Listening {
@@ -174,6 +242,8 @@ pub fn destination_add_labeled(
                         }),
                         weight: 0,
                         metric_labels: addr_labels,
+                        tls_identity: None,
+                        protocol_hint: None,
                     },
                 ],
                 metric_labels: set_labels,
@@ -182,6 +252,122 @@ pub fn destination_add_labeled(
     }
 }
 
+pub fn destination_add_each_labeled(addrs: Vec<(SocketAddr, Labels)>) -> pb::Update {
+    let mut update = destination_add_none();
+    if let Some(pb::update::Update::Add(ref mut set)) = update.update {
+        for (addr, addr_labels) in addrs {
+            let labeled = destination_add_labeled(addr, HashMap::new(), addr_labels);
+            if let Some(pb::update::Update::Add(labeled)) = labeled.update {
+                set.addrs.extend(labeled.addrs);
+            }
+        }
+    }
+    update
+}
+
+/// Adds an endpoint whose proxy is known to accept HTTP/2.
+pub fn destination_add_h2_hinted(addr: SocketAddr) -> pb::Update {
+    let mut update = destination_add(addr);
+    if let Some(pb::update::Update::Add(ref mut set)) = update.update {
+        for addr in &mut set.addrs {
+            addr.protocol_hint = Some(pb::ProtocolHint {
+                protocol: Some(pb::protocol_hint::Protocol::H2(pb::protocol_hint::H2 {})),
+            });
+        }
+    }
+    update
+}
+
+/// Adds a meshed endpoint whose certificate is valid for `server_name`.
+pub fn destination_add_tls(addr: SocketAddr, server_name: &str) -> pb::Update {
+    with_tls(destination_add(addr), server_name)
+}
+
+/// Adds a meshed endpoint, whose certificate is valid for `server_name`, that
+/// is known to accept HTTP/2.
+pub fn destination_add_h2_hinted_tls(addr: SocketAddr, server_name: &str) -> pb::Update {
+    with_tls(destination_add_h2_hinted(addr), server_name)
+}
+
+fn with_tls(mut update: pb::Update, server_name: &str) -> pb::Update {
+    if let Some(pb::update::Update::Add(ref mut set)) = update.update {
+        for addr in &mut set.addrs {
+            addr.tls_identity = Some(pb::TlsIdentity {
+                server_name: server_name.into(),
+            });
+        }
+    }
+    update
+}
+
+/// A profile with a single route, which matches requests by `path_regex` and
+/// bounds them by `timeout`.
+pub fn profile_with_route_timeout(path_regex: &str, timeout: Duration) -> pb::DestinationProfile {
+    pb::DestinationProfile {
+        routes: vec![
+            pb::Route {
+                condition: Some(pb::RequestMatch {
+                    method: None,
+                    path_regex: path_regex.into(),
+                }),
+                response_classes: vec![],
+                timeout: Some(::conduit_proxy_controller_grpc::pb_duration(&timeout)),
+                is_retryable: false,
+                name: String::new(),
+            },
+        ],
+        dst_overrides: vec![],
+        mirror: None,
+    }
+}
+
+/// A profile with a single route, named `name`.
+pub fn profile_with_named_route(name: &str, path_regex: &str) -> pb::DestinationProfile {
+    pb::DestinationProfile {
+        routes: vec![
+            pb::Route {
+                condition: Some(pb::RequestMatch {
+                    method: None,
+                    path_regex: path_regex.into(),
+                }),
+                response_classes: vec![],
+                timeout: None,
+                is_retryable: false,
+                name: name.into(),
+            },
+        ],
+        dst_overrides: vec![],
+        mirror: None,
+    }
+}
+
+/// A profile that splits requests between the given authorities by weight.
+pub fn profile_with_dst_overrides(dsts: Vec<(&str, u32)>) -> pb::DestinationProfile {
+    pb::DestinationProfile {
+        routes: vec![],
+        dst_overrides: dsts
+            .into_iter()
+            .map(|(authority, weight)| pb::WeightedDst {
+                authority: authority.into(),
+                weight,
+            })
+            .collect(),
+        mirror: None,
+    }
+}
+
+/// A profile that mirrors `percent` of requests to `authority`.
+pub fn profile_with_mirror(authority: &str, percent: u32) -> pb::DestinationProfile {
+    pb::DestinationProfile {
+        routes: vec![],
+        dst_overrides: vec![],
+        mirror: Some(pb::Mirror {
+            authority: authority.into(),
+            percent,
+        }),
+    }
+}
+
 pub fn destination_add_none() -> pb::Update {
     pb::Update {
         update: Some(pb::update::Update::Add(
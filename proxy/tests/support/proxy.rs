@@ -12,6 +12,8 @@ pub struct Proxy {
     controller: Option<controller::Listening>,
     inbound: Option<server::Listening>,
     outbound: Option<server::Listening>,
+    outbound_orig_dst: Option<SocketAddr>,
+    tls: bool,
 
     inbound_disable_ports_protocol_detection: Option<Vec<u16>>,
     outbound_disable_ports_protocol_detection: Option<Vec<u16>>,
@@ -37,6 +39,8 @@ impl Proxy {
             controller: None,
             inbound: None,
             outbound: None,
+            outbound_orig_dst: None,
+            tls: false,
 
             inbound_disable_ports_protocol_detection: None,
             outbound_disable_ports_protocol_detection: None,
@@ -62,6 +66,21 @@ impl Proxy {
         self
     }
 
+    /// Sets the original destination of outbound connections, instead of the
+    /// outbound server's address.
+    pub fn outbound_orig_dst(mut self, addr: SocketAddr) -> Self {
+        self.outbound_orig_dst = Some(addr);
+        self
+    }
+
+    /// Enables TLS, with a certificate for
+    /// `foo.ns1.conduit-managed.conduit.svc.cluster.local` that is issued by
+    /// the test CA.
+    pub fn tls(mut self) -> Self {
+        self.tls = true;
+        self
+    }
+
     pub fn disable_inbound_ports_protocol_detection(mut self, ports: Vec<u16>) -> Self {
         self.inbound_disable_ports_protocol_detection = Some(ports);
         self
@@ -139,11 +158,21 @@ fn run(proxy: Proxy, mut env: config::TestEnv) -> Listening {
     if let Some(ref outbound) = outbound {
         mock_orig_dst.outbound_orig_addr = Some(outbound.addr);
     }
+    if let Some(addr) = proxy.outbound_orig_dst {
+        mock_orig_dst.outbound_orig_addr = Some(addr);
+    }
     env.put(config::ENV_PUBLIC_LISTENER, "tcp://127.0.0.1:0".to_owned());
     env.put(config::ENV_CONTROL_LISTENER, "tcp://127.0.0.1:0".to_owned());
     env.put(config::ENV_METRICS_LISTENER, "tcp://127.0.0.1:0".to_owned());
     env.put(config::ENV_POD_NAMESPACE, "test".to_owned());
 
+    if proxy.tls {
+        let testdata = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/testdata");
+        env.put(config::ENV_TLS_TRUST_ANCHORS, format!("{}/ca.pem", testdata));
+        env.put(config::ENV_TLS_CERT, format!("{}/foo-ns1-cert.pem", testdata));
+        env.put(config::ENV_TLS_PRIVATE_KEY, format!("{}/foo-ns1-key.p8.pem", testdata));
+    }
+
     if let Some(ports) = proxy.inbound_disable_ports_protocol_detection {
         let ports = ports.into_iter()
             .map(|p| p.to_string())
@@ -185,10 +214,14 @@ fn run(proxy: Proxy, mut env: config::TestEnv) -> Listening {
 
             let main = conduit_proxy::Main::new(config, mock_orig_dst.clone());
 
-            let control_addr = main.control_addr();
+            let control_addr = main.control_addr()
+                .socket_addr()
+                .expect("control listener must be TCP");
             let inbound_addr = main.inbound_addr();
             let outbound_addr = main.outbound_addr();
-            let metrics_addr = main.metrics_addr();
+            let metrics_addr = main.metrics_addr()
+                .socket_addr()
+                .expect("metrics listener must be TCP");
 
             {
                 let mut inner = mock_orig_dst.0.lock().unwrap();
@@ -146,6 +146,64 @@ fn metrics_endpoint_outbound_request_count() {
 
 }
 
+#[test]
+fn metrics_endpoint_outbound_injected_faults() {
+    let _ = env_logger::try_init();
+    let srv = server::new()
+        .route("/", "hello")
+        .run();
+    let ctrl = controller::new()
+        .destination_and_close("tele.test.svc.cluster.local", srv.addr)
+        .run();
+
+    let mut env = config::TestEnv::new();
+    env.put(
+        config::ENV_OUTBOUND_FAULTS,
+        "tele.test.svc.cluster.local=abort:503@100".to_owned(),
+    );
+    let proxy = proxy::new()
+        .controller(ctrl)
+        .outbound(srv)
+        .run_with_test_env(env);
+    let metrics = client::http1(proxy.metrics, "localhost");
+    let client = client::new(proxy.outbound, "tele.test.svc.cluster.local");
+
+    info!("client.request(/)");
+    let rsp = client.request(&mut client.request_builder("/"));
+    assert_eq!(rsp.status(), http::StatusCode::SERVICE_UNAVAILABLE);
+
+    assert_contains!(metrics.get("/metrics"), "request_total{authority=\"tele.test.svc.cluster.local\",direction=\"outbound\",fault=\"abort\"} 1");
+}
+
+#[test]
+fn outbound_injected_resets_reset_the_stream() {
+    let _ = env_logger::try_init();
+    let srv = server::http2()
+        .route("/", "hello")
+        .run();
+    let ctrl = controller::new()
+        .destination_and_close("tele.test.svc.cluster.local", srv.addr)
+        .run();
+
+    let mut env = config::TestEnv::new();
+    env.put(
+        config::ENV_OUTBOUND_FAULTS,
+        "tele.test.svc.cluster.local=reset@100".to_owned(),
+    );
+    let proxy = proxy::new()
+        .controller(ctrl)
+        .outbound(srv)
+        .run_with_test_env(env);
+    let metrics = client::http1(proxy.metrics, "localhost");
+    let client = client::http2(proxy.outbound, "tele.test.svc.cluster.local");
+
+    info!("client.request(/)");
+    let rsp = client.request_async(&mut client.request_builder("/")).wait();
+    assert!(rsp.is_err(), "injected reset was answered: {:?}", rsp);
+
+    assert_contains!(metrics.get("/metrics"), "proxy_errors_total{direction=\"outbound\",reason=\"reset\"} 1");
+}
+
 mod response_classification {
     use super::support::*;
     use super::Fixture;
@@ -248,6 +306,45 @@ mod response_classification {
     }
 }
 
+#[test]
+#[cfg_attr(not(feature = "flaky_tests"), ignore)]
+fn metrics_endpoint_outbound_request_routes() {
+    let _ = env_logger::try_init();
+    let mut env = config::TestEnv::new();
+    env.put(config::ENV_DESTINATION_PROFILES_ENABLED, "true".to_owned());
+
+    info!("running test server");
+    let srv = server::new()
+        .route("/books/1", "hello")
+        .route("/authors/1", "hello")
+        .run();
+    let ctrl = controller::new()
+        .destination_and_close("tele.test.svc.cluster.local", srv.addr);
+    let profile_tx = ctrl.profile_tx("tele.test.svc.cluster.local");
+    profile_tx.send(controller::profile_with_named_route("books \"v1\"", "/books/.*"));
+
+    let proxy = proxy::new()
+        .controller(ctrl.run())
+        .outbound(srv)
+        .run_with_test_env(env);
+    let metrics = client::http1(proxy.metrics, "localhost");
+    let client = client::new(proxy.outbound, "tele.test.svc.cluster.local");
+
+    // The first request binds the route, which starts watching its profile.
+    assert_eq!(client.get("/authors/1"), "hello");
+
+    // Wait for the profile to be received. TODO: Replace this flaky logic.
+    ::std::thread::sleep(Duration::from_millis(500));
+
+    assert_eq!(client.get("/books/1"), "hello");
+    assert_eq!(client.get("/authors/1"), "hello");
+
+    assert_contains!(metrics.get("/metrics"),
+        "request_total{authority=\"tele.test.svc.cluster.local\",direction=\"outbound\",route=\"books \\\"v1\\\"\"} 1");
+    assert_contains!(metrics.get("/metrics"),
+        "request_total{authority=\"tele.test.svc.cluster.local\",direction=\"outbound\",route=\"unknown\"} 1");
+}
+
 // Ignore this test on CI, because our method of adding latency to requests
 // (calling `thread::sleep`) is likely to be flakey on Travis.
 // Eventually, we can add some kind of mock timer system for simulating latency
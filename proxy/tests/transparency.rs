@@ -30,49 +30,114 @@ fn inbound_http1() {
 }
 
 #[test]
-fn http1_connect_not_supported() {
+fn http1_removes_connection_headers() {
     let _ = env_logger::try_init();
 
-    let srv = server::tcp()
+    let srv = server::http1()
+        .route_fn("/", |req| {
+            assert!(!req.headers().contains_key("x-foo-bar"));
+            Response::builder()
+                .header("x-server-quux", "lorem ipsum")
+                .header("connection", "close, x-server-quux")
+                .body("".into())
+                .unwrap()
+        })
         .run();
     let proxy = proxy::new()
         .inbound(srv)
         .run();
+    let client = client::http1(proxy.inbound, "transparency.test.svc.cluster.local");
 
-    let client = client::tcp(proxy.inbound);
+    let res = client.request(client.request_builder("/")
+        .header("x-foo-bar", "baz")
+        .header("connection", "x-foo-bar, close"));
 
-    let tcp_client = client.connect();
-    tcp_client.write("CONNECT foo.bar:443 HTTP/1.1\r\nHost: foo.bar:443\r\n\r\n");
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert!(!res.headers().contains_key("x-server-quux"));
+}
 
-    let expected = "HTTP/1.1 502 Bad Gateway\r\n";
-    assert_eq!(s(&tcp_client.read()[..expected.len()]), expected);
+#[test]
+fn http1_upgraded_to_h2_between_meshed_proxies() {
+    let _ = env_logger::try_init();
+
+    let host = "transparency.test.svc.cluster.local";
+    let srv = server::http1()
+        .route_fn("/", move |req| {
+            assert_eq!(req.version(), http::Version::HTTP_11);
+            assert!(!req.headers().contains_key("conduit-orig-proto"));
+            assert_eq!(req.headers()["host"], host);
+            Response::builder()
+                .body("hello h1 over h2".into())
+                .unwrap()
+        })
+        .run();
+    let inbound = proxy::new()
+        .inbound(srv)
+        .tls()
+        .run();
+
+    // The outbound proxy upgrades requests to the inbound proxy, which
+    // downgrades them once it has authenticated the outbound proxy.
+    let ctrl = controller::new();
+    ctrl.destination_tx(host)
+        .send_h2_hinted_tls(inbound.inbound, "foo.ns1.conduit-managed.conduit.svc.cluster.local");
+    let outbound = proxy::new()
+        .controller(ctrl.run())
+        .tls()
+        .run();
+    let client = client::http1(outbound.outbound, host);
+
+    let res = client.request(&mut client.request_builder("/"));
+    assert_eq!(res.status(), http::StatusCode::OK);
+    assert_eq!(res.version(), http::Version::HTTP_11);
+    assert_eq!(client.get("/"), "hello h1 over h2");
 }
 
 #[test]
-fn http1_removes_connection_headers() {
+fn outbound_http1_not_upgraded_without_tls() {
     let _ = env_logger::try_init();
 
     let srv = server::http1()
         .route_fn("/", |req| {
-            assert!(!req.headers().contains_key("x-foo-bar"));
+            assert_eq!(req.version(), http::Version::HTTP_11);
+            assert!(!req.headers().contains_key("conduit-orig-proto"));
             Response::builder()
-                .header("x-server-quux", "lorem ipsum")
-                .header("connection", "close, x-server-quux")
-                .body("".into())
+                .body("hello h1".into())
+                .unwrap()
+        })
+        .run();
+    let ctrl = controller::new();
+    let dst = ctrl.destination_tx("transparency.test.svc.cluster.local");
+    dst.send_h2_hinted(srv.addr);
+    let proxy = proxy::new().controller(ctrl.run()).outbound(srv).run();
+    let client = client::http1(proxy.outbound, "transparency.test.svc.cluster.local");
+
+    assert_eq!(client.get("/"), "hello h1");
+}
+
+#[test]
+fn inbound_strips_orig_proto_from_unauthenticated_peers() {
+    let _ = env_logger::try_init();
+
+    let host = "transparency.test.svc.cluster.local";
+    let srv = server::http2()
+        .route_fn("/", move |req| {
+            assert_eq!(req.version(), http::Version::HTTP_2);
+            assert!(!req.headers().contains_key("conduit-orig-proto"));
+            Response::builder()
+                .body("hello h2".into())
                 .unwrap()
         })
         .run();
     let proxy = proxy::new()
         .inbound(srv)
         .run();
-    let client = client::http1(proxy.inbound, "transparency.test.svc.cluster.local");
+    let client = client::http2(proxy.inbound, host);
 
     let res = client.request(client.request_builder("/")
-        .header("x-foo-bar", "baz")
-        .header("connection", "x-foo-bar, close"));
-
+        .header("conduit-orig-proto", "HTTP/1.1"));
     assert_eq!(res.status(), http::StatusCode::OK);
-    assert!(!res.headers().contains_key("x-server-quux"));
+    assert_eq!(res.version(), http::Version::HTTP_2);
 }
 
 #[test]
@@ -212,6 +277,107 @@ fn inbound_tcp() {
     assert_eq!(tcp_client.read(), msg2.as_bytes());
 }
 
+#[test]
+fn inbound_tcp_with_proxy_protocol() {
+    use std::net::IpAddr;
+    use std::sync::mpsc;
+
+    let _ = env_logger::try_init();
+
+    const LEN: usize = 128;
+    let msg1 = "custom tcp hello";
+
+    let (tx, rx) = mpsc::channel();
+
+    let srv = server::tcp()
+        .accept_fut(move |sock| {
+            tokio_io::io::read_exact(sock, vec![0; LEN])
+                .map(move |(_sock, vec)| {
+                    tx.send(vec).unwrap();
+                })
+                .map_err(|e| panic!("tcp server error: {}", e))
+        })
+        .run();
+    let srv_addr = srv.addr;
+    let srv_ip = match srv_addr.ip() {
+        IpAddr::V4(ip) => ip,
+        IpAddr::V6(_) => panic!("test server must listen on IPv4"),
+    };
+
+    let mut env = config::TestEnv::new();
+    env.put(config::ENV_INBOUND_ACCEPT_PROXY_PROTOCOL, "true".to_owned());
+    env.put(config::ENV_INBOUND_SEND_PROXY_PROTOCOL, "true".to_owned());
+    let proxy = proxy::new()
+        .inbound(srv)
+        .run_with_test_env(env);
+
+    let client = client::tcp(proxy.inbound);
+
+    let tcp_client = client.connect();
+
+    // The load balancer sends a binary (v2) header...
+    let mut sent = b"\r\n\r\n\0\r\nQUIT\n\x21\x11\x00\x0c".to_vec();
+    sent.extend_from_slice(&[10, 1, 2, 3]);
+    sent.extend_from_slice(&srv_ip.octets());
+    sent.extend_from_slice(&[0xdc, 0x04]);
+    sent.extend_from_slice(&[(srv_addr.port() >> 8) as u8, srv_addr.port() as u8]);
+
+    // ...and the proxy passes the client's address on to the application in
+    // a text (v1) header.
+    let mut expected = format!(
+        "PROXY TCP4 10.1.2.3 {} 56324 {}\r\n{}",
+        srv_ip,
+        srv_addr.port(),
+        msg1,
+    ).into_bytes();
+    let padding = vec![b'.'; LEN - expected.len()];
+    expected.extend_from_slice(&padding);
+
+    sent.extend_from_slice(msg1.as_bytes());
+    sent.extend_from_slice(&padding);
+    tcp_client.write(sent);
+
+    let read = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(read, expected);
+}
+
+#[test]
+fn inbound_closes_connections_with_truncated_proxy_protocol_header() {
+    use std::sync::mpsc;
+
+    let _ = env_logger::try_init();
+
+    let (tx, rx) = mpsc::channel();
+
+    let srv = server::tcp()
+        .accept_fut(move |_sock| {
+            tx.send(()).unwrap();
+            Ok(())
+        })
+        .run();
+
+    let mut env = config::TestEnv::new();
+    env.put(config::ENV_INBOUND_ACCEPT_PROXY_PROTOCOL, "true".to_owned());
+    env.put(config::ENV_PROTOCOL_DETECTION_TIMEOUT, "100ms".to_owned());
+    let proxy = proxy::new()
+        .inbound(srv)
+        .run_with_test_env(env);
+
+    let client = client::tcp(proxy.inbound);
+
+    let tcp_client = client.connect();
+
+    // Only the signature of a binary (v2) header is sent, so the header is
+    // never complete.
+    tcp_client.write(&b"\r\n\r\n\0\r\nQUIT\n"[..]);
+
+    // Once the detection timeout passes, the connection is closed without
+    // being proxied.
+    let read = tcp_client.try_read().unwrap_or_default();
+    assert!(read.is_empty(), "truncated header read {:?}", read);
+    assert!(rx.try_recv().is_err(), "connection was proxied");
+}
+
 #[test]
 fn tcp_server_first() {
     use std::sync::mpsc;
@@ -250,6 +416,262 @@ fn tcp_server_first() {
     rx.recv_timeout(Duration::from_secs(5)).unwrap();
 }
 
+#[test]
+fn tcp_server_first_detection_timeout() {
+    use std::sync::mpsc;
+
+    let _ = env_logger::try_init();
+
+    let msg1 = "custom tcp server starts";
+    let msg2 = "custom tcp client second";
+
+    let (tx, rx) = mpsc::channel();
+
+    let srv = server::tcp()
+        .accept_fut(move |sock| {
+            tokio_io::io::write_all(sock, msg1.as_bytes())
+                .and_then(move |(sock, _)| {
+                    tokio_io::io::read(sock, vec![0; 512])
+                })
+                .map(move |(_sock, vec, n)| {
+                    assert_eq!(&vec[..n], msg2.as_bytes());
+                    tx.send(()).unwrap();
+                })
+                .map_err(|e| panic!("tcp server error: {}", e))
+        })
+        .run();
+
+    // Protocol detection is left enabled, so the server's port is only
+    // proxied as TCP once the client has been silent for long enough.
+    let mut env = config::TestEnv::new();
+    env.put(config::ENV_PROTOCOL_DETECTION_TIMEOUT, "100ms".to_owned());
+
+    let proxy = proxy::new()
+        .inbound(srv)
+        .run_with_test_env(env);
+
+    let client = client::tcp(proxy.inbound);
+
+    let tcp_client = client.connect();
+
+    assert_eq!(tcp_client.read(), msg1.as_bytes());
+    tcp_client.write(msg2);
+    rx.recv_timeout(Duration::from_secs(5)).unwrap();
+}
+
+#[test]
+fn inbound_closes_connections_over_limit() {
+    use std::sync::mpsc;
+
+    let _ = env_logger::try_init();
+
+    let msg1 = "custom tcp server starts";
+    let msg2 = "custom tcp client second";
+
+    let (tx, rx) = mpsc::channel();
+
+    let srv = server::tcp()
+        .accept_fut(move |sock| {
+            tokio_io::io::write_all(sock, msg1.as_bytes())
+                .and_then(move |(sock, _)| {
+                    tokio_io::io::read(sock, vec![0; 512])
+                })
+                .map(move |(_sock, vec, n)| {
+                    assert_eq!(&vec[..n], msg2.as_bytes());
+                    tx.send(()).unwrap();
+                })
+                .map_err(|e| panic!("tcp server error: {}", e))
+        })
+        .run();
+
+    let mut env = config::TestEnv::new();
+    env.put(config::ENV_INBOUND_MAX_CONNECTIONS, "1".to_owned());
+    env.put(config::ENV_INBOUND_CONNECTIONS_OVER_LIMIT, "close".to_owned());
+
+    let proxy = proxy::new()
+        .disable_inbound_ports_protocol_detection(vec![srv.addr.port()])
+        .inbound(srv)
+        .run_with_test_env(env);
+
+    let client = client::tcp(proxy.inbound);
+
+    let first = client.connect();
+    assert_eq!(first.read(), msg1.as_bytes());
+
+    // While the first connection is open, the second is closed without
+    // being proxied.
+    let second = client.connect();
+    let read = second.try_read().unwrap_or_default();
+    assert!(read.is_empty(), "over-limit connection read {:?}", read);
+
+    first.write(msg2);
+    rx.recv_timeout(Duration::from_secs(5)).unwrap();
+}
+
+#[test]
+fn http1_upgrade_is_tunneled() {
+    let _ = env_logger::try_init();
+
+    let upgrade_req = "\
+        GET /chat HTTP/1.1\r\n\
+        Host: transparency.test.svc.cluster.local\r\n\
+        Connection: upgrade\r\n\
+        Upgrade: websocket\r\n\
+        \r\n\
+        ";
+    let upgrade_res = "\
+        HTTP/1.1 101 Switching Protocols\r\n\
+        connection: upgrade\r\n\
+        upgrade: websocket\r\n\
+        \r\n\
+        ";
+    let msg1 = "custom websocket client";
+    let msg2 = "custom websocket server";
+
+    let srv = server::tcp()
+        .accept_fut(move |sock| {
+            tokio_io::io::read(sock, vec![0; 512])
+                .and_then(move |(sock, vec, n)| {
+                    let head = String::from_utf8_lossy(&vec[..n]).to_lowercase();
+                    assert!(head.starts_with("get /chat http/1.1\r\n"), "{:?}", head);
+                    assert!(head.contains("\r\nupgrade: websocket\r\n"), "{:?}", head);
+                    tokio_io::io::write_all(sock, upgrade_res.as_bytes())
+                })
+                .and_then(move |(sock, _)| {
+                    tokio_io::io::read(sock, vec![0; 512])
+                })
+                .and_then(move |(sock, vec, n)| {
+                    assert_eq!(&vec[..n], msg1.as_bytes());
+                    tokio_io::io::write_all(sock, msg2.as_bytes())
+                })
+                .map(|_| ())
+                .map_err(|e| panic!("tcp server error: {}", e))
+        })
+        .run();
+    let proxy = proxy::new()
+        .inbound(srv)
+        .run();
+
+    let client = client::tcp(proxy.inbound);
+
+    let tcp_client = client.connect();
+
+    tcp_client.write(upgrade_req);
+    assert_eq!(tcp_client.read(), upgrade_res.as_bytes());
+    tcp_client.write(msg1);
+    assert_eq!(tcp_client.read(), msg2.as_bytes());
+}
+
+#[test]
+fn http1_upgrade_is_tunneled_when_head_is_split() {
+    let _ = env_logger::try_init();
+
+    let upgrade_req1 = "\
+        GET /chat HTTP/1.1\r\n\
+        Host: transparency.test.svc.cluster.local\r\n\
+        ";
+    let upgrade_req2 = "\
+        Connection: upgrade\r\n\
+        Upgrade: websocket\r\n\
+        \r\n\
+        ";
+    let upgrade_res = "\
+        HTTP/1.1 101 Switching Protocols\r\n\
+        connection: upgrade\r\n\
+        upgrade: websocket\r\n\
+        \r\n\
+        ";
+    let msg1 = "custom websocket client";
+    let msg2 = "custom websocket server";
+
+    let srv = server::tcp()
+        .accept_fut(move |sock| {
+            tokio_io::io::read(sock, vec![0; 512])
+                .and_then(move |(sock, vec, n)| {
+                    let head = String::from_utf8_lossy(&vec[..n]).to_lowercase();
+                    assert!(head.starts_with("get /chat http/1.1\r\n"), "{:?}", head);
+                    assert!(head.contains("\r\nupgrade: websocket\r\n"), "{:?}", head);
+                    tokio_io::io::write_all(sock, upgrade_res.as_bytes())
+                })
+                .and_then(move |(sock, _)| {
+                    tokio_io::io::read(sock, vec![0; 512])
+                })
+                .and_then(move |(sock, vec, n)| {
+                    assert_eq!(&vec[..n], msg1.as_bytes());
+                    tokio_io::io::write_all(sock, msg2.as_bytes())
+                })
+                .map(|_| ())
+                .map_err(|e| panic!("tcp server error: {}", e))
+        })
+        .run();
+    let proxy = proxy::new()
+        .inbound(srv)
+        .run();
+
+    let client = client::tcp(proxy.inbound);
+
+    let tcp_client = client.connect();
+
+    // The upgrade headers arrive after protocol detection has peeked the
+    // start of the head.
+    tcp_client.write(upgrade_req1);
+    ::std::thread::sleep(Duration::from_millis(100));
+    tcp_client.write(upgrade_req2);
+    assert_eq!(tcp_client.read(), upgrade_res.as_bytes());
+    tcp_client.write(msg1);
+    assert_eq!(tcp_client.read(), msg2.as_bytes());
+}
+
+#[test]
+fn http1_connect_is_tunneled() {
+    let _ = env_logger::try_init();
+
+    let connect_req = "\
+        CONNECT transparency.test.svc.cluster.local:443 HTTP/1.1\r\n\
+        Host: transparency.test.svc.cluster.local:443\r\n\
+        \r\n\
+        ";
+    let connect_res = "HTTP/1.1 200 OK\r\n\r\n";
+    let msg1 = "custom tunneled client";
+    let msg2 = "custom tunneled server";
+
+    let srv = server::tcp()
+        .accept_fut(move |sock| {
+            tokio_io::io::read(sock, vec![0; 512])
+                .and_then(move |(sock, vec, n)| {
+                    let head = String::from_utf8_lossy(&vec[..n]).to_lowercase();
+                    assert!(
+                        head.starts_with("connect transparency.test.svc.cluster.local:443 http/1.1\r\n"),
+                        "{:?}",
+                        head
+                    );
+                    tokio_io::io::write_all(sock, connect_res.as_bytes())
+                })
+                .and_then(move |(sock, _)| {
+                    tokio_io::io::read(sock, vec![0; 512])
+                })
+                .and_then(move |(sock, vec, n)| {
+                    assert_eq!(&vec[..n], msg1.as_bytes());
+                    tokio_io::io::write_all(sock, msg2.as_bytes())
+                })
+                .map(|_| ())
+                .map_err(|e| panic!("tcp server error: {}", e))
+        })
+        .run();
+    let proxy = proxy::new()
+        .inbound(srv)
+        .run();
+
+    let client = client::tcp(proxy.inbound);
+
+    let tcp_client = client.connect();
+
+    tcp_client.write(connect_req);
+    assert_eq!(tcp_client.read(), connect_res.as_bytes());
+    tcp_client.write(msg1);
+    assert_eq!(tcp_client.read(), msg2.as_bytes());
+}
+
 #[test]
 fn tcp_with_no_orig_dst() {
     let _ = env_logger::try_init();
@@ -321,50 +743,6 @@ fn tcp_connections_close_if_client_closes() {
     rx.recv_timeout(Duration::from_secs(5)).unwrap();
 }
 
-#[test]
-fn http11_upgrade_not_supported() {
-    let _ = env_logger::try_init();
-
-    // our h1 proxy will strip the Connection header
-    // and headers it mentions
-    let msg1 = "\
-        GET /chat HTTP/1.1\r\n\
-        Host: foo.bar\r\n\
-        Connection: Upgrade\r\n\
-        Upgrade: websocket\r\n\
-        \r\n\
-        ";
-
-    // but let's pretend the server tries to upgrade
-    // anyways
-    let msg2 = "\
-        HTTP/1.1 101 Switching Protocols\r\n\
-        Upgrade: websocket\r\n\
-        Connection: Upgrade\r\n\
-        \r\n\
-        ";
-
-    let srv = server::tcp()
-        .accept(move |read| {
-            let head = s(&read);
-            assert!(!head.contains("Upgrade: websocket"));
-            msg2
-        })
-        .run();
-    let proxy = proxy::new()
-        .inbound(srv)
-        .run();
-
-    let client = client::tcp(proxy.inbound);
-
-    let tcp_client = client.connect();
-
-    tcp_client.write(msg1);
-
-    let expected = "HTTP/1.1 500 ";
-    assert_eq!(s(&tcp_client.read()[..expected.len()]), expected);
-}
-
 #[test]
 fn http1_requests_without_body_doesnt_add_transfer_encoding() {
     let _ = env_logger::try_init();